@@ -4,6 +4,7 @@ import android.os.Bundle
 import androidx.activity.ComponentActivity
 import androidx.activity.compose.setContent
 import androidx.activity.enableEdgeToEdge
+import androidx.activity.viewModels
 import androidx.compose.foundation.background
 import androidx.compose.foundation.layout.Arrangement
 import androidx.compose.foundation.layout.Box
@@ -22,12 +23,15 @@ import androidx.compose.ui.unit.dp
 import androidx.core.view.WindowCompat
 import androidx.core.view.WindowInsetsCompat
 import androidx.core.view.WindowInsetsControllerCompat
+import site.nyaalex.paint.core.CoreViewModel
 import site.nyaalex.paint.rust.Logging
 import site.nyaalex.paint.ui.Viewport
 import site.nyaalex.paint.ui.color_picker.ColorPicker
 import site.nyaalex.paint.ui.theme.AppTheme
 
 class MainActivity : ComponentActivity() {
+    private val coreViewModel: CoreViewModel by viewModels()
+
     override fun onCreate(savedInstanceState: Bundle?) {
         super.onCreate(savedInstanceState)
 
@@ -55,6 +59,11 @@ class MainActivity : ComponentActivity() {
             }
         }
     }
+
+    override fun onStop() {
+        super.onStop()
+        coreViewModel.runtime.saveCaches()
+    }
 }
 
 @Composable
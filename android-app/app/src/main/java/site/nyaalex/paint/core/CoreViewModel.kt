@@ -1,11 +1,12 @@
 package site.nyaalex.paint.core
 
-import androidx.lifecycle.ViewModel
+import android.app.Application
+import androidx.lifecycle.AndroidViewModel
 import site.nyaalex.paint.rust.Behaviour
 import site.nyaalex.paint.rust.Runtime
 
-class CoreViewModel : ViewModel() {
-    val runtime: Runtime = Runtime()
+class CoreViewModel(application: Application) : AndroidViewModel(application) {
+    val runtime: Runtime = Runtime(application.cacheDir)
     init { addCloseable { runtime }}
 
     val behaviour: Behaviour = Behaviour(runtime)
@@ -0,0 +1,16 @@
+package site.nyaalex.paint.core.color
+
+import site.nyaalex.paint.rust.ColorUtils
+
+/** Color harmony, in the same order as `paint_core::color::Harmony::ALL`. */
+enum class Harmony {
+    Complementary,
+    SplitComplementary,
+    Triadic,
+    Tetradic,
+    Analogous,
+    Monochromatic;
+
+    /** Hues of the scheme in radians, starting with [baseHue]. */
+    fun hues(baseHue: Float): FloatArray = ColorUtils.harmonyHues(baseHue, this)
+}
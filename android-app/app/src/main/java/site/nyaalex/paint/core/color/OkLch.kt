@@ -0,0 +1,6 @@
+package site.nyaalex.paint.core.color
+
+import androidx.compose.runtime.Immutable
+
+@Immutable
+data class OkLch(val l: Float, val c: Float, val h: Float)
@@ -0,0 +1,6 @@
+package site.nyaalex.paint.core.color
+
+import androidx.compose.runtime.Immutable
+
+@Immutable
+data class Okhsl(val h: Float, val s: Float, val l: Float)
@@ -2,6 +2,167 @@ package site.nyaalex.paint.rust
 
 import java.lang.AutoCloseable
 
+/** Color space in which layers and brush strokes are blended. */
+enum class BlendingSpace {
+    LinearLight,
+    GammaSrgb,
+    Oklab,
+}
+
+/** Pixel format of the canvas, see [Behaviour.setCanvasFormat]. */
+enum class CanvasFormat {
+    /** 8-bit sRGB-encoded color. */
+    Rgba8NonlinearSrgb,
+    /** 16-bit normalized linear color, not supported by all devices. */
+    Rgba16LinearSrgb,
+    /** 16-bit floating point linear color. */
+    Rgba16FloatLinearSrgb,
+}
+
+/** Post-processing of the viewport, which doesn't change the painting. */
+enum class ViewFilter {
+    None,
+    /** Grays of the same lightness. */
+    Luminance,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+    /** Lightness quantized into a few grays. */
+    ValueBands,
+}
+
+/** How brush strokes move the painting around instead of painting. */
+enum class LiquifyMode {
+    Push,
+    TwirlCounterclockwise,
+    TwirlClockwise,
+    Pinch,
+    Bloat,
+    Reconstruct,
+}
+
+/** Shape which brush strokes draw instead of painting. */
+enum class ShapeKind {
+    Line,
+    Rectangle,
+    Ellipse,
+    /** Regular polygon, centered where the stroke starts. */
+    Polygon,
+}
+
+/** Point of a tone curve, with both coordinates between 0 and 1. */
+data class CurvePoint(val x: Float, val y: Float)
+
+/** Offsets of sRGB-encoded components. */
+data class RgbOffset(val r: Float = 0f, val g: Float = 0f, val b: Float = 0f)
+
+/** A color adjustment, see [AdjustmentLayer]. */
+sealed interface Adjustment {
+    /** Remaps the range of sRGB-encoded components, with a midtone gamma. */
+    data class Levels(
+        val inputBlack: Float = 0f,
+        val inputWhite: Float = 1f,
+        val gamma: Float = 1f,
+        val outputBlack: Float = 0f,
+        val outputWhite: Float = 1f,
+    ) : Adjustment
+
+    /** Tone curves of the sRGB-encoded components and the Oklab lightness. */
+    data class Curves(
+        val red: List<CurvePoint> = IDENTITY,
+        val green: List<CurvePoint> = IDENTITY,
+        val blue: List<CurvePoint> = IDENTITY,
+        val lightness: List<CurvePoint> = IDENTITY,
+    ) : Adjustment {
+        companion object {
+            val IDENTITY = listOf(CurvePoint(0f, 0f), CurvePoint(1f, 1f))
+        }
+    }
+
+    /** Shift in OkLCh, with [hue] in radians and [chroma] as a factor. */
+    data class HueChromaLightness(
+        val hue: Float = 0f,
+        val chroma: Float = 1f,
+        val lightness: Float = 0f,
+    ) : Adjustment
+
+    /** RGB offsets of the shadows, midtones and highlights. */
+    data class ColorBalance(
+        val shadows: RgbOffset = RgbOffset(),
+        val midtones: RgbOffset = RgbOffset(),
+        val highlights: RgbOffset = RgbOffset(),
+    ) : Adjustment
+
+    data object Invert : Adjustment
+}
+
+/** A layer above the canvas, adjusting the colors of everything below it. */
+data class AdjustmentLayer(
+    val adjustment: Adjustment,
+    val opacity: Float = 1f,
+    val visible: Boolean = true,
+)
+
+/**
+ * A filter, which is previewed until it's committed to the canvas.
+ *
+ * Blurs reach at most 256 pixels on each side, larger sizes are clamped.
+ */
+sealed interface Filter {
+    /** Gaussian blur, whose kernel reaches [radius] pixels. */
+    data class GaussianBlur(val radius: Float) : Filter
+
+    /** Average of a square of `2 * radius + 1` pixels. */
+    data class BoxBlur(val radius: Int) : Filter
+
+    /** Average along a line of [length] pixels, at [angle] radians counterclockwise. */
+    data class MotionBlur(val angle: Float, val length: Float) : Filter
+
+    /** Sharpens by [amount], leaving out differences below [threshold]. */
+    data class UnsharpMask(
+        val radius: Float,
+        val amount: Float,
+        val threshold: Float = 0f,
+    ) : Filter
+
+    /** Uniform noise of up to [amount], the same for all components if [monochrome]. */
+    data class Noise(
+        val amount: Float,
+        val seed: Int = 0,
+        val monochrome: Boolean = false,
+    ) : Filter
+}
+
+/** Bundled font of a [TextLayer]. */
+enum class TextFont {
+    Sans,
+    Serif,
+    Mono,
+}
+
+/** Horizontal alignment of the lines of a [TextLayer] relative to each other. */
+enum class TextAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/**
+ * A text above the canvas, which stays editable until it's rasterized.
+ *
+ * [size] is the height of a line and [x], [y] the top left corner, in canvas
+ * pixels. The alpha of the [color] int is ignored.
+ */
+data class TextLayer(
+    val text: String,
+    val size: Float,
+    val color: Int,
+    val x: Float,
+    val y: Float,
+    val font: TextFont = TextFont.Sans,
+    val alignment: TextAlignment = TextAlignment.Left,
+)
+
 class Behaviour(runtime: Runtime) : AutoCloseable {
     private object Native {
         init {
@@ -12,6 +173,56 @@ class Behaviour(runtime: Runtime) : AutoCloseable {
 
         external fun setViewportTransform(ptr: Long, scale: Float, angle: Float, x: Float, y: Float)
 
+        external fun setBlendingSpace(ptr: Long, space: Int)
+
+        external fun setCanvasFormat(ptr: Long, format: Int)
+
+        external fun setViewFilter(ptr: Long, filter: Int, bands: Int)
+
+        external fun setAdjustmentLayers(ptr: Long, kinds: IntArray, values: FloatArray)
+
+        external fun setTextLayers(
+            ptr: Long,
+            texts: Array<String>,
+            ints: IntArray,
+            floats: FloatArray,
+        )
+
+        external fun rasterizeTextLayer(ptr: Long, index: Int)
+
+        external fun previewFilter(
+            ptr: Long,
+            kind: Int,
+            a: Float,
+            b: Float,
+            c: Float,
+            seed: Int,
+            monochrome: Boolean,
+        )
+
+        external fun commitFilter(ptr: Long)
+
+        external fun cancelFilter(ptr: Long)
+
+        external fun setLiquify(ptr: Long, mode: Int, radius: Float, strength: Float)
+
+        external fun setShapeTool(ptr: Long, kind: Int, sides: Int, strokeWidth: Float)
+
+        external fun editShape(
+            ptr: Long,
+            kind: Int,
+            sides: Int,
+            strokeWidth: Float,
+            startX: Float,
+            startY: Float,
+            endX: Float,
+            endY: Float,
+        )
+
+        external fun commitShape(ptr: Long)
+
+        external fun cancelShape(ptr: Long)
+
         external fun beginBrushStroke(ptr: Long)
 
         external fun updateBrushStroke(ptr: Long, x: Float, y: Float, pressure: Float)
@@ -30,6 +241,162 @@ class Behaviour(runtime: Runtime) : AutoCloseable {
         Native.setViewportTransform(ptr, scale, angle, x, y)
     }
 
+    fun setBlendingSpace(space: BlendingSpace) {
+        Native.setBlendingSpace(ptr, space.ordinal)
+    }
+
+    /**
+     * Converts the canvas to the format, which is ignored if the device doesn't
+     * support it.
+     */
+    fun setCanvasFormat(format: CanvasFormat) {
+        Native.setCanvasFormat(ptr, format.ordinal)
+    }
+
+    /** Sets the view filter, [bands] is only used by [ViewFilter.ValueBands]. */
+    fun setViewFilter(filter: ViewFilter, bands: Int = 5) {
+        Native.setViewFilter(ptr, filter.ordinal, bands)
+    }
+
+    /** Replaces the adjustment layers, from bottom to top. */
+    fun setAdjustmentLayers(layers: List<AdjustmentLayer>) {
+        // the kinds and the layout of the values must match the Rust side
+        val kinds = IntArray(layers.size)
+        val values = ArrayList<Float>()
+        fun curve(points: List<CurvePoint>) {
+            values.add(points.size.toFloat())
+            points.forEach { values.addAll(listOf(it.x, it.y)) }
+        }
+        fun offset(offset: RgbOffset) {
+            values.addAll(listOf(offset.r, offset.g, offset.b))
+        }
+
+        layers.forEachIndexed { i, layer ->
+            values.add(layer.opacity)
+            values.add(if (layer.visible) 1f else 0f)
+            kinds[i] = when (val adjustment = layer.adjustment) {
+                is Adjustment.Levels -> {
+                    values.addAll(
+                        listOf(
+                            adjustment.inputBlack,
+                            adjustment.inputWhite,
+                            adjustment.gamma,
+                            adjustment.outputBlack,
+                            adjustment.outputWhite,
+                        )
+                    )
+                    0
+                }
+                is Adjustment.Curves -> {
+                    curve(adjustment.red)
+                    curve(adjustment.green)
+                    curve(adjustment.blue)
+                    curve(adjustment.lightness)
+                    1
+                }
+                is Adjustment.HueChromaLightness -> {
+                    values.addAll(listOf(adjustment.hue, adjustment.chroma, adjustment.lightness))
+                    2
+                }
+                is Adjustment.ColorBalance -> {
+                    offset(adjustment.shadows)
+                    offset(adjustment.midtones)
+                    offset(adjustment.highlights)
+                    3
+                }
+                Adjustment.Invert -> 4
+            }
+        }
+
+        Native.setAdjustmentLayers(ptr, kinds, values.toFloatArray())
+    }
+
+    /**
+     * Replaces the text layers above the adjustment layers, from bottom to top.
+     *
+     * Only the layers which have changed are rasterized again.
+     */
+    fun setTextLayers(layers: List<TextLayer>) {
+        val texts = layers.map { it.text }.toTypedArray()
+        val ints = layers
+            .flatMap { listOf(it.font.ordinal, it.color, it.alignment.ordinal) }
+            .toIntArray()
+        val floats = layers.flatMap { listOf(it.size, it.x, it.y) }.toFloatArray()
+        Native.setTextLayers(ptr, texts, ints, floats)
+    }
+
+    /** Draws the text layer at [index] onto the canvas and removes it. */
+    fun rasterizeTextLayer(index: Int) {
+        Native.rasterizeTextLayer(ptr, index)
+    }
+
+    /** Shows the canvas with the filter applied, replacing any previewed filter. */
+    fun previewFilter(filter: Filter) {
+        // the kinds and the parameters must match the Rust side
+        when (filter) {
+            is Filter.GaussianBlur -> Native.previewFilter(ptr, 0, filter.radius, 0f, 0f, 0, false)
+            is Filter.BoxBlur ->
+                Native.previewFilter(ptr, 1, filter.radius.toFloat(), 0f, 0f, 0, false)
+            is Filter.MotionBlur ->
+                Native.previewFilter(ptr, 2, filter.angle, filter.length, 0f, 0, false)
+            is Filter.UnsharpMask -> Native.previewFilter(
+                ptr, 3, filter.radius, filter.amount, filter.threshold, 0, false
+            )
+            is Filter.Noise -> Native.previewFilter(
+                ptr, 4, filter.amount, 0f, 0f, filter.seed, filter.monochrome
+            )
+        }
+    }
+
+    /** Applies the previewed filter to the canvas. */
+    fun commitFilter() {
+        Native.commitFilter(ptr)
+    }
+
+    /** Stops previewing the filter, leaving the canvas as is. */
+    fun cancelFilter() {
+        Native.cancelFilter(ptr)
+    }
+
+    /**
+     * Makes brush strokes liquify the painting, with [strength] between 0 and 1,
+     * or paint again when [mode] is null.
+     */
+    fun setLiquify(mode: LiquifyMode?, radius: Float = 50f, strength: Float = 0.5f) {
+        Native.setLiquify(ptr, mode?.ordinal ?: -1, radius, strength)
+    }
+
+    /**
+     * Makes brush strokes draw shapes, stroked [strokeWidth] pixels wide or
+     * filled when it's null, or paint again when [kind] is null.
+     *
+     * [sides] is only used by [ShapeKind.Polygon].
+     */
+    fun setShapeTool(kind: ShapeKind?, sides: Int = 5, strokeWidth: Float? = null) {
+        Native.setShapeTool(ptr, kind?.ordinal ?: -1, sides, strokeWidth ?: 0f)
+    }
+
+    /** Replaces the drawn shape, which stays editable until committed. */
+    fun editShape(
+        kind: ShapeKind,
+        sides: Int,
+        strokeWidth: Float?,
+        startX: Float,
+        startY: Float,
+        endX: Float,
+        endY: Float,
+    ) {
+        Native.editShape(ptr, kind.ordinal, sides, strokeWidth ?: 0f, startX, startY, endX, endY)
+    }
+
+    fun commitShape() {
+        Native.commitShape(ptr)
+    }
+
+    fun cancelShape() {
+        Native.cancelShape(ptr)
+    }
+
     fun beginBrushStroke() {
         Native.beginBrushStroke(ptr)
     }
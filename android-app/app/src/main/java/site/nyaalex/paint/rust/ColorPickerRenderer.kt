@@ -1,5 +1,10 @@
 package site.nyaalex.paint.rust
 
+import site.nyaalex.paint.core.color.LinearSrgb
+import site.nyaalex.paint.core.color.OkLch
+import site.nyaalex.paint.core.color.Okhsl
+import site.nyaalex.paint.core.color.Okhsv
+import site.nyaalex.paint.ui.color_picker.ColorPickerValue
 import java.io.Closeable
 
 class ColorPickerRenderer(runtime: Runtime) : Closeable {
@@ -15,17 +20,97 @@ class ColorPickerRenderer(runtime: Runtime) : Closeable {
 
         external fun renderOkhsvHueSlice(ptr: Long, surfacePtr: Long, hue: Float)
 
-        external fun renderOkhslHueVerticalGradient(ptr: Long, surfacePtr: Long)
+        external fun renderOkhslHueVerticalGradient(
+            ptr: Long,
+            surfacePtr: Long,
+            markers: FloatArray
+        )
+
+        external fun renderOkhslHueSlice(ptr: Long, surfacePtr: Long, hue: Float)
+
+        external fun renderOkhslLightnessWheel(ptr: Long, surfacePtr: Long, lightness: Float)
+
+        external fun renderOkhsvHueRing(ptr: Long, surfacePtr: Long)
+
+        external fun renderOkLchChromaLightnessPlane(ptr: Long, surfacePtr: Long, hue: Float)
+
+        external fun renderAlphaStrip(ptr: Long, surfacePtr: Long, r: Float, g: Float, b: Float)
+
+        external fun setSelection(ptr: Long, space: Int, c0: Float, c1: Float, c2: Float)
+
+        external fun pick(ptr: Long, x: Float, y: Float): FloatArray
 
         external fun destroy(ptr: Long)
     }
 
+    // color spaces of picked values, matching `color_picker.rs`
+    private companion object {
+        const val SPACE_NONE = -1
+        const val SPACE_OKHSV = 0
+        const val SPACE_OKHSL = 1
+        const val SPACE_OKLCH = 2
+        const val SPACE_ALPHA = 3
+    }
+
     fun renderOkhsvHueSlice(surface: Surface, hue: Float) {
         Native.renderOkhsvHueSlice(ptr, surface.ptr, hue)
     }
 
-    fun renderOkhslHueVerticalGradient(surface: Surface) {
-        Native.renderOkhslHueVerticalGradient(ptr, surface.ptr)
+    fun renderOkhslHueVerticalGradient(surface: Surface, markers: FloatArray) {
+        Native.renderOkhslHueVerticalGradient(ptr, surface.ptr, markers)
+    }
+
+    fun renderOkhslHueSlice(surface: Surface, hue: Float) {
+        Native.renderOkhslHueSlice(ptr, surface.ptr, hue)
+    }
+
+    fun renderOkhslLightnessWheel(surface: Surface, lightness: Float) {
+        Native.renderOkhslLightnessWheel(ptr, surface.ptr, lightness)
+    }
+
+    fun renderOkhsvHueRing(surface: Surface) {
+        Native.renderOkhsvHueRing(ptr, surface.ptr)
+    }
+
+    fun renderOkLchChromaLightnessPlane(surface: Surface, hue: Float) {
+        Native.renderOkLchChromaLightnessPlane(ptr, surface.ptr, hue)
+    }
+
+    fun renderAlphaStrip(surface: Surface, color: LinearSrgb) {
+        Native.renderAlphaStrip(ptr, surface.ptr, color.r, color.g, color.b)
+    }
+
+    /** Sets the value marked on the next rendered slice, or none if `null`. */
+    fun setSelection(value: ColorPickerValue?) {
+        when (value) {
+            is ColorPickerValue.OkhsvValue -> value.color.let {
+                Native.setSelection(ptr, SPACE_OKHSV, it.h, it.s, it.v)
+            }
+            is ColorPickerValue.OkhslValue -> value.color.let {
+                Native.setSelection(ptr, SPACE_OKHSL, it.h, it.s, it.l)
+            }
+            is ColorPickerValue.OkLchValue -> value.color.let {
+                Native.setSelection(ptr, SPACE_OKLCH, it.l, it.c, it.h)
+            }
+            is ColorPickerValue.Alpha ->
+                Native.setSelection(ptr, SPACE_ALPHA, value.alpha, 0f, 0f)
+            null -> Native.setSelection(ptr, SPACE_NONE, 0f, 0f, 0f)
+        }
+    }
+
+    /**
+     * Returns the value at ([x], [y]) on the last rendered slice, in coordinates
+     * normalized between 0 and 1, Y down, or `null` if nothing was rendered yet.
+     */
+    fun pick(x: Float, y: Float): ColorPickerValue? {
+        val arr = Native.pick(ptr, x, y)
+        if (arr.isEmpty()) return null
+        return when (arr[0].toInt()) {
+            SPACE_OKHSV -> ColorPickerValue.OkhsvValue(Okhsv(arr[1], arr[2], arr[3]))
+            SPACE_OKHSL -> ColorPickerValue.OkhslValue(Okhsl(arr[1], arr[2], arr[3]))
+            SPACE_OKLCH -> ColorPickerValue.OkLchValue(OkLch(arr[1], arr[2], arr[3]))
+            else -> ColorPickerValue.Alpha(arr[1])
+        }
     }
 
     override fun close() {
@@ -1,5 +1,6 @@
 package site.nyaalex.paint.rust
 
+import site.nyaalex.paint.core.color.Harmony
 import site.nyaalex.paint.core.color.LinearSrgb
 import site.nyaalex.paint.core.color.Okhsv
 
@@ -10,10 +11,15 @@ object ColorUtils {
         }
 
         external fun okhsvToLinearSrgb(h: Float, s: Float, v: Float): FloatArray
+
+        external fun harmonyHues(hue: Float, harmony: Int): FloatArray
     }
 
     fun okhsvToLinearSrgb(okhsv: Okhsv): LinearSrgb {
         val arr = Native.okhsvToLinearSrgb(okhsv.h, okhsv.s, okhsv.v)
         return LinearSrgb(arr[0], arr[1], arr[2]);
     }
+
+    fun harmonyHues(hue: Float, harmony: Harmony): FloatArray =
+        Native.harmonyHues(hue, harmony.ordinal)
 }
\ No newline at end of file
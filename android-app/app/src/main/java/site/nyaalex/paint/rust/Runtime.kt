@@ -1,9 +1,10 @@
 package site.nyaalex.paint.rust
 
 import java.io.Closeable
+import java.io.File
 
-class Runtime : Closeable {
-    internal var ptr: Long = Native.create()
+class Runtime(cacheDir: File) : Closeable {
+    internal var ptr: Long = Native.create(cacheDir.absolutePath)
         private set
 
     private object Native {
@@ -11,11 +12,17 @@ class Runtime : Closeable {
             System.loadLibrary("paint_android")
         }
 
-        external fun create(): Long
+        external fun create(cacheDir: String): Long
+
+        external fun saveCaches(ptr: Long)
 
         external fun destroy(ptr: Long)
     }
 
+    fun saveCaches() {
+        Native.saveCaches(ptr)
+    }
+
     override fun close() {
         if (ptr == 0L) return
         Native.destroy(ptr)
@@ -12,7 +12,11 @@ import site.nyaalex.paint.rust.ColorPickerRenderer
 import site.nyaalex.paint.rust.Surface
 
 @Composable
-fun ColorPickerSurface(slice: Slice, modifier: Modifier = Modifier) {
+fun ColorPickerSurface(
+    slice: Slice,
+    modifier: Modifier = Modifier,
+    selection: ColorPickerValue? = null
+) {
     val coreViewModel: CoreViewModel = viewModel()
 
     AndroidView(
@@ -20,7 +24,7 @@ fun ColorPickerSurface(slice: Slice, modifier: Modifier = Modifier) {
             ColorPickerSurfaceView(context)
         },
         update = { view ->
-            view.update(coreViewModel, slice)
+            view.update(coreViewModel, slice, selection)
         },
         modifier = modifier
     )
@@ -29,6 +33,7 @@ fun ColorPickerSurface(slice: Slice, modifier: Modifier = Modifier) {
 private class ColorPickerSurfaceView(context: Context) : SurfaceView(context) {
     private var coreViewModel: CoreViewModel? = null
     private var slice: Slice? = null
+    private var selection: ColorPickerValue? = null
     private var surface: Surface? = null
     private var renderer: ColorPickerRenderer? = null
 
@@ -60,9 +65,10 @@ private class ColorPickerSurfaceView(context: Context) : SurfaceView(context) {
         })
     }
 
-    fun update(viewModel: CoreViewModel, slice: Slice) {
+    fun update(viewModel: CoreViewModel, slice: Slice, selection: ColorPickerValue?) {
         this.coreViewModel = viewModel
         this.slice = slice
+        this.selection = selection
         render()
     }
 
@@ -71,11 +77,22 @@ private class ColorPickerSurfaceView(context: Context) : SurfaceView(context) {
         val surface = surface ?: return
         val slice = slice ?: return
 
+        renderer.setSelection(selection)
         when (slice) {
             is Slice.OkhsvHue ->
                 renderer.renderOkhsvHueSlice(surface, slice.hue)
             is Slice.OkhslHueVerticalGradient ->
-                renderer.renderOkhslHueVerticalGradient(surface)
+                renderer.renderOkhslHueVerticalGradient(surface, slice.markers)
+            is Slice.OkhslHue ->
+                renderer.renderOkhslHueSlice(surface, slice.hue)
+            is Slice.OkhslLightnessWheel ->
+                renderer.renderOkhslLightnessWheel(surface, slice.lightness)
+            is Slice.OkhsvHueRing ->
+                renderer.renderOkhsvHueRing(surface)
+            is Slice.OkLchChromaLightness ->
+                renderer.renderOkLchChromaLightnessPlane(surface, slice.hue)
+            is Slice.AlphaStrip ->
+                renderer.renderAlphaStrip(surface, slice.color)
         }
     }
 }
\ No newline at end of file
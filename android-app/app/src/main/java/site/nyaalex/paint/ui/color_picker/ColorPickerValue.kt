@@ -0,0 +1,17 @@
+package site.nyaalex.paint.ui.color_picker
+
+import site.nyaalex.paint.core.color.OkLch
+import site.nyaalex.paint.core.color.Okhsl
+import site.nyaalex.paint.core.color.Okhsv
+
+/** A value picked from a [Slice], mirroring `paint_core::presentation::ColorPickerValue`. */
+sealed class ColorPickerValue {
+    data class OkhsvValue(val color: Okhsv) : ColorPickerValue()
+
+    data class OkhslValue(val color: Okhsl) : ColorPickerValue()
+
+    data class OkLchValue(val color: OkLch) : ColorPickerValue()
+
+    /** Opacity, between 0 and 1. */
+    data class Alpha(val alpha: Float) : ColorPickerValue()
+}
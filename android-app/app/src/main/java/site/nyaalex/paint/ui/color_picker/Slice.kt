@@ -1,7 +1,20 @@
 package site.nyaalex.paint.ui.color_picker
 
+import site.nyaalex.paint.core.color.LinearSrgb
+
 sealed class Slice {
     class OkhsvHue(val hue: Float) : Slice()
 
-    object OkhslHueVerticalGradient : Slice()
+    /** Hue gradient, with [markers] as hues in radians. */
+    class OkhslHueVerticalGradient(val markers: FloatArray = FloatArray(0)) : Slice()
+
+    class OkhslHue(val hue: Float) : Slice()
+
+    class OkhslLightnessWheel(val lightness: Float) : Slice()
+
+    object OkhsvHueRing : Slice()
+
+    class OkLchChromaLightness(val hue: Float) : Slice()
+
+    class AlphaStrip(val color: LinearSrgb) : Slice()
 }
\ No newline at end of file
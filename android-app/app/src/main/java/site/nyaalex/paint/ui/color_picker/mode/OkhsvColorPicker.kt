@@ -46,7 +46,7 @@ fun OkhsvColorPicker(modifier: Modifier = Modifier) {
             Spacer(modifier = Modifier.width(32.dp))
 
             VerticalSelector(
-                Slice.OkhslHueVerticalGradient,
+                Slice.OkhslHueVerticalGradient(),
                 value = color.h / 2f / PI.toFloat(),
                 onChange = { color = color.copy(h = it * 2f * PI.toFloat()) },
                 modifier = Modifier
@@ -2,18 +2,28 @@ use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Weak};
 use std::thread::JoinHandle;
 
+use glam::UVec2;
 use paint_core::behaviour::{Action, BrushState, Event};
-use paint_core::presentation;
+use paint_core::color::BlendingSpace;
+use paint_core::liquify::{LiquifyMode, LiquifySettings};
+use paint_core::presentation::ViewFilter;
+use paint_core::shape::{Shape, ShapeKind, ShapeSettings, ShapeStyle};
+use paint_core::{persistence, presentation};
 use paint_wgpu::Texture;
 
 use crate::runtime::Runtime;
 use crate::surface::Surface;
 
 pub mod ffi {
-    use glam::{Affine2, UVec2, Vec2};
+    use glam::{Affine2, UVec2, Vec2, Vec3};
     use jni::JNIEnv;
-    use jni::objects::JObject;
+    use jni::objects::{JFloatArray, JIntArray, JObject, JObjectArray, JString};
+    use jni::sys::jboolean;
     use jni_fn::jni_fn;
+    use paint_core::adjustment::{Adjustment, AdjustmentLayer, Curve, Curves, Levels};
+    use paint_core::color::NonlinearSrgb;
+    use paint_core::filter::Filter;
+    use paint_core::text::{Font, TextAlignment, TextLayer};
 
     use super::*;
 
@@ -33,6 +43,349 @@ pub mod ffi {
         behaviour.handle_event(event);
     }
 
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setBlendingSpace(_env: JNIEnv, _this: JObject, ptr: usize, space: i32) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        // must match the ordinals of the Kotlin enum
+        let space = match space {
+            1 => BlendingSpace::GammaSrgb,
+            2 => BlendingSpace::Oklab,
+            _ => BlendingSpace::LinearLight,
+        };
+        behaviour.handle_event(Event::SetBlendingSpace(space));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setCanvasFormat(_env: JNIEnv, _this: JObject, ptr: usize, format: i32) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        // must match the ordinals of the Kotlin enum
+        let format = match format {
+            1 => persistence::TextureFormat::Rgba16LinearSrgb,
+            2 => persistence::TextureFormat::Rgba16FloatLinearSrgb,
+            _ => persistence::TextureFormat::Rgba8NonlinearSrgb,
+        };
+        behaviour.handle_event(Event::SetCanvasFormat(format));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setViewFilter(_env: JNIEnv, _this: JObject, ptr: usize, filter: i32, bands: u32) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        // must match the ordinals of the Kotlin enum
+        let filter = match filter {
+            1 => ViewFilter::Luminance,
+            2 => ViewFilter::Protanopia,
+            3 => ViewFilter::Deuteranopia,
+            4 => ViewFilter::Tritanopia,
+            5 => ViewFilter::ValueBands { bands },
+            _ => ViewFilter::None,
+        };
+        behaviour.handle_event(Event::SetViewFilter(filter));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setAdjustmentLayers(
+        env: JNIEnv,
+        _this: JObject,
+        ptr: usize,
+        kinds: JIntArray,
+        values: JFloatArray,
+    ) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+
+        let len = env.get_array_length(&kinds).unwrap();
+        let mut kinds_buf = vec![0; len as usize];
+        env.get_int_array_region(&kinds, 0, &mut kinds_buf).unwrap();
+
+        let len = env.get_array_length(&values).unwrap();
+        let mut values_buf = vec![0.0; len as usize];
+        env.get_float_array_region(&values, 0, &mut values_buf)
+            .unwrap();
+
+        let layers = adjustment_layers(&kinds_buf, &values_buf);
+        behaviour.handle_event(Event::SetAdjustmentLayers(layers));
+    }
+
+    /// Adjustment layers from their kinds and flattened values, as written by
+    /// `Behaviour.setAdjustmentLayers()` in Kotlin.
+    ///
+    /// Each layer has its opacity, its visibility as 0 or 1, and then the
+    /// parameters of its adjustment. A curve is its number of points,
+    /// followed by their X and Y coordinates.
+    fn adjustment_layers(kinds: &[i32], values: &[f32]) -> Vec<AdjustmentLayer> {
+        let mut values = values.iter().copied();
+        let mut next = || values.next().unwrap_or(0.0);
+
+        kinds
+            .iter()
+            .map(|&kind| {
+                let opacity = next();
+                let visible = next() != 0.0;
+                let mut curve = || {
+                    let len = next() as usize;
+                    Curve::new((0..len).map(|_| Vec2::new(next(), next())))
+                };
+
+                // must match the kinds written by Kotlin
+                let adjustment = match kind {
+                    0 => Adjustment::Levels(Levels {
+                        input_black: next(),
+                        input_white: next(),
+                        gamma: next(),
+                        output_black: next(),
+                        output_white: next(),
+                    }),
+                    1 => Adjustment::Curves(Curves {
+                        red: curve(),
+                        green: curve(),
+                        blue: curve(),
+                        lightness: curve(),
+                    }),
+                    2 => Adjustment::HueChromaLightness {
+                        hue: next(),
+                        chroma: next(),
+                        lightness: next(),
+                    },
+                    3 => Adjustment::ColorBalance {
+                        shadows: Vec3::new(next(), next(), next()),
+                        midtones: Vec3::new(next(), next(), next()),
+                        highlights: Vec3::new(next(), next(), next()),
+                    },
+                    _ => Adjustment::Invert,
+                };
+
+                AdjustmentLayer {
+                    adjustment,
+                    opacity,
+                    visible,
+                }
+            })
+            .collect()
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setTextLayers(
+        mut env: JNIEnv,
+        _this: JObject,
+        ptr: usize,
+        texts: JObjectArray,
+        ints: JIntArray,
+        floats: JFloatArray,
+    ) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+
+        // the font, the color and the alignment, then the size and the
+        // position of each layer
+        let len = env.get_array_length(&texts).unwrap();
+        let mut ints_buf = vec![0; 3 * len as usize];
+        env.get_int_array_region(&ints, 0, &mut ints_buf).unwrap();
+        let mut floats_buf = vec![0.0; 3 * len as usize];
+        env.get_float_array_region(&floats, 0, &mut floats_buf)
+            .unwrap();
+
+        let mut layers = Vec::with_capacity(len as usize);
+        for (i, (ints, floats)) in ints_buf
+            .chunks_exact(3)
+            .zip(floats_buf.chunks_exact(3))
+            .enumerate()
+        {
+            let text = JString::from(env.get_object_array_element(&texts, i as i32).unwrap());
+            let text: String = env.get_string(&text).unwrap().into();
+
+            // the Kotlin enum has the same order as `Font::ALL`
+            let font = Font::ALL.get(ints[0] as usize).copied().unwrap_or_default();
+            // an Android color int, the alpha is ignored
+            let [_, r, g, b] = ints[1].to_be_bytes();
+            // must match the ordinals of the Kotlin enum
+            let alignment = match ints[2] {
+                1 => TextAlignment::Center,
+                2 => TextAlignment::Right,
+                _ => TextAlignment::Left,
+            };
+
+            layers.push(TextLayer {
+                text,
+                font,
+                size: floats[0],
+                color: NonlinearSrgb::new(r, g, b),
+                alignment,
+                position: Vec2::new(floats[1], floats[2]),
+            });
+        }
+
+        behaviour.handle_event(Event::SetTextLayers(layers));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn rasterizeTextLayer(_env: JNIEnv, _this: JObject, ptr: usize, index: i32) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        // out of range indices, including negative ones, are ignored
+        behaviour.handle_event(Event::RasterizeTextLayer(index as usize));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn previewFilter(
+        _env: JNIEnv,
+        _this: JObject,
+        ptr: usize,
+        kind: i32,
+        a: f32,
+        b: f32,
+        c: f32,
+        seed: i32,
+        monochrome: jboolean,
+    ) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        // must match the kinds and parameters written by Kotlin
+        let filter = match kind {
+            0 => Filter::GaussianBlur { radius: a },
+            1 => Filter::BoxBlur {
+                radius: a.max(0.0).round() as u32,
+            },
+            2 => Filter::MotionBlur {
+                angle: a,
+                length: b,
+            },
+            3 => Filter::UnsharpMask {
+                radius: a,
+                amount: b,
+                threshold: c,
+            },
+            _ => Filter::Noise {
+                amount: a,
+                seed: seed as u32,
+                monochrome: monochrome != 0,
+            },
+        };
+        behaviour.handle_event(Event::PreviewFilter(filter));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn commitFilter(_env: JNIEnv, _this: JObject, ptr: usize) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        behaviour.handle_event(Event::CommitFilter);
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn cancelFilter(_env: JNIEnv, _this: JObject, ptr: usize) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        behaviour.handle_event(Event::CancelFilter);
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setLiquify(
+        _env: JNIEnv,
+        _this: JObject,
+        ptr: usize,
+        mode: i32,
+        radius: f32,
+        strength: f32,
+    ) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        // must match the ordinals of the Kotlin enum, negative for painting
+        let mode = match mode {
+            0 => Some(LiquifyMode::Push),
+            1 => Some(LiquifyMode::Twirl { clockwise: false }),
+            2 => Some(LiquifyMode::Twirl { clockwise: true }),
+            3 => Some(LiquifyMode::Pinch),
+            4 => Some(LiquifyMode::Bloat),
+            5 => Some(LiquifyMode::Reconstruct),
+            _ => None,
+        };
+        let settings = mode.map(|mode| LiquifySettings {
+            mode,
+            radius,
+            strength,
+        });
+        behaviour.handle_event(Event::SetLiquify(settings));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn setShapeTool(
+        _env: JNIEnv,
+        _this: JObject,
+        ptr: usize,
+        kind: i32,
+        sides: u32,
+        stroke_width: f32,
+    ) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        let settings = shape_settings(kind, sides, stroke_width);
+        behaviour.handle_event(Event::SetShapeTool(settings));
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn editShape(
+        _env: JNIEnv,
+        _this: JObject,
+        ptr: usize,
+        kind: i32,
+        sides: u32,
+        stroke_width: f32,
+        start_x: f32,
+        start_y: f32,
+        end_x: f32,
+        end_y: f32,
+    ) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        let Some(settings) = shape_settings(kind, sides, stroke_width) else {
+            return;
+        };
+        let event = Event::EditShape(Shape {
+            settings,
+            start: Vec2::new(start_x, start_y),
+            end: Vec2::new(end_x, end_y),
+        });
+        behaviour.handle_event(event);
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn commitShape(_env: JNIEnv, _this: JObject, ptr: usize) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        behaviour.handle_event(Event::CommitShape);
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
+    pub fn cancelShape(_env: JNIEnv, _this: JObject, ptr: usize) {
+        let behaviour = unsafe { &*(ptr as *const Behaviour) };
+        behaviour.handle_event(Event::CancelShape);
+    }
+
+    /// Shape settings from the ordinal of the Kotlin enum, negative for
+    /// painting, filled unless the stroke width is positive.
+    fn shape_settings(kind: i32, sides: u32, stroke_width: f32) -> Option<ShapeSettings> {
+        let kind = match kind {
+            0 => ShapeKind::Line,
+            1 => ShapeKind::Rectangle,
+            2 => ShapeKind::Ellipse,
+            3 => ShapeKind::Polygon { sides },
+            _ => return None,
+        };
+        let style = if stroke_width > 0.0 {
+            ShapeStyle::Stroke {
+                width: stroke_width,
+            }
+        } else {
+            ShapeStyle::Fill
+        };
+        Some(ShapeSettings { kind, style })
+    }
+
     #[unsafe(no_mangle)]
     #[jni_fn("site.nyaalex.paint.rust.Behaviour$Native")]
     pub fn setViewportTransform(
@@ -112,6 +465,10 @@ impl paint_core::behaviour::Impls for Impls {
     type Compositor = paint_wgpu::Compositor;
     type BrushEngine = paint_wgpu::BrushEngine;
     type BrushStroke = paint_wgpu::BrushStroke;
+    type LiquifyEngine = paint_wgpu::LiquifyEngine;
+    type LiquifyStroke = paint_wgpu::LiquifyStroke;
+    type ShapeEngine = paint_wgpu::ShapeEngine;
+    type ShapeDrawing = paint_wgpu::ShapeDrawing;
 }
 
 type BehaviourImpl = paint_behaviour::Behaviour<Impls>;
@@ -177,9 +534,16 @@ struct BehaviourThread {
 
 impl BehaviourThread {
     pub fn new(runtime: &Runtime, command_receiver: Receiver<Command>) -> Self {
-        let compositor = paint_wgpu::Compositor::new(runtime.context.clone());
+        let compositor = paint_wgpu::Compositor::new(
+            runtime.context.clone(),
+            UVec2::new(2304, 1440),
+            persistence::TextureFormat::Rgba8NonlinearSrgb,
+        );
         let brush_engine = paint_wgpu::BrushEngine::new(runtime.context.clone());
-        let behaviour_impl = BehaviourImpl::new(compositor, brush_engine);
+        let liquify_engine = paint_wgpu::LiquifyEngine::new(runtime.context.clone());
+        let shape_engine = paint_wgpu::ShapeEngine::new(runtime.context.clone());
+        let behaviour_impl =
+            BehaviourImpl::new(compositor, brush_engine, liquify_engine, shape_engine);
         let frame_context = LazyFrameContext::new(runtime.context.clone());
         let viewport_renderer = runtime.viewport_renderer.clone();
 
@@ -2,7 +2,7 @@ pub mod ffi {
     use jni::JNIEnv;
     use jni::objects::{JFloatArray, JObject};
     use jni_fn::jni_fn;
-    use paint_core::color::{Color, Okhsv};
+    use paint_core::color::{Color, Harmony, Okhsv};
 
     #[unsafe(no_mangle)]
     #[jni_fn("site.nyaalex.paint.rust.ColorUtils$Native")]
@@ -20,4 +20,19 @@ pub mod ffi {
             .unwrap();
         array
     }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorUtils$Native")]
+    pub fn harmonyHues<'env>(
+        env: JNIEnv<'env>,
+        _this: JObject,
+        hue: f32,
+        harmony: i32,
+    ) -> JFloatArray<'env> {
+        // the Kotlin enum has the same order as `Harmony::ALL`
+        let hues = Harmony::ALL[harmony as usize].hues(hue);
+        let array = env.new_float_array(hues.len() as i32).unwrap();
+        env.set_float_array_region(&array, 0, &hues).unwrap();
+        array
+    }
 }
@@ -1,5 +1,7 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use glam::Vec2;
+use paint_core::color::{LinearSrgb, OkLch, Okhsl, Okhsv};
 use paint_core::presentation;
 
 use crate::runtime::Runtime;
@@ -7,7 +9,7 @@ use crate::surface::Surface;
 
 pub mod ffi {
     use jni::JNIEnv;
-    use jni::objects::JObject;
+    use jni::objects::{JFloatArray, JObject};
     use jni_fn::jni_fn;
 
     use super::*;
@@ -40,19 +42,168 @@ pub mod ffi {
     #[unsafe(no_mangle)]
     #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
     pub fn renderOkhslHueVerticalGradient(
+        env: JNIEnv,
+        _this: JObject,
+        this_ptr: usize,
+        surface_ptr: usize,
+        markers: JFloatArray,
+    ) {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+        let surface = unsafe { &*(surface_ptr as *const Arc<Surface>) };
+
+        let len = env.get_array_length(&markers).unwrap();
+        let mut markers_buf = vec![0.0; len as usize];
+        env.get_float_array_region(&markers, 0, &mut markers_buf)
+            .unwrap();
+
+        this.render(
+            surface,
+            presentation::ColorPickerSlice::OkhslHueVerticalGradient {
+                markers: markers_buf,
+            },
+        );
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn renderOkhslHueSlice(
         _env: JNIEnv,
         _this: JObject,
         this_ptr: usize,
         surface_ptr: usize,
+        hue: f32,
     ) {
         let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
         let surface = unsafe { &*(surface_ptr as *const Arc<Surface>) };
         this.render(
             surface,
-            presentation::ColorPickerSlice::OkhslHueVerticalGradient,
+            presentation::ColorPickerSlice::OkhslHueSlice { hue },
         );
     }
 
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn renderOkhslLightnessWheel(
+        _env: JNIEnv,
+        _this: JObject,
+        this_ptr: usize,
+        surface_ptr: usize,
+        lightness: f32,
+    ) {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+        let surface = unsafe { &*(surface_ptr as *const Arc<Surface>) };
+        this.render(
+            surface,
+            presentation::ColorPickerSlice::OkhslLightnessWheel { lightness },
+        );
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn renderOkhsvHueRing(_env: JNIEnv, _this: JObject, this_ptr: usize, surface_ptr: usize) {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+        let surface = unsafe { &*(surface_ptr as *const Arc<Surface>) };
+        this.render(surface, presentation::ColorPickerSlice::OkhsvHueRing);
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn renderOkLchChromaLightnessPlane(
+        _env: JNIEnv,
+        _this: JObject,
+        this_ptr: usize,
+        surface_ptr: usize,
+        hue: f32,
+    ) {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+        let surface = unsafe { &*(surface_ptr as *const Arc<Surface>) };
+        this.render(
+            surface,
+            presentation::ColorPickerSlice::OkLchChromaLightnessPlane { hue },
+        );
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn renderAlphaStrip(
+        _env: JNIEnv,
+        _this: JObject,
+        this_ptr: usize,
+        surface_ptr: usize,
+        r: f32,
+        g: f32,
+        b: f32,
+    ) {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+        let surface = unsafe { &*(surface_ptr as *const Arc<Surface>) };
+        this.render(
+            surface,
+            presentation::ColorPickerSlice::AlphaStrip {
+                color: LinearSrgb::new(r, g, b),
+            },
+        );
+    }
+
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn setSelection(
+        _env: JNIEnv,
+        _this: JObject,
+        this_ptr: usize,
+        space: i32,
+        c0: f32,
+        c1: f32,
+        c2: f32,
+    ) {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+        let value = match space {
+            SPACE_OKHSV => Some(presentation::ColorPickerValue::Okhsv(Okhsv::new(
+                c0, c1, c2,
+            ))),
+            SPACE_OKHSL => Some(presentation::ColorPickerValue::Okhsl(Okhsl::new(
+                c0, c1, c2,
+            ))),
+            SPACE_OKLCH => Some(presentation::ColorPickerValue::OkLch(OkLch::new(
+                c0, c1, c2,
+            ))),
+            SPACE_ALPHA => Some(presentation::ColorPickerValue::Alpha(c0)),
+            _ => None,
+        };
+        this.set_selection(value);
+    }
+
+    /// Returns `[space, c0, c1, c2]` for the value at the normalized point,
+    /// or an empty array if nothing was rendered yet.
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
+    pub fn pick<'env>(
+        env: JNIEnv<'env>,
+        _this: JObject,
+        this_ptr: usize,
+        x: f32,
+        y: f32,
+    ) -> JFloatArray<'env> {
+        let this = unsafe { &*(this_ptr as *const ColorPickerRenderer) };
+
+        let values = match this.pick(Vec2::new(x, y)) {
+            Some(presentation::ColorPickerValue::Okhsv(c)) => {
+                vec![SPACE_OKHSV as f32, c.h, c.s, c.v]
+            }
+            Some(presentation::ColorPickerValue::Okhsl(c)) => {
+                vec![SPACE_OKHSL as f32, c.h, c.s, c.l]
+            }
+            Some(presentation::ColorPickerValue::OkLch(c)) => {
+                vec![SPACE_OKLCH as f32, c.l, c.c, c.h]
+            }
+            Some(presentation::ColorPickerValue::Alpha(a)) => vec![SPACE_ALPHA as f32, a, 0.0, 0.0],
+            None => vec![],
+        };
+
+        let array = env.new_float_array(values.len() as i32).unwrap();
+        env.set_float_array_region(&array, 0, &values).unwrap();
+        array
+    }
+
     #[unsafe(no_mangle)]
     #[jni_fn("site.nyaalex.paint.rust.ColorPickerRenderer$Native")]
     pub fn destroy(_env: JNIEnv, _this: JObject, ptr: usize) {
@@ -62,9 +213,18 @@ pub mod ffi {
     }
 }
 
+// color spaces of picked values, matching `ColorPickerRenderer.kt`
+const SPACE_OKHSV: i32 = 0;
+const SPACE_OKHSL: i32 = 1;
+const SPACE_OKLCH: i32 = 2;
+const SPACE_ALPHA: i32 = 3;
+
 pub struct ColorPickerRenderer {
     global_context: Arc<paint_wgpu::GlobalContext>,
     inner: Arc<paint_wgpu::ColorPickerRenderer>,
+    /// Last rendered slice, to pick values from.
+    slice: Mutex<Option<presentation::ColorPickerSlice>>,
+    selection: Mutex<Option<presentation::ColorPickerValue>>,
 }
 
 impl ColorPickerRenderer {
@@ -72,13 +232,30 @@ impl ColorPickerRenderer {
         Self {
             global_context: runtime.context.clone(),
             inner: runtime.color_picker_renderer.clone(),
+            slice: Mutex::new(None),
+            selection: Mutex::new(None),
         }
     }
 
     pub fn render(&self, surface: &Surface, slice: presentation::ColorPickerSlice) {
+        *self.slice.lock().unwrap() = Some(slice.clone());
+        let selection = *self.selection.lock().unwrap();
+
         let ctx = paint_wgpu::FrameContext::new(&self.global_context);
         surface.render(|target| {
-            self.inner.render(ctx, target, slice);
+            self.inner.render(ctx, target, slice, selection);
         });
     }
+
+    /// Sets the value marked on the next rendered slice.
+    pub fn set_selection(&self, value: Option<presentation::ColorPickerValue>) {
+        *self.selection.lock().unwrap() = value;
+    }
+
+    /// Returns the value at `point` on the last rendered slice, in
+    /// coordinates normalized between 0 and 1, Y down.
+    pub fn pick(&self, point: Vec2) -> Option<presentation::ColorPickerValue> {
+        let slice = self.slice.lock().unwrap();
+        slice.as_ref().map(|slice| slice.value_at(point))
+    }
 }
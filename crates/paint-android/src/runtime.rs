@@ -1,21 +1,30 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use futures_lite::future::block_on;
 
 mod ffi {
     use jni::JNIEnv;
-    use jni::objects::JObject;
+    use jni::objects::{JObject, JString};
     use jni_fn::jni_fn;
 
     use super::*;
 
     #[unsafe(no_mangle)]
     #[jni_fn("site.nyaalex.paint.rust.Runtime$Native")]
-    pub fn create(_env: JNIEnv, _this: JObject) -> usize {
-        let runtime = Runtime::new();
+    pub fn create(mut env: JNIEnv, _this: JObject, cache_dir: JString) -> usize {
+        let cache_dir: String = env.get_string(&cache_dir).unwrap().into();
+        let runtime = Runtime::new(PathBuf::from(cache_dir));
         Box::into_raw(Box::new(runtime)) as usize
     }
 
+    #[unsafe(no_mangle)]
+    #[jni_fn("site.nyaalex.paint.rust.Runtime$Native")]
+    pub fn saveCaches(_env: JNIEnv, _this: JObject, ptr: usize) {
+        let runtime = unsafe { &*(ptr as *const Runtime) };
+        runtime.context.save_pipeline_cache();
+    }
+
     #[unsafe(no_mangle)]
     #[jni_fn("site.nyaalex.paint.rust.Runtime$Native")]
     pub fn destroy(_env: JNIEnv, _this: JObject, ptr: usize) {
@@ -36,7 +45,7 @@ pub struct Runtime {
 }
 
 impl Runtime {
-    pub fn new() -> Self {
+    pub fn new(cache_dir: PathBuf) -> Self {
         let start_time = std::time::Instant::now();
         tracing::info!("Initializing runtime");
 
@@ -51,16 +60,31 @@ impl Runtime {
         let info = adapter.get_info();
         tracing::info!("Adapter info: {info:#?}");
 
+        let optional_features = paint_wgpu::get_optional_wgpu_features() & adapter.features();
+
         let device_fut = adapter.request_device(&wgpu::DeviceDescriptor {
-            required_features: paint_wgpu::get_required_wgpu_features(),
+            required_features: paint_wgpu::get_required_wgpu_features() | optional_features,
             required_limits: paint_wgpu::get_required_wgpu_limits(),
             ..Default::default()
         });
         let (device, queue) = block_on(device_fut).unwrap();
 
-        let context = Arc::new(paint_wgpu::GlobalContext::new(device.clone(), queue));
-
-        let viewport_renderer = Arc::new(paint_wgpu::ViewportRenderer::new(context.clone()));
+        let pipeline_cache = paint_wgpu::PipelineCacheSettings {
+            directory: cache_dir,
+            adapter_info: info,
+        };
+
+        let context = Arc::new(paint_wgpu::GlobalContext::new(
+            &adapter,
+            device.clone(),
+            queue,
+            Some(pipeline_cache),
+        ));
+
+        let viewport_renderer = Arc::new(paint_wgpu::ViewportRenderer::new(
+            context.clone(),
+            paint_wgpu::ViewportSettings::default(),
+        ));
         let color_picker_renderer = Arc::new(paint_wgpu::ColorPickerRenderer::new(context.clone()));
 
         tracing::info!("Finished initialization in {:?}", start_time.elapsed());
@@ -75,9 +99,3 @@ impl Runtime {
         }
     }
 }
-
-impl Default for Runtime {
-    fn default() -> Self {
-        Self::new()
-    }
-}
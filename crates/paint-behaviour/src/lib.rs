@@ -1,33 +1,113 @@
 use glam::{Affine2, UVec2};
 use paint_core::behaviour::{
-    Action, BrushEngine, BrushStroke, Compositor, Event, Impls, StrokeSettings,
+    Action, BrushEngine, BrushStroke, Compositor, Event, Impls, LiquifyEngine, ShapeDrawing,
+    ShapeEngine, StrokeSettings, Texture as _,
 };
+use paint_core::color::BlendingSpace;
+use paint_core::filter::Filter;
+use paint_core::geometry::{DamageRegion, Rect};
+use paint_core::liquify::LiquifySettings;
 use paint_core::presentation;
+use paint_core::shape::{Shape, ShapeSettings};
+use paint_core::text::TextLayer;
 
 pub struct Behaviour<I: Impls> {
     state: State<I>,
     compositor: I::Compositor,
     brush_engine: I::BrushEngine,
+    liquify_engine: I::LiquifyEngine,
+    shape_engine: I::ShapeEngine,
 }
 
 struct State<I: Impls> {
+    /// Whether the whole viewport has to be presented again.
     viewport_dirty: bool,
+    /// Area of the canvas which has changed since the viewport was last
+    /// presented, when the rest of the viewport is still up to date.
+    canvas_damage: DamageRegion,
     canvas_resolution: UVec2,
     viewport_transform: Affine2,
-    brush_stroke: Option<I::BrushStroke>,
+    blending_space: BlendingSpace,
+    view_filter: presentation::ViewFilter,
+    tool: Tool,
+    stroke: Option<Stroke<I>>,
+    /// Shape which is still editable, until committed or cancelled.
+    shape: Option<EditableShape<I>>,
+    /// Filter previewed on the canvas, until committed or cancelled.
+    filter: Option<Filter>,
+    /// Text layers from bottom to top.
+    text_layers: Vec<RasterizedTextLayer<I>>,
+}
+
+/// What brush strokes do.
+#[derive(Debug, Clone, Copy)]
+enum Tool {
+    Brush,
+    Liquify(LiquifySettings),
+    Shape(ShapeSettings),
+}
+
+/// A stroke in progress.
+enum Stroke<I: Impls> {
+    Brush(I::BrushStroke),
+    Liquify(I::LiquifyStroke),
+    /// Dragging out the editable shape.
+    Shape(ShapeSettings),
+}
+
+struct RasterizedTextLayer<I: Impls> {
+    layer: TextLayer,
+    /// Resolution of the canvas which the text is clipped to.
+    canvas_resolution: UVec2,
+    /// The rasterized text and the area it covers, unless nothing is
+    /// visible.
+    texture: Option<(I::Texture, Rect)>,
+}
+
+impl<I: Impls> RasterizedTextLayer<I> {
+    fn new(ctx: &mut I::Context, layer: TextLayer, canvas_resolution: UVec2) -> Self {
+        let texture = layer
+            .rasterize(canvas_resolution)
+            .map(|text| (I::Texture::upload(ctx, text.as_persistence()), text.rect()));
+        Self {
+            layer,
+            canvas_resolution,
+            texture,
+        }
+    }
+}
+
+struct EditableShape<I: Impls> {
+    drawing: I::ShapeDrawing,
+    /// The shape, once the stroke drawing it has a position.
+    shape: Option<Shape>,
 }
 
 impl<I: Impls> Behaviour<I> {
-    pub fn new(compositor: I::Compositor, brush_engine: I::BrushEngine) -> Self {
+    pub fn new(
+        compositor: I::Compositor,
+        brush_engine: I::BrushEngine,
+        liquify_engine: I::LiquifyEngine,
+        shape_engine: I::ShapeEngine,
+    ) -> Self {
         Self {
             state: State {
                 viewport_dirty: true,
+                canvas_damage: DamageRegion::new(),
                 canvas_resolution: UVec2::new(2304, 1440),
                 viewport_transform: Affine2::IDENTITY,
-                brush_stroke: None,
+                blending_space: BlendingSpace::default(),
+                view_filter: presentation::ViewFilter::default(),
+                tool: Tool::Brush,
+                stroke: None,
+                shape: None,
+                filter: None,
+                text_layers: Vec::new(),
             },
             compositor,
             brush_engine,
+            liquify_engine,
+            shape_engine,
         }
     }
 
@@ -47,33 +127,259 @@ impl<I: Impls> Behaviour<I> {
                 self.state.viewport_dirty = true;
             }
 
-            Event::BeginBrushStroke => {
-                self.state.brush_stroke = Some(self.brush_engine.begin_stroke(&StrokeSettings {
-                    canvas_resolution: self.state.canvas_resolution,
-                }));
+            Event::SetBlendingSpace(space) => {
+                self.state.blending_space = space;
+                self.state.viewport_dirty = true;
             }
 
-            Event::UpdateBrushStroke(state) => {
-                if let Some(stroke) = &mut self.state.brush_stroke {
-                    stroke.update(&state);
+            Event::SetCanvasFormat(format) => {
+                if format == self.compositor.format() {
+                    return;
+                }
+
+                if !self.compositor.is_format_supported(format) {
+                    tracing::warn!("Canvas format {format:?} is not supported on this device");
+                    return;
+                }
+
+                // the editable shape is drawn in the previous format
+                self.commit_shape(ctx);
+                self.compositor.set_format(ctx, format);
+                self.state.viewport_dirty = true;
+            }
+
+            Event::SetViewFilter(filter) => {
+                self.state.view_filter = filter;
+                self.state.viewport_dirty = true;
+            }
+
+            Event::SetAdjustmentLayers(layers) => {
+                self.compositor.set_adjustment_layers(ctx, &layers);
+                self.state.viewport_dirty = true;
+            }
+
+            Event::SetTextLayers(layers) => {
+                let resolution = self.state.canvas_resolution;
+                let mut previous = std::mem::take(&mut self.state.text_layers).into_iter();
+                self.state.text_layers = layers
+                    .into_iter()
+                    .map(|layer| match previous.next() {
+                        Some(rasterized)
+                            if rasterized.layer == layer
+                                && rasterized.canvas_resolution == resolution =>
+                        {
+                            rasterized
+                        }
+                        _ => RasterizedTextLayer::new(ctx, layer, resolution),
+                    })
+                    .collect();
+                self.state.viewport_dirty = true;
+            }
+
+            Event::RasterizeTextLayer(index) => {
+                if index >= self.state.text_layers.len() {
+                    return;
+                }
+
+                // the text is on the canvas from now on, so it can't be
+                // edited anymore
+                let rasterized = self.state.text_layers.remove(index);
+                if let Some(text) = rasterized.layer.rasterize(self.state.canvas_resolution) {
+                    let texture =
+                        I::Texture::upload(ctx, text.to_canvas(self.state.canvas_resolution));
+                    self.compositor.put_texture(
+                        ctx,
+                        texture,
+                        &DamageRegion::from_rect(text.rect()),
+                        self.state.blending_space,
+                    );
+                }
+                self.state.viewport_dirty = true;
+            }
+
+            Event::PreviewFilter(filter) => {
+                // the preview would hide the shape
+                self.commit_shape(ctx);
+                self.state.filter = Some(filter);
+                self.state.viewport_dirty = true;
+            }
+
+            Event::CommitFilter => {
+                if let Some(filter) = self.state.filter.take() {
+                    self.compositor.apply_filter(ctx, &filter);
                     self.state.viewport_dirty = true;
                 }
             }
 
-            Event::EndBrushStroke => {
-                if let Some(mut stroke) = self.state.brush_stroke.take() {
-                    let stroke_texture = stroke.render(ctx);
-                    self.compositor.put_texture(ctx, stroke_texture);
+            Event::CancelFilter => {
+                if self.state.filter.take().is_some() {
                     self.state.viewport_dirty = true;
                 }
             }
+
+            Event::SetLiquify(settings) => match settings {
+                Some(settings) => self.state.tool = Tool::Liquify(settings),
+                None if matches!(self.state.tool, Tool::Liquify(_)) => {
+                    self.state.tool = Tool::Brush;
+                }
+                None => {}
+            },
+
+            Event::SetShapeTool(settings) => match settings {
+                Some(settings) => self.state.tool = Tool::Shape(settings),
+                None if matches!(self.state.tool, Tool::Shape(_)) => {
+                    self.state.tool = Tool::Brush;
+                }
+                None => {}
+            },
+
+            Event::EditShape(shape) => {
+                if let Some(editable) = &mut self.state.shape {
+                    // the previous shape has to disappear as well
+                    self.state
+                        .canvas_damage
+                        .add_region(editable.drawing.damage());
+                    editable.drawing.set_shape(&shape);
+                    editable.shape = Some(shape);
+                    self.state
+                        .canvas_damage
+                        .add_region(editable.drawing.damage());
+                }
+            }
+
+            Event::CommitShape => {
+                self.commit_shape(ctx);
+            }
+
+            Event::CancelShape => {
+                if let Some(editable) = self.state.shape.take() {
+                    self.state
+                        .canvas_damage
+                        .add_region(editable.drawing.damage());
+                }
+            }
+
+            Event::BeginBrushStroke => {
+                // painting over a filter preview would lose the stroke or
+                // filter the stroke as well
+                if self.state.filter.take().is_some() {
+                    self.state.viewport_dirty = true;
+                }
+
+                self.commit_shape(ctx);
+
+                let settings = StrokeSettings {
+                    canvas_resolution: self.state.canvas_resolution,
+                    canvas_format: self.compositor.format(),
+                    blending_space: self.state.blending_space,
+                };
+
+                self.state.stroke = Some(match self.state.tool {
+                    Tool::Brush => Stroke::Brush(self.brush_engine.begin_stroke(&settings)),
+                    Tool::Liquify(liquify) => {
+                        Stroke::Liquify(self.liquify_engine.begin_stroke(&settings, &liquify))
+                    }
+                    Tool::Shape(shape) => {
+                        self.state.shape = Some(EditableShape {
+                            drawing: self.shape_engine.begin_drawing(&settings),
+                            shape: None,
+                        });
+                        Stroke::Shape(shape)
+                    }
+                });
+            }
+
+            Event::UpdateBrushStroke(state) => match &mut self.state.stroke {
+                Some(Stroke::Brush(stroke)) => {
+                    stroke.update(&state);
+                    self.state.canvas_damage.add_region(stroke.damage());
+                }
+                Some(Stroke::Liquify(stroke)) => {
+                    stroke.update(&state);
+                    self.state.canvas_damage.add_region(stroke.damage());
+                }
+                Some(Stroke::Shape(settings)) => {
+                    let Some(editable) = &mut self.state.shape else {
+                        return;
+                    };
+
+                    let shape = match editable.shape {
+                        Some(shape) => Shape {
+                            end: state.position,
+                            ..shape
+                        },
+                        None => Shape::new(*settings, state.position),
+                    };
+                    self.state
+                        .canvas_damage
+                        .add_region(editable.drawing.damage());
+                    editable.drawing.set_shape(&shape);
+                    editable.shape = Some(shape);
+                    self.state
+                        .canvas_damage
+                        .add_region(editable.drawing.damage());
+                }
+                None => {}
+            },
+
+            Event::EndBrushStroke => match self.state.stroke.take() {
+                Some(Stroke::Brush(mut stroke)) => {
+                    let stroke_texture = stroke.render(ctx);
+                    self.compositor.put_texture(
+                        ctx,
+                        stroke_texture,
+                        stroke.damage(),
+                        self.state.blending_space,
+                    );
+                    self.state.canvas_damage.add_region(stroke.damage());
+                }
+                Some(Stroke::Liquify(mut stroke)) => {
+                    let field = stroke.render(ctx);
+                    self.compositor
+                        .put_displacement(ctx, field, stroke.damage());
+                    self.state.canvas_damage.add_region(stroke.damage());
+                }
+                // the shape stays editable, unless the stroke never had a
+                // position
+                Some(Stroke::Shape(_))
+                    if self
+                        .state
+                        .shape
+                        .as_ref()
+                        .is_some_and(|editable| editable.shape.is_none()) =>
+                {
+                    self.state.shape = None;
+                }
+                Some(Stroke::Shape(_)) | None => {}
+            },
+        }
+    }
+
+    /// Draws the editable shape onto the canvas, if there's one.
+    fn commit_shape(&mut self, ctx: &mut I::Context) {
+        let Some(mut editable) = self.state.shape.take() else {
+            return;
+        };
+
+        if editable.shape.is_some() {
+            let texture = editable.drawing.render(ctx);
+            self.compositor.put_texture(
+                ctx,
+                texture,
+                editable.drawing.damage(),
+                self.state.blending_space,
+            );
+            self.state
+                .canvas_damage
+                .add_region(editable.drawing.damage());
         }
     }
 
     pub fn perform_action(&mut self, ctx: &mut I::Context) -> Option<Action<I>> {
-        if self.state.viewport_dirty {
+        if self.state.viewport_dirty || !self.state.canvas_damage.is_empty() {
             let viewport = self.present_viewport(ctx);
             self.state.viewport_dirty = false;
+            self.state.canvas_damage.clear();
             return Some(Action::PresentViewport(viewport));
         }
 
@@ -83,13 +389,47 @@ impl<I: Impls> Behaviour<I> {
     fn present_viewport(&mut self, ctx: &mut I::Context) -> presentation::Viewport<I::Texture> {
         let mut layers = Vec::new();
 
-        let composite = self.compositor.render(ctx);
+        // the stroke in progress is blended by the compositor as well, so it
+        // looks the same before and after it's finished
+        let composite = if let Some(Stroke::Brush(stroke)) = &mut self.state.stroke {
+            let texture = stroke.render(ctx);
+            self.compositor.preview_texture(
+                ctx,
+                texture,
+                stroke.damage(),
+                self.state.blending_space,
+            )
+        } else if let Some(Stroke::Liquify(stroke)) = &mut self.state.stroke {
+            let field = stroke.render(ctx);
+            self.compositor
+                .preview_displacement(ctx, field, stroke.damage())
+        } else if let Some(EditableShape {
+            drawing,
+            shape: Some(_),
+        }) = &mut self.state.shape
+        {
+            let texture = drawing.render(ctx);
+            self.compositor.preview_texture(
+                ctx,
+                texture,
+                drawing.damage(),
+                self.state.blending_space,
+            )
+        } else if let Some(filter) = &self.state.filter {
+            self.compositor.preview_filter(ctx, filter)
+        } else {
+            self.compositor.render(ctx)
+        };
+
         layers.push(presentation::Layer::Texture(composite));
 
-        if let Some(stroke) = &mut self.state.brush_stroke {
-            let texture = stroke.render(ctx);
-            layers.push(presentation::Layer::Texture(texture));
-        }
+        layers.extend(
+            self.state
+                .text_layers
+                .iter()
+                .filter_map(|text| text.texture.clone())
+                .map(|(texture, rect)| presentation::Layer::Placed { texture, rect }),
+        );
 
         presentation::Viewport {
             transform: self.state.viewport_transform,
@@ -97,6 +437,8 @@ impl<I: Impls> Behaviour<I> {
                 resolution: self.state.canvas_resolution,
                 layers,
             },
+            filter: self.state.view_filter,
+            damage: (!self.state.viewport_dirty).then(|| self.state.canvas_damage.clone()),
         }
     }
 }
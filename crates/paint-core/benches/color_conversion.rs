@@ -0,0 +1,89 @@
+//! Compares converting a pixel buffer one color at a time with the batch
+//! conversions from [`paint_core::color::batch`].
+//!
+//! Run with `cargo bench -p paint-core --bench color_conversion`.
+
+use std::time::{Duration, Instant};
+
+use paint_core::color::{Color, GamutMapping, LinearSrgb, NonlinearSrgb, Oklab, batch};
+
+const PIXELS: usize = 1 << 20;
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let srgb: Vec<_> = (0..PIXELS as u32)
+        .map(|i| NonlinearSrgb::new(i as u8, (i >> 8) as u8, (i >> 16) as u8))
+        .collect();
+    let mut linear = vec![LinearSrgb::default(); PIXELS];
+    let mut lab = vec![Oklab::default(); PIXELS];
+    let mut out = vec![NonlinearSrgb::default(); PIXELS];
+
+    batch::decode_srgb_u8(&srgb, &mut linear);
+    batch::linear_srgb_to_oklab(&linear, &mut lab);
+
+    let scalar_time = measure(|| {
+        for (s, d) in srgb.iter().zip(linear.iter_mut()) {
+            *d = s.to_linear_srgb();
+        }
+    });
+    let batch_time = measure(|| batch::decode_srgb_u8(&srgb, &mut linear));
+    report("sRGB u8 to linear", scalar_time, batch_time);
+
+    let scalar_time = measure(|| {
+        for (s, d) in linear.iter().zip(out.iter_mut()) {
+            *d = NonlinearSrgb::from_linear_srgb(*s);
+        }
+    });
+    let batch_time = measure(|| batch::encode_srgb_u8(&linear, &mut out));
+    report("linear to sRGB u8", scalar_time, batch_time);
+
+    let scalar_time = measure(|| {
+        for (s, d) in linear.iter().zip(lab.iter_mut()) {
+            *d = Oklab::from_linear_srgb(*s);
+        }
+    });
+    let batch_time = measure(|| batch::linear_srgb_to_oklab(&linear, &mut lab));
+    report("linear to Oklab", scalar_time, batch_time);
+
+    let scalar_time = measure(|| {
+        for (s, d) in srgb.iter().zip(lab.iter_mut()) {
+            *d = Oklab::from_linear_srgb(s.to_linear_srgb());
+        }
+    });
+    let batch_time = measure(|| batch::srgb_u8_to_oklab(&srgb, &mut lab));
+    report("sRGB u8 to Oklab", scalar_time, batch_time);
+
+    let scalar_time = measure(|| {
+        for (s, d) in lab.iter().zip(out.iter_mut()) {
+            *d = NonlinearSrgb::from_linear_srgb(s.to_linear_srgb_clamped());
+        }
+    });
+    let batch_time = measure(|| batch::oklab_to_srgb_u8(&lab, &mut out, GamutMapping::default()));
+    report("Oklab to sRGB u8", scalar_time, batch_time);
+}
+
+fn report(name: &str, scalar_time: Duration, batch_time: Duration) {
+    println!(
+        "{name}: scalar {:.2} ns/px, batch {:.2} ns/px, {:.1}x",
+        nanos_per_pixel(scalar_time),
+        nanos_per_pixel(batch_time),
+        scalar_time.as_secs_f64() / batch_time.as_secs_f64(),
+    );
+}
+
+fn measure(mut f: impl FnMut()) -> Duration {
+    // warm up caches and lookup tables
+    f();
+
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        f();
+    }
+
+    start.elapsed() / ITERATIONS
+}
+
+fn nanos_per_pixel(time: Duration) -> f64 {
+    time.as_nanos() as f64 / PIXELS as f64
+}
@@ -0,0 +1,507 @@
+//! Non-destructive color adjustments, applied to everything below them.
+
+use glam::{Vec2, Vec3};
+
+use crate::color::{Color, GamutMapping, LinearSrgb, NonlinearSrgb, OkLch, Oklab};
+
+/// A layer above the canvas, adjusting the colors of everything below it.
+///
+/// Adjustments are evaluated whenever the canvas is composited, so their
+/// parameters stay editable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjustmentLayer {
+    pub adjustment: Adjustment,
+    /// How much of the adjusted color is mixed in, between 0 and 1.
+    pub opacity: f32,
+    /// Hidden layers don't adjust anything.
+    pub visible: bool,
+}
+
+impl AdjustmentLayer {
+    /// Creates a visible, fully opaque layer.
+    pub fn new(adjustment: Adjustment) -> Self {
+        Self {
+            adjustment,
+            opacity: 1.0,
+            visible: true,
+        }
+    }
+
+    /// Whether the layer changes any colors.
+    pub fn is_active(&self) -> bool {
+        self.visible && self.opacity > 0.0
+    }
+
+    /// Adjusts a color in the sRGB gamut, mixing in linear light by the
+    /// opacity.
+    ///
+    /// This is the reference implementation, which the GPU compositing must
+    /// match.
+    pub fn apply(&self, c: LinearSrgb) -> LinearSrgb {
+        if !self.is_active() {
+            return c;
+        }
+
+        let adjusted = self.adjustment.apply(c);
+        let t = self.opacity.clamp(0.0, 1.0);
+        LinearSrgb::new(
+            c.r + (adjusted.r - c.r) * t,
+            c.g + (adjusted.g - c.g) * t,
+            c.b + (adjusted.b - c.b) * t,
+        )
+    }
+}
+
+/// A color adjustment.
+///
+/// Levels, curves, color balance and inversion work on sRGB-encoded
+/// components, like in most painting software. Adjustments in Oklab map the
+/// result into the gamut with [`GamutMapping::default()`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Adjustment {
+    Levels(Levels),
+    Curves(Curves),
+    /// Shift in OkLCh.
+    HueChromaLightness {
+        /// Added to the hue, in radians.
+        hue: f32,
+        /// Multiplies the chroma, 1 keeps it as is.
+        chroma: f32,
+        /// Added to the lightness, between -1 and 1.
+        lightness: f32,
+    },
+    /// Adds to the encoded RGB components, weighted by how much the color
+    /// belongs to the shadows, the midtones or the highlights.
+    ColorBalance {
+        shadows: Vec3,
+        midtones: Vec3,
+        highlights: Vec3,
+    },
+    /// Inverts the encoded RGB components.
+    Invert,
+}
+
+impl Adjustment {
+    /// Adjusts a color in the sRGB gamut, returning a color in the gamut.
+    pub fn apply(&self, c: LinearSrgb) -> LinearSrgb {
+        match self {
+            Adjustment::Levels(levels) => map_encoded(
+                c,
+                |x| levels.apply(x),
+                |x| levels.apply(x),
+                |x| levels.apply(x),
+            ),
+            Adjustment::Curves(curves) => {
+                let c = map_encoded(
+                    c,
+                    |x| curves.red.evaluate(x),
+                    |x| curves.green.evaluate(x),
+                    |x| curves.blue.evaluate(x),
+                );
+
+                let mut lab = Oklab::from_linear_srgb(c);
+                lab.l = curves.lightness.evaluate(lab.l);
+                GamutMapping::default().map_oklab(lab)
+            }
+            Adjustment::HueChromaLightness {
+                hue,
+                chroma,
+                lightness,
+            } => {
+                let mut lch = OkLch::from_linear_srgb(c);
+                lch.h += hue;
+                lch.c = (lch.c * chroma).max(0.0);
+                lch.l = (lch.l + lightness).clamp(0.0, 1.0);
+                GamutMapping::default().map_oklab(lch.to_oklab())
+            }
+            Adjustment::ColorBalance {
+                shadows,
+                midtones,
+                highlights,
+            } => {
+                let l = Oklab::from_linear_srgb(c).l.clamp(0.0, 1.0);
+                let shadow_weight = (1.0 - l) * (1.0 - l);
+                let highlight_weight = l * l;
+                let midtone_weight = 1.0 - shadow_weight - highlight_weight;
+
+                let offset = shadows * shadow_weight
+                    + midtones * midtone_weight
+                    + highlights * highlight_weight;
+                map_encoded(c, |x| x + offset.x, |x| x + offset.y, |x| x + offset.z)
+            }
+            Adjustment::Invert => map_encoded(c, |x| 1.0 - x, |x| 1.0 - x, |x| 1.0 - x),
+        }
+    }
+}
+
+/// Maps the sRGB-encoded components, clamping the results.
+fn map_encoded(
+    c: LinearSrgb,
+    r: impl FnOnce(f32) -> f32,
+    g: impl FnOnce(f32) -> f32,
+    b: impl FnOnce(f32) -> f32,
+) -> LinearSrgb {
+    let e = NonlinearSrgb::<f32>::from_linear_srgb(c);
+    NonlinearSrgb::new(
+        r(e.r).clamp(0.0, 1.0),
+        g(e.g).clamp(0.0, 1.0),
+        b(e.b).clamp(0.0, 1.0),
+    )
+    .to_linear_srgb()
+}
+
+/// Remaps the range of encoded components, with a gamma curve in between.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    /// Input mapped to `output_black`.
+    pub input_black: f32,
+    /// Input mapped to `output_white`.
+    pub input_white: f32,
+    /// Midtone gamma, greater than 1 brightens.
+    pub gamma: f32,
+    pub output_black: f32,
+    pub output_white: f32,
+}
+
+impl Default for Levels {
+    /// Levels which don't change anything.
+    fn default() -> Self {
+        Self {
+            input_black: 0.0,
+            input_white: 1.0,
+            gamma: 1.0,
+            output_black: 0.0,
+            output_white: 1.0,
+        }
+    }
+}
+
+impl Levels {
+    /// Maps an encoded component.
+    pub fn apply(&self, x: f32) -> f32 {
+        let range = (self.input_white - self.input_black).max(f32::EPSILON);
+        let x = ((x - self.input_black) / range).clamp(0.0, 1.0);
+        let x = x.powf(1.0 / self.gamma.max(f32::EPSILON));
+        self.output_black + x * (self.output_white - self.output_black)
+    }
+}
+
+/// Tone curves of each encoded RGB component, followed by a curve of the
+/// Oklab lightness.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Curves {
+    pub red: Curve,
+    pub green: Curve,
+    pub blue: Curve,
+    pub lightness: Curve,
+}
+
+impl Curves {
+    /// The same curve for all RGB components.
+    pub fn rgb(curve: Curve) -> Self {
+        Self {
+            red: curve.clone(),
+            green: curve.clone(),
+            blue: curve,
+            lightness: Curve::default(),
+        }
+    }
+}
+
+/// Smooth curve through control points between 0 and 1, monotonic between
+/// consecutive points, so it doesn't overshoot.
+///
+/// Uses the monotone cubic interpolation of Fritsch and Carlson. Outside the
+/// control points the curve is flat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<Vec2>,
+    /// Slope at each point.
+    tangents: Vec<f32>,
+}
+
+impl Default for Curve {
+    /// The identity curve.
+    fn default() -> Self {
+        Self::new([Vec2::ZERO, Vec2::ONE])
+    }
+}
+
+impl Curve {
+    /// Creates a curve through the points, which are clamped between 0 and 1.
+    /// Of points with the same X, only the last one is kept.
+    ///
+    /// Without any points, the curve is the identity.
+    pub fn new(points: impl IntoIterator<Item = Vec2>) -> Self {
+        let mut points: Vec<Vec2> = points
+            .into_iter()
+            .map(|p| p.clamp(Vec2::ZERO, Vec2::ONE))
+            .collect();
+
+        if points.is_empty() {
+            return Self::default();
+        }
+
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        // keep the last of equal points, dedup keeps the first one
+        points.reverse();
+        points.dedup_by(|a, b| a.x == b.x);
+        points.reverse();
+
+        let tangents = tangents(&points);
+        Self { points, tangents }
+    }
+
+    /// Control points, sorted by X.
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let points = &self.points;
+        let first = points[0];
+        let last = points[points.len() - 1];
+
+        if x <= first.x {
+            return first.y;
+        }
+        if x >= last.x {
+            return last.y;
+        }
+
+        // the segment containing x, points.len() >= 2 here
+        let i = points.partition_point(|p| p.x <= x) - 1;
+        let (p0, p1) = (points[i], points[i + 1]);
+        let h = p1.x - p0.x;
+        let t = (x - p0.x) / h;
+
+        // cubic Hermite basis
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+
+        let y =
+            h00 * p0.y + h10 * h * self.tangents[i] + h01 * p1.y + h11 * h * self.tangents[i + 1];
+        y.clamp(0.0, 1.0)
+    }
+}
+
+/// Fritsch-Carlson tangents, limited to keep each segment monotonic.
+fn tangents(points: &[Vec2]) -> Vec<f32> {
+    let n = points.len();
+    if n < 2 {
+        return vec![0.0; n];
+    }
+
+    let secants: Vec<f32> = points
+        .windows(2)
+        .map(|w| (w[1].y - w[0].y) / (w[1].x - w[0].x))
+        .collect();
+
+    let mut tangents = Vec::with_capacity(n);
+    tangents.push(secants[0]);
+    for w in secants.windows(2) {
+        if w[0] * w[1] <= 0.0 {
+            // local extremum
+            tangents.push(0.0);
+        } else {
+            tangents.push(0.5 * (w[0] + w[1]));
+        }
+    }
+    tangents.push(secants[n - 2]);
+
+    for (i, &secant) in secants.iter().enumerate() {
+        if secant == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+
+        let a = tangents[i] / secant;
+        let b = tangents[i + 1] / secant;
+        let s = a * a + b * b;
+        if s > 9.0 {
+            let tau = 3.0 / s.sqrt();
+            tangents[i] = tau * a * secant;
+            tangents[i + 1] = tau * b * secant;
+        }
+    }
+
+    tangents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors() -> impl Iterator<Item = LinearSrgb> {
+        (0..=4).flat_map(|r| {
+            (0..=4).flat_map(move |g| {
+                (0..=4)
+                    .map(move |b| LinearSrgb::new(r as f32 / 4.0, g as f32 / 4.0, b as f32 / 4.0))
+            })
+        })
+    }
+
+    fn assert_close(a: LinearSrgb, b: LinearSrgb, tolerance: f32) {
+        for (x, y) in [(a.r, b.r), (a.g, b.g), (a.b, b.b)] {
+            assert!((x - y).abs() < tolerance, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn neutral_adjustments_keep_colors() {
+        let adjustments = [
+            Adjustment::Levels(Levels::default()),
+            Adjustment::Curves(Curves::default()),
+            Adjustment::HueChromaLightness {
+                hue: 0.0,
+                chroma: 1.0,
+                lightness: 0.0,
+            },
+            Adjustment::ColorBalance {
+                shadows: Vec3::ZERO,
+                midtones: Vec3::ZERO,
+                highlights: Vec3::ZERO,
+            },
+        ];
+
+        for adjustment in adjustments {
+            for c in colors() {
+                assert_close(adjustment.apply(c), c, 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn adjusted_colors_in_gamut() {
+        let adjustments = [
+            Adjustment::Levels(Levels {
+                input_black: 0.2,
+                input_white: 0.8,
+                gamma: 1.5,
+                output_black: 0.1,
+                output_white: 0.9,
+            }),
+            Adjustment::Curves(Curves {
+                lightness: Curve::new([Vec2::new(0.0, 0.2), Vec2::new(0.5, 0.9)]),
+                ..Curves::rgb(Curve::new([Vec2::new(0.3, 0.0), Vec2::new(0.7, 1.0)]))
+            }),
+            Adjustment::HueChromaLightness {
+                hue: 1.0,
+                chroma: 2.0,
+                lightness: 0.1,
+            },
+            Adjustment::ColorBalance {
+                shadows: Vec3::new(0.3, 0.0, -0.3),
+                midtones: Vec3::new(0.0, 0.2, 0.0),
+                highlights: Vec3::new(-0.2, 0.0, 0.4),
+            },
+            Adjustment::Invert,
+        ];
+
+        for adjustment in adjustments {
+            for c in colors() {
+                let adjusted = adjustment.apply(c);
+                assert!(
+                    [adjusted.r, adjusted.g, adjusted.b]
+                        .iter()
+                        .all(|x| (0.0..=1.0).contains(x)),
+                    "{adjustment:?} {c:?}: {adjusted:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn levels() {
+        let levels = Levels {
+            input_black: 0.25,
+            input_white: 0.75,
+            gamma: 2.0,
+            output_black: 0.0,
+            output_white: 1.0,
+        };
+
+        assert_eq!(levels.apply(0.1), 0.0);
+        assert_eq!(levels.apply(0.9), 1.0);
+        assert!((levels.apply(0.375) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn invert_twice() {
+        for c in colors() {
+            let inverted = Adjustment::Invert.apply(Adjustment::Invert.apply(c));
+            assert_close(inverted, c, 1e-5);
+        }
+    }
+
+    #[test]
+    fn hue_shift_keeps_lightness() {
+        let adjustment = Adjustment::HueChromaLightness {
+            hue: 2.0,
+            chroma: 0.5,
+            lightness: 0.0,
+        };
+
+        let c = OkLch::new(0.6, 0.1, 1.0);
+        let adjusted = OkLch::from_linear_srgb(adjustment.apply(c.to_linear_srgb()));
+        assert!((adjusted.l - 0.6).abs() < 1e-4);
+        assert!((adjusted.c - 0.05).abs() < 1e-4);
+        assert!((adjusted.h - 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn curve_through_points() {
+        let points = [
+            Vec2::new(0.0, 0.1),
+            Vec2::new(0.3, 0.2),
+            Vec2::new(0.6, 0.9),
+            Vec2::new(0.8, 0.85),
+        ];
+        let curve = Curve::new(points);
+
+        for p in points {
+            assert!((curve.evaluate(p.x) - p.y).abs() < 1e-6);
+        }
+
+        // flat outside the points
+        assert_eq!(curve.evaluate(0.95), 0.85);
+
+        // monotonic between the points, no overshoot
+        let samples: Vec<f32> = (0..=100)
+            .map(|i| curve.evaluate(0.3 + 0.3 * i as f32 / 100.0))
+            .collect();
+        assert!(samples.windows(2).all(|w| w[0] <= w[1]));
+        assert!(samples.iter().all(|&y| (0.2..=0.9).contains(&y)));
+
+        let identity = Curve::default();
+        assert!((identity.evaluate(0.42) - 0.42).abs() < 1e-6);
+        assert_eq!(Curve::new([]), identity);
+    }
+
+    #[test]
+    fn layer_opacity() {
+        let c = LinearSrgb::new(0.2, 0.4, 0.6);
+        let mut layer = AdjustmentLayer::new(Adjustment::Invert);
+        let inverted = Adjustment::Invert.apply(c);
+
+        layer.opacity = 0.5;
+        let mixed = layer.apply(c);
+        assert_close(
+            mixed,
+            LinearSrgb::new(
+                0.5 * (c.r + inverted.r),
+                0.5 * (c.g + inverted.g),
+                0.5 * (c.b + inverted.b),
+            ),
+            1e-6,
+        );
+
+        layer.visible = false;
+        assert_eq!(layer.apply(c), c);
+    }
+}
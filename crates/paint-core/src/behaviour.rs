@@ -1,5 +1,12 @@
 use glam::{Affine2, UVec2, Vec2};
 
+use crate::adjustment::AdjustmentLayer;
+use crate::color::BlendingSpace;
+use crate::filter::Filter;
+use crate::geometry::DamageRegion;
+use crate::liquify::LiquifySettings;
+use crate::shape::{Shape, ShapeSettings};
+use crate::text::TextLayer;
 use crate::{persistence, presentation};
 
 /// App behaviour implementation.
@@ -35,6 +42,10 @@ pub trait Impls {
     type Compositor: Compositor<Context = Self::Context, Texture = Self::Texture>;
     type BrushEngine: BrushEngine<Stroke = Self::BrushStroke>;
     type BrushStroke: BrushStroke<Context = Self::Context, Texture = Self::Texture>;
+    type LiquifyEngine: LiquifyEngine<Stroke = Self::LiquifyStroke>;
+    type LiquifyStroke: BrushStroke<Context = Self::Context, Texture = Self::Texture>;
+    type ShapeEngine: ShapeEngine<Drawing = Self::ShapeDrawing>;
+    type ShapeDrawing: ShapeDrawing<Context = Self::Context, Texture = Self::Texture>;
 }
 
 pub trait Context {}
@@ -61,6 +72,42 @@ pub enum Event {
     InvalidateViewport,
     SetCanvasResolution(UVec2),
     SetViewportTransform(Affine2),
+    SetBlendingSpace(BlendingSpace),
+    /// Converts the canvas to the format, if the device supports it.
+    SetCanvasFormat(persistence::TextureFormat),
+    SetViewFilter(presentation::ViewFilter),
+    /// Replaces the adjustment layers, from bottom to top.
+    SetAdjustmentLayers(Vec<AdjustmentLayer>),
+    /// Replaces the text layers above the canvas and its adjustment layers,
+    /// from bottom to top.
+    ///
+    /// Only the layers which have changed are rasterized again.
+    SetTextLayers(Vec<TextLayer>),
+    /// Draws the text layer at the index onto the canvas, and removes it
+    /// from the text layers.
+    RasterizeTextLayer(usize),
+    /// Shows the canvas with the filter applied, replacing any previewed
+    /// filter.
+    PreviewFilter(Filter),
+    /// Applies the previewed filter to the canvas.
+    CommitFilter,
+    /// Stops previewing the filter, leaving the canvas as is.
+    CancelFilter,
+    /// Makes brush strokes liquify the canvas with the settings, or paint
+    /// again with `None`.
+    SetLiquify(Option<LiquifySettings>),
+    /// Makes brush strokes draw shapes with the settings, or paint again
+    /// with `None`.
+    ///
+    /// A drawn shape stays editable until committed, or until the next stroke
+    /// commits it.
+    SetShapeTool(Option<ShapeSettings>),
+    /// Replaces the shape which is still editable.
+    EditShape(Shape),
+    /// Draws the editable shape onto the canvas.
+    CommitShape,
+    /// Removes the editable shape, leaving the canvas as is.
+    CancelShape,
     BeginBrushStroke,
     UpdateBrushStroke(BrushState),
     EndBrushStroke,
@@ -86,12 +133,46 @@ pub trait BrushStroke {
 
     fn update(&mut self, state: &BrushState);
 
+    /// Area of the canvas touched by the stroke so far.
+    fn damage(&self) -> &DamageRegion;
+
+    fn render(&mut self, ctx: &mut Self::Context) -> Self::Texture;
+}
+
+/// Creates liquify strokes, whose textures are displacement fields, see
+/// [`Compositor::put_displacement()`].
+pub trait LiquifyEngine {
+    type Stroke: BrushStroke;
+
+    fn begin_stroke(&self, settings: &StrokeSettings, liquify: &LiquifySettings) -> Self::Stroke;
+}
+
+/// Creates shape drawings, whose textures are composited like brush strokes.
+pub trait ShapeEngine {
+    type Drawing: ShapeDrawing;
+
+    fn begin_drawing(&self, settings: &StrokeSettings) -> Self::Drawing;
+}
+
+/// A shape which is still being edited, and is drawn anew on every change.
+pub trait ShapeDrawing {
+    type Texture: Texture;
+    type Context: Context;
+
+    fn set_shape(&mut self, shape: &Shape);
+
+    /// Area of the canvas touched by the current shape.
+    fn damage(&self) -> &DamageRegion;
+
     fn render(&mut self, ctx: &mut Self::Context) -> Self::Texture;
 }
 
 #[derive(Debug, Clone)]
 pub struct StrokeSettings {
     pub canvas_resolution: UVec2,
+    pub canvas_format: persistence::TextureFormat,
+    /// Space which overlapping brush dabs are blended in.
+    pub blending_space: BlendingSpace,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -105,7 +186,70 @@ pub trait Compositor {
     type Texture: Texture;
     type Context: Context;
 
-    fn put_texture(&mut self, ctx: &mut Self::Context, texture: Self::Texture);
+    /// Composites the texture onto the canvas, only within the damaged area.
+    fn put_texture(
+        &mut self,
+        ctx: &mut Self::Context,
+        texture: Self::Texture,
+        damage: &DamageRegion,
+        blending_space: BlendingSpace,
+    );
+
+    /// Renders the canvas as [`Compositor::put_texture()`] would leave it,
+    /// without modifying the canvas.
+    ///
+    /// Meant for showing a texture which is still changing, such as a brush
+    /// stroke in progress.
+    fn preview_texture(
+        &mut self,
+        ctx: &mut Self::Context,
+        texture: Self::Texture,
+        damage: &DamageRegion,
+        blending_space: BlendingSpace,
+    ) -> Self::Texture;
+
+    /// Resamples the canvas through a displacement field, only within the
+    /// damaged area.
+    ///
+    /// The field holds the offset in pixels where each pixel is sampled from,
+    /// as in [`DisplacementField`](crate::liquify::DisplacementField).
+    fn put_displacement(
+        &mut self,
+        ctx: &mut Self::Context,
+        field: Self::Texture,
+        damage: &DamageRegion,
+    );
+
+    /// Renders the canvas as [`Compositor::put_displacement()`] would leave
+    /// it, without modifying the canvas.
+    fn preview_displacement(
+        &mut self,
+        ctx: &mut Self::Context,
+        field: Self::Texture,
+        damage: &DamageRegion,
+    ) -> Self::Texture;
+
+    /// Sets the adjustment layers above the canvas, from bottom to top.
+    ///
+    /// The canvas itself is left as is, [`Compositor::render()`] and
+    /// [`Compositor::preview_texture()`] return the adjusted result.
+    fn set_adjustment_layers(&mut self, ctx: &mut Self::Context, layers: &[AdjustmentLayer]);
+
+    /// Renders the canvas as [`Compositor::apply_filter()`] would leave it,
+    /// without modifying the canvas.
+    fn preview_filter(&mut self, ctx: &mut Self::Context, filter: &Filter) -> Self::Texture;
+
+    /// Replaces the canvas with the filtered canvas.
+    fn apply_filter(&mut self, ctx: &mut Self::Context, filter: &Filter);
+
+    /// Format of the canvas texture, which textures are composited in.
+    fn format(&self) -> persistence::TextureFormat;
+
+    /// Whether the canvas can use the format on this device.
+    fn is_format_supported(&self, format: persistence::TextureFormat) -> bool;
+
+    /// Converts the canvas to the format, which has to be supported.
+    fn set_format(&mut self, ctx: &mut Self::Context, format: persistence::TextureFormat);
 
     fn render(&mut self, ctx: &mut Self::Context) -> Self::Texture;
 }
@@ -0,0 +1,368 @@
+//! Conversions of whole pixel buffers.
+//!
+//! These match the scalar [`Color`] conversions, but are faster: 8-bit sRGB
+//! goes through lookup tables, and Oklab is computed in `f32` on fixed size
+//! chunks which the compiler vectorizes, instead of through `f64`
+//! intermediates.
+//!
+//! All functions panic if `src` and `dst` have different lengths.
+
+use std::sync::LazyLock;
+
+use super::gamut::is_unit_cube;
+use super::srgb::{transfer_decode, transfer_encode};
+use super::{Color, Component, GamutMapping, LinearSrgb, NonlinearSrgb, Oklab};
+
+/// Number of colors converted at once, enough for 256-bit vectors.
+const LANES: usize = 8;
+
+/// Linear value of each 8-bit sRGB code.
+static DECODE_U8: LazyLock<[f32; 256]> =
+    LazyLock::new(|| std::array::from_fn(|i| transfer_decode((i as u8).as_f32())));
+
+/// Number of buckets of [`EncodeTable::buckets`], dense enough for 8-bit
+/// codes to be at most one apart within a bucket.
+const ENCODE_BUCKETS: usize = 4096;
+
+struct EncodeTable {
+    /// Smallest linear value encoding to each 8-bit sRGB code, found by
+    /// bisecting the scalar conversion, so that the table matches it exactly.
+    thresholds: [f32; 256],
+    /// Code at the start of each bucket of linear values between 0 and 1.
+    buckets: [u8; ENCODE_BUCKETS + 1],
+}
+
+static ENCODE_U8: LazyLock<EncodeTable> = LazyLock::new(|| {
+    let encode = |x: f32| u8::from_f32(transfer_encode(x));
+
+    let thresholds = std::array::from_fn(|code| {
+        if code == 0 {
+            return f32::NEG_INFINITY;
+        }
+
+        // non-negative floats are ordered like their bits
+        let mut low = 0u32;
+        let mut high = 1f32.to_bits();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if encode(f32::from_bits(mid)) as usize >= code {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        f32::from_bits(low)
+    });
+
+    let buckets = std::array::from_fn(|i| encode(i as f32 / ENCODE_BUCKETS as f32));
+
+    EncodeTable {
+        thresholds,
+        buckets,
+    }
+});
+
+fn decode_u8(x: u8) -> f32 {
+    DECODE_U8[x as usize]
+}
+
+fn encode_u8(table: &EncodeTable, x: f32) -> u8 {
+    if !x.is_finite() {
+        return 0;
+    }
+
+    // saturating, negative values land in the first bucket
+    let bucket = ((x * ENCODE_BUCKETS as f32) as usize).min(ENCODE_BUCKETS);
+    let mut code = table.buckets[bucket];
+    while code < u8::MAX && x >= table.thresholds[code as usize + 1] {
+        code += 1;
+    }
+    code
+}
+
+/// Converts 8-bit sRGB to linear sRGB.
+pub fn decode_srgb_u8(src: &[NonlinearSrgb<u8>], dst: &mut [LinearSrgb]) {
+    assert_eq!(src.len(), dst.len());
+
+    for (s, d) in src.iter().zip(dst) {
+        *d = LinearSrgb::new(decode_u8(s.r), decode_u8(s.g), decode_u8(s.b));
+    }
+}
+
+/// Converts linear sRGB to 8-bit sRGB, clamping each channel.
+pub fn encode_srgb_u8(src: &[LinearSrgb], dst: &mut [NonlinearSrgb<u8>]) {
+    assert_eq!(src.len(), dst.len());
+
+    let table = &*ENCODE_U8;
+    for (s, d) in src.iter().zip(dst) {
+        *d = NonlinearSrgb::new(
+            encode_u8(table, s.r),
+            encode_u8(table, s.g),
+            encode_u8(table, s.b),
+        );
+    }
+}
+
+/// Converts linear sRGB to Oklab.
+///
+/// The coefficients are those of the scalar conversion, rounded to `f32`.
+pub fn linear_srgb_to_oklab(src: &[LinearSrgb], dst: &mut [Oklab]) {
+    for_each_chunk(src, dst, |src, dst| {
+        for (c, lab) in src.iter().zip(dst) {
+            let l = 0.41222146 * c.r + 0.53633255 * c.g + 0.051445995 * c.b;
+            let m = 0.2119035 * c.r + 0.6806995 * c.g + 0.10739696 * c.b;
+            let s = 0.08830246 * c.r + 0.28171885 * c.g + 0.6299787 * c.b;
+
+            let l_ = cbrt(l);
+            let m_ = cbrt(m);
+            let s_ = cbrt(s);
+
+            *lab = Oklab::new(
+                0.21045426 * l_ + 0.7936178 * m_ - 0.004072047 * s_,
+                1.9779985 * l_ - 2.4285922 * m_ + 0.4505937 * s_,
+                0.025904037 * l_ + 0.78277177 * m_ - 0.80867577 * s_,
+            );
+        }
+    });
+}
+
+/// Converts Oklab to linear sRGB, preserving out of gamut colors.
+pub fn oklab_to_linear_srgb(src: &[Oklab], dst: &mut [LinearSrgb]) {
+    for_each_chunk(src, dst, |src, dst| {
+        for (lab, c) in src.iter().zip(dst) {
+            let l_ = lab.l + 0.39633778 * lab.a + 0.21580376 * lab.b;
+            let m_ = lab.l - 0.105561346 * lab.a - 0.06385417 * lab.b;
+            let s_ = lab.l - 0.08948418 * lab.a - 1.2914855 * lab.b;
+
+            let l = l_ * l_ * l_;
+            let m = m_ * m_ * m_;
+            let s = s_ * s_ * s_;
+
+            *c = LinearSrgb::new(
+                4.0767417 * l - 3.3077116 * m + 0.23096994 * s,
+                -1.268438 * l + 2.6097574 * m - 0.34131938 * s,
+                -0.0041960864 * l - 0.7034186 * m + 1.7076147 * s,
+            );
+        }
+    });
+}
+
+/// Converts 8-bit sRGB to Oklab.
+pub fn srgb_u8_to_oklab(src: &[NonlinearSrgb<u8>], dst: &mut [Oklab]) {
+    for_each_chunk(src, dst, |src, dst| {
+        let mut linear = [LinearSrgb::default(); LANES];
+        decode_srgb_u8(src, &mut linear);
+        linear_srgb_to_oklab(&linear, dst);
+    });
+}
+
+/// Converts Oklab to 8-bit sRGB, mapping out of gamut colors with `mapping`.
+///
+/// In gamut colors are converted in `f32`, others through the scalar
+/// [`GamutMapping::map_oklab()`].
+pub fn oklab_to_srgb_u8(src: &[Oklab], dst: &mut [NonlinearSrgb<u8>], mapping: GamutMapping) {
+    for_each_chunk(src, dst, |src, dst| {
+        let mut linear = [LinearSrgb::default(); LANES];
+        oklab_to_linear_srgb(src, &mut linear);
+
+        for (lab, c) in src.iter().zip(&mut linear) {
+            if !is_unit_cube([c.r, c.g, c.b]) {
+                *c = mapping.map_oklab(*lab);
+            }
+        }
+
+        encode_srgb_u8(&linear, dst);
+    });
+}
+
+/// Converts any colors to 8-bit sRGB, mapping out of gamut colors with
+/// `mapping`.
+///
+/// This is the generic fallback, converting each color with
+/// [`Color::to_linear_srgb_mapped()`] and only encoding in bulk.
+pub fn to_srgb_u8<C: Color>(src: &[C], dst: &mut [NonlinearSrgb<u8>], mapping: GamutMapping) {
+    assert_eq!(src.len(), dst.len());
+
+    let table = &*ENCODE_U8;
+    for (s, d) in src.iter().zip(dst) {
+        let c = s.to_linear_srgb_mapped(mapping);
+        *d = NonlinearSrgb::new(
+            encode_u8(table, c.r),
+            encode_u8(table, c.g),
+            encode_u8(table, c.b),
+        );
+    }
+}
+
+/// Calls `f` on chunks of [`LANES`] colors, padding the last chunk so that
+/// every color goes through the same code.
+fn for_each_chunk<S: Copy + Default, D: Copy + Default>(
+    src: &[S],
+    dst: &mut [D],
+    mut f: impl FnMut(&[S], &mut [D]),
+) {
+    assert_eq!(src.len(), dst.len());
+
+    let mut src_chunks = src.chunks_exact(LANES);
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    for (s, d) in (&mut src_chunks).zip(&mut dst_chunks) {
+        f(s, d);
+    }
+
+    let (src, dst) = (src_chunks.remainder(), dst_chunks.into_remainder());
+    if !src.is_empty() {
+        let mut s = [S::default(); LANES];
+        let mut d = [D::default(); LANES];
+        s[..src.len()].copy_from_slice(src);
+        f(&s, &mut d);
+        dst.copy_from_slice(&d[..dst.len()]);
+    }
+}
+
+/// Cube root in `f32` without branches, accurate to about 1 ulp.
+fn cbrt(x: f32) -> f32 {
+    let a = x.abs();
+
+    // estimate from the exponent, refined with two Halley iterations
+    let mut y = f32::from_bits(a.to_bits() / 3 + 709_921_077);
+    for _ in 0..2 {
+        let y3 = y * y * y;
+        y *= (y3 + 2.0 * a) / (2.0 * y3 + a);
+    }
+
+    let y = if a == 0.0 { 0.0 } else { y };
+    y.copysign(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{OkLch, Okhsv};
+
+    /// Linear values around every 8-bit code, and out of range ones.
+    fn linear_samples() -> Vec<LinearSrgb> {
+        let mut values: Vec<f32> = (0..=4096).map(|i| i as f32 / 4096.0).collect();
+        values.extend((0..=255u8).map(|x| x.as_f32()));
+        values.extend((0..255).map(|i| ENCODE_U8.thresholds[i + 1]));
+        values.extend((0..255).map(|i| f32::from_bits(ENCODE_U8.thresholds[i + 1].to_bits() - 1)));
+        values.extend([-1.0, -0.0, 1.5, f32::NAN, f32::INFINITY, f32::NEG_INFINITY]);
+
+        values
+            .chunks(3)
+            .map(|c| LinearSrgb::new(c[0], c[c.len() / 2], c[c.len() - 1]))
+            .chain(values.iter().map(|&v| LinearSrgb::new(v, v, v)))
+            .collect()
+    }
+
+    #[test]
+    fn srgb_u8_matches_scalar() {
+        let src: Vec<_> = (0..=255u8)
+            .map(|x| NonlinearSrgb::new(x, 255 - x, x / 2))
+            .collect();
+        let mut dst = vec![LinearSrgb::default(); src.len()];
+        decode_srgb_u8(&src, &mut dst);
+        for (s, d) in src.iter().zip(&dst) {
+            assert_eq!(*d, s.to_linear_srgb());
+        }
+
+        let src = linear_samples();
+        let mut dst = vec![NonlinearSrgb::default(); src.len()];
+        encode_srgb_u8(&src, &mut dst);
+        for (s, d) in src.iter().zip(&dst) {
+            assert_eq!(*d, NonlinearSrgb::<u8>::from_linear_srgb(*s), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn oklab_matches_scalar() {
+        let src: Vec<_> = linear_samples()
+            .into_iter()
+            .filter(|c| [c.r, c.g, c.b].iter().all(|x| x.is_finite()))
+            .collect();
+
+        let mut lab = vec![Oklab::default(); src.len()];
+        linear_srgb_to_oklab(&src, &mut lab);
+        for (s, d) in src.iter().zip(&lab) {
+            let expected = Oklab::from_linear_srgb(*s);
+            assert!(d.delta_e(&expected) < 1e-5, "{s:?}: {d:?} {expected:?}");
+        }
+
+        let mut rgb = vec![LinearSrgb::default(); lab.len()];
+        oklab_to_linear_srgb(&lab, &mut rgb);
+        for (s, d) in lab.iter().zip(&rgb) {
+            let expected = s.to_linear_srgb();
+            for (a, b) in [(d.r, expected.r), (d.g, expected.g), (d.b, expected.b)] {
+                assert!((a - b).abs() < 1e-5, "{s:?}: {d:?} {expected:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_u8_oklab_round_trip() {
+        // an odd length, to exercise the padded last chunk
+        let src: Vec<_> = (0..4099u32)
+            .map(|i| NonlinearSrgb::new((i * 7) as u8, (i * 13) as u8, (i * 29) as u8))
+            .collect();
+
+        let mut lab = vec![Oklab::default(); src.len()];
+        srgb_u8_to_oklab(&src, &mut lab);
+
+        let mut dst = vec![NonlinearSrgb::default(); src.len()];
+        oklab_to_srgb_u8(&lab, &mut dst, GamutMapping::default());
+        assert_eq!(src, dst);
+    }
+
+    #[test]
+    fn mapped_matches_scalar() {
+        let src: Vec<_> = (0..=20)
+            .flat_map(|l| (0..=8).map(move |c| OkLch::new(l as f32 / 20.0, c as f32 * 0.05, 1.0)))
+            .collect();
+
+        for mapping in [
+            GamutMapping::default(),
+            GamutMapping::Css,
+            GamutMapping::Clip,
+        ] {
+            let lab: Vec<_> = src.iter().map(|c| c.to_oklab()).collect();
+            let mut dst = vec![NonlinearSrgb::default(); src.len()];
+            oklab_to_srgb_u8(&lab, &mut dst, mapping);
+
+            let mut generic = vec![NonlinearSrgb::default(); src.len()];
+            to_srgb_u8(&src, &mut generic, mapping);
+
+            for ((s, d), g) in src.iter().zip(&dst).zip(&generic) {
+                let expected =
+                    NonlinearSrgb::<u8>::from_linear_srgb(s.to_linear_srgb_mapped(mapping));
+                assert_eq!(*g, expected, "{mapping:?} {s:?}");
+
+                // in gamut colors are converted in f32
+                for (a, b) in [(d.r, expected.r), (d.g, expected.g), (d.b, expected.b)] {
+                    assert!(a.abs_diff(b) <= 1, "{mapping:?} {s:?}: {d:?} {expected:?}");
+                }
+            }
+        }
+
+        let hsv = [Okhsv::new(1.0, 0.5, 0.5), Okhsv::new(4.0, 1.0, 1.0)];
+        let mut dst = [NonlinearSrgb::default(); 2];
+        to_srgb_u8(&hsv, &mut dst, GamutMapping::default());
+        for (s, d) in hsv.iter().zip(dst) {
+            assert_eq!(
+                d,
+                NonlinearSrgb::<u8>::from_linear_srgb(s.to_linear_srgb_clamped())
+            );
+        }
+    }
+
+    #[test]
+    fn cbrt_matches_std() {
+        for i in -1000..=1000 {
+            let x = i as f32 / 100.0;
+            let expected = x.cbrt();
+            assert!(
+                (cbrt(x) - expected).abs() <= 2.0 * f32::EPSILON * expected.abs().max(1e-30),
+                "{x}"
+            );
+        }
+        assert_eq!(cbrt(0.0), 0.0);
+    }
+}
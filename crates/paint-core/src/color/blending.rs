@@ -0,0 +1,269 @@
+use super::{Color, LinearSrgb, NonlinearSrgb, Oklab, WithAlpha};
+
+/// Color space in which colors are mixed when blending.
+///
+/// Only color is affected, alpha is always combined the same way.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum BlendingSpace {
+    /// Linear light, which is physically correct. Dark colors tend to look
+    /// thin when mixed with light ones.
+    #[default]
+    LinearLight,
+    /// sRGB-encoded values, matching most traditional software.
+    GammaSrgb,
+    /// Oklab, which mixes colors roughly perceptually uniformly.
+    Oklab,
+}
+
+impl BlendingSpace {
+    /// All blending spaces.
+    pub const ALL: [BlendingSpace; 3] = [
+        BlendingSpace::LinearLight,
+        BlendingSpace::GammaSrgb,
+        BlendingSpace::Oklab,
+    ];
+
+    /// Composites `src` over `dst` ("source over" operator) in this space.
+    ///
+    /// Colors are premultiplied by alpha, mixed, and unpremultiplied in the
+    /// blending space. The result is clamped to the sRGB gamut.
+    ///
+    /// This is the reference implementation, which the GPU blending must match.
+    pub fn source_over(
+        self,
+        dst: WithAlpha<LinearSrgb>,
+        src: WithAlpha<LinearSrgb>,
+    ) -> WithAlpha<LinearSrgb> {
+        let src_alpha = src.alpha.clamp(0.0, 1.0);
+        let dst_alpha = dst.alpha.clamp(0.0, 1.0);
+
+        let dst_weight = dst_alpha * (1.0 - src_alpha);
+        let alpha = src_alpha + dst_weight;
+
+        if alpha <= 0.0 {
+            return WithAlpha::transparent(LinearSrgb::default());
+        }
+
+        let src_color = self.encode(src.color);
+        let dst_color = self.encode(dst.color);
+        let mix = |s: f32, d: f32| (s * src_alpha + d * dst_weight) / alpha;
+
+        let color = self.decode([
+            mix(src_color[0], dst_color[0]),
+            mix(src_color[1], dst_color[1]),
+            mix(src_color[2], dst_color[2]),
+        ]);
+
+        WithAlpha::new(clamp(color), alpha)
+    }
+
+    /// Coordinates of the color in this space, which colors are mixed in.
+    pub fn encode(self, c: LinearSrgb) -> [f32; 3] {
+        match self {
+            BlendingSpace::LinearLight => [c.r, c.g, c.b],
+            BlendingSpace::GammaSrgb => {
+                let c = NonlinearSrgb::<f32>::from_linear_srgb(c);
+                [c.r, c.g, c.b]
+            }
+            BlendingSpace::Oklab => {
+                let c = Oklab::from_linear_srgb(c);
+                [c.l, c.a, c.b]
+            }
+        }
+    }
+
+    fn decode(self, [x, y, z]: [f32; 3]) -> LinearSrgb {
+        match self {
+            BlendingSpace::LinearLight => LinearSrgb::new(x, y, z),
+            BlendingSpace::GammaSrgb => NonlinearSrgb::new(x, y, z).to_linear_srgb(),
+            BlendingSpace::Oklab => Oklab::new(x, y, z).to_linear_srgb(),
+        }
+    }
+}
+
+fn clamp(c: LinearSrgb) -> LinearSrgb {
+    LinearSrgb::new(
+        c.r.clamp(0.0, 1.0),
+        c.g.clamp(0.0, 1.0),
+        c.b.clamp(0.0, 1.0),
+    )
+}
+
+/// Reference results of [`BlendingSpace::source_over()`].
+///
+/// Expected colors are given as 8-bit sRGB, which is what the user sees.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: NonlinearSrgb<u8> = NonlinearSrgb::new(0, 0, 0);
+    const WHITE: NonlinearSrgb<u8> = NonlinearSrgb::new(255, 255, 255);
+    const RED: NonlinearSrgb<u8> = NonlinearSrgb::new(255, 0, 0);
+    const GREEN: NonlinearSrgb<u8> = NonlinearSrgb::new(0, 255, 0);
+    const BLUE: NonlinearSrgb<u8> = NonlinearSrgb::new(0, 0, 255);
+    const YELLOW: NonlinearSrgb<u8> = NonlinearSrgb::new(255, 255, 0);
+
+    fn blend(
+        space: BlendingSpace,
+        dst: (NonlinearSrgb<u8>, f32),
+        src: (NonlinearSrgb<u8>, f32),
+    ) -> (NonlinearSrgb<u8>, f32) {
+        let dst = WithAlpha::new(dst.0.to_linear_srgb(), dst.1);
+        let src = WithAlpha::new(src.0.to_linear_srgb(), src.1);
+        let result = space.source_over(dst, src);
+        (NonlinearSrgb::from_linear_srgb(result.color), result.alpha)
+    }
+
+    fn assert_blend(
+        space: BlendingSpace,
+        dst: (NonlinearSrgb<u8>, f32),
+        src: (NonlinearSrgb<u8>, f32),
+        expected: (NonlinearSrgb<u8>, f32),
+    ) {
+        let (color, alpha) = blend(space, dst, src);
+        let (expected_color, expected_alpha) = expected;
+
+        let close = |a: u8, b: u8| a.abs_diff(b) <= 1;
+        assert!(
+            close(color.r, expected_color.r)
+                && close(color.g, expected_color.g)
+                && close(color.b, expected_color.b),
+            "{space:?}: {src:?} over {dst:?} is {color:?}, expected {expected_color:?}"
+        );
+        assert!(
+            (alpha - expected_alpha).abs() < 1e-6,
+            "{space:?}: alpha is {alpha}, expected {expected_alpha}"
+        );
+    }
+
+    #[test]
+    fn opaque_source_replaces() {
+        for space in BlendingSpace::ALL {
+            assert_blend(space, (WHITE, 1.0), (RED, 1.0), (RED, 1.0));
+            assert_blend(space, (BLUE, 0.3), (GREEN, 1.0), (GREEN, 1.0));
+        }
+    }
+
+    #[test]
+    fn transparent_source_keeps_destination() {
+        for space in BlendingSpace::ALL {
+            assert_blend(space, (BLUE, 1.0), (RED, 0.0), (BLUE, 1.0));
+            assert_blend(space, (YELLOW, 0.5), (BLACK, 0.0), (YELLOW, 0.5));
+        }
+    }
+
+    #[test]
+    fn transparent_over_transparent() {
+        for space in BlendingSpace::ALL {
+            let (_, alpha) = blend(space, (WHITE, 0.0), (RED, 0.0));
+            assert_eq!(alpha, 0.0);
+        }
+    }
+
+    #[test]
+    fn source_over_transparent_keeps_source() {
+        for space in BlendingSpace::ALL {
+            assert_blend(space, (WHITE, 0.0), (RED, 0.5), (RED, 0.5));
+        }
+    }
+
+    #[test]
+    fn alpha_is_independent_of_space() {
+        for space in BlendingSpace::ALL {
+            assert_blend(space, (RED, 0.5), (RED, 0.5), (RED, 0.75));
+            assert_blend(space, (WHITE, 0.2), (WHITE, 0.6), (WHITE, 0.68));
+        }
+    }
+
+    #[test]
+    fn half_black_over_white() {
+        // physically half the light
+        assert_blend(
+            BlendingSpace::LinearLight,
+            (WHITE, 1.0),
+            (BLACK, 0.5),
+            (NonlinearSrgb::new(188, 188, 188), 1.0),
+        );
+        // half of the encoded value
+        assert_blend(
+            BlendingSpace::GammaSrgb,
+            (WHITE, 1.0),
+            (BLACK, 0.5),
+            (NonlinearSrgb::new(128, 128, 128), 1.0),
+        );
+        // half of the perceived lightness, Oklab L = 0.5
+        assert_blend(
+            BlendingSpace::Oklab,
+            (WHITE, 1.0),
+            (BLACK, 0.5),
+            (NonlinearSrgb::new(99, 99, 99), 1.0),
+        );
+    }
+
+    #[test]
+    fn half_red_over_green() {
+        assert_blend(
+            BlendingSpace::LinearLight,
+            (GREEN, 1.0),
+            (RED, 0.5),
+            (NonlinearSrgb::new(188, 188, 0), 1.0),
+        );
+        assert_blend(
+            BlendingSpace::GammaSrgb,
+            (GREEN, 1.0),
+            (RED, 0.5),
+            (NonlinearSrgb::new(128, 128, 0), 1.0),
+        );
+        assert_blend(
+            BlendingSpace::Oklab,
+            (GREEN, 1.0),
+            (RED, 0.5),
+            (NonlinearSrgb::new(208, 168, 0), 1.0),
+        );
+    }
+
+    #[test]
+    fn half_blue_over_yellow() {
+        assert_blend(
+            BlendingSpace::LinearLight,
+            (YELLOW, 1.0),
+            (BLUE, 0.5),
+            (NonlinearSrgb::new(188, 188, 188), 1.0),
+        );
+        assert_blend(
+            BlendingSpace::GammaSrgb,
+            (YELLOW, 1.0),
+            (BLUE, 0.5),
+            (NonlinearSrgb::new(128, 128, 128), 1.0),
+        );
+        assert_blend(
+            BlendingSpace::Oklab,
+            (YELLOW, 1.0),
+            (BLUE, 0.5),
+            (NonlinearSrgb::new(108, 171, 199), 1.0),
+        );
+    }
+
+    #[test]
+    fn partially_transparent_destination() {
+        // colors are weighted by their visible coverage: 0.5 red, 0.25 green
+        assert_blend(
+            BlendingSpace::LinearLight,
+            (GREEN, 0.5),
+            (RED, 0.5),
+            (NonlinearSrgb::new(213, 156, 0), 0.75),
+        );
+        assert_blend(
+            BlendingSpace::GammaSrgb,
+            (GREEN, 0.5),
+            (RED, 0.5),
+            (NonlinearSrgb::new(170, 85, 0), 0.75),
+        );
+        assert_blend(
+            BlendingSpace::Oklab,
+            (GREEN, 0.5),
+            (RED, 0.5),
+            (NonlinearSrgb::new(229, 135, 0), 0.75),
+        );
+    }
+}
@@ -0,0 +1,845 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{
+    Color, Component, DisplayP3, Lab, Lch, LinearSrgb, NonlinearSrgb, OkLch, Oklab, Rec2020,
+    WithAlpha, Xyz, XyzD50,
+};
+
+/// Color parsed from CSS syntax, in the color space of its notation.
+///
+/// Supports the syntax of [CSS Color Module Level 4]:
+///
+/// - hex colors: `#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa`,
+/// - `rgb()`, `rgba()`, `hsl()` and `hsla()`, both with the modern
+///   space-separated and the legacy comma-separated syntax,
+/// - `lab()`, `lch()`, `oklab()` and `oklch()`,
+/// - `color()` with `srgb`, `srgb-linear`, `display-p3`, `rec2020`, `xyz`,
+///   `xyz-d65` and `xyz-d50` color spaces.
+///
+/// Named colors, `currentcolor`, relative colors and `calc()` aren't
+/// supported. The `none` keyword is treated as zero.
+///
+/// [CSS Color Module Level 4]: https://www.w3.org/TR/css-color-4/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CssColor {
+    /// Hex colors, `rgb()`, `hsl()` and `color(srgb ...)`.
+    Srgb(WithAlpha<NonlinearSrgb>),
+    /// `color(srgb-linear ...)`.
+    LinearSrgb(WithAlpha<LinearSrgb>),
+    /// `color(display-p3 ...)`.
+    DisplayP3(WithAlpha<DisplayP3>),
+    /// `color(rec2020 ...)`.
+    Rec2020(WithAlpha<Rec2020>),
+    /// `color(xyz ...)` and `color(xyz-d65 ...)`.
+    Xyz(WithAlpha<Xyz>),
+    /// `color(xyz-d50 ...)`.
+    XyzD50(WithAlpha<XyzD50>),
+    /// `lab()`.
+    Lab(WithAlpha<Lab>),
+    /// `lch()`.
+    Lch(WithAlpha<Lch>),
+    /// `oklab()`.
+    Oklab(WithAlpha<Oklab>),
+    /// `oklch()`.
+    OkLch(WithAlpha<OkLch>),
+}
+
+/// Applies an expression to the [`WithAlpha`] of any [`CssColor`] variant.
+macro_rules! each_variant {
+    ($value:expr, $c:ident => $e:expr) => {
+        match $value {
+            CssColor::Srgb($c) => $e,
+            CssColor::LinearSrgb($c) => $e,
+            CssColor::DisplayP3($c) => $e,
+            CssColor::Rec2020($c) => $e,
+            CssColor::Xyz($c) => $e,
+            CssColor::XyzD50($c) => $e,
+            CssColor::Lab($c) => $e,
+            CssColor::Lch($c) => $e,
+            CssColor::Oklab($c) => $e,
+            CssColor::OkLch($c) => $e,
+        }
+    };
+}
+
+impl CssColor {
+    /// Alpha channel, between 0 and 1.
+    pub fn alpha(&self) -> f32 {
+        each_variant!(self, c => c.alpha)
+    }
+
+    /// Converts the color to [`LinearSrgb`], preserving out of gamut colors.
+    pub fn to_linear_srgb(&self) -> WithAlpha<LinearSrgb> {
+        each_variant!(self, c => WithAlpha::new(c.color.to_linear_srgb(), c.alpha))
+    }
+
+    /// Converts the color to any other color type.
+    pub fn to_color<C: Color>(&self) -> WithAlpha<C> {
+        let c = self.to_linear_srgb();
+        WithAlpha::new(C::from_linear_srgb(c.color), c.alpha)
+    }
+}
+
+/// Supported color functions.
+const FUNCTIONS: [&str; 9] = [
+    "rgb", "rgba", "hsl", "hsla", "lab", "lch", "oklab", "oklch", "color",
+];
+
+impl FromStr for CssColor {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+
+        if s.is_empty() {
+            return Err(ParseColorError::Empty);
+        }
+
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex).map(CssColor::Srgb);
+        }
+
+        let Some((name, rest)) = s.split_once('(') else {
+            return Err(ParseColorError::UnknownSyntax(s));
+        };
+
+        let Some((args, trailing)) = rest.split_once(')') else {
+            return Err(ParseColorError::MissingClosingParenthesis);
+        };
+
+        if !trailing.trim().is_empty() {
+            return Err(ParseColorError::TrailingInput(trailing.trim().to_owned()));
+        }
+
+        let name = name.trim_end();
+        if !FUNCTIONS.contains(&name) {
+            return Err(ParseColorError::UnknownFunction(name.to_owned()));
+        }
+
+        let legacy = matches!(name, "rgb" | "rgba" | "hsl" | "hsla");
+        let Arguments { components, alpha } = split_arguments(args, legacy)?;
+
+        let alpha = match alpha {
+            Some(token) => component(token, 1.0)?.clamp(0.0, 1.0),
+            None => 1.0,
+        };
+
+        if name == "color" {
+            let Some((&space, components)) = components.split_first() else {
+                return Err(ParseColorError::WrongComponentCount {
+                    expected: 4,
+                    found: 0,
+                });
+            };
+            return parse_color_function(space, components, alpha);
+        }
+
+        let [x, y, z] = expect_three(&components)?;
+
+        let color = match name {
+            "rgb" | "rgba" => {
+                let f = |token| Ok((component(token, 255.0)? / 255.0).clamp(0.0, 1.0));
+                CssColor::Srgb(WithAlpha::new(
+                    NonlinearSrgb::new(f(x)?, f(y)?, f(z)?),
+                    alpha,
+                ))
+            }
+            "hsl" | "hsla" => {
+                let f = |token| Ok((component(token, 100.0)? / 100.0).clamp(0.0, 1.0));
+                let rgb = hsl_to_srgb(hue(x)?, f(y)?, f(z)?);
+                CssColor::Srgb(WithAlpha::new(rgb, alpha))
+            }
+            "lab" => CssColor::Lab(WithAlpha::new(
+                Lab::new(
+                    component(x, 100.0)?.max(0.0),
+                    component(y, 125.0)?,
+                    component(z, 125.0)?,
+                ),
+                alpha,
+            )),
+            "lch" => CssColor::Lch(WithAlpha::new(
+                Lch::new(
+                    component(x, 100.0)?.max(0.0),
+                    component(y, 150.0)?.max(0.0),
+                    hue(z)?,
+                ),
+                alpha,
+            )),
+            "oklab" => CssColor::Oklab(WithAlpha::new(
+                Oklab::new(
+                    component(x, 1.0)?.max(0.0),
+                    component(y, 0.4)?,
+                    component(z, 0.4)?,
+                ),
+                alpha,
+            )),
+            "oklch" => CssColor::OkLch(WithAlpha::new(
+                OkLch::new(
+                    component(x, 1.0)?.max(0.0),
+                    component(y, 0.4)?.max(0.0),
+                    hue(z)?,
+                ),
+                alpha,
+            )),
+            _ => unreachable!("unhandled color function {name}"),
+        };
+
+        Ok(color)
+    }
+}
+
+impl fmt::Display for CssColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        each_variant!(self, c => fmt::Display::fmt(c, f))
+    }
+}
+
+/// Error returned when parsing a [`CssColor`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseColorError {
+    /// The input is empty or only whitespace.
+    Empty,
+    /// Hex color with a number of digits other than 3, 4, 6 or 8.
+    InvalidHexLength(usize),
+    /// Character in a hex color which isn't a hex digit.
+    InvalidHexDigit(char),
+    /// Input which is neither a hex color nor a function, such as a named
+    /// color.
+    UnknownSyntax(String),
+    /// Function which isn't a supported color function.
+    UnknownFunction(String),
+    /// Color space in `color()` which isn't supported.
+    UnknownColorSpace(String),
+    /// Function without the closing parenthesis.
+    MissingClosingParenthesis,
+    /// Input after the closing parenthesis.
+    TrailingInput(String),
+    /// Wrong number of function arguments, not counting alpha.
+    WrongComponentCount {
+        /// Number of arguments the function takes.
+        expected: usize,
+        /// Number of arguments in the input.
+        found: usize,
+    },
+    /// Argument which isn't a number, percentage or angle, or isn't allowed in
+    /// its position (e.g. an angle as a lightness).
+    InvalidComponent(String),
+    /// Commas mixed with spaces or a slash, or used with a function which
+    /// doesn't support the legacy comma-separated syntax.
+    InvalidSeparators,
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseColorError::Empty => write!(f, "empty color string"),
+            ParseColorError::InvalidHexLength(len) => {
+                write!(f, "hex color has {len} digits, expected 3, 4, 6 or 8")
+            }
+            ParseColorError::InvalidHexDigit(c) => write!(f, "invalid hex digit {c:?}"),
+            ParseColorError::UnknownSyntax(s) => write!(f, "unsupported color syntax {s:?}"),
+            ParseColorError::UnknownFunction(name) => {
+                write!(f, "unknown color function {name:?}")
+            }
+            ParseColorError::UnknownColorSpace(name) => {
+                write!(f, "unknown color space {name:?}")
+            }
+            ParseColorError::MissingClosingParenthesis => write!(f, "missing closing parenthesis"),
+            ParseColorError::TrailingInput(s) => write!(f, "unexpected {s:?} after the color"),
+            ParseColorError::WrongComponentCount { expected, found } => {
+                write!(f, "expected {expected} arguments, found {found}")
+            }
+            ParseColorError::InvalidComponent(s) => write!(f, "invalid argument {s:?}"),
+            ParseColorError::InvalidSeparators => write!(f, "invalid argument separators"),
+        }
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+fn parse_hex(hex: &str) -> Result<WithAlpha<NonlinearSrgb>, ParseColorError> {
+    if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(ParseColorError::InvalidHexDigit(c));
+    }
+
+    let digits: Vec<u8> = hex
+        .chars()
+        .filter_map(|c| c.to_digit(16))
+        .map(|d| d as u8)
+        .collect();
+
+    // short forms repeat each digit, so `f` is `ff`
+    let bytes: Vec<u8> = match digits.len() {
+        3 | 4 => digits.iter().map(|d| d * 17).collect(),
+        6 | 8 => digits.chunks(2).map(|d| d[0] * 16 + d[1]).collect(),
+        len => return Err(ParseColorError::InvalidHexLength(len)),
+    };
+
+    let alpha = bytes.get(3).copied().unwrap_or(u8::MAX);
+    let color = NonlinearSrgb::new(bytes[0].as_f32(), bytes[1].as_f32(), bytes[2].as_f32());
+    Ok(WithAlpha::new(color, alpha.as_f32()))
+}
+
+fn parse_color_function(
+    space: &str,
+    components: &[&str],
+    alpha: f32,
+) -> Result<CssColor, ParseColorError> {
+    let [x, y, z] = expect_three(components)?;
+    let [x, y, z] = [component(x, 1.0)?, component(y, 1.0)?, component(z, 1.0)?];
+
+    let color = match space {
+        "srgb" => CssColor::Srgb(WithAlpha::new(NonlinearSrgb::new(x, y, z), alpha)),
+        "srgb-linear" => CssColor::LinearSrgb(WithAlpha::new(LinearSrgb::new(x, y, z), alpha)),
+        "display-p3" => CssColor::DisplayP3(WithAlpha::new(DisplayP3::new(x, y, z), alpha)),
+        "rec2020" => CssColor::Rec2020(WithAlpha::new(Rec2020::new(x, y, z), alpha)),
+        "xyz" | "xyz-d65" => CssColor::Xyz(WithAlpha::new(Xyz::new(x, y, z), alpha)),
+        "xyz-d50" => CssColor::XyzD50(WithAlpha::new(XyzD50::new(x, y, z), alpha)),
+        _ => return Err(ParseColorError::UnknownColorSpace(space.to_owned())),
+    };
+
+    Ok(color)
+}
+
+struct Arguments<'a> {
+    components: Vec<&'a str>,
+    alpha: Option<&'a str>,
+}
+
+/// Splits function arguments into components and alpha.
+///
+/// The legacy syntax separates all arguments with commas, the modern syntax
+/// uses spaces and an optional slash before alpha.
+fn split_arguments(args: &str, allow_legacy: bool) -> Result<Arguments<'_>, ParseColorError> {
+    if args.contains(',') {
+        if !allow_legacy || args.contains('/') {
+            return Err(ParseColorError::InvalidSeparators);
+        }
+
+        let mut components: Vec<&str> = args.split(',').map(str::trim).collect();
+        if components
+            .iter()
+            .any(|c| c.is_empty() || c.contains(char::is_whitespace))
+        {
+            return Err(ParseColorError::InvalidSeparators);
+        }
+
+        let alpha = if components.len() == 4 {
+            components.pop()
+        } else {
+            None
+        };
+
+        return Ok(Arguments { components, alpha });
+    }
+
+    let (components, alpha) = match args.split_once('/') {
+        Some((components, alpha)) => {
+            let mut tokens = alpha.split_whitespace();
+            let (Some(alpha), None) = (tokens.next(), tokens.next()) else {
+                return Err(ParseColorError::InvalidComponent(alpha.trim().to_owned()));
+            };
+            (components, Some(alpha))
+        }
+        None => (args, None),
+    };
+
+    Ok(Arguments {
+        components: components.split_whitespace().collect(),
+        alpha,
+    })
+}
+
+fn expect_three<'a>(components: &[&'a str]) -> Result<[&'a str; 3], ParseColorError> {
+    components
+        .try_into()
+        .map_err(|_| ParseColorError::WrongComponentCount {
+            expected: 3,
+            found: components.len(),
+        })
+}
+
+/// Parses a number, or a percentage where 100% is `percent_scale`.
+fn component(token: &str, percent_scale: f32) -> Result<f32, ParseColorError> {
+    if token == "none" {
+        return Ok(0.0);
+    }
+
+    match token.strip_suffix('%') {
+        Some(percentage) => Ok(number(percentage, token)? / 100.0 * percent_scale),
+        None => number(token, token),
+    }
+}
+
+/// Parses an angle or a number of degrees, returning radians between 0 and
+/// 2π.
+fn hue(token: &str) -> Result<f32, ParseColorError> {
+    if token == "none" {
+        return Ok(0.0);
+    }
+
+    const UNITS: [(&str, f32); 4] = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / std::f32::consts::PI),
+        ("turn", 360.0),
+    ];
+
+    let degrees = match UNITS
+        .iter()
+        .find_map(|&(unit, scale)| Some((token.strip_suffix(unit)?, scale)))
+    {
+        Some((value, scale)) => number(value, token)? * scale,
+        None => number(token, token)?,
+    };
+
+    Ok(degrees.to_radians().rem_euclid(TAU))
+}
+
+fn number(s: &str, token: &str) -> Result<f32, ParseColorError> {
+    // `f32::from_str` also accepts "inf" and "nan"
+    match s.parse::<f32>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(ParseColorError::InvalidComponent(token.to_owned())),
+    }
+}
+
+/// Converts HSL with hue in radians to sRGB, as specified by CSS.
+fn hsl_to_srgb(h: f32, s: f32, l: f32) -> NonlinearSrgb {
+    let h = h.to_degrees() / 30.0;
+    let a = s * l.min(1.0 - l);
+    let f = |n: f32| {
+        let k = (n + h).rem_euclid(12.0);
+        l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    NonlinearSrgb::new(f(0.0), f(8.0), f(4.0))
+}
+
+/// Colors with a CSS notation.
+///
+/// This provides [`Display`](fmt::Display) for the color types, both alone and
+/// in [`WithAlpha`]. The output can be parsed back with [`CssColor`].
+pub trait CssNotation {
+    /// Writes the color in CSS syntax, including alpha if it's given.
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result;
+}
+
+/// Writes a CSS function, such as `oklab(0.5 0.1 0 / 0.5)`.
+///
+/// `prefix` includes the opening parenthesis, and the color space for
+/// `color()`. Alpha is omitted when opaque.
+fn write_function(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    components: [f32; 3],
+    alpha: Option<f32>,
+) -> fmt::Result {
+    let [x, y, z] = components.map(Number);
+    write!(f, "{prefix}{x} {y} {z}")?;
+
+    match alpha {
+        Some(alpha) if alpha != 1.0 => write!(f, " / {})", Number(alpha)),
+        _ => write!(f, ")"),
+    }
+}
+
+/// Number with up to 5 decimal places and no trailing zeros.
+struct Number(f32);
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.0.is_finite() {
+            return write!(f, "none");
+        }
+
+        let s = format!("{:.5}", self.0);
+        let s = s.trim_end_matches('0').trim_end_matches('.');
+        f.write_str(if s == "-0" { "0" } else { s })
+    }
+}
+
+impl CssNotation for NonlinearSrgb<u8> {
+    /// Writes `#rrggbb`, or `#rrggbbaa` with alpha.
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+
+        match alpha.map(u8::from_f32) {
+            Some(alpha) if alpha != u8::MAX => write!(f, "{alpha:02x}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl CssNotation for NonlinearSrgb {
+    /// Writes `rgb()` with components between 0 and 255.
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        let rgb = [self.r, self.g, self.b].map(|c| c * 255.0);
+        write_function(f, "rgb(", rgb, alpha)
+    }
+}
+
+impl CssNotation for LinearSrgb {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "color(srgb-linear ", [self.r, self.g, self.b], alpha)
+    }
+}
+
+impl CssNotation for DisplayP3 {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "color(display-p3 ", [self.r, self.g, self.b], alpha)
+    }
+}
+
+impl CssNotation for Rec2020 {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "color(rec2020 ", [self.r, self.g, self.b], alpha)
+    }
+}
+
+impl CssNotation for Xyz {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "color(xyz-d65 ", [self.x, self.y, self.z], alpha)
+    }
+}
+
+impl CssNotation for XyzD50 {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "color(xyz-d50 ", [self.x, self.y, self.z], alpha)
+    }
+}
+
+impl CssNotation for Lab {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "lab(", [self.l, self.a, self.b], alpha)
+    }
+}
+
+impl CssNotation for Lch {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "lch(", [self.l, self.c, self.h.to_degrees()], alpha)
+    }
+}
+
+impl CssNotation for Oklab {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "oklab(", [self.l, self.a, self.b], alpha)
+    }
+}
+
+impl CssNotation for OkLch {
+    fn fmt_css(&self, f: &mut fmt::Formatter<'_>, alpha: Option<f32>) -> fmt::Result {
+        write_function(f, "oklch(", [self.l, self.c, self.h.to_degrees()], alpha)
+    }
+}
+
+macro_rules! impl_display {
+    ($($t:ty),*) => {
+        $(
+            impl fmt::Display for $t {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    self.fmt_css(f, None)
+                }
+            }
+        )*
+    };
+}
+
+impl_display!(
+    NonlinearSrgb<u8>,
+    NonlinearSrgb,
+    LinearSrgb,
+    DisplayP3,
+    Rec2020,
+    Xyz,
+    XyzD50,
+    Lab,
+    Lch,
+    Oklab,
+    OkLch
+);
+
+impl<C: CssNotation> fmt::Display for WithAlpha<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.color.fmt_css(f, Some(self.alpha))
+    }
+}
+
+impl fmt::Display for WithAlpha<NonlinearSrgb<u8>, u8> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.color.fmt_css(f, Some(self.alpha.as_f32()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> CssColor {
+        s.parse()
+            .unwrap_or_else(|e| panic!("failed to parse {s:?}: {e}"))
+    }
+
+    fn parse_err(s: &str) -> ParseColorError {
+        s.parse::<CssColor>()
+            .expect_err(&format!("{s:?} should not parse"))
+    }
+
+    fn srgb8(s: &str) -> (NonlinearSrgb<u8>, u8) {
+        let c = parse(s).to_color::<NonlinearSrgb<u8>>();
+        (c.color, u8::from_f32(c.alpha))
+    }
+
+    fn assert_close(a: [f32; 4], b: [f32; 4], context: &str) {
+        let close = a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-4);
+        assert!(close, "{context}: {a:?} != {b:?}");
+    }
+
+    fn components(c: &CssColor) -> [f32; 4] {
+        let alpha = c.alpha();
+        match *c {
+            CssColor::Srgb(c) => [c.color.r, c.color.g, c.color.b, alpha],
+            CssColor::LinearSrgb(c) => [c.color.r, c.color.g, c.color.b, alpha],
+            CssColor::DisplayP3(c) => [c.color.r, c.color.g, c.color.b, alpha],
+            CssColor::Rec2020(c) => [c.color.r, c.color.g, c.color.b, alpha],
+            CssColor::Xyz(c) => [c.color.x, c.color.y, c.color.z, alpha],
+            CssColor::XyzD50(c) => [c.color.x, c.color.y, c.color.z, alpha],
+            CssColor::Lab(c) => [c.color.l, c.color.a, c.color.b, alpha],
+            CssColor::Lch(c) => [c.color.l, c.color.c, c.color.h, alpha],
+            CssColor::Oklab(c) => [c.color.l, c.color.a, c.color.b, alpha],
+            CssColor::OkLch(c) => [c.color.l, c.color.c, c.color.h, alpha],
+        }
+    }
+
+    #[test]
+    fn hex() {
+        let orange = NonlinearSrgb::new(255, 136, 0);
+        assert_eq!(srgb8("#f80"), (orange, 255));
+        assert_eq!(srgb8("#F808"), (orange, 136));
+        assert_eq!(srgb8("#ff8800"), (orange, 255));
+        assert_eq!(srgb8("  #FF880080 "), (orange, 128));
+    }
+
+    #[test]
+    fn rgb() {
+        let expected = (NonlinearSrgb::new(255, 128, 0), 128);
+        assert_eq!(srgb8("rgb(255 128 0 / 0.5)"), expected);
+        assert_eq!(srgb8("rgb(100% 50.2% 0%/50%)"), expected);
+        assert_eq!(srgb8("rgba(255, 128, 0, 0.5)"), expected);
+        assert_eq!(srgb8("RGB(255,128,0,50%)"), expected);
+        assert_eq!(srgb8("rgb(300 none -5)").0, NonlinearSrgb::new(255, 0, 0));
+    }
+
+    #[test]
+    fn hsl() {
+        let green = (NonlinearSrgb::new(0, 255, 0), 255);
+        assert_eq!(srgb8("hsl(120 100% 50%)"), green);
+        assert_eq!(srgb8("hsl(120deg, 100%, 50%)"), green);
+        assert_eq!(srgb8("hsla(0.33333turn 100 50)"), green);
+        assert_eq!(
+            srgb8("hsl(-330 100% 50%)").0,
+            NonlinearSrgb::new(255, 128, 0)
+        );
+        assert_eq!(srgb8("hsl(30 100% 50% / 0.2)").1, 51);
+        assert_eq!(srgb8("hsl(210 50% 25%)").0, NonlinearSrgb::new(32, 64, 96));
+        assert_eq!(srgb8("hsl(0 0% 100%)").0, NonlinearSrgb::new(255, 255, 255));
+    }
+
+    #[test]
+    fn oklab_and_oklch() {
+        assert_eq!(
+            parse("oklab(0.5 0.1 -0.1)"),
+            CssColor::Oklab(WithAlpha::opaque(Oklab::new(0.5, 0.1, -0.1)))
+        );
+        assert_close(
+            components(&parse("oklab(50% 25% -25% / 0.3)")),
+            [0.5, 0.1, -0.1, 0.3],
+            "percentages",
+        );
+
+        // CSS red
+        let red = parse("oklch(62.796% 0.25768 29.2339deg)");
+        assert!(matches!(red, CssColor::OkLch(_)));
+        assert_eq!(srgb8(&red.to_string()).0, NonlinearSrgb::new(255, 0, 0));
+
+        let c = components(&parse("oklch(0.7 100% 0.5turn)"));
+        assert_close(c, [0.7, 0.4, std::f32::consts::PI, 1.0], "oklch");
+    }
+
+    #[test]
+    fn lab_and_lch() {
+        // CSS red
+        let lab = parse("lab(54.2905 80.8049 69.891)");
+        let lch = parse("lch(54.2905 106.8372 40.8577)");
+        assert_eq!(srgb8(&lab.to_string()).0, NonlinearSrgb::new(255, 0, 0));
+        assert_eq!(srgb8(&lch.to_string()).0, NonlinearSrgb::new(255, 0, 0));
+        assert_close(
+            components(&parse("lab(50% 100% -100%)")),
+            [50.0, 125.0, -125.0, 1.0],
+            "lab percentages",
+        );
+    }
+
+    #[test]
+    fn color_function() {
+        let c = parse("color(display-p3 1 0 0 / 25%)");
+        assert_eq!(
+            c,
+            CssColor::DisplayP3(WithAlpha::new(DisplayP3::new(1.0, 0.0, 0.0), 0.25))
+        );
+
+        // outside of sRGB
+        let linear = c.to_linear_srgb().color;
+        assert!(linear.r > 1.0 && linear.g < 0.0);
+
+        assert_eq!(
+            parse("color(srgb 100% 50% 0)"),
+            CssColor::Srgb(WithAlpha::opaque(NonlinearSrgb::new(1.0, 0.5, 0.0)))
+        );
+        assert!(matches!(parse("color(xyz 0.5 0.5 0.5)"), CssColor::Xyz(_)));
+        assert!(matches!(
+            parse("color(xyz-d50 0.5 0.5 0.5)"),
+            CssColor::XyzD50(_)
+        ));
+        assert!(matches!(
+            parse("color(rec2020 1 1 1)"),
+            CssColor::Rec2020(_)
+        ));
+        assert!(matches!(
+            parse("color(srgb-linear 1 1 1)"),
+            CssColor::LinearSrgb(_)
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        use ParseColorError as E;
+
+        assert_eq!(parse_err(""), E::Empty);
+        assert_eq!(parse_err("  "), E::Empty);
+        assert_eq!(parse_err("#12"), E::InvalidHexLength(2));
+        assert_eq!(parse_err("#12345"), E::InvalidHexLength(5));
+        assert_eq!(parse_err("#"), E::InvalidHexLength(0));
+        assert_eq!(parse_err("#12g"), E::InvalidHexDigit('g'));
+        assert_eq!(parse_err("red"), E::UnknownSyntax("red".into()));
+        assert_eq!(
+            parse_err("cmyk(0 0 0 0)"),
+            E::UnknownFunction("cmyk".into())
+        );
+        assert_eq!(
+            parse_err("color(adobe-rgb 1 1 1)"),
+            E::UnknownColorSpace("adobe-rgb".into())
+        );
+        assert_eq!(parse_err("rgb(1 2 3"), E::MissingClosingParenthesis);
+        assert_eq!(parse_err("rgb(1 2 3) x"), E::TrailingInput("x".into()));
+        assert_eq!(
+            parse_err("rgb(1 2)"),
+            E::WrongComponentCount {
+                expected: 3,
+                found: 2
+            }
+        );
+        assert_eq!(
+            parse_err("rgb(1, 2, 3, 4, 5)"),
+            E::WrongComponentCount {
+                expected: 3,
+                found: 5
+            }
+        );
+        assert_eq!(
+            parse_err("color()"),
+            E::WrongComponentCount {
+                expected: 4,
+                found: 0
+            }
+        );
+        assert_eq!(parse_err("rgb(1 x 3)"), E::InvalidComponent("x".into()));
+        assert_eq!(parse_err("rgb(1 inf 3)"), E::InvalidComponent("inf".into()));
+        assert_eq!(parse_err("rgb(1 2 3 / )"), E::InvalidComponent("".into()));
+        assert_eq!(
+            parse_err("rgb(1 2 3 / 1 2)"),
+            E::InvalidComponent("1 2".into())
+        );
+        assert_eq!(
+            parse_err("oklab(90deg 0 0)"),
+            E::InvalidComponent("90deg".into())
+        );
+        assert_eq!(
+            parse_err("oklch(1 0 50%)"),
+            E::InvalidComponent("50%".into())
+        );
+        assert_eq!(parse_err("rgb(1, 2 3)"), E::InvalidSeparators);
+        assert_eq!(parse_err("rgb(1, 2, 3 / 1)"), E::InvalidSeparators);
+        assert_eq!(parse_err("oklab(1, 0, 0)"), E::InvalidSeparators);
+    }
+
+    #[test]
+    fn display() {
+        let orange = NonlinearSrgb::<u8>::new(255, 136, 0);
+        assert_eq!(orange.to_string(), "#ff8800");
+        assert_eq!(WithAlpha::new(orange, 128u8).to_string(), "#ff880080");
+        assert_eq!(WithAlpha::new(orange, 255u8).to_string(), "#ff8800");
+
+        assert_eq!(
+            WithAlpha::new(Oklab::new(0.5, -0.125, 0.0), 0.5).to_string(),
+            "oklab(0.5 -0.125 0 / 0.5)"
+        );
+        assert_eq!(
+            OkLch::new(0.7, 0.1, std::f32::consts::FRAC_PI_2).to_string(),
+            "oklch(0.7 0.1 90)"
+        );
+        assert_eq!(
+            DisplayP3::new(1.0, 0.5, 0.0).to_string(),
+            "color(display-p3 1 0.5 0)"
+        );
+        assert_eq!(
+            NonlinearSrgb::new(1.0, 0.5, 0.0).to_string(),
+            "rgb(255 127.5 0)"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let inputs = [
+            "#12345678",
+            "rgb(10 20 30 / 0.4)",
+            "hsl(200 40% 60%)",
+            "color(srgb-linear 0.1 1.2 -0.3 / 0.75)",
+            "color(display-p3 0.9 0.1 0.2)",
+            "color(rec2020 0.3 0.6 0.9 / 0)",
+            "color(xyz-d65 0.2 0.3 0.4)",
+            "color(xyz-d50 0.2 0.3 0.4)",
+            "lab(40 -20 30 / 0.9)",
+            "lch(70 50 300)",
+            "oklab(0.3 0.05 -0.2)",
+            "oklch(0.8 0.15 145 / 0.6)",
+        ];
+
+        for input in inputs {
+            let color = parse(input);
+            let output = color.to_string();
+            let parsed = parse(&output);
+
+            assert_eq!(
+                std::mem::discriminant(&color),
+                std::mem::discriminant(&parsed),
+                "{input} -> {output}"
+            );
+            assert_close(
+                components(&color),
+                components(&parsed),
+                &format!("{input} -> {output}"),
+            );
+        }
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for (r, g, b, a) in [(0, 0, 0, 0), (255, 255, 255, 255), (18, 52, 86, 120)] {
+            let color = WithAlpha::new(NonlinearSrgb::<u8>::new(r, g, b), a);
+            let parsed = srgb8(&color.to_string());
+            assert_eq!((color.color, color.alpha), parsed);
+        }
+    }
+}
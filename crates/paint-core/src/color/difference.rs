@@ -0,0 +1,325 @@
+use super::{Color, Lab, LinearSrgb, Oklab};
+
+/// Color difference formula (ΔE), measuring how different two colors look.
+///
+/// The scales differ: a just noticeable difference is roughly 0.02 for
+/// [`DeltaE::Ok`] and roughly 1 to 2 for the CIE formulas.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum DeltaE {
+    /// Euclidean distance in Oklab, as used by CSS gamut mapping.
+    #[default]
+    Ok,
+    /// CIE 1976, Euclidean distance in CIELAB.
+    Cie76,
+    /// CIE 1994 with the graphic arts weights. Isn't symmetric.
+    Cie94,
+    /// CIEDE2000, the most accurate CIE formula.
+    Ciede2000,
+}
+
+impl DeltaE {
+    /// All difference formulas.
+    pub const ALL: [DeltaE; 4] = [DeltaE::Ok, DeltaE::Cie76, DeltaE::Cie94, DeltaE::Ciede2000];
+
+    /// Computes the difference of `sample` from `reference`.
+    pub fn difference(self, reference: LinearSrgb, sample: LinearSrgb) -> f32 {
+        match self {
+            DeltaE::Ok => {
+                Oklab::from_linear_srgb(reference).delta_e(&Oklab::from_linear_srgb(sample))
+            }
+            DeltaE::Cie76 => {
+                Lab::from_linear_srgb(reference).delta_e_76(&Lab::from_linear_srgb(sample))
+            }
+            DeltaE::Cie94 => {
+                Lab::from_linear_srgb(reference).delta_e_94(&Lab::from_linear_srgb(sample))
+            }
+            DeltaE::Ciede2000 => {
+                Lab::from_linear_srgb(reference).delta_e_2000(&Lab::from_linear_srgb(sample))
+            }
+        }
+    }
+}
+
+impl Oklab {
+    /// ΔE OK, the Euclidean distance to another color.
+    pub fn delta_e(&self, other: &Oklab) -> f32 {
+        let [dl, da, db] = [self.l - other.l, self.a - other.a, self.b - other.b];
+        (dl * dl + da * da + db * db).sqrt()
+    }
+}
+
+impl Lab {
+    /// CIE76 ΔE*ab, the Euclidean distance to another color.
+    pub fn delta_e_76(&self, other: &Lab) -> f32 {
+        let [dl, da, db] = [self.l - other.l, self.a - other.a, self.b - other.b];
+        (dl * dl + da * da + db * db).sqrt()
+    }
+
+    /// CIE94 ΔE*94 of `sample` from this reference color, with the graphic
+    /// arts weights (kL = 1, K1 = 0.045, K2 = 0.015).
+    pub fn delta_e_94(&self, sample: &Lab) -> f32 {
+        let [l1, a1, b1] = [self.l, self.a, self.b].map(f64::from);
+        let [l2, a2, b2] = [sample.l, sample.a, sample.b].map(f64::from);
+
+        let c1 = a1.hypot(b1);
+        let c2 = a2.hypot(b2);
+
+        let dl = l1 - l2;
+        let dc = c1 - c2;
+        let (da, db) = (a1 - a2, b1 - b2);
+        // ΔH² can be slightly negative due to rounding
+        let dh2 = (da * da + db * db - dc * dc).max(0.0);
+
+        let sc = 1.0 + 0.045 * c1;
+        let sh = 1.0 + 0.015 * c1;
+
+        (dl * dl + (dc / sc).powi(2) + dh2 / (sh * sh)).sqrt() as f32
+    }
+
+    /// CIEDE2000 ΔE00 to another color, with unit weights (kL = kC = kH = 1).
+    ///
+    /// Follows "The CIEDE2000 Color-Difference Formula: Implementation Notes,
+    /// Supplementary Test Data, and Mathematical Observations" by Sharma, Wu
+    /// and Dalal.
+    pub fn delta_e_2000(&self, other: &Lab) -> f32 {
+        let [l1, a1, b1] = [self.l, self.a, self.b].map(f64::from);
+        let [l2, a2, b2] = [other.l, other.a, other.b].map(f64::from);
+
+        let pow7 = |x: f64| x.powi(7);
+        const POW7_25: f64 = 6_103_515_625.0;
+
+        let c_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+        let g = 0.5 * (1.0 - (pow7(c_mean) / (pow7(c_mean) + POW7_25)).sqrt());
+
+        let a1 = (1.0 + g) * a1;
+        let a2 = (1.0 + g) * a2;
+        let c1 = a1.hypot(b1);
+        let c2 = a2.hypot(b2);
+
+        let hue = |a: f64, b: f64| {
+            if a == 0.0 && b == 0.0 {
+                0.0
+            } else {
+                b.atan2(a).to_degrees().rem_euclid(360.0)
+            }
+        };
+        let h1 = hue(a1, b1);
+        let h2 = hue(a2, b2);
+
+        let chromatic = c1 * c2 != 0.0;
+
+        let dl = l2 - l1;
+        let dc = c2 - c1;
+        let dh = match h2 - h1 {
+            _ if !chromatic => 0.0,
+            d if d > 180.0 => d - 360.0,
+            d if d < -180.0 => d + 360.0,
+            d => d,
+        };
+        let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+        let l_mean = (l1 + l2) / 2.0;
+        let c_mean = (c1 + c2) / 2.0;
+        let h_mean = if !chromatic {
+            h1 + h2
+        } else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        } else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        } else {
+            (h1 + h2 - 360.0) / 2.0
+        };
+
+        let cos = |degrees: f64| degrees.to_radians().cos();
+        let t = 1.0 - 0.17 * cos(h_mean - 30.0)
+            + 0.24 * cos(2.0 * h_mean)
+            + 0.32 * cos(3.0 * h_mean + 6.0)
+            - 0.20 * cos(4.0 * h_mean - 63.0);
+
+        let d_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+        let rc = 2.0 * (pow7(c_mean) / (pow7(c_mean) + POW7_25)).sqrt();
+        let l50 = (l_mean - 50.0).powi(2);
+        let sl = 1.0 + 0.015 * l50 / (20.0 + l50).sqrt();
+        let sc = 1.0 + 0.045 * c_mean;
+        let sh = 1.0 + 0.015 * c_mean * t;
+        let rt = -(2.0 * d_theta).to_radians().sin() * rc;
+
+        let (l, c, h) = (dl / sl, dc / sc, dh / sh);
+        (l * l + c * c + h * h + rt * c * h).sqrt() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pairs from the supplementary test data by Sharma et al., with the
+    /// expected CIEDE2000 difference.
+    const SHARMA: [([f32; 3], [f32; 3], f32); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2972, 0.0], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 1.8634, 0.5757], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2592, 0.3350], 1.0000),
+        (
+            [60.2574, -34.0099, 36.2677],
+            [60.4626, -34.1751, 39.4387],
+            1.2644,
+        ),
+        (
+            [63.0109, -31.0961, -5.8663],
+            [62.8187, -29.7946, -4.0864],
+            1.2630,
+        ),
+        (
+            [61.2901, 3.7196, -5.3901],
+            [61.4292, 2.2480, -4.9620],
+            1.8731,
+        ),
+        (
+            [35.0831, -44.1164, 3.7933],
+            [35.0232, -40.0716, 1.5901],
+            1.8645,
+        ),
+        (
+            [22.7233, 20.0904, -46.6940],
+            [23.0331, 14.9730, -42.5619],
+            2.0373,
+        ),
+        (
+            [36.4612, 47.8580, 18.3852],
+            [36.2715, 50.5065, 21.2231],
+            1.4146,
+        ),
+        (
+            [90.8027, -2.0831, 1.4410],
+            [91.1528, -1.6435, 0.0447],
+            1.4441,
+        ),
+        (
+            [90.9257, -0.5406, -0.9208],
+            [88.6381, -0.8985, -0.7239],
+            1.5381,
+        ),
+        (
+            [6.7747, -0.2908, -2.4247],
+            [5.8714, -0.0985, -2.2286],
+            0.6377,
+        ),
+        (
+            [2.0776, 0.0795, -1.1350],
+            [0.9033, -0.0636, -0.5514],
+            0.9082,
+        ),
+    ];
+
+    fn lab([l, a, b]: [f32; 3]) -> Lab {
+        Lab::new(l, a, b)
+    }
+
+    #[test]
+    fn ciede2000_sharma() {
+        for (i, (a, b, expected)) in SHARMA.into_iter().enumerate() {
+            let (a, b) = (lab(a), lab(b));
+            let forward = a.delta_e_2000(&b);
+            let backward = b.delta_e_2000(&a);
+
+            assert!(
+                (forward - expected).abs() < 1e-4,
+                "pair {}: {forward}, expected {expected}",
+                i + 1
+            );
+            assert!(
+                (forward - backward).abs() < 1e-5,
+                "pair {} isn't symmetric",
+                i + 1
+            );
+        }
+    }
+
+    #[test]
+    fn cie94() {
+        // Sharma pairs 1, 8, 17 and 25, expected values from the reference
+        // formula
+        let cases = [
+            (SHARMA[0], 1.3950),
+            (SHARMA[7], 2.0316),
+            (SHARMA[16], 34.6892),
+            (SHARMA[24], 1.3910),
+        ];
+
+        for ((a, b, _), expected) in cases {
+            let actual = lab(a).delta_e_94(&lab(b));
+            assert!(
+                (actual - expected).abs() < 1e-4,
+                "{actual}, expected {expected}"
+            );
+        }
+
+        // the reference chroma weights the difference
+        let gray = Lab::new(50.0, 0.0, 0.0);
+        let green = Lab::new(50.0, -1.0, 2.0);
+        assert!((gray.delta_e_94(&green) - 2.2361).abs() < 1e-4);
+        assert!((green.delta_e_94(&gray) - 2.0316).abs() < 1e-4);
+    }
+
+    #[test]
+    fn cie76() {
+        let a = Lab::new(50.0, 2.5, 0.0);
+        let b = Lab::new(73.0, 25.0, -18.0);
+        assert!((a.delta_e_76(&b) - 36.8680).abs() < 1e-4);
+        assert_eq!(a.delta_e_76(&a), 0.0);
+    }
+
+    #[test]
+    fn delta_e_ok() {
+        let white = Oklab::from_linear_srgb(LinearSrgb::new(1.0, 1.0, 1.0));
+        let black = Oklab::from_linear_srgb(LinearSrgb::new(0.0, 0.0, 0.0));
+        assert!((white.delta_e(&black) - 1.0).abs() < 1e-4);
+
+        let a = Oklab::new(0.5, 0.1, -0.1);
+        let b = Oklab::new(0.6, 0.1, 0.0);
+        assert!((a.delta_e(&b) - 0.02f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn identical_colors() {
+        let c = LinearSrgb::new(0.2, 0.5, 0.7);
+        for formula in DeltaE::ALL {
+            assert!(formula.difference(c, c) < 1e-5, "{formula:?}");
+        }
+    }
+
+    #[test]
+    fn formulas_agree_on_order() {
+        let red = LinearSrgb::new(1.0, 0.0, 0.0);
+        let dark_red = LinearSrgb::new(0.8, 0.0, 0.0);
+        let blue = LinearSrgb::new(0.0, 0.0, 1.0);
+
+        for formula in DeltaE::ALL {
+            assert!(
+                formula.difference(red, dark_red) < formula.difference(red, blue),
+                "{formula:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Color, Component, LinearSrgb, Oklab, gamut, srgb, transform};
+
+/// Linear Display P3 to linear sRGB. Both use the D65 white point.
+const TO_SRGB: [[f64; 3]; 3] = [
+    [1.2249401763, -0.2249401763, 0.0],
+    [-0.0420569547, 1.0420569547, 0.0],
+    [-0.0196375546, -0.0786360456, 1.0982736001],
+];
+
+/// Linear sRGB to linear Display P3.
+const FROM_SRGB: [[f64; 3]; 3] = [
+    [0.8224619687, 0.1775380313, 0.0],
+    [0.0331941989, 0.9668058011, 0.0],
+    [0.0170826307, 0.0723974407, 0.9105199286],
+];
+
+/// Linear Display P3 color.
+///
+/// Display P3 has the DCI-P3 primaries with the D65 white point, and covers
+/// about 25% more colors than sRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LinearDisplayP3<T = f32> {
+    /// Red channel.
+    pub r: T,
+    /// Green channel.
+    pub g: T,
+    /// Blue channel.
+    pub b: T,
+}
+
+impl<T> LinearDisplayP3<T> {
+    /// Constructs a color with the provided RGB components.
+    pub const fn new(r: T, g: T, b: T) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl LinearDisplayP3 {
+    /// Whether the color is inside the Display P3 gamut.
+    pub fn is_in_gamut(&self) -> bool {
+        gamut::is_unit_cube([self.r, self.g, self.b])
+    }
+
+    /// Maps the color into the Display P3 gamut, reducing chroma while
+    /// preserving lightness and hue.
+    pub fn gamut_clip(&self) -> Self {
+        let [r, g, b] = gamut::clip_chroma(self.to_linear_srgb(), |c| {
+            transform(&FROM_SRGB, [c.r, c.g, c.b])
+        });
+        Self::new(r, g, b)
+    }
+}
+
+impl<T: Component> Color for LinearDisplayP3<T> {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        let [r, g, b] = transform(&FROM_SRGB, [c.r, c.g, c.b]);
+        Self::new(T::from_f32(r), T::from_f32(g), T::from_f32(b))
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        let rgb = [self.r.as_f32(), self.g.as_f32(), self.b.as_f32()];
+        let [r, g, b] = transform(&TO_SRGB, rgb);
+        LinearSrgb::new(r, g, b)
+    }
+
+    fn to_linear_srgb_clamped(&self) -> LinearSrgb {
+        Oklab::from_linear_srgb(self.to_linear_srgb()).to_linear_srgb_clamped()
+    }
+}
+
+/// Display P3 color, encoded with the sRGB transfer function.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DisplayP3<T = f32> {
+    /// Red channel.
+    pub r: T,
+    /// Green channel.
+    pub g: T,
+    /// Blue channel.
+    pub b: T,
+}
+
+impl<T> DisplayP3<T> {
+    /// Constructs a color with the provided RGB components.
+    pub const fn new(r: T, g: T, b: T) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl<T: Component> DisplayP3<T> {
+    /// Converts to linear Display P3.
+    pub fn to_linear(&self) -> LinearDisplayP3 {
+        let f = |x: T| srgb::transfer_decode(x.as_f32());
+        LinearDisplayP3::new(f(self.r), f(self.g), f(self.b))
+    }
+
+    /// Converts from linear Display P3.
+    pub fn from_linear(c: LinearDisplayP3) -> Self {
+        let f = |x: f32| T::from_f32(srgb::transfer_encode(x));
+        Self::new(f(c.r), f(c.g), f(c.b))
+    }
+
+    /// Whether the color is inside the Display P3 gamut.
+    pub fn is_in_gamut(&self) -> bool {
+        self.to_linear().is_in_gamut()
+    }
+
+    /// Maps the color into the Display P3 gamut, reducing chroma while
+    /// preserving lightness and hue.
+    pub fn gamut_clip(&self) -> Self {
+        Self::from_linear(self.to_linear().gamut_clip())
+    }
+}
+
+impl<T: Component> Color for DisplayP3<T> {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        Self::from_linear(LinearDisplayP3::from_linear_srgb(c))
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        self.to_linear().to_linear_srgb()
+    }
+
+    fn to_linear_srgb_clamped(&self) -> LinearSrgb {
+        self.to_linear().to_linear_srgb_clamped()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::NonlinearSrgb;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        let step = 15;
+        for r in (0..=255u8).step_by(step) {
+            for g in (0..=255u8).step_by(step) {
+                for b in (0..=255u8).step_by(step) {
+                    let c1 = NonlinearSrgb::new(r, g, b);
+                    let p3 = DisplayP3::<f32>::from_linear_srgb(c1.to_linear_srgb());
+                    assert!(p3.is_in_gamut());
+                    let c2 = NonlinearSrgb::<u8>::from_linear_srgb(p3.to_linear_srgb());
+                    assert_eq!(c1, c2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_primaries() {
+        // values from CSS Color 4
+        let red = DisplayP3::<f32>::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert_close([red.r, red.g, red.b], [0.91749, 0.20029, 0.13856]);
+
+        let green = DisplayP3::<f32>::from_linear_srgb(LinearSrgb::new(0.0, 1.0, 0.0));
+        assert_close([green.r, green.g, green.b], [0.45840, 0.98526, 0.29829]);
+
+        let white = DisplayP3::<f32>::from_linear_srgb(LinearSrgb::new(1.0, 1.0, 1.0));
+        assert_close([white.r, white.g, white.b], [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn p3_red_is_outside_srgb() {
+        let red = LinearDisplayP3::new(1.0, 0.0, 0.0).to_linear_srgb();
+        assert_close([red.r, red.g, red.b], [1.22494, -0.04206, -0.01964]);
+
+        let clamped = LinearDisplayP3::new(1.0, 0.0, 0.0).to_linear_srgb_clamped();
+        for x in [clamped.r, clamped.g, clamped.b] {
+            assert!((0.0..=1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn gamut_clip_preserves_lightness_and_hue() {
+        // scRGB color outside of both sRGB and Display P3
+        let c = LinearSrgb::new(-0.2, 1.1, -0.1);
+        let p3 = LinearDisplayP3::<f32>::from_linear_srgb(c);
+        assert!(!p3.is_in_gamut());
+
+        let clipped = p3.gamut_clip();
+        assert!(clipped.is_in_gamut());
+
+        let lab1 = Oklab::from_linear_srgb(c);
+        let lab2 = Oklab::from_linear_srgb(clipped.to_linear_srgb());
+        assert!((lab1.l - lab2.l).abs() < 1e-3);
+        let hue1 = lab1.b.atan2(lab1.a);
+        let hue2 = lab2.b.atan2(lab2.a);
+        assert!((hue1 - hue2).abs() < 1e-3);
+
+        // colors in gamut are left as is
+        let inside = LinearDisplayP3::new(0.2, 0.5, 0.9);
+        assert_eq!(inside.gamut_clip(), inside);
+    }
+}
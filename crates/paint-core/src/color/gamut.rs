@@ -0,0 +1,39 @@
+use super::{Color, LinearSrgb, Oklab};
+
+/// Tolerance for components slightly outside of the gamut due to rounding.
+const EPSILON: f32 = 1e-5;
+
+/// Whether all components are between 0 and 1, within rounding errors.
+pub(super) fn is_unit_cube(c: [f32; 3]) -> bool {
+    c.iter().all(|x| (-EPSILON..=1.0 + EPSILON).contains(x))
+}
+
+/// Maps a color into an RGB gamut by reducing its Oklab chroma, preserving
+/// lightness and hue. Lightness outside of 0..1 is clamped first.
+///
+/// `to_rgb` converts linear sRGB to the linear RGB space of the gamut.
+pub(super) fn clip_chroma(c: LinearSrgb, to_rgb: impl Fn(LinearSrgb) -> [f32; 3]) -> [f32; 3] {
+    let rgb = to_rgb(c);
+    if is_unit_cube(rgb) {
+        return rgb.map(|x| x.clamp(0.0, 1.0));
+    }
+
+    let lab = Oklab::from_linear_srgb(c);
+    let l = lab.l.clamp(0.0, 1.0);
+
+    // binary search for the largest chroma scale which is still in gamut
+    let mut low = 0.0;
+    let mut high = 1.0;
+    for _ in 0..24 {
+        let mid = 0.5 * (low + high);
+        let rgb = to_rgb(Oklab::new(l, lab.a * mid, lab.b * mid).to_linear_srgb());
+        if is_unit_cube(rgb) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    let rgb = to_rgb(Oklab::new(l, lab.a * low, lab.b * low).to_linear_srgb());
+    rgb.map(|x| x.clamp(0.0, 1.0))
+}
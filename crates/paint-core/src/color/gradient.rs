@@ -0,0 +1,521 @@
+use std::borrow::Cow;
+use std::f32::consts::{PI, TAU};
+
+use glam::UVec2;
+use half::f16;
+
+use super::{Color, LinearSrgb, OkLch, Okhsv, Oklab, WithAlpha};
+use crate::persistence;
+
+/// Chroma or saturation below which a color is treated as achromatic, and its
+/// hue is ignored.
+const ACHROMATIC_THRESHOLD: f32 = 1e-4;
+
+/// Color space in which gradient colors are interpolated.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum InterpolationSpace {
+    /// Linear light, the same as mixing light physically.
+    LinearSrgb,
+    /// Oklab, which mixes roughly perceptually uniformly.
+    #[default]
+    Oklab,
+    /// OkLCh, keeping chroma while the hue changes.
+    OkLch(HueInterpolation),
+    /// Okhsv, keeping saturation while the hue changes.
+    Okhsv(HueInterpolation),
+}
+
+/// Direction in which hue goes around the color wheel, as in CSS.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum HueInterpolation {
+    /// Takes the shorter way around.
+    #[default]
+    Shorter,
+    /// Takes the longer way around.
+    Longer,
+    /// Always increases the hue.
+    Increasing,
+    /// Always decreases the hue.
+    Decreasing,
+}
+
+impl HueInterpolation {
+    /// Adjusts hues in radians, so that linear interpolation between them goes
+    /// in this direction.
+    fn fix_up(self, mut a: f32, mut b: f32) -> (f32, f32) {
+        let diff = b - a;
+        match self {
+            HueInterpolation::Shorter => {
+                if diff > PI {
+                    a += TAU;
+                } else if diff < -PI {
+                    b += TAU;
+                }
+            }
+            HueInterpolation::Longer => {
+                if 0.0 < diff && diff < PI {
+                    a += TAU;
+                } else if -PI < diff && diff <= 0.0 {
+                    b += TAU;
+                }
+            }
+            HueInterpolation::Increasing => {
+                if b < a {
+                    b += TAU;
+                }
+            }
+            HueInterpolation::Decreasing => {
+                if a < b {
+                    a += TAU;
+                }
+            }
+        }
+        (a, b)
+    }
+}
+
+/// Easing of the transition between two gradient stops.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    /// Starts slowly.
+    EaseIn,
+    /// Ends slowly.
+    EaseOut,
+    /// Starts and ends slowly.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps progress between 0 and 1 to eased progress between 0 and 1.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+impl InterpolationSpace {
+    /// Interpolates between two colors, `t` being between 0 and 1.
+    ///
+    /// Colors are premultiplied by alpha, so a transparent color doesn't tint
+    /// the other one. Hue isn't premultiplied, and the hue of an achromatic
+    /// color is taken from the other color.
+    pub fn interpolate(
+        self,
+        a: WithAlpha<LinearSrgb>,
+        b: WithAlpha<LinearSrgb>,
+        t: f32,
+    ) -> WithAlpha<LinearSrgb> {
+        let mut x = self.encode(a.color);
+        let mut y = self.encode(b.color);
+
+        let hue = self.hue_axes();
+        if let Some((hue, chroma, mode)) = hue {
+            if x[chroma] < ACHROMATIC_THRESHOLD {
+                x[hue] = y[hue];
+            }
+            if y[chroma] < ACHROMATIC_THRESHOLD {
+                y[hue] = x[hue];
+            }
+            (x[hue], y[hue]) = mode.fix_up(x[hue], y[hue]);
+        }
+
+        let is_hue = |i: usize| hue.is_some_and(|(hue, _, _)| hue == i);
+        let alpha = lerp(a.alpha, b.alpha, t);
+
+        let mut mixed = [0.0; 3];
+        for i in 0..3 {
+            mixed[i] = if is_hue(i) {
+                lerp(x[i], y[i], t).rem_euclid(TAU)
+            } else {
+                let premultiplied = lerp(x[i] * a.alpha, y[i] * b.alpha, t);
+                if alpha > 0.0 {
+                    premultiplied / alpha
+                } else {
+                    0.0
+                }
+            };
+        }
+
+        WithAlpha::new(self.decode(mixed), alpha)
+    }
+
+    fn encode(self, c: LinearSrgb) -> [f32; 3] {
+        match self {
+            InterpolationSpace::LinearSrgb => [c.r, c.g, c.b],
+            InterpolationSpace::Oklab => {
+                let c = Oklab::from_linear_srgb(c);
+                [c.l, c.a, c.b]
+            }
+            InterpolationSpace::OkLch(_) => {
+                let c = OkLch::from_linear_srgb(c);
+                [c.l, c.c, c.h]
+            }
+            InterpolationSpace::Okhsv(_) => {
+                let c = Okhsv::from_linear_srgb(c);
+                [c.h, c.s, c.v]
+            }
+        }
+    }
+
+    fn decode(self, [x, y, z]: [f32; 3]) -> LinearSrgb {
+        match self {
+            InterpolationSpace::LinearSrgb => LinearSrgb::new(x, y, z),
+            InterpolationSpace::Oklab => Oklab::new(x, y, z).to_linear_srgb(),
+            InterpolationSpace::OkLch(_) => OkLch::new(x, y, z).to_linear_srgb(),
+            InterpolationSpace::Okhsv(_) => Okhsv::new(x, y, z).to_linear_srgb(),
+        }
+    }
+
+    /// Indices of the hue and chroma (or saturation) components for polar
+    /// spaces.
+    fn hue_axes(self) -> Option<(usize, usize, HueInterpolation)> {
+        match self {
+            InterpolationSpace::LinearSrgb | InterpolationSpace::Oklab => None,
+            InterpolationSpace::OkLch(mode) => Some((2, 1, mode)),
+            InterpolationSpace::Okhsv(mode) => Some((0, 1, mode)),
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// A color at a position of a [`Gradient`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientStop {
+    /// Position, usually between 0 and 1.
+    pub position: f32,
+    /// Color at the position.
+    pub color: WithAlpha<LinearSrgb>,
+    /// Easing of the transition to the next stop.
+    pub easing: Easing,
+}
+
+impl GradientStop {
+    /// Creates a stop with linear easing.
+    pub fn new(position: f32, color: WithAlpha<LinearSrgb>) -> Self {
+        Self {
+            position,
+            color,
+            easing: Easing::Linear,
+        }
+    }
+}
+
+/// Smooth transition between colors at multiple stops.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gradient {
+    /// Stops sorted by position.
+    stops: Vec<GradientStop>,
+    /// Color space for the interpolation.
+    pub space: InterpolationSpace,
+}
+
+impl Gradient {
+    /// Creates a gradient without stops.
+    pub fn new(space: InterpolationSpace) -> Self {
+        Self {
+            stops: Vec::new(),
+            space,
+        }
+    }
+
+    /// Creates a gradient from `start` at 0 to `end` at 1.
+    pub fn two_color(
+        start: WithAlpha<LinearSrgb>,
+        end: WithAlpha<LinearSrgb>,
+        space: InterpolationSpace,
+    ) -> Self {
+        let mut gradient = Self::new(space);
+        gradient.add_stop(GradientStop::new(0.0, start));
+        gradient.add_stop(GradientStop::new(1.0, end));
+        gradient
+    }
+
+    /// Stops sorted by position.
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// Adds a stop, returning its index.
+    ///
+    /// A stop at the same position as existing stops is placed after them,
+    /// which creates a hard transition.
+    pub fn add_stop(&mut self, stop: GradientStop) -> usize {
+        let index = self.stops.partition_point(|s| s.position <= stop.position);
+        self.stops.insert(index, stop);
+        index
+    }
+
+    /// Removes the stop at the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn remove_stop(&mut self, index: usize) -> GradientStop {
+        self.stops.remove(index)
+    }
+
+    /// Samples the color at the position.
+    ///
+    /// Positions before the first stop or after the last stop have the color
+    /// of that stop. A gradient without stops is transparent.
+    pub fn sample(&self, position: f32) -> WithAlpha<LinearSrgb> {
+        let next = self.stops.partition_point(|s| s.position <= position);
+
+        let (prev, next) = match (next.checked_sub(1), self.stops.get(next)) {
+            (Some(prev), Some(next)) => (&self.stops[prev], next),
+            (Some(prev), None) => return self.stops[prev].color,
+            (None, Some(next)) => return next.color,
+            (None, None) => return WithAlpha::transparent(LinearSrgb::default()),
+        };
+
+        let t = (position - prev.position) / (next.position - prev.position);
+        self.space
+            .interpolate(prev.color, next.color, prev.easing.apply(t))
+    }
+
+    /// Samples the gradient between 0 and 1 at the centers of `width` pixels.
+    pub fn bake(&self, width: u32) -> Vec<WithAlpha<LinearSrgb>> {
+        (0..width)
+            .map(|x| self.sample((x as f32 + 0.5) / width as f32))
+            .collect()
+    }
+
+    /// Bakes the gradient into a lookup texture of `width`×1 pixels, to be
+    /// sampled with linear filtering on the GPU.
+    ///
+    /// Uses 16-bit floats, so out of gamut colors are preserved.
+    pub fn bake_texture(&self, width: u32) -> persistence::Texture<'static> {
+        let format = persistence::TextureFormat::Rgba16FloatLinearSrgb;
+
+        let data = self
+            .bake(width)
+            .into_iter()
+            .flat_map(|c| [c.color.r, c.color.g, c.color.b, c.alpha])
+            .flat_map(|v| f16::from_f32(v).to_le_bytes())
+            .collect();
+
+        persistence::Texture {
+            resolution: UVec2::new(width, 1),
+            format,
+            data: Cow::Owned(data),
+            row_stride: width as usize * format.bytes_per_pixel(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: LinearSrgb = LinearSrgb::new(1.0, 0.0, 0.0);
+    const BLUE: LinearSrgb = LinearSrgb::new(0.0, 0.0, 1.0);
+    const WHITE: LinearSrgb = LinearSrgb::new(1.0, 1.0, 1.0);
+    const BLACK: LinearSrgb = LinearSrgb::new(0.0, 0.0, 0.0);
+
+    fn opaque(c: LinearSrgb) -> WithAlpha<LinearSrgb> {
+        WithAlpha::opaque(c)
+    }
+
+    fn assert_close(actual: WithAlpha<LinearSrgb>, expected: WithAlpha<LinearSrgb>) {
+        let a = [actual.color.r, actual.color.g, actual.color.b, actual.alpha];
+        let e = [
+            expected.color.r,
+            expected.color.g,
+            expected.color.b,
+            expected.alpha,
+        ];
+        let close = a.iter().zip(&e).all(|(a, e)| (a - e).abs() < 1e-4);
+        assert!(close, "{actual:?} != {expected:?}");
+    }
+
+    /// Hue in degrees of the OkLCh gradient midpoint between two hues.
+    fn midpoint_hue(mode: HueInterpolation, from: f32, to: f32) -> f32 {
+        let color = |h: f32| opaque(OkLch::new(0.7, 0.1, h.to_radians()).to_linear_srgb());
+        let space = InterpolationSpace::OkLch(mode);
+        let mid = space.interpolate(color(from), color(to), 0.5);
+        OkLch::from_linear_srgb(mid.color).h.to_degrees()
+    }
+
+    fn assert_hue(actual: f32, expected: f32) {
+        let diff = (actual - expected).rem_euclid(360.0);
+        assert!(
+            diff.min(360.0 - diff) < 0.5,
+            "hue {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn linear_srgb() {
+        let gradient =
+            Gradient::two_color(opaque(RED), opaque(BLUE), InterpolationSpace::LinearSrgb);
+
+        assert_close(gradient.sample(0.0), opaque(RED));
+        assert_close(gradient.sample(1.0), opaque(BLUE));
+        assert_close(
+            gradient.sample(0.25),
+            opaque(LinearSrgb::new(0.75, 0.0, 0.25)),
+        );
+    }
+
+    #[test]
+    fn oklab() {
+        let gradient = Gradient::two_color(opaque(BLACK), opaque(WHITE), InterpolationSpace::Oklab);
+
+        let mid = Oklab::from_linear_srgb(gradient.sample(0.5).color);
+        assert!((mid.l - 0.5).abs() < 1e-4);
+        assert!(mid.a.abs() < 1e-4 && mid.b.abs() < 1e-4);
+    }
+
+    #[test]
+    fn hue_modes() {
+        use HueInterpolation::*;
+
+        assert_hue(midpoint_hue(Shorter, 10.0, 350.0), 0.0);
+        assert_hue(midpoint_hue(Shorter, 350.0, 10.0), 0.0);
+        assert_hue(midpoint_hue(Longer, 10.0, 350.0), 180.0);
+        assert_hue(midpoint_hue(Longer, 350.0, 10.0), 180.0);
+        assert_hue(midpoint_hue(Increasing, 10.0, 350.0), 180.0);
+        assert_hue(midpoint_hue(Increasing, 350.0, 10.0), 0.0);
+        assert_hue(midpoint_hue(Decreasing, 10.0, 350.0), 0.0);
+        assert_hue(midpoint_hue(Decreasing, 350.0, 10.0), 180.0);
+
+        assert_hue(midpoint_hue(Shorter, 100.0, 200.0), 150.0);
+        assert_hue(midpoint_hue(Longer, 100.0, 200.0), 330.0);
+    }
+
+    #[test]
+    fn achromatic_takes_other_hue() {
+        let red_hue = OkLch::from_linear_srgb(RED).h;
+
+        for space in [
+            InterpolationSpace::OkLch(HueInterpolation::Shorter),
+            InterpolationSpace::Okhsv(HueInterpolation::Shorter),
+        ] {
+            let mid = space.interpolate(opaque(WHITE), opaque(RED), 0.5);
+            let hue = OkLch::from_linear_srgb(mid.color).h;
+            assert!((hue - red_hue).abs() < 1e-3, "{space:?}: {hue}");
+        }
+    }
+
+    #[test]
+    fn premultiplied_alpha() {
+        // hue isn't premultiplied, so only rectangular spaces keep the color
+        for space in [InterpolationSpace::LinearSrgb, InterpolationSpace::Oklab] {
+            // the transparent color doesn't tint the opaque one
+            let mid = space.interpolate(opaque(RED), WithAlpha::transparent(BLUE), 0.5);
+            assert_close(mid, WithAlpha::new(RED, 0.5));
+        }
+
+        let mid = InterpolationSpace::LinearSrgb.interpolate(
+            WithAlpha::new(RED, 0.2),
+            WithAlpha::new(BLUE, 0.6),
+            0.5,
+        );
+        assert_close(mid, WithAlpha::new(LinearSrgb::new(0.25, 0.0, 0.75), 0.4));
+    }
+
+    #[test]
+    fn easing() {
+        let mut gradient = Gradient::new(InterpolationSpace::LinearSrgb);
+        gradient.add_stop(GradientStop {
+            easing: Easing::EaseIn,
+            ..GradientStop::new(0.0, opaque(BLACK))
+        });
+        gradient.add_stop(GradientStop::new(1.0, opaque(WHITE)));
+
+        assert_close(
+            gradient.sample(0.5),
+            opaque(LinearSrgb::new(0.25, 0.25, 0.25)),
+        );
+
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+    }
+
+    #[test]
+    fn multiple_stops() {
+        let mut gradient = Gradient::new(InterpolationSpace::LinearSrgb);
+        gradient.add_stop(GradientStop::new(1.0, opaque(BLUE)));
+        gradient.add_stop(GradientStop::new(0.2, opaque(RED)));
+        assert_eq!(gradient.add_stop(GradientStop::new(0.6, opaque(WHITE))), 1);
+
+        let positions: Vec<f32> = gradient.stops().iter().map(|s| s.position).collect();
+        assert_eq!(positions, [0.2, 0.6, 1.0]);
+
+        // clamped outside the stops
+        assert_close(gradient.sample(-1.0), opaque(RED));
+        assert_close(gradient.sample(0.1), opaque(RED));
+        assert_close(gradient.sample(2.0), opaque(BLUE));
+
+        assert_close(gradient.sample(0.4), opaque(LinearSrgb::new(1.0, 0.5, 0.5)));
+        assert_close(gradient.sample(0.8), opaque(LinearSrgb::new(0.5, 0.5, 1.0)));
+
+        gradient.remove_stop(1);
+        assert_close(gradient.sample(0.6), opaque(LinearSrgb::new(0.5, 0.0, 0.5)));
+    }
+
+    #[test]
+    fn hard_stop() {
+        let mut gradient = Gradient::new(InterpolationSpace::Oklab);
+        gradient.add_stop(GradientStop::new(0.0, opaque(RED)));
+        gradient.add_stop(GradientStop::new(0.5, opaque(RED)));
+        gradient.add_stop(GradientStop::new(0.5, opaque(BLUE)));
+        gradient.add_stop(GradientStop::new(1.0, opaque(BLUE)));
+
+        assert_close(gradient.sample(0.4999), opaque(RED));
+        assert_close(gradient.sample(0.5), opaque(BLUE));
+    }
+
+    #[test]
+    fn empty() {
+        let gradient = Gradient::default();
+        assert_eq!(gradient.sample(0.5).alpha, 0.0);
+        assert_eq!(
+            Gradient::two_color(opaque(RED), opaque(RED), gradient.space)
+                .sample(0.5)
+                .alpha,
+            1.0
+        );
+    }
+
+    #[test]
+    fn bake() {
+        let gradient = Gradient::two_color(
+            opaque(BLACK),
+            WithAlpha::new(WHITE, 0.0),
+            InterpolationSpace::LinearSrgb,
+        );
+
+        let lut = gradient.bake(4);
+        assert_eq!(lut.len(), 4);
+        assert_close(lut[0], WithAlpha::new(BLACK, 0.875));
+        assert_close(lut[3], WithAlpha::new(BLACK, 0.125));
+
+        let texture = gradient.bake_texture(4);
+        assert_eq!(texture.resolution, UVec2::new(4, 1));
+        assert_eq!(texture.data.len(), texture.row_stride);
+        assert_eq!(texture.data.len(), 4 * 8);
+
+        let alpha = f16::from_le_bytes([texture.data[6], texture.data[7]]);
+        assert_eq!(alpha, f16::from_f32(0.875));
+    }
+}
@@ -0,0 +1,143 @@
+use std::f32::consts::TAU;
+
+use super::Okhsl;
+
+/// Color harmony, a scheme of colors derived from a base color.
+///
+/// Schemes are generated in Okhsl, where rotating the hue keeps the perceived
+/// lightness constant, unlike HSL.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Harmony {
+    /// The base color and the opposite hue.
+    #[default]
+    Complementary,
+    /// The base color and the two hues adjacent to its complement, 30° apart.
+    SplitComplementary,
+    /// Three hues evenly spaced around the wheel.
+    Triadic,
+    /// Two complementary pairs 60° apart, forming a rectangle on the wheel.
+    Tetradic,
+    /// The base color and its neighbours, 30° apart on either side.
+    Analogous,
+    /// The base hue at decreasing saturations.
+    Monochromatic,
+}
+
+impl Harmony {
+    /// All harmonies.
+    pub const ALL: [Harmony; 6] = [
+        Harmony::Complementary,
+        Harmony::SplitComplementary,
+        Harmony::Triadic,
+        Harmony::Tetradic,
+        Harmony::Analogous,
+        Harmony::Monochromatic,
+    ];
+
+    /// Hue offsets from the base color in degrees, starting with the base
+    /// color itself.
+    fn hue_offsets(self) -> &'static [f32] {
+        match self {
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::SplitComplementary => &[0.0, 150.0, 210.0],
+            Harmony::Triadic => &[0.0, 120.0, 240.0],
+            Harmony::Tetradic => &[0.0, 60.0, 180.0, 240.0],
+            Harmony::Analogous => &[0.0, -30.0, 30.0],
+            Harmony::Monochromatic => &[0.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Returns the hues of the scheme in radians between 0 and 2π, starting
+    /// with `base_hue`.
+    ///
+    /// Hues may repeat, for example all hues of [`Harmony::Monochromatic`]
+    /// are the base hue.
+    pub fn hues(self, base_hue: f32) -> Vec<f32> {
+        self.hue_offsets()
+            .iter()
+            .map(|offset| (base_hue + offset.to_radians()).rem_euclid(TAU))
+            .collect()
+    }
+
+    /// Generates the scheme for `base`, which is always the first color.
+    ///
+    /// All colors have the lightness of `base`. [`Harmony::Monochromatic`]
+    /// varies saturation instead of hue, in equal steps towards gray.
+    pub fn generate(self, base: Okhsl) -> Vec<Okhsl> {
+        let hues = self.hues(base.h);
+        let steps = hues.len() as f32;
+
+        hues.into_iter()
+            .enumerate()
+            .map(|(i, h)| match self {
+                Harmony::Monochromatic => Okhsl::new(h, base.s * (1.0 - i as f32 / steps), base.l),
+                _ => Okhsl::new(h, base.s, base.l),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{Color, LinearSrgb, Oklab};
+
+    fn hue_distance(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(TAU);
+        d.min(TAU - d)
+    }
+
+    #[test]
+    fn hues() {
+        let base = 350f32.to_radians();
+
+        let expected: [(Harmony, &[f32]); 5] = [
+            (Harmony::Complementary, &[350.0, 170.0]),
+            (Harmony::SplitComplementary, &[350.0, 140.0, 200.0]),
+            (Harmony::Triadic, &[350.0, 110.0, 230.0]),
+            (Harmony::Tetradic, &[350.0, 50.0, 170.0, 230.0]),
+            (Harmony::Analogous, &[350.0, 320.0, 20.0]),
+        ];
+
+        for (harmony, degrees) in expected {
+            let hues = harmony.hues(base);
+            assert_eq!(hues.len(), degrees.len(), "{harmony:?}");
+            for (h, d) in hues.iter().zip(degrees) {
+                assert!((0.0..TAU).contains(h), "{harmony:?}");
+                assert!(hue_distance(*h, d.to_radians()) < 1e-5, "{harmony:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn constant_lightness() {
+        let base = Okhsl::from_linear_srgb(LinearSrgb::new(0.8, 0.3, 0.05));
+        let lightness = Oklab::from_linear_srgb(base.to_linear_srgb()).l;
+
+        for harmony in Harmony::ALL {
+            let colors = harmony.generate(base);
+            assert_eq!(colors[0], base, "{harmony:?}");
+
+            for c in colors {
+                assert_eq!(c.l, base.l, "{harmony:?}");
+
+                // Okhsl lightness maps to a single Oklab lightness
+                let l = Oklab::from_linear_srgb(c.to_linear_srgb()).l;
+                assert!((l - lightness).abs() < 1e-4, "{harmony:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn monochromatic() {
+        let base = Okhsl::new(1.0, 0.8, 0.5);
+        let colors = Harmony::Monochromatic.generate(base);
+
+        assert_eq!(colors.len(), 4);
+        for pair in colors.windows(2) {
+            assert_eq!(pair[0].h, pair[1].h);
+            assert!(pair[1].s < pair[0].s);
+        }
+        assert!(colors[3].s > 0.0);
+    }
+}
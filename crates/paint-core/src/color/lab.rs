@@ -0,0 +1,187 @@
+use super::{Color, LinearSrgb, XyzD50, from_polar, to_polar};
+
+/// CIELAB constants, as exact fractions.
+const EPSILON: f32 = 216.0 / 24389.0;
+const KAPPA: f32 = 24389.0 / 27.0;
+
+/// Color in CIELAB color space, relative to the D50 white point.
+///
+/// This is the variant used by ICC profiles and CSS. Compared to
+/// [`Oklab`](super::Oklab), it's less perceptually uniform, especially for
+/// blue hues.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Lab {
+    /// Lightness, between 0 and 100.
+    pub l: f32,
+    /// Green–red axis.
+    ///
+    /// Typically between -125 and 125.
+    pub a: f32,
+    /// Blue–yellow axis.
+    ///
+    /// Typically between -125 and 125.
+    pub b: f32,
+}
+
+impl Lab {
+    /// Creates a new [`Lab`] color with the given components.
+    pub const fn new(l: f32, a: f32, b: f32) -> Self {
+        Self { l, a, b }
+    }
+
+    /// Converts [`XyzD50`] to [`Lab`].
+    pub fn from_xyz(c: XyzD50) -> Self {
+        let white = XyzD50::WHITE;
+
+        let f = |t: f32| {
+            if t > EPSILON {
+                t.cbrt()
+            } else {
+                (KAPPA * t + 16.0) / 116.0
+            }
+        };
+
+        let fx = f(c.x / white.x);
+        let fy = f(c.y / white.y);
+        let fz = f(c.z / white.z);
+
+        Self::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// Converts [`Lab`] to [`XyzD50`].
+    pub fn to_xyz(&self) -> XyzD50 {
+        let white = XyzD50::WHITE;
+
+        let fy = (self.l + 16.0) / 116.0;
+        let fx = self.a / 500.0 + fy;
+        let fz = fy - self.b / 200.0;
+
+        let f_inv = |t: f32| {
+            let t3 = t * t * t;
+            if t3 > EPSILON {
+                t3
+            } else {
+                (116.0 * t - 16.0) / KAPPA
+            }
+        };
+
+        let y = if self.l > KAPPA * EPSILON {
+            fy * fy * fy
+        } else {
+            self.l / KAPPA
+        };
+
+        XyzD50::new(f_inv(fx) * white.x, y * white.y, f_inv(fz) * white.z)
+    }
+
+    /// Converts to the polar form.
+    pub fn to_lch(&self) -> Lch {
+        let (c, h) = to_polar(self.a, self.b);
+        Lch::new(self.l, c, h)
+    }
+}
+
+impl Color for Lab {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        Self::from_xyz(XyzD50::from_linear_srgb(c))
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        self.to_xyz().to_linear_srgb()
+    }
+}
+
+/// Color in CIE LCh color space, the polar form of [`Lab`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Lch {
+    /// Lightness, between 0 and 100.
+    pub l: f32,
+    /// Chroma.
+    ///
+    /// Typically between 0 and 150.
+    pub c: f32,
+    /// Hue, in radians between 0 and 2π.
+    pub h: f32,
+}
+
+impl Lch {
+    /// Creates a new [`Lch`] color with the given components.
+    pub const fn new(l: f32, c: f32, h: f32) -> Self {
+        Self { l, c, h }
+    }
+
+    /// Converts to the rectangular form.
+    pub fn to_lab(&self) -> Lab {
+        let (a, b) = from_polar(self.c, self.h);
+        Lab::new(self.l, a, b)
+    }
+}
+
+impl Color for Lch {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        Lab::from_linear_srgb(c).to_lch()
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        self.to_lab().to_linear_srgb()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::NonlinearSrgb;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < tolerance, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn srgb_reference_values() {
+        // values from CSS Color 4
+        let lab = Lab::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert_close([lab.l, lab.a, lab.b], [54.2905, 80.8049, 69.8910], 1e-2);
+
+        let lab = Lab::from_linear_srgb(LinearSrgb::new(1.0, 1.0, 1.0));
+        assert_close([lab.l, lab.a, lab.b], [100.0, 0.0, 0.0], 1e-2);
+
+        let lab = Lab::from_linear_srgb(LinearSrgb::new(0.0, 0.0, 0.0));
+        assert_close([lab.l, lab.a, lab.b], [0.0, 0.0, 0.0], 1e-4);
+
+        let lch = Lch::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert_close(
+            [lch.l, lch.c, lch.h],
+            [54.2905, 106.8372, 40.8577_f32.to_radians()],
+            1e-2,
+        );
+    }
+
+    #[test]
+    fn hue_is_positive() {
+        // blue has a negative b axis
+        let lch = Lch::from_linear_srgb(LinearSrgb::new(0.0, 0.0, 1.0));
+        assert!((0.0..std::f32::consts::TAU).contains(&lch.h));
+        assert_close(
+            [lch.h, 0.0, 0.0],
+            [301.3643_f32.to_radians(), 0.0, 0.0],
+            1e-3,
+        );
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        let step = 15;
+        for r in (0..=255u8).step_by(step) {
+            for g in (0..=255u8).step_by(step) {
+                for b in (0..=255u8).step_by(step) {
+                    let c1 = NonlinearSrgb::new(r, g, b);
+                    let lch = Lch::from_linear_srgb(c1.to_linear_srgb());
+                    let c2 = NonlinearSrgb::<u8>::from_linear_srgb(lch.to_linear_srgb());
+                    assert_eq!(c1, c2);
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,30 @@
+pub mod batch;
+mod blending;
+mod css;
+mod difference;
+mod display_p3;
+mod gamut;
+mod gradient;
+mod harmony;
+mod lab;
 mod oklab;
+mod rec2020;
 mod srgb;
+mod xyz;
 
 use half::f16;
 
-pub use self::oklab::{Okhsl, Okhsv, Oklab};
+pub use self::blending::BlendingSpace;
+pub use self::css::{CssColor, CssNotation, ParseColorError};
+pub use self::difference::DeltaE;
+pub use self::display_p3::{DisplayP3, LinearDisplayP3};
+pub use self::gradient::{Easing, Gradient, GradientStop, HueInterpolation, InterpolationSpace};
+pub use self::harmony::Harmony;
+pub use self::lab::{Lab, Lch};
+pub use self::oklab::{GamutMapping, OkLch, Okhsl, Okhsv, Oklab};
+pub use self::rec2020::{LinearRec2020, Rec2020};
 pub use self::srgb::{LinearSrgb, NonlinearSrgb};
+pub use self::xyz::{Xyz, XyzD50};
 
 /// A color component.
 pub trait Component: Copy + PartialEq + std::fmt::Debug {
@@ -121,6 +141,33 @@ pub trait Color {
         rgb.b = rgb.b.clamp(0.0, 1.0);
         rgb
     }
+
+    /// Converts the color to [`LinearSrgb`], mapping out of gamut colors with
+    /// the given strategy.
+    ///
+    /// The default implementation maps the result of [`to_linear_srgb()`].
+    fn to_linear_srgb_mapped(&self, mapping: GamutMapping) -> LinearSrgb {
+        mapping.map(self.to_linear_srgb())
+    }
+}
+
+/// Multiplies a row-major 3x3 matrix by a column vector.
+fn transform(m: &[[f64; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = v.map(f64::from);
+    m.map(|row| row[0].mul_add(x, row[1].mul_add(y, row[2] * z)) as f32)
+}
+
+/// Converts rectangular chroma axes to chroma and hue in radians between 0
+/// and 2π.
+fn to_polar(a: f32, b: f32) -> (f32, f32) {
+    let hue = b.atan2(a).rem_euclid(std::f32::consts::TAU);
+    (a.hypot(b), hue)
+}
+
+/// Inverse of [`to_polar()`].
+fn from_polar(chroma: f32, hue: f32) -> (f32, f32) {
+    let (sin, cos) = hue.sin_cos();
+    (chroma * cos, chroma * sin)
 }
 
 /// Color with an alpha channel. Uses straight alpha (non-premultiplied).
@@ -162,4 +209,8 @@ impl<C: Color, A: Component> Color for WithAlpha<C, A> {
     fn to_linear_srgb_clamped(&self) -> LinearSrgb {
         self.color.to_linear_srgb_clamped()
     }
+
+    fn to_linear_srgb_mapped(&self, mapping: GamutMapping) -> LinearSrgb {
+        self.color.to_linear_srgb_mapped(mapping)
+    }
 }
@@ -0,0 +1,268 @@
+use super::{OkLch, Oklab, utils};
+use crate::color::gamut::is_unit_cube;
+use crate::color::{Color, LinearSrgb};
+
+/// Strategy for mapping out of gamut colors into sRGB.
+///
+/// Except for [`GamutMapping::Clip`], colors are mapped in Oklab, keeping
+/// the hue. The projections follow Björn Ottosson's
+/// [sRGB gamut clipping](https://bottosson.github.io/posts/gamutclipping/),
+/// each moving the color in a straight line towards a point `L0` on the gray
+/// axis until it reaches the gamut boundary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamutMapping {
+    /// Clamps each linear sRGB channel, which is fast but shifts hue and
+    /// lightness.
+    Clip,
+    /// Reduces chroma at constant lightness, clamped between 0 and 1.
+    PreserveChroma,
+    /// Projects towards `L0 = 0.5`, trading lightness for chroma.
+    ProjectToMidLightness,
+    /// Projects towards the lightness of the cusp, the most chromatic color
+    /// of the hue.
+    ProjectToCusp,
+    /// Projects towards a `L0` between the color's lightness and 0.5,
+    /// preserving more lightness as `alpha` grows, and more chroma near 0.
+    AdaptiveL0 { alpha: f32 },
+    /// The [CSS Color 4](https://www.w3.org/TR/css-color-4/#binsearch) binary
+    /// search for the largest OkLCh chroma whose clipped color is less than a
+    /// just noticeable difference away.
+    Css,
+}
+
+impl Default for GamutMapping {
+    /// [`GamutMapping::AdaptiveL0`] with an `alpha` of 0.05.
+    fn default() -> Self {
+        GamutMapping::AdaptiveL0 { alpha: 0.05 }
+    }
+}
+
+impl GamutMapping {
+    /// Maps an sRGB color, possibly out of gamut, into the gamut.
+    pub fn map(self, c: LinearSrgb) -> LinearSrgb {
+        match self {
+            GamutMapping::Clip => clip(c),
+            _ => self.map_oklab(Oklab::from_linear_srgb(c)),
+        }
+    }
+
+    /// Converts an [`Oklab`] color to sRGB, mapping it into the gamut.
+    pub fn map_oklab(self, lab: Oklab) -> LinearSrgb {
+        let rgb = utils::oklab_to_linear_srgb(lab);
+
+        let rgb = match self {
+            GamutMapping::Clip => utils::clamp_rgb(rgb),
+            GamutMapping::PreserveChroma => utils::gamut_clip_preserve_chroma(rgb, lab),
+            GamutMapping::ProjectToMidLightness => utils::gamut_clip_project_to_0_5(rgb, lab),
+            GamutMapping::ProjectToCusp => utils::gamut_clip_project_to_l_cusp(rgb, lab),
+            GamutMapping::AdaptiveL0 { alpha } => {
+                utils::gamut_clip_adaptive_l0_0_5(rgb, lab, f64::from(alpha))
+            }
+            GamutMapping::Css => return css(lab.to_oklch()),
+        };
+
+        LinearSrgb::new(rgb.r as f32, rgb.g as f32, rgb.b as f32)
+    }
+}
+
+fn clip(c: LinearSrgb) -> LinearSrgb {
+    LinearSrgb::new(
+        c.r.clamp(0.0, 1.0),
+        c.g.clamp(0.0, 1.0),
+        c.b.clamp(0.0, 1.0),
+    )
+}
+
+/// CSS Color 4 gamut mapping, with sRGB as the destination.
+fn css(origin: OkLch) -> LinearSrgb {
+    // just noticeable difference in ΔE OK, and the chroma precision
+    const JND: f32 = 0.02;
+    const EPSILON: f32 = 0.0001;
+
+    if origin.l >= 1.0 {
+        return LinearSrgb::new(1.0, 1.0, 1.0);
+    }
+    if origin.l <= 0.0 {
+        return LinearSrgb::new(0.0, 0.0, 0.0);
+    }
+
+    let rgb = origin.to_linear_srgb();
+    if is_unit_cube([rgb.r, rgb.g, rgb.b]) {
+        return clip(rgb);
+    }
+
+    let delta_e = |current: OkLch, clipped: LinearSrgb| {
+        current
+            .to_oklab()
+            .delta_e(&Oklab::from_linear_srgb(clipped))
+    };
+
+    let mut current = origin;
+    let mut clipped = clip(rgb);
+    if delta_e(current, clipped) < JND {
+        return clipped;
+    }
+
+    let mut min = 0.0;
+    let mut max = origin.c;
+    let mut min_in_gamut = true;
+
+    while max - min > EPSILON {
+        current.c = 0.5 * (min + max);
+        let rgb = current.to_linear_srgb();
+
+        if min_in_gamut && is_unit_cube([rgb.r, rgb.g, rgb.b]) {
+            min = current.c;
+            continue;
+        }
+
+        clipped = clip(rgb);
+        let e = delta_e(current, clipped);
+        if e < JND {
+            if JND - e < EPSILON {
+                break;
+            }
+            min_in_gamut = false;
+            min = current.c;
+        } else {
+            max = current.c;
+        }
+    }
+
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+
+    const ALL: [GamutMapping; 7] = [
+        GamutMapping::Clip,
+        GamutMapping::PreserveChroma,
+        GamutMapping::ProjectToMidLightness,
+        GamutMapping::ProjectToCusp,
+        GamutMapping::AdaptiveL0 { alpha: 0.05 },
+        GamutMapping::AdaptiveL0 { alpha: 0.5 },
+        GamutMapping::Css,
+    ];
+
+    /// OkLCh colors on a grid, most of them out of gamut.
+    fn samples() -> impl Iterator<Item = OkLch> {
+        (0..=10).flat_map(|l| {
+            (1..=8).flat_map(move |c| {
+                (0..24).map(move |h| {
+                    OkLch::new(l as f32 / 10.0, c as f32 * 0.05, h as f32 / 24.0 * TAU)
+                })
+            })
+        })
+    }
+
+    /// Out of gamut colors which are mapped, rather than clamped as being
+    /// within the rounding tolerance of the gamut, which is large near black.
+    fn projected_samples() -> impl Iterator<Item = OkLch> {
+        samples().filter(|color| {
+            let rgb = color.to_linear_srgb();
+            [rgb.r, rgb.g, rgb.b]
+                .iter()
+                .any(|c| !(-1e-3..=1.0 + 1e-3).contains(c))
+        })
+    }
+
+    fn hue_distance(a: f32, b: f32) -> f32 {
+        let d = (a - b).rem_euclid(TAU);
+        d.min(TAU - d)
+    }
+
+    #[test]
+    fn output_in_gamut() {
+        for mapping in ALL {
+            for color in samples() {
+                let rgb = mapping.map_oklab(color.to_oklab());
+                assert!(
+                    [rgb.r, rgb.g, rgb.b]
+                        .iter()
+                        .all(|c| (0.0..=1.0).contains(c)),
+                    "{mapping:?} {color:?}: {rgb:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn in_gamut_unchanged() {
+        let colors = [
+            LinearSrgb::new(0.2, 0.5, 0.8),
+            LinearSrgb::new(1.0, 0.0, 0.0),
+            LinearSrgb::new(0.0, 0.0, 0.0),
+            LinearSrgb::new(1.0, 1.0, 1.0),
+        ];
+
+        for mapping in ALL {
+            for c in colors {
+                let mapped = mapping.map(c);
+                for (a, b) in [(mapped.r, c.r), (mapped.g, c.g), (mapped.b, c.b)] {
+                    assert!((a - b).abs() < 1e-4, "{mapping:?} {c:?}: {mapped:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hue_preserving() {
+        for mapping in ALL {
+            // clipping doesn't preserve hue
+            if mapping == GamutMapping::Clip {
+                continue;
+            }
+
+            for color in projected_samples() {
+                let mapped = OkLch::from_linear_srgb(mapping.map_oklab(color.to_oklab()));
+
+                // the hue of grays is undefined
+                if mapped.c < 0.02 {
+                    continue;
+                }
+
+                let d = hue_distance(mapped.h, color.h);
+                if mapping == GamutMapping::Css {
+                    // CSS only keeps the hue within a JND, as an arc in Oklab
+                    assert!(mapped.c * d < 0.02, "{mapping:?} {color:?}: {mapped:?}");
+                } else {
+                    assert!(d < 0.01, "{mapping:?} {color:?}: {mapped:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn preserve_chroma_keeps_lightness() {
+        for color in projected_samples() {
+            let mapped =
+                Oklab::from_linear_srgb(GamutMapping::PreserveChroma.map_oklab(color.to_oklab()));
+            assert!((mapped.l - color.l).abs() < 1e-3, "{color:?}: {mapped:?}");
+        }
+    }
+
+    #[test]
+    fn usable_from_every_color() {
+        let color = OkLch::new(0.7, 0.35, 2.5);
+        let expected = GamutMapping::Css.map_oklab(color.to_oklab());
+
+        assert_eq!(color.to_linear_srgb_mapped(GamutMapping::Css), expected);
+
+        let rgb = color.to_linear_srgb();
+        let mapped = rgb.to_linear_srgb_mapped(GamutMapping::Css);
+        for (a, b) in [
+            (mapped.r, expected.r),
+            (mapped.g, expected.g),
+            (mapped.b, expected.b),
+        ] {
+            assert!((a - b).abs() < 1e-3, "{mapped:?} {expected:?}");
+        }
+
+        let default = GamutMapping::default().map_oklab(color.to_oklab());
+        assert_eq!(color.to_linear_srgb_clamped(), default);
+    }
+}
@@ -1,6 +1,8 @@
+mod gamut_mapping;
 mod utils;
 
-use super::{Color, LinearSrgb};
+pub use self::gamut_mapping::GamutMapping;
+use super::{Color, LinearSrgb, from_polar, to_polar};
 
 /// Color in Oklab color space.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -24,6 +26,12 @@ impl Oklab {
     pub const fn new(l: f32, a: f32, b: f32) -> Self {
         Self { l, a, b }
     }
+
+    /// Converts to the polar form.
+    pub fn to_oklch(&self) -> OkLch {
+        let (c, h) = to_polar(self.a, self.b);
+        OkLch::new(self.l, c, h)
+    }
 }
 
 impl Color for Oklab {
@@ -41,9 +49,57 @@ impl Color for Oklab {
     }
 
     fn to_linear_srgb_clamped(&self) -> LinearSrgb {
-        let rgb = utils::oklab_to_linear_srgb(*self);
-        let rgb = utils::gamut_clip_adaptive_l0_0_5(rgb, *self);
-        LinearSrgb::new(rgb.r as f32, rgb.g as f32, rgb.b as f32)
+        self.to_linear_srgb_mapped(GamutMapping::default())
+    }
+
+    fn to_linear_srgb_mapped(&self, mapping: GamutMapping) -> LinearSrgb {
+        mapping.map_oklab(*self)
+    }
+}
+
+/// Color in OkLCh color space, the polar form of [`Oklab`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OkLch {
+    /// Lightness.
+    ///
+    /// Typically between 0 and 1 for sRGB colors.
+    pub l: f32,
+    /// Chroma.
+    ///
+    /// Typically between 0 and 0.4 for sRGB colors.
+    pub c: f32,
+    /// Hue, in radians between 0 and 2π.
+    pub h: f32,
+}
+
+impl OkLch {
+    /// Creates a new [`OkLch`] color with the given components.
+    pub const fn new(l: f32, c: f32, h: f32) -> Self {
+        Self { l, c, h }
+    }
+
+    /// Converts to the rectangular form.
+    pub fn to_oklab(&self) -> Oklab {
+        let (a, b) = from_polar(self.c, self.h);
+        Oklab::new(self.l, a, b)
+    }
+}
+
+impl Color for OkLch {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        Oklab::from_linear_srgb(c).to_oklch()
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        self.to_oklab().to_linear_srgb()
+    }
+
+    fn to_linear_srgb_clamped(&self) -> LinearSrgb {
+        self.to_oklab().to_linear_srgb_clamped()
+    }
+
+    fn to_linear_srgb_mapped(&self, mapping: GamutMapping) -> LinearSrgb {
+        self.to_oklab().to_linear_srgb_mapped(mapping)
     }
 }
 
@@ -87,6 +143,10 @@ impl Color for Okhsl {
     fn to_linear_srgb_clamped(&self) -> LinearSrgb {
         self.to_oklab().to_linear_srgb_clamped()
     }
+
+    fn to_linear_srgb_mapped(&self, mapping: GamutMapping) -> LinearSrgb {
+        self.to_oklab().to_linear_srgb_mapped(mapping)
+    }
 }
 
 /// Color in Okhsv color space.
@@ -129,6 +189,10 @@ impl Color for Okhsv {
     fn to_linear_srgb_clamped(&self) -> LinearSrgb {
         self.to_oklab().to_linear_srgb_clamped()
     }
+
+    fn to_linear_srgb_mapped(&self, mapping: GamutMapping) -> LinearSrgb {
+        self.to_oklab().to_linear_srgb_mapped(mapping)
+    }
 }
 
 #[cfg(test)]
@@ -165,6 +229,24 @@ mod tests {
         test_round_trip(|c| Oklab::from_linear_srgb(c).to_linear_srgb_clamped())
     }
 
+    #[test]
+    fn srgb_oklch_round_trip() {
+        test_round_trip(|c| OkLch::from_linear_srgb(c).to_linear_srgb_clamped())
+    }
+
+    #[test]
+    fn oklch_reference_values() {
+        // values from CSS Color 4
+        let red = OkLch::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert!((red.l - 0.62796).abs() < 1e-4);
+        assert!((red.c - 0.25768).abs() < 1e-4);
+        assert!((red.h - 29.2339_f32.to_radians()).abs() < 1e-4);
+
+        // hue agrees with Okhsv
+        let okhsv = Okhsv::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert!((red.h - okhsv.h).abs() < 1e-4);
+    }
+
     #[test]
     fn srgb_okhsv_round_trip() {
         test_round_trip(|c| Okhsv::from_linear_srgb(c).to_linear_srgb_clamped())
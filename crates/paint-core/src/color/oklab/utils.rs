@@ -256,40 +256,68 @@ fn find_cusp(a: f64, b: f64) -> Cusp {
     }
 }
 
-pub fn gamut_clip_adaptive_l0_0_5(mut rgb: Rgb, lab: Oklab) -> Rgb {
+/// Whether the color is inside the sRGB gamut, with a tolerance for rounding.
+pub fn is_in_gamut(rgb: Rgb) -> bool {
     let eps = 1e-3;
     let max = 1.0 + eps;
     let min = -eps;
 
-    if rgb.r > max || rgb.g > max || rgb.b > max || rgb.r < min || rgb.g < min || rgb.b < min {
-        let alpha = 0.05;
+    !(rgb.r > max || rgb.g > max || rgb.b > max || rgb.r < min || rgb.g < min || rgb.b < min)
+}
 
-        let l = f64::from(lab.l);
-        let eps = 0.00001;
-        let (a, b) = (f64::from(lab.a), f64::from(lab.b));
-        let c = f64::max(eps, f64::hypot(a, b));
-        let (a_, b_) = (a / c, b / c);
+pub fn clamp_rgb(mut rgb: Rgb) -> Rgb {
+    rgb.r = rgb.r.clamp(0.0, 1.0);
+    rgb.g = rgb.g.clamp(0.0, 1.0);
+    rgb.b = rgb.b.clamp(0.0, 1.0);
+    rgb
+}
 
-        let ld = l - 0.5;
-        let e1 = 0.5 + f64::abs(ld) + alpha * c;
-        let l0 = 0.5 * (1.0 + f64::signum(ld) * (e1 - f64::sqrt(e1 * e1 - 2.0 * f64::abs(ld))));
+/// Projects an out of gamut color onto the gamut boundary, along the line
+/// towards lightness `L0` on the gray axis. `l0` computes `L0` from the
+/// lightness and chroma of the color, and the cusp of its hue.
+fn gamut_clip(rgb: Rgb, lab: Oklab, l0: impl FnOnce(f64, f64, Cusp) -> f64) -> Rgb {
+    if is_in_gamut(rgb) {
+        return clamp_rgb(rgb);
+    }
 
-        let t = find_gamut_intersection(a_, b_, l, c, l0, find_cusp(a_, b_));
-        let l_clipped = l0 * (1.0 - t) + t * l;
-        let c_clipped = t * c;
+    let l = f64::from(lab.l);
+    let eps = 0.00001;
+    let (a, b) = (f64::from(lab.a), f64::from(lab.b));
+    let c = f64::max(eps, f64::hypot(a, b));
+    let (a_, b_) = (a / c, b / c);
 
-        rgb = oklab_to_linear_srgb(Oklab::new(
-            l_clipped as f32,
-            (c_clipped * a_) as f32,
-            (c_clipped * b_) as f32,
-        ))
-    }
+    let cusp = find_cusp(a_, b_);
+    let l0 = l0(l, c, cusp);
 
-    rgb.r = rgb.r.clamp(0.0, 1.0);
-    rgb.g = rgb.g.clamp(0.0, 1.0);
-    rgb.b = rgb.b.clamp(0.0, 1.0);
+    let t = find_gamut_intersection(a_, b_, l, c, l0, cusp);
+    let l_clipped = l0 * (1.0 - t) + t * l;
+    let c_clipped = t * c;
 
-    rgb
+    clamp_rgb(oklab_to_linear_srgb(Oklab::new(
+        l_clipped as f32,
+        (c_clipped * a_) as f32,
+        (c_clipped * b_) as f32,
+    )))
+}
+
+pub fn gamut_clip_preserve_chroma(rgb: Rgb, lab: Oklab) -> Rgb {
+    gamut_clip(rgb, lab, |l, _c, _cusp| l.clamp(0.0, 1.0))
+}
+
+pub fn gamut_clip_project_to_0_5(rgb: Rgb, lab: Oklab) -> Rgb {
+    gamut_clip(rgb, lab, |_l, _c, _cusp| 0.5)
+}
+
+pub fn gamut_clip_project_to_l_cusp(rgb: Rgb, lab: Oklab) -> Rgb {
+    gamut_clip(rgb, lab, |_l, _c, cusp| cusp.l)
+}
+
+pub fn gamut_clip_adaptive_l0_0_5(rgb: Rgb, lab: Oklab, alpha: f64) -> Rgb {
+    gamut_clip(rgb, lab, |l, c, _cusp| {
+        let ld = l - 0.5;
+        let e1 = 0.5 + f64::abs(ld) + alpha * c;
+        0.5 * (1.0 + f64::signum(ld) * (e1 - f64::sqrt(e1 * e1 - 2.0 * f64::abs(ld))))
+    })
 }
 
 fn toe(x: f64) -> f64 {
@@ -0,0 +1,226 @@
+use super::{Color, Component, LinearSrgb, Oklab, gamut, transform};
+
+/// Linear Rec. 2020 to linear sRGB. Both use the D65 white point.
+const TO_SRGB: [[f64; 3]; 3] = [
+    [1.6604910021, -0.5876411388, -0.0728498633],
+    [-0.1245504745, 1.1328998971, -0.0083494226],
+    [-0.0181507634, -0.1005788980, 1.1187296614],
+];
+
+/// Linear sRGB to linear Rec. 2020.
+const FROM_SRGB: [[f64; 3]; 3] = [
+    [0.6274038959, 0.3292830384, 0.0433130657],
+    [0.0690972894, 0.9195403951, 0.0113623156],
+    [0.0163914389, 0.0880133079, 0.8955952532],
+];
+
+// Rec. 2020 transfer function constants, as used by CSS Color 4
+const ALPHA: f32 = 1.099_296_8;
+const BETA: f32 = 0.018_053_97;
+
+/// Rec. 2020 transfer function (linear to encoded).
+fn transfer_encode(x: f32) -> f32 {
+    if x < BETA {
+        4.5 * x
+    } else {
+        ALPHA * x.powf(0.45) - (ALPHA - 1.0)
+    }
+}
+
+/// Inverse of [`transfer_encode()`].
+fn transfer_decode(x: f32) -> f32 {
+    if x < 4.5 * BETA {
+        x / 4.5
+    } else {
+        ((x + ALPHA - 1.0) / ALPHA).powf(1.0 / 0.45)
+    }
+}
+
+/// Linear Rec. 2020 (BT.2020) color.
+///
+/// Rec. 2020 has monochromatic primaries, covering most visible colors.
+/// Physical displays rarely reproduce the whole gamut.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LinearRec2020<T = f32> {
+    /// Red channel.
+    pub r: T,
+    /// Green channel.
+    pub g: T,
+    /// Blue channel.
+    pub b: T,
+}
+
+impl<T> LinearRec2020<T> {
+    /// Constructs a color with the provided RGB components.
+    pub const fn new(r: T, g: T, b: T) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl LinearRec2020 {
+    /// Whether the color is inside the Rec. 2020 gamut.
+    pub fn is_in_gamut(&self) -> bool {
+        gamut::is_unit_cube([self.r, self.g, self.b])
+    }
+
+    /// Maps the color into the Rec. 2020 gamut, reducing chroma while
+    /// preserving lightness and hue.
+    pub fn gamut_clip(&self) -> Self {
+        let [r, g, b] = gamut::clip_chroma(self.to_linear_srgb(), |c| {
+            transform(&FROM_SRGB, [c.r, c.g, c.b])
+        });
+        Self::new(r, g, b)
+    }
+}
+
+impl<T: Component> Color for LinearRec2020<T> {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        let [r, g, b] = transform(&FROM_SRGB, [c.r, c.g, c.b]);
+        Self::new(T::from_f32(r), T::from_f32(g), T::from_f32(b))
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        let rgb = [self.r.as_f32(), self.g.as_f32(), self.b.as_f32()];
+        let [r, g, b] = transform(&TO_SRGB, rgb);
+        LinearSrgb::new(r, g, b)
+    }
+
+    fn to_linear_srgb_clamped(&self) -> LinearSrgb {
+        Oklab::from_linear_srgb(self.to_linear_srgb()).to_linear_srgb_clamped()
+    }
+}
+
+/// Rec. 2020 color, encoded with the BT.2020 transfer function.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rec2020<T = f32> {
+    /// Red channel.
+    pub r: T,
+    /// Green channel.
+    pub g: T,
+    /// Blue channel.
+    pub b: T,
+}
+
+impl<T> Rec2020<T> {
+    /// Constructs a color with the provided RGB components.
+    pub const fn new(r: T, g: T, b: T) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl<T: Component> Rec2020<T> {
+    /// Converts to linear Rec. 2020.
+    pub fn to_linear(&self) -> LinearRec2020 {
+        let f = |x: T| transfer_decode(x.as_f32());
+        LinearRec2020::new(f(self.r), f(self.g), f(self.b))
+    }
+
+    /// Converts from linear Rec. 2020.
+    pub fn from_linear(c: LinearRec2020) -> Self {
+        let f = |x: f32| T::from_f32(transfer_encode(x));
+        Self::new(f(c.r), f(c.g), f(c.b))
+    }
+
+    /// Whether the color is inside the Rec. 2020 gamut.
+    pub fn is_in_gamut(&self) -> bool {
+        self.to_linear().is_in_gamut()
+    }
+
+    /// Maps the color into the Rec. 2020 gamut, reducing chroma while
+    /// preserving lightness and hue.
+    pub fn gamut_clip(&self) -> Self {
+        Self::from_linear(self.to_linear().gamut_clip())
+    }
+}
+
+impl<T: Component> Color for Rec2020<T> {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        Self::from_linear(LinearRec2020::from_linear_srgb(c))
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        self.to_linear().to_linear_srgb()
+    }
+
+    fn to_linear_srgb_clamped(&self) -> LinearSrgb {
+        self.to_linear().to_linear_srgb_clamped()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{LinearDisplayP3, NonlinearSrgb};
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn transfer_round_trip() {
+        for i in 0..=1000 {
+            let x = i as f32 / 1000.0;
+            assert!((transfer_decode(transfer_encode(x)) - x).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        let step = 15;
+        for r in (0..=255u8).step_by(step) {
+            for g in (0..=255u8).step_by(step) {
+                for b in (0..=255u8).step_by(step) {
+                    let c1 = NonlinearSrgb::new(r, g, b);
+                    let rec2020 = Rec2020::<f32>::from_linear_srgb(c1.to_linear_srgb());
+                    assert!(rec2020.is_in_gamut());
+                    let c2 = NonlinearSrgb::<u8>::from_linear_srgb(rec2020.to_linear_srgb());
+                    assert_eq!(c1, c2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_primaries() {
+        // values from CSS Color 4
+        let red = Rec2020::<f32>::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert_close([red.r, red.g, red.b], [0.79198, 0.23098, 0.07376]);
+
+        let white = Rec2020::<f32>::from_linear_srgb(LinearSrgb::new(1.0, 1.0, 1.0));
+        assert_close([white.r, white.g, white.b], [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn mostly_contains_display_p3() {
+        for p3 in [
+            LinearDisplayP3::new(0.0, 1.0, 0.0),
+            LinearDisplayP3::new(0.0, 0.0, 1.0),
+            LinearDisplayP3::new(1.0, 1.0, 0.0),
+        ] {
+            let rec2020 = LinearRec2020::<f32>::from_linear_srgb(p3.to_linear_srgb());
+            assert!(rec2020.is_in_gamut(), "{p3:?}");
+        }
+
+        // the Display P3 red primary sticks out of Rec. 2020 ever so slightly
+        let red = LinearRec2020::<f32>::from_linear_srgb(
+            LinearDisplayP3::new(1.0, 0.0, 0.0).to_linear_srgb(),
+        );
+        assert_close([red.r, red.g, red.b], [0.75383, 0.04574, -0.00121]);
+    }
+
+    #[test]
+    fn gamut_clip_into_display_p3() {
+        let green = LinearRec2020::new(0.0, 1.0, 0.0);
+        let p3 = LinearDisplayP3::<f32>::from_linear_srgb(green.to_linear_srgb());
+        assert!(!p3.is_in_gamut());
+
+        let clipped = p3.gamut_clip();
+        assert!(clipped.is_in_gamut());
+
+        let lab1 = Oklab::from_linear_srgb(green.to_linear_srgb());
+        let lab2 = Oklab::from_linear_srgb(clipped.to_linear_srgb());
+        assert!((lab1.l - lab2.l).abs() < 1e-3);
+    }
+}
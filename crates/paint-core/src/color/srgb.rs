@@ -55,32 +55,34 @@ impl<T> NonlinearSrgb<T> {
 
 impl<T: Component> Color for NonlinearSrgb<T> {
     fn from_linear_srgb(c: LinearSrgb) -> Self {
-        let f = |x: f32| {
-            let y = if x <= 0.0031308 {
-                x * 12.92
-            } else {
-                1.055 * x.powf(1.0 / 2.4) - 0.055
-            };
-            T::from_f32(y)
-        };
-
+        let f = |x: f32| T::from_f32(transfer_encode(x));
         Self::new(f(c.r), f(c.g), f(c.b))
     }
 
     fn to_linear_srgb(&self) -> LinearSrgb {
-        let f = |x: T| {
-            let x = x.as_f32();
-            if x <= 0.04045 {
-                x / 12.92
-            } else {
-                ((x + 0.055) / 1.055).powf(2.4)
-            }
-        };
-
+        let f = |x: T| transfer_decode(x.as_f32());
         LinearSrgb::new(f(self.r), f(self.g), f(self.b))
     }
 }
 
+/// sRGB transfer function (linear to encoded), also used by Display P3.
+pub(super) fn transfer_encode(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse of [`transfer_encode()`].
+pub(super) fn transfer_decode(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,155 @@
+use super::{Color, LinearSrgb, transform};
+
+/// Linear sRGB to XYZ with the D65 white point.
+const FROM_SRGB: [[f64; 3]; 3] = [
+    [0.4123907993, 0.3575843394, 0.1804807884],
+    [0.2126390059, 0.7151686788, 0.0721923154],
+    [0.0193308187, 0.1191947798, 0.9505321522],
+];
+
+/// XYZ with the D65 white point to linear sRGB.
+const TO_SRGB: [[f64; 3]; 3] = [
+    [3.2409699419, -1.5373831776, -0.4986107603],
+    [-0.9692436363, 1.8759675015, 0.0415550574],
+    [0.0556300797, -0.2039769589, 1.0569715142],
+];
+
+/// Bradford chromatic adaptation from D65 to D50.
+const D65_TO_D50: [[f64; 3]; 3] = [
+    [1.0479297925, 0.0229468706, -0.0501922663],
+    [0.0296278088, 0.9904344268, -0.0170737991],
+    [-0.0092430406, 0.0150551915, 0.7518742814],
+];
+
+/// Bradford chromatic adaptation from D50 to D65.
+const D50_TO_D65: [[f64; 3]; 3] = [
+    [0.9554734215, -0.0230984549, 0.0632592432],
+    [-0.0283697093, 1.0099953981, 0.0210414412],
+    [0.0123140149, -0.0205076493, 1.3303659262],
+];
+
+/// Color in CIE 1931 XYZ color space, relative to the D65 white point.
+///
+/// `y` is the relative luminance, 1.0 for the reference white.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Xyz {
+    /// X component.
+    pub x: f32,
+    /// Y component (luminance).
+    pub y: f32,
+    /// Z component.
+    pub z: f32,
+}
+
+impl Xyz {
+    /// D65 white point, which is the white of sRGB and Display P3.
+    pub const WHITE: Self = Self::new(0.9504559, 1.0, 1.0890578);
+
+    /// Creates a new [`Xyz`] color with the given components.
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Adapts the color to the D50 white point with the Bradford transform.
+    pub fn to_d50(&self) -> XyzD50 {
+        let [x, y, z] = transform(&D65_TO_D50, [self.x, self.y, self.z]);
+        XyzD50::new(x, y, z)
+    }
+
+    /// Adapts the color from the D50 white point with the Bradford transform.
+    pub fn from_d50(c: XyzD50) -> Self {
+        let [x, y, z] = transform(&D50_TO_D65, [c.x, c.y, c.z]);
+        Self::new(x, y, z)
+    }
+}
+
+impl Color for Xyz {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        let [x, y, z] = transform(&FROM_SRGB, [c.r, c.g, c.b]);
+        Self::new(x, y, z)
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        let [r, g, b] = transform(&TO_SRGB, [self.x, self.y, self.z]);
+        LinearSrgb::new(r, g, b)
+    }
+}
+
+/// Color in CIE 1931 XYZ color space, relative to the D50 white point.
+///
+/// D50 is used by ICC profiles and CIELAB (see [`Lab`](super::Lab)).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct XyzD50 {
+    /// X component.
+    pub x: f32,
+    /// Y component (luminance).
+    pub y: f32,
+    /// Z component.
+    pub z: f32,
+}
+
+impl XyzD50 {
+    /// D50 white point.
+    pub const WHITE: Self = Self::new(0.9642957, 1.0, 0.8251046);
+
+    /// Creates a new [`XyzD50`] color with the given components.
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl Color for XyzD50 {
+    fn from_linear_srgb(c: LinearSrgb) -> Self {
+        Xyz::from_linear_srgb(c).to_d50()
+    }
+
+    fn to_linear_srgb(&self) -> LinearSrgb {
+        Xyz::from_d50(*self).to_linear_srgb()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn white_points() {
+        let white = Xyz::from_linear_srgb(LinearSrgb::new(1.0, 1.0, 1.0));
+        assert_close([white.x, white.y, white.z], [0.95046, 1.0, 1.08906]);
+
+        let white = white.to_d50();
+        let expected = XyzD50::WHITE;
+        assert_close(
+            [white.x, white.y, white.z],
+            [expected.x, expected.y, expected.z],
+        );
+    }
+
+    #[test]
+    fn srgb_primaries() {
+        let red = Xyz::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert_close([red.x, red.y, red.z], [0.41239, 0.21264, 0.01933]);
+
+        // values from CSS Color 4
+        let red = XyzD50::from_linear_srgb(LinearSrgb::new(1.0, 0.0, 0.0));
+        assert_close([red.x, red.y, red.z], [0.43607, 0.22249, 0.01392]);
+    }
+
+    #[test]
+    fn round_trip() {
+        for c in [
+            LinearSrgb::new(0.2, 0.5, 0.9),
+            LinearSrgb::new(1.0, 0.0, 0.3),
+            LinearSrgb::new(0.01, 0.02, 0.0),
+        ] {
+            let c2 = XyzD50::from_linear_srgb(c).to_linear_srgb();
+            assert_close([c2.r, c2.g, c2.b], [c.r, c.g, c.b]);
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! Image filters, which replace the canvas with a filtered version of it.
+
+use glam::{UVec2, Vec2, Vec4};
+
+use crate::color::{LinearSrgb, WithAlpha};
+
+/// Largest distance in pixels which blurs reach on each side of a pixel.
+/// Larger radii and lengths are clamped, as every pixel samples the whole
+/// kernel.
+pub const MAX_BLUR_RADIUS: u32 = 256;
+
+/// An image filter.
+///
+/// Filters work on linear light colors premultiplied by alpha, so transparent
+/// pixels don't bleed their color into opaque ones. Pixels outside the image
+/// repeat the nearest edge pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Gaussian blur, whose kernel reaches `radius` pixels, three standard
+    /// deviations, up to [`MAX_BLUR_RADIUS`].
+    GaussianBlur { radius: f32 },
+    /// Average of a square of `2 * radius + 1` pixels, up to
+    /// [`MAX_BLUR_RADIUS`].
+    BoxBlur { radius: u32 },
+    /// Average along a line of `length` pixels centered on the pixel, at
+    /// `angle` radians counterclockwise from the right. Reaches up to
+    /// [`MAX_BLUR_RADIUS`] on each side.
+    MotionBlur { angle: f32, length: f32 },
+    /// Sharpens by adding the difference from a [`Filter::GaussianBlur`]
+    /// of `radius`, multiplied by `amount`. Differences smaller than
+    /// `threshold` are left out, so noise isn't sharpened.
+    UnsharpMask {
+        radius: f32,
+        amount: f32,
+        threshold: f32,
+    },
+    /// Adds uniform noise of up to `amount` to each component, the same to
+    /// all components if `monochrome`. Different seeds give different noise.
+    Noise {
+        amount: f32,
+        seed: u32,
+        monochrome: bool,
+    },
+}
+
+/// Parameters of a single 1D convolution pass, see [`Filter::blur_passes()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlurPass {
+    /// Distance between consecutive samples, in pixels.
+    pub step: Vec2,
+    /// Number of samples on each side of the pixel.
+    pub radius: u32,
+    /// Standard deviation of the Gaussian weights in samples, or 0 for equal
+    /// weights.
+    pub sigma: f32,
+}
+
+impl Filter {
+    /// The blur passes of the filter, applied one after another. Unsharp mask
+    /// combines the result with the original image.
+    pub fn blur_passes(&self) -> Vec<BlurPass> {
+        match *self {
+            Filter::GaussianBlur { radius } | Filter::UnsharpMask { radius, .. } => {
+                let radius = radius.clamp(0.0, MAX_BLUR_RADIUS as f32);
+                let samples = radius.ceil() as u32;
+                if samples == 0 {
+                    return Vec::new();
+                }
+
+                let sigma = radius / 3.0;
+                vec![
+                    BlurPass {
+                        step: Vec2::X,
+                        radius: samples,
+                        sigma,
+                    },
+                    BlurPass {
+                        step: Vec2::Y,
+                        radius: samples,
+                        sigma,
+                    },
+                ]
+            }
+            Filter::BoxBlur { radius } => {
+                let radius = radius.min(MAX_BLUR_RADIUS);
+                if radius == 0 {
+                    return Vec::new();
+                }
+
+                vec![
+                    BlurPass {
+                        step: Vec2::X,
+                        radius,
+                        sigma: 0.0,
+                    },
+                    BlurPass {
+                        step: Vec2::Y,
+                        radius,
+                        sigma: 0.0,
+                    },
+                ]
+            }
+            Filter::MotionBlur { angle, length } => {
+                let half_length = (0.5 * length).clamp(0.0, MAX_BLUR_RADIUS as f32);
+                let samples = half_length.ceil() as u32;
+                if samples == 0 {
+                    return Vec::new();
+                }
+
+                // Y points down in images
+                let direction = Vec2::new(angle.cos(), -angle.sin());
+                vec![BlurPass {
+                    step: direction * (half_length / samples as f32),
+                    radius: samples,
+                    sigma: 0.0,
+                }]
+            }
+            Filter::Noise { .. } => Vec::new(),
+        }
+    }
+
+    /// Filters a row-major image of straight alpha colors.
+    ///
+    /// This is the reference implementation, which the GPU filters must match.
+    pub fn apply(
+        &self,
+        resolution: UVec2,
+        pixels: &[WithAlpha<LinearSrgb>],
+    ) -> Vec<WithAlpha<LinearSrgb>> {
+        let original = Image::premultiplied(resolution, pixels);
+
+        let blurred = self
+            .blur_passes()
+            .iter()
+            .fold(original.clone(), |image, pass| image.blur(pass));
+
+        let filtered = match *self {
+            Filter::UnsharpMask {
+                amount, threshold, ..
+            } => original.zip(&blurred, |o, b| unsharp_mask(o, b, amount, threshold)),
+            Filter::Noise {
+                amount,
+                seed,
+                monochrome,
+            } => original.map(|position, c| add_noise(position, c, amount, seed, monochrome)),
+            _ => blurred,
+        };
+
+        filtered.unpremultiplied()
+    }
+}
+
+/// Premultiplied image, which the reference implementations work on.
+#[derive(Debug, Clone)]
+pub(crate) struct Image {
+    resolution: UVec2,
+    pixels: Vec<Vec4>,
+}
+
+impl Image {
+    pub(crate) fn premultiplied(resolution: UVec2, pixels: &[WithAlpha<LinearSrgb>]) -> Self {
+        Self {
+            resolution,
+            pixels: pixels
+                .iter()
+                .map(|c| {
+                    let a = c.alpha.clamp(0.0, 1.0);
+                    Vec4::new(c.color.r * a, c.color.g * a, c.color.b * a, a)
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn unpremultiplied(&self) -> Vec<WithAlpha<LinearSrgb>> {
+        self.pixels
+            .iter()
+            .map(|c| {
+                let rgb = if c.w > 0.0 {
+                    c.truncate() / c.w
+                } else {
+                    c.truncate() * 0.0
+                };
+                WithAlpha {
+                    color: LinearSrgb::new(rgb.x, rgb.y, rgb.z),
+                    alpha: c.w,
+                }
+            })
+            .collect()
+    }
+
+    fn load(&self, x: i32, y: i32) -> Vec4 {
+        let max = self.resolution.as_ivec2() - 1;
+        let x = x.clamp(0, max.x) as usize;
+        let y = y.clamp(0, max.y) as usize;
+        self.pixels[y * self.resolution.x as usize + x]
+    }
+
+    /// Bilinear interpolation between pixel centers, at integer coordinates.
+    pub(crate) fn load_bilinear(&self, p: Vec2) -> Vec4 {
+        let p0 = p.floor();
+        let f = p - p0;
+        let (x, y) = (p0.x as i32, p0.y as i32);
+
+        let top = self.load(x, y).lerp(self.load(x + 1, y), f.x);
+        let bottom = self.load(x, y + 1).lerp(self.load(x + 1, y + 1), f.x);
+        top.lerp(bottom, f.y)
+    }
+
+    pub(crate) fn map(&self, f: impl Fn(UVec2, Vec4) -> Vec4) -> Self {
+        let width = self.resolution.x as usize;
+        Self {
+            resolution: self.resolution,
+            pixels: self
+                .pixels
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| f(UVec2::new((i % width) as u32, (i / width) as u32), c))
+                .collect(),
+        }
+    }
+
+    fn zip(&self, other: &Image, f: impl Fn(Vec4, Vec4) -> Vec4) -> Self {
+        Self {
+            resolution: self.resolution,
+            pixels: self
+                .pixels
+                .iter()
+                .zip(&other.pixels)
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+        }
+    }
+
+    fn blur(&self, pass: &BlurPass) -> Self {
+        let r = pass.radius as i32;
+        self.map(|position, _| {
+            let position = position.as_vec2();
+            let mut sum = Vec4::ZERO;
+            let mut weight_sum = 0.0;
+
+            for i in -r..=r {
+                let weight = blur_weight(i, pass.sigma);
+                sum += self.load_bilinear(position + pass.step * i as f32) * weight;
+                weight_sum += weight;
+            }
+
+            sum / weight_sum
+        })
+    }
+}
+
+fn blur_weight(i: i32, sigma: f32) -> f32 {
+    if sigma > 0.0 {
+        let x = i as f32 / sigma;
+        (-0.5 * x * x).exp()
+    } else {
+        1.0
+    }
+}
+
+fn unsharp_mask(original: Vec4, blurred: Vec4, amount: f32, threshold: f32) -> Vec4 {
+    let difference = original.truncate() - blurred.truncate();
+    let mask = difference.abs().cmpge(glam::Vec3::splat(threshold));
+    let difference = glam::Vec3::select(mask, difference, glam::Vec3::ZERO);
+
+    let rgb = (original.truncate() + difference * amount)
+        .clamp(glam::Vec3::ZERO, glam::Vec3::splat(original.w));
+    rgb.extend(original.w)
+}
+
+fn add_noise(position: UVec2, c: Vec4, amount: f32, seed: u32, monochrome: bool) -> Vec4 {
+    let hash = pcg(position
+        .x
+        .wrapping_add(pcg(position.y.wrapping_add(pcg(seed)))));
+    let noise = |channel: u32| {
+        let hash = if monochrome {
+            hash
+        } else {
+            pcg(hash.wrapping_add(channel))
+        };
+        // uniform between -1 and 1
+        (hash >> 8) as f32 / (1 << 23) as f32 - 1.0
+    };
+
+    let rgb = c.truncate() + glam::Vec3::new(noise(0), noise(1), noise(2)) * amount * c.w;
+    rgb.clamp(glam::Vec3::ZERO, glam::Vec3::splat(c.w))
+        .extend(c.w)
+}
+
+/// PCG hash by Jarzynski and Olano, "Hash Functions for GPU Rendering".
+fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: UVec2 = UVec2::new(16, 8);
+
+    /// Vertical stripes of alternating colors, transparent on the right.
+    fn stripes() -> Vec<WithAlpha<LinearSrgb>> {
+        (0..RESOLUTION.x * RESOLUTION.y)
+            .map(|i| {
+                let x = i % RESOLUTION.x;
+                let color = if x.is_multiple_of(2) {
+                    LinearSrgb::new(1.0, 0.5, 0.0)
+                } else {
+                    LinearSrgb::new(0.0, 0.5, 1.0)
+                };
+                let alpha = if x < RESOLUTION.x - 4 { 1.0 } else { 0.0 };
+                WithAlpha { color, alpha }
+            })
+            .collect()
+    }
+
+    fn pixel(image: &[WithAlpha<LinearSrgb>], x: u32, y: u32) -> WithAlpha<LinearSrgb> {
+        image[(y * RESOLUTION.x + x) as usize]
+    }
+
+    #[test]
+    fn blur_averages_stripes() {
+        let image = stripes();
+
+        for filter in [
+            Filter::GaussianBlur { radius: 6.0 },
+            Filter::BoxBlur { radius: 2 },
+            Filter::MotionBlur {
+                angle: 0.0,
+                length: 8.0,
+            },
+        ] {
+            let blurred = filter.apply(RESOLUTION, &image);
+            let c = pixel(&blurred, 5, 3);
+            assert!((c.color.r - 0.5).abs() < 0.2, "{filter:?}: {c:?}");
+            assert!(
+                (c.color.r + c.color.b - 1.0).abs() < 1e-4,
+                "{filter:?}: {c:?}"
+            );
+            assert!((c.alpha - 1.0).abs() < 1e-6, "{filter:?}: {c:?}");
+
+            // transparent pixels don't darken the edge
+            let edge = pixel(&blurred, RESOLUTION.x - 5, 3);
+            assert!(edge.alpha < 1.0, "{filter:?}: {edge:?}");
+            assert!((edge.color.g - 0.5).abs() < 1e-4, "{filter:?}: {edge:?}");
+        }
+    }
+
+    #[test]
+    fn vertical_motion_blur_keeps_vertical_stripes() {
+        let image = stripes();
+        let filter = Filter::MotionBlur {
+            angle: std::f32::consts::FRAC_PI_2,
+            length: 5.0,
+        };
+
+        let blurred = filter.apply(RESOLUTION, &image);
+        for (a, b) in blurred.iter().zip(&image).filter(|(_, b)| b.alpha > 0.0) {
+            assert!((a.color.r - b.color.r).abs() < 1e-5);
+            assert!((a.alpha - b.alpha).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn zero_radius_keeps_image() {
+        let image = stripes();
+
+        for filter in [
+            Filter::GaussianBlur { radius: 0.0 },
+            Filter::BoxBlur { radius: 0 },
+            Filter::UnsharpMask {
+                radius: 2.0,
+                amount: 0.0,
+                threshold: 0.0,
+            },
+            Filter::Noise {
+                amount: 0.0,
+                seed: 1,
+                monochrome: false,
+            },
+        ] {
+            let filtered = filter.apply(RESOLUTION, &image);
+            for (a, b) in filtered.iter().zip(&image) {
+                if b.alpha > 0.0 {
+                    assert_eq!(a, b, "{filter:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn huge_blurs_are_clamped() {
+        let image = stripes();
+
+        let clamped = MAX_BLUR_RADIUS as f32;
+        for (huge, limit) in [
+            (
+                Filter::GaussianBlur { radius: 1e30 },
+                Filter::GaussianBlur { radius: clamped },
+            ),
+            (
+                Filter::BoxBlur { radius: u32::MAX },
+                Filter::BoxBlur {
+                    radius: MAX_BLUR_RADIUS,
+                },
+            ),
+            (
+                Filter::MotionBlur {
+                    angle: 0.3,
+                    length: f32::INFINITY,
+                },
+                Filter::MotionBlur {
+                    angle: 0.3,
+                    length: 2.0 * clamped,
+                },
+            ),
+            (
+                Filter::UnsharpMask {
+                    radius: 1e30,
+                    amount: 0.5,
+                    threshold: 0.0,
+                },
+                Filter::UnsharpMask {
+                    radius: clamped,
+                    amount: 0.5,
+                    threshold: 0.0,
+                },
+            ),
+        ] {
+            assert_eq!(huge.blur_passes(), limit.blur_passes(), "{huge:?}");
+            assert!(
+                huge.blur_passes()
+                    .iter()
+                    .all(|pass| pass.radius <= MAX_BLUR_RADIUS),
+                "{huge:?}"
+            );
+
+            let filtered = huge.apply(RESOLUTION, &image);
+            assert_eq!(filtered, limit.apply(RESOLUTION, &image), "{huge:?}");
+            assert!(
+                filtered
+                    .iter()
+                    .all(|c| c.alpha.is_finite() && c.color.r.is_finite()),
+                "{huge:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unsharp_mask_increases_contrast() {
+        let image = stripes();
+        let filter = Filter::UnsharpMask {
+            radius: 3.0,
+            amount: 0.5,
+            threshold: 0.0,
+        };
+
+        let sharpened = filter.apply(RESOLUTION, &image);
+        let orange = pixel(&sharpened, 4, 3);
+        let blue = pixel(&sharpened, 5, 3);
+        assert_eq!(orange.color.r, 1.0);
+        assert_eq!(blue.color.r, 0.0);
+        assert!(orange.color.b == 0.0 && blue.color.b == 1.0);
+
+        // nothing exceeds the threshold
+        let filter = Filter::UnsharpMask {
+            radius: 3.0,
+            amount: 0.5,
+            threshold: 1.0,
+        };
+        assert_eq!(filter.apply(RESOLUTION, &image)[..4], image[..4]);
+    }
+
+    #[test]
+    fn noise_is_deterministic() {
+        let image = stripes();
+        let noise = |seed, monochrome| {
+            Filter::Noise {
+                amount: 0.2,
+                seed,
+                monochrome,
+            }
+            .apply(RESOLUTION, &image)
+        };
+
+        assert_eq!(noise(1, false), noise(1, false));
+        assert_ne!(noise(1, false), noise(2, false));
+
+        let c = pixel(&noise(1, true), 1, 1);
+        let original = pixel(&image, 1, 1);
+        let d = c.color.g - original.color.g;
+        assert!(d.abs() <= 0.2 && d != 0.0);
+        assert!((c.color.r - (original.color.r + d).clamp(0.0, 1.0)).abs() < 1e-6);
+        assert!((c.color.b - (original.color.b + d).clamp(0.0, 1.0)).abs() < 1e-6);
+
+        // transparent pixels stay transparent and black
+        let c = pixel(&noise(1, false), RESOLUTION.x - 1, 0);
+        assert_eq!(c.alpha, 0.0);
+        assert_eq!(c.color, LinearSrgb::new(0.0, 0.0, 0.0));
+    }
+}
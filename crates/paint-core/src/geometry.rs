@@ -0,0 +1,314 @@
+use glam::{UVec2, Vec2};
+
+/// Axis-aligned rectangle.
+///
+/// A rectangle with `max <= min` on any axis is empty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    /// Minimum (inclusive) corner.
+    pub min: Vec2,
+    /// Maximum (exclusive) corner.
+    pub max: Vec2,
+}
+
+impl Rect {
+    /// Empty rectangle, which is the identity for [`Rect::union()`].
+    pub const EMPTY: Self = Self {
+        min: Vec2::INFINITY,
+        max: Vec2::NEG_INFINITY,
+    };
+
+    /// Creates a rectangle from the minimum and maximum corners.
+    pub const fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    /// Creates a rectangle from the minimum corner and the size.
+    pub fn from_min_size(min: Vec2, size: Vec2) -> Self {
+        Self::new(min, min + size)
+    }
+
+    /// Creates a rectangle from its center and half of its size.
+    pub fn from_center_half_size(center: Vec2, half_size: Vec2) -> Self {
+        Self::new(center - half_size, center + half_size)
+    }
+
+    /// Creates a rectangle covering a whole texture of the given resolution.
+    pub fn from_resolution(resolution: UVec2) -> Self {
+        Self::new(Vec2::ZERO, resolution.as_vec2())
+    }
+
+    /// Whether the rectangle has no area.
+    pub fn is_empty(&self) -> bool {
+        self.max.x <= self.min.x || self.max.y <= self.min.y
+    }
+
+    /// Size of the rectangle, or zero if it's empty.
+    pub fn size(&self) -> Vec2 {
+        (self.max - self.min).max(Vec2::ZERO)
+    }
+
+    /// Area of the rectangle, or zero if it's empty.
+    pub fn area(&self) -> f32 {
+        let size = self.size();
+        size.x * size.y
+    }
+
+    /// Smallest rectangle containing both rectangles.
+    pub fn union(self, other: Self) -> Self {
+        if self.is_empty() {
+            return other;
+        }
+
+        if other.is_empty() {
+            return self;
+        }
+
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Area common to both rectangles.
+    pub fn intersection(self, other: Self) -> Self {
+        Self::new(self.min.max(other.min), self.max.min(other.max))
+    }
+
+    /// Whether the rectangles share any area. Touching edges don't count.
+    pub fn intersects(&self, other: &Self) -> bool {
+        !self.intersection(*other).is_empty()
+    }
+
+    /// Whether `other` lies entirely inside this rectangle.
+    pub fn contains_rect(&self, other: &Self) -> bool {
+        other.is_empty() || (self.min.cmple(other.min).all() && self.max.cmpge(other.max).all())
+    }
+
+    /// Grows the rectangle by `amount` in every direction.
+    pub fn expand(self, amount: f32) -> Self {
+        Self::new(self.min - amount, self.max + amount)
+    }
+
+    /// Multiplies both corners by `factor`.
+    pub fn scale(self, factor: f32) -> Self {
+        Self::new(self.min * factor, self.max * factor)
+    }
+
+    /// Expands the rectangle to the nearest integer coordinates.
+    pub fn round_out(self) -> Self {
+        Self::new(self.min.floor(), self.max.ceil())
+    }
+
+    /// Converts the rectangle to whole pixels inside a texture of the given
+    /// resolution.
+    ///
+    /// Returns the pixel offset and size, or [`None`] if nothing is left after
+    /// clipping.
+    pub fn to_pixels(self, resolution: UVec2) -> Option<(UVec2, UVec2)> {
+        let rect = self
+            .round_out()
+            .intersection(Self::from_resolution(resolution));
+
+        if rect.is_empty() {
+            return None;
+        }
+
+        Some((rect.min.as_uvec2(), rect.size().as_uvec2()))
+    }
+}
+
+impl Default for Rect {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+/// Set of areas which need to be redrawn, in pixel units.
+///
+/// Rectangles are rounded out to whole pixels and kept disjoint, so each
+/// pixel is covered at most once. When there would be too many rectangles,
+/// the pair wasting the least area on merging is merged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamageRegion {
+    rects: Vec<Rect>,
+    max_rects: usize,
+}
+
+impl DamageRegion {
+    /// Default limit on the number of disjoint rectangles.
+    pub const DEFAULT_MAX_RECTS: usize = 16;
+
+    /// Creates an empty region.
+    pub fn new() -> Self {
+        Self::with_max_rects(Self::DEFAULT_MAX_RECTS)
+    }
+
+    /// Creates an empty region, storing at most `max_rects` rectangles.
+    pub fn with_max_rects(max_rects: usize) -> Self {
+        Self {
+            rects: Vec::new(),
+            max_rects: max_rects.max(1),
+        }
+    }
+
+    /// Creates a region covering a single rectangle.
+    pub fn from_rect(rect: Rect) -> Self {
+        let mut region = Self::new();
+        region.add(rect);
+        region
+    }
+
+    /// Disjoint rectangles making up the region.
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    /// Whether the region covers nothing.
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Total area covered by the region.
+    pub fn area(&self) -> f32 {
+        self.rects.iter().map(Rect::area).sum()
+    }
+
+    /// Smallest rectangle containing the whole region.
+    pub fn bounds(&self) -> Rect {
+        self.rects.iter().copied().fold(Rect::EMPTY, Rect::union)
+    }
+
+    /// Removes all rectangles.
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+
+    /// Adds a rectangle to the region.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+
+        self.insert(rect.round_out());
+
+        while self.rects.len() > self.max_rects {
+            self.merge_cheapest_pair();
+        }
+    }
+
+    /// Adds all rectangles from another region.
+    pub fn add_region(&mut self, other: &DamageRegion) {
+        for rect in &other.rects {
+            self.add(*rect);
+        }
+    }
+
+    /// Inserts a rectangle, merging it with any rectangles it overlaps.
+    fn insert(&mut self, mut rect: Rect) {
+        while let Some(idx) = self.rects.iter().position(|r| r.intersects(&rect)) {
+            rect = rect.union(self.rects.swap_remove(idx));
+        }
+
+        self.rects.push(rect);
+    }
+
+    fn merge_cheapest_pair(&mut self) {
+        let mut best = (0, 1, f32::INFINITY);
+
+        for i in 0..self.rects.len() {
+            for j in (i + 1)..self.rects.len() {
+                let (a, b) = (self.rects[i], self.rects[j]);
+                let waste = a.union(b).area() - a.area() - b.area();
+                if waste < best.2 {
+                    best = (i, j, waste);
+                }
+            }
+        }
+
+        let (i, j, _) = best;
+        let b = self.rects.swap_remove(j);
+        let a = self.rects.swap_remove(i);
+        self.insert(a.union(b));
+    }
+}
+
+impl Default for DamageRegion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect {
+        Rect::new(Vec2::new(x0, y0), Vec2::new(x1, y1))
+    }
+
+    fn assert_disjoint(region: &DamageRegion) {
+        let rects = region.rects();
+        for i in 0..rects.len() {
+            for j in (i + 1)..rects.len() {
+                assert!(!rects[i].intersects(&rects[j]), "{rects:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn union_with_empty() {
+        let r = rect(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(Rect::EMPTY.union(r), r);
+        assert_eq!(r.union(Rect::EMPTY), r);
+        assert!(Rect::EMPTY.is_empty());
+    }
+
+    #[test]
+    fn to_pixels_clips_and_rounds() {
+        let r = rect(-3.5, 10.2, 20.1, 600.0);
+        let res = UVec2::new(100, 100);
+        assert_eq!(
+            r.to_pixels(res),
+            Some((UVec2::new(0, 10), UVec2::new(21, 90)))
+        );
+        assert_eq!(rect(200.0, 0.0, 300.0, 10.0).to_pixels(res), None);
+    }
+
+    #[test]
+    fn damage_merges_overlapping() {
+        let mut region = DamageRegion::new();
+        region.add(rect(0.0, 0.0, 10.0, 10.0));
+        region.add(rect(5.0, 5.0, 15.0, 15.0));
+        assert_eq!(region.rects(), &[rect(0.0, 0.0, 15.0, 15.0)]);
+
+        region.add(rect(20.0, 20.0, 30.0, 30.0));
+        assert_eq!(region.rects().len(), 2);
+        assert_disjoint(&region);
+    }
+
+    #[test]
+    fn damage_rounds_to_pixels() {
+        let mut region = DamageRegion::new();
+        region.add(rect(0.2, 0.2, 4.9, 4.9));
+        region.add(rect(4.95, 0.2, 8.0, 4.0));
+
+        // second rect touches the first only after rounding
+        assert_eq!(region.rects(), &[rect(0.0, 0.0, 8.0, 5.0)]);
+    }
+
+    #[test]
+    fn damage_respects_max_rects() {
+        let mut region = DamageRegion::with_max_rects(4);
+
+        // diagonal stroke of small dabs
+        for i in 0..100 {
+            let center = Vec2::splat(i as f32 * 10.0);
+            region.add(Rect::from_center_half_size(center, Vec2::splat(3.0)));
+        }
+
+        assert!(region.rects().len() <= 4);
+        assert_disjoint(&region);
+
+        // still much smaller than the bounding box
+        assert!(region.area() < 0.5 * region.bounds().area());
+        assert_eq!(region.bounds(), rect(-3.0, -3.0, 993.0, 993.0));
+    }
+}
@@ -1,4 +1,11 @@
+pub mod adjustment;
 pub mod behaviour;
 pub mod color;
+pub mod filter;
+pub mod geometry;
+pub mod liquify;
+pub mod palette;
 pub mod persistence;
 pub mod presentation;
+pub mod shape;
+pub mod text;
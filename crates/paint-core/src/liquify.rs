@@ -0,0 +1,333 @@
+//! Liquify, which smears the canvas by dragging a brush over it.
+//!
+//! Dabs accumulate into a [`DisplacementField`], and the canvas is resampled
+//! through the field, so repeated dabs don't blur the canvas more and more.
+
+use glam::{Mat2, UVec2, Vec2};
+
+use crate::behaviour::BrushState;
+use crate::color::{LinearSrgb, WithAlpha};
+use crate::filter::Image;
+use crate::geometry::Rect;
+
+/// Rotation of a full strength [`LiquifyMode::Twirl`] dab at its center, in
+/// radians.
+pub const MAX_TWIRL_ANGLE: f32 = 0.1;
+
+/// Scaling of a full strength [`LiquifyMode::Pinch`] or [`LiquifyMode::Bloat`]
+/// dab at its center.
+pub const MAX_SCALE: f32 = 0.05;
+
+/// Distance between dabs, relative to the radius.
+const SPACING: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiquifyMode {
+    /// Pushes the canvas along the stroke.
+    Push,
+    /// Rotates the canvas around the brush, counterclockwise unless
+    /// `clockwise`.
+    Twirl { clockwise: bool },
+    /// Shrinks the canvas towards the brush.
+    Pinch,
+    /// Grows the canvas away from the brush.
+    Bloat,
+    /// Gradually undoes the displacement of the stroke.
+    Reconstruct,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiquifySettings {
+    pub mode: LiquifyMode,
+    /// Radius of the brush, in canvas pixels.
+    pub radius: f32,
+    /// Strength between 0 and 1, which is multiplied by the pressure.
+    pub strength: f32,
+}
+
+/// A single application of the liquify brush.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dab {
+    pub position: Vec2,
+    /// Movement since the previous dab, which [`LiquifyMode::Push`] pushes
+    /// the canvas by.
+    pub delta: Vec2,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl Dab {
+    /// Area of the canvas which the dab displaces.
+    pub fn bounds(&self) -> Rect {
+        Rect::from_center_half_size(self.position, Vec2::splat(self.radius + 1.0))
+    }
+
+    /// Weight of the dab at a distance from its center, falling off smoothly
+    /// to 0 at the radius.
+    pub fn weight(&self, distance: f32) -> f32 {
+        let d = distance / self.radius;
+        if d >= 1.0 {
+            return 0.0;
+        }
+
+        let t = 1.0 - d * d;
+        self.strength * t * t
+    }
+}
+
+impl LiquifySettings {
+    /// Dabs spaced along the stroke from the previous position, or a single
+    /// dab at the start of the stroke.
+    pub fn dabs(&self, previous: Option<Vec2>, state: &BrushState) -> Vec<Dab> {
+        let radius = self.radius.max(1.0);
+        let strength = self.strength.clamp(0.0, 1.0) * state.pressure.clamp(0.0, 1.0);
+
+        let Some(previous) = previous else {
+            return vec![Dab {
+                position: state.position,
+                delta: Vec2::ZERO,
+                radius,
+                strength,
+            }];
+        };
+
+        let movement = state.position - previous;
+        let count = (movement.length() / (radius * SPACING)).ceil().max(1.0) as u32;
+        let delta = movement / count as f32;
+
+        (1..=count)
+            .map(|i| Dab {
+                position: previous + delta * i as f32,
+                delta,
+                radius,
+                strength,
+            })
+            .collect()
+    }
+}
+
+/// Offsets in pixels, where the canvas should be sampled for each pixel.
+///
+/// This is the reference implementation, which the GPU liquify must match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplacementField {
+    resolution: UVec2,
+    offsets: Vec<Vec2>,
+}
+
+impl DisplacementField {
+    /// Creates a field which leaves the canvas as is.
+    pub fn new(resolution: UVec2) -> Self {
+        Self {
+            resolution,
+            offsets: vec![Vec2::ZERO; (resolution.x * resolution.y) as usize],
+        }
+    }
+
+    pub fn offset(&self, x: u32, y: u32) -> Vec2 {
+        self.offsets[(y * self.resolution.x + x) as usize]
+    }
+
+    fn load(&self, x: i32, y: i32) -> Vec2 {
+        let max = self.resolution.as_ivec2() - 1;
+        self.offset(x.clamp(0, max.x) as u32, y.clamp(0, max.y) as u32)
+    }
+
+    /// Bilinear interpolation between pixel centers, at integer coordinates.
+    fn load_bilinear(&self, p: Vec2) -> Vec2 {
+        let p0 = p.floor();
+        let f = p - p0;
+        let (x, y) = (p0.x as i32, p0.y as i32);
+
+        let top = self.load(x, y).lerp(self.load(x + 1, y), f.x);
+        let bottom = self.load(x, y + 1).lerp(self.load(x + 1, y + 1), f.x);
+        top.lerp(bottom, f.y)
+    }
+
+    /// Applies a dab, which moves where the pixels within its radius are
+    /// sampled from.
+    pub fn apply(&mut self, mode: LiquifyMode, dab: &Dab) {
+        let Some((min, size)) = dab.bounds().to_pixels(self.resolution) else {
+            return;
+        };
+
+        let previous = self.clone();
+        for y in min.y..min.y + size.y {
+            for x in min.x..min.x + size.x {
+                let i = UVec2::new(x, y);
+                self.offsets[(y * self.resolution.x + x) as usize] =
+                    displace(mode, dab, i, |p| previous.load_bilinear(p));
+            }
+        }
+    }
+
+    /// Resamples a row-major image of straight alpha colors through the field.
+    pub fn resample(&self, pixels: &[WithAlpha<LinearSrgb>]) -> Vec<WithAlpha<LinearSrgb>> {
+        let image = Image::premultiplied(self.resolution, pixels);
+        image
+            .map(|i, _| image.load_bilinear(i.as_vec2() + self.offset(i.x, i.y)))
+            .unpremultiplied()
+    }
+}
+
+/// The new offset of pixel `i`, given the bilinear interpolation of the
+/// previous offsets.
+fn displace(mode: LiquifyMode, dab: &Dab, i: UVec2, previous: impl Fn(Vec2) -> Vec2) -> Vec2 {
+    // pixel centers are at half-integer canvas coordinates
+    let x = i.as_vec2() + 0.5;
+    let current = previous(i.as_vec2());
+
+    let weight = dab.weight(x.distance(dab.position));
+    if weight == 0.0 {
+        return current;
+    }
+
+    // the point whose previous sample the pixel shows from now on
+    let y = match mode {
+        LiquifyMode::Push => x - dab.delta * weight,
+        LiquifyMode::Twirl { clockwise } => {
+            // the canvas moves opposite to where it's sampled from, and
+            // positive angles are clockwise with Y pointing down
+            let angle = weight * MAX_TWIRL_ANGLE * if clockwise { -1.0 } else { 1.0 };
+            dab.position + Mat2::from_angle(angle) * (x - dab.position)
+        }
+        LiquifyMode::Pinch => dab.position + (x - dab.position) * (1.0 + weight * MAX_SCALE),
+        LiquifyMode::Bloat => dab.position + (x - dab.position) * (1.0 - weight * MAX_SCALE),
+        LiquifyMode::Reconstruct => return current * (1.0 - weight),
+    };
+
+    y - x + previous(y - 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: UVec2 = UVec2::new(32, 32);
+
+    fn dab(position: Vec2, delta: Vec2) -> Dab {
+        Dab {
+            position,
+            delta,
+            radius: 8.0,
+            strength: 1.0,
+        }
+    }
+
+    #[test]
+    fn dabs_are_spaced_along_the_stroke() {
+        let settings = LiquifySettings {
+            mode: LiquifyMode::Push,
+            radius: 8.0,
+            strength: 0.5,
+        };
+        let state = BrushState {
+            position: Vec2::new(10.0, 0.0),
+            pressure: 0.5,
+        };
+
+        let dabs = settings.dabs(None, &state);
+        assert_eq!(dabs.len(), 1);
+        assert_eq!(dabs[0].delta, Vec2::ZERO);
+        assert_eq!(dabs[0].strength, 0.25);
+
+        let dabs = settings.dabs(Some(Vec2::ZERO), &state);
+        assert_eq!(dabs.len(), 5);
+        assert_eq!(dabs.last().unwrap().position, state.position);
+        assert!(dabs.iter().all(|dab| dab.delta == Vec2::new(2.0, 0.0)));
+    }
+
+    #[test]
+    fn push_moves_towards_the_stroke() {
+        let mut field = DisplacementField::new(RESOLUTION);
+        field.apply(
+            LiquifyMode::Push,
+            &dab(Vec2::new(16.0, 16.0), Vec2::new(2.0, 0.0)),
+        );
+
+        // the center shows what was behind it
+        let offset = field.offset(15, 15);
+        assert!(offset.x < -1.5 && offset.y.abs() < 1e-6, "{offset:?}");
+
+        // outside the radius nothing changes
+        assert_eq!(field.offset(0, 15), Vec2::ZERO);
+        assert_eq!(field.offset(15, 31), Vec2::ZERO);
+    }
+
+    #[test]
+    fn pinch_and_bloat_are_opposite() {
+        // on the center of the pixel row, so offsets along it are horizontal
+        let center = Vec2::new(16.5, 15.5);
+        let mut pinched = DisplacementField::new(RESOLUTION);
+        pinched.apply(LiquifyMode::Pinch, &dab(center, Vec2::ZERO));
+        let mut bloated = DisplacementField::new(RESOLUTION);
+        bloated.apply(LiquifyMode::Bloat, &dab(center, Vec2::ZERO));
+
+        // pinching samples further away from the center
+        let offset = pinched.offset(19, 15);
+        assert!(offset.x > 0.0 && offset.y.abs() < 1e-6, "{offset:?}");
+        assert!((offset + bloated.offset(19, 15)).length() < 1e-6);
+    }
+
+    #[test]
+    fn twirl_rotates_around_the_dab() {
+        let center = Vec2::new(16.0, 16.0);
+        let mut field = DisplacementField::new(RESOLUTION);
+        field.apply(
+            LiquifyMode::Twirl { clockwise: false },
+            &dab(center, Vec2::ZERO),
+        );
+
+        // the offsets are perpendicular to the direction from the center
+        let offset = field.offset(19, 15);
+        assert!(offset.x.abs() < 0.05 && offset.y > 0.1, "{offset:?}");
+        assert!((offset.length() - field.offset(15, 19).length()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reconstruct_undoes_displacement() {
+        let center = Vec2::new(16.0, 16.0);
+        let mut field = DisplacementField::new(RESOLUTION);
+        field.apply(LiquifyMode::Bloat, &dab(center, Vec2::ZERO));
+
+        let before = field.offset(19, 15).length();
+        field.apply(
+            LiquifyMode::Reconstruct,
+            &Dab {
+                strength: 0.5,
+                ..dab(center, Vec2::ZERO)
+            },
+        );
+        let after = field.offset(19, 15).length();
+        assert!(after < before && after > 0.0, "{before} {after}");
+
+        for _ in 0..100 {
+            field.apply(LiquifyMode::Reconstruct, &dab(center, Vec2::ZERO));
+        }
+        assert!(field.offset(19, 15).length() < 1e-6);
+    }
+
+    #[test]
+    fn resample_reads_through_the_field() {
+        let pixels: Vec<_> = (0..RESOLUTION.x * RESOLUTION.y)
+            .map(|i| WithAlpha {
+                color: LinearSrgb::new((i % RESOLUTION.x) as f32 / 32.0, 0.5, 0.0),
+                alpha: 1.0,
+            })
+            .collect();
+
+        let field = DisplacementField::new(RESOLUTION);
+        assert_eq!(field.resample(&pixels), pixels);
+
+        let mut field = DisplacementField::new(RESOLUTION);
+        field.apply(
+            LiquifyMode::Push,
+            &dab(Vec2::new(16.0, 16.0), Vec2::new(2.0, 0.0)),
+        );
+        let resampled = field.resample(&pixels);
+        let i = (15 * RESOLUTION.x + 15) as usize;
+        let expected = (15.0 + field.offset(15, 15).x) / 32.0;
+        assert!((resampled[i].color.r - expected).abs() < 1e-6);
+        assert!(resampled[i].color.r < pixels[i].color.r);
+    }
+}
@@ -0,0 +1,223 @@
+//! Photoshop color swatches (`.aco`), a binary format of 16-bit colors.
+//!
+//! Files start with a version 1 section without names, usually followed by a
+//! version 2 section with the same colors and their names.
+
+use super::binary::{Reader, write_utf16};
+use super::{Palette, PaletteError, Swatch, cmyk_to_linear_srgb};
+use crate::color::{Color, Lab, LinearSrgb, NonlinearSrgb};
+
+const RGB: u16 = 0;
+const HSB: u16 = 1;
+const CMYK: u16 = 2;
+const LAB: u16 = 7;
+const GRAYSCALE: u16 = 8;
+
+impl Palette {
+    /// Reads a Photoshop color swatches file.
+    ///
+    /// RGB, HSB, Lab and grayscale colors are converted exactly, CMYK colors
+    /// are approximated without a color profile. Version 1 files have unnamed
+    /// swatches, and neither version stores a palette name or groups.
+    pub fn from_aco(data: &[u8]) -> Result<Self, PaletteError> {
+        let mut reader = Reader::new(data);
+        let mut palette = Palette::default();
+
+        let mut version = reader.u16()?;
+        if version == 1 {
+            let count = reader.u16()?;
+            for _ in 0..count {
+                palette.add_swatch(Swatch::new("", read_color(&mut reader)?));
+            }
+
+            if reader.is_empty() {
+                return Ok(palette);
+            }
+
+            version = reader.u16()?;
+        }
+
+        if version != 2 {
+            return Err(PaletteError::UnsupportedVersion(version.into()));
+        }
+
+        // the named colors replace the version 1 ones
+        palette = Palette::default();
+
+        let count = reader.u16()?;
+        for _ in 0..count {
+            let color = read_color(&mut reader)?;
+            let len = reader.u32()?;
+            let name = reader.utf16(len as usize)?;
+            palette.add_swatch(Swatch::new(name, color));
+        }
+
+        Ok(palette)
+    }
+
+    /// Writes a Photoshop color swatches file with both versions, with colors
+    /// as 16-bit sRGB.
+    pub fn to_aco(&self) -> Vec<u8> {
+        let count = self.swatches.len().min(u16::MAX.into()) as u16;
+        let swatches = &self.swatches[..count.into()];
+
+        let mut out = Vec::new();
+
+        for version in [1u16, 2] {
+            out.extend(version.to_be_bytes());
+            out.extend(count.to_be_bytes());
+
+            for swatch in swatches {
+                let c = NonlinearSrgb::<u16>::from_linear_srgb(swatch.color.color);
+                for v in [RGB, c.r, c.g, c.b, 0] {
+                    out.extend(v.to_be_bytes());
+                }
+
+                if version == 2 {
+                    // reserve the length, which is only known after encoding
+                    let start = out.len();
+                    out.extend([0; 4]);
+                    let len = write_utf16(&mut out, &swatch.name) as u32;
+                    out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+                }
+            }
+        }
+
+        out
+    }
+}
+
+fn read_color(reader: &mut Reader) -> Result<LinearSrgb, PaletteError> {
+    let space = reader.u16()?;
+    let [w, x, y, z] = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+
+    let unit = |v: u16| f32::from(v) / f32::from(u16::MAX);
+
+    let color = match space {
+        RGB => NonlinearSrgb::new(unit(w), unit(x), unit(y)).to_linear_srgb(),
+        HSB => hsv_to_srgb(unit(w), unit(x), unit(y)).to_linear_srgb(),
+        // components are stored inverted, 0 being full ink
+        CMYK => cmyk_to_linear_srgb(1.0 - unit(w), 1.0 - unit(x), 1.0 - unit(y), 1.0 - unit(z)),
+        // lightness is scaled by 100, a and b are signed and scaled by 100
+        LAB => Lab::new(
+            f32::from(w) / 100.0,
+            f32::from(x as i16) / 100.0,
+            f32::from(y as i16) / 100.0,
+        )
+        .to_linear_srgb(),
+        // amount of black ink, from 0 to 10000
+        GRAYSCALE => {
+            let v = 1.0 - f32::from(w) / 10000.0;
+            NonlinearSrgb::new(v, v, v).to_linear_srgb()
+        }
+        _ => return Err(PaletteError::UnsupportedColorModel(space.to_string())),
+    };
+
+    Ok(color)
+}
+
+/// Converts HSV with all components between 0 and 1 to sRGB.
+fn hsv_to_srgb(h: f32, s: f32, v: f32) -> NonlinearSrgb {
+    let f = |n: f32| {
+        let k = (n + h * 6.0).rem_euclid(6.0);
+        v - v * s * k.min(4.0 - k).clamp(0.0, 1.0)
+    };
+    NonlinearSrgb::new(f(5.0), f(3.0), f(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../../fixtures/palettes/sample.aco");
+    const FIXTURE_V1: &[u8] = include_bytes!("../../fixtures/palettes/sample_v1.aco");
+
+    const EXPECTED: [(&str, NonlinearSrgb<u8>); 5] = [
+        ("Orange", NonlinearSrgb::new(255, 128, 0)),
+        ("Green", NonlinearSrgb::new(0, 255, 0)),
+        ("Red", NonlinearSrgb::new(255, 0, 0)),
+        ("Lab Gray", NonlinearSrgb::new(119, 119, 119)),
+        ("Light Gray", NonlinearSrgb::new(191, 191, 191)),
+    ];
+
+    fn srgb8(swatch: &Swatch) -> NonlinearSrgb<u8> {
+        NonlinearSrgb::from_linear_srgb(swatch.color.color)
+    }
+
+    #[test]
+    fn read_fixture() {
+        let palette = Palette::from_aco(FIXTURE).unwrap();
+        assert_eq!(palette.len(), EXPECTED.len());
+
+        for (swatch, (name, color)) in palette.swatches().iter().zip(EXPECTED) {
+            assert_eq!(swatch.name, name);
+            assert_eq!(srgb8(swatch), color, "{name}");
+        }
+    }
+
+    #[test]
+    fn read_version_1_fixture() {
+        let palette = Palette::from_aco(FIXTURE_V1).unwrap();
+        assert_eq!(palette.len(), EXPECTED.len());
+
+        for (swatch, (name, color)) in palette.swatches().iter().zip(EXPECTED) {
+            assert_eq!(swatch.name, "");
+            assert_eq!(srgb8(swatch), color, "{name}");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let palette = Palette::from_aco(FIXTURE).unwrap();
+        let written = palette.to_aco();
+        let read = Palette::from_aco(&written).unwrap();
+
+        for (a, b) in read.swatches().iter().zip(palette.swatches()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(srgb8(a), srgb8(b));
+        }
+
+        // the version 1 section alone is also readable
+        let v1_len = 4 + 10 * palette.len();
+        let v1 = Palette::from_aco(&written[..v1_len]).unwrap();
+        assert_eq!(v1.len(), palette.len());
+    }
+
+    #[test]
+    fn hsv() {
+        let to_u8 = |c: NonlinearSrgb| NonlinearSrgb::<u8>::from_linear_srgb(c.to_linear_srgb());
+        assert_eq!(
+            to_u8(hsv_to_srgb(0.0, 1.0, 1.0)),
+            NonlinearSrgb::new(255, 0, 0)
+        );
+        assert_eq!(
+            to_u8(hsv_to_srgb(0.5, 1.0, 0.5)),
+            NonlinearSrgb::new(0, 128, 128)
+        );
+        assert_eq!(
+            to_u8(hsv_to_srgb(0.75, 0.5, 1.0)),
+            NonlinearSrgb::new(191, 128, 255)
+        );
+        assert_eq!(
+            to_u8(hsv_to_srgb(0.3, 0.0, 0.2)),
+            NonlinearSrgb::new(51, 51, 51)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Palette::from_aco(&[]), Err(PaletteError::UnexpectedEnd));
+        assert_eq!(
+            Palette::from_aco(&[0, 3, 0, 0]),
+            Err(PaletteError::UnsupportedVersion(3))
+        );
+        assert_eq!(
+            Palette::from_aco(&FIXTURE_V1[..FIXTURE_V1.len() - 1]),
+            Err(PaletteError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Palette::from_aco(&[0, 1, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(PaletteError::UnsupportedColorModel("3".into()))
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Adobe Swatch Exchange (`.ase`), a binary format with named colors in
+//! various color models, optionally in groups.
+
+use super::binary::{Reader, write_utf16};
+use super::{Palette, PaletteError, Swatch, cmyk_to_linear_srgb};
+use crate::color::{Color, Lab, LinearSrgb, NonlinearSrgb, WithAlpha};
+
+const SIGNATURE: &[u8; 4] = b"ASEF";
+
+const GROUP_START: u16 = 0xc001;
+const GROUP_END: u16 = 0xc002;
+const COLOR_ENTRY: u16 = 0x0001;
+
+/// Color type of entries written, as opposed to global or spot colors.
+const NORMAL_COLOR: u16 = 2;
+
+impl Palette {
+    /// Reads an Adobe Swatch Exchange file.
+    ///
+    /// RGB, Lab and gray colors are converted exactly, CMYK colors are
+    /// approximated without a color profile. The format doesn't store a
+    /// palette name.
+    pub fn from_ase(data: &[u8]) -> Result<Self, PaletteError> {
+        let mut reader = Reader::new(data);
+
+        if &reader.array::<4>()? != SIGNATURE {
+            return Err(PaletteError::InvalidSignature);
+        }
+
+        let major = reader.u16()?;
+        let _minor = reader.u16()?;
+        if major != 1 {
+            return Err(PaletteError::UnsupportedVersion(major.into()));
+        }
+
+        let block_count = reader.u32()?;
+        let mut palette = Palette::default();
+        let mut group = None;
+
+        for _ in 0..block_count {
+            let block_type = reader.u16()?;
+            let len = reader.u32()? as usize;
+            let mut block = Reader::new(reader.bytes(len)?);
+
+            match block_type {
+                GROUP_START => {
+                    if group.is_some() {
+                        return Err(PaletteError::InvalidGroup);
+                    }
+                    let name = read_name(&mut block)?;
+                    group = Some(palette.add_group(name));
+                }
+                GROUP_END => {
+                    group.take().ok_or(PaletteError::InvalidGroup)?;
+                }
+                COLOR_ENTRY => {
+                    let name = read_name(&mut block)?;
+                    let color = read_color(&mut block)?;
+                    palette.add_swatch(Swatch {
+                        name,
+                        color: WithAlpha::opaque(color),
+                        group,
+                    });
+                }
+                // unknown blocks can be skipped thanks to their length
+                _ => {}
+            }
+        }
+
+        Ok(palette)
+    }
+
+    /// Writes an Adobe Swatch Exchange file, with colors as sRGB clamped to
+    /// its gamut.
+    pub fn to_ase(&self) -> Vec<u8> {
+        let mut blocks = Vec::new();
+        let mut block_count: u32 = 0;
+
+        let mut push_block = |block_type: u16, body: &[u8]| {
+            blocks.extend(block_type.to_be_bytes());
+            blocks.extend((body.len() as u32).to_be_bytes());
+            blocks.extend(body);
+            block_count += 1;
+        };
+
+        let mut group = None;
+        for swatch in &self.swatches {
+            if swatch.group != group {
+                if group.is_some() {
+                    push_block(GROUP_END, &[]);
+                }
+                if let Some(index) = swatch.group {
+                    let mut body = Vec::new();
+                    write_name(&mut body, &self.groups[index]);
+                    push_block(GROUP_START, &body);
+                }
+                group = swatch.group;
+            }
+
+            let c = NonlinearSrgb::<f32>::from_linear_srgb(swatch.color.to_linear_srgb_clamped());
+
+            let mut body = Vec::new();
+            write_name(&mut body, &swatch.name);
+            body.extend(b"RGB ");
+            for v in [c.r, c.g, c.b] {
+                body.extend(v.to_be_bytes());
+            }
+            body.extend(NORMAL_COLOR.to_be_bytes());
+            push_block(COLOR_ENTRY, &body);
+        }
+
+        if group.is_some() {
+            push_block(GROUP_END, &[]);
+        }
+
+        let mut out = Vec::with_capacity(12 + blocks.len());
+        out.extend(SIGNATURE);
+        out.extend(1u16.to_be_bytes());
+        out.extend(0u16.to_be_bytes());
+        out.extend(block_count.to_be_bytes());
+        out.extend(blocks);
+        out
+    }
+}
+
+fn read_name(reader: &mut Reader) -> Result<String, PaletteError> {
+    let len = reader.u16()?;
+    reader.utf16(len.into())
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    // reserve the length, which is only known after encoding
+    let start = out.len();
+    out.extend([0, 0]);
+    let len = write_utf16(out, name) as u16;
+    out[start..start + 2].copy_from_slice(&len.to_be_bytes());
+}
+
+fn read_color(reader: &mut Reader) -> Result<LinearSrgb, PaletteError> {
+    let model = reader.array::<4>()?;
+
+    let color = match &model {
+        b"RGB " => {
+            let [r, g, b] = [reader.f32()?, reader.f32()?, reader.f32()?];
+            NonlinearSrgb::new(r, g, b).to_linear_srgb()
+        }
+        b"CMYK" => {
+            let [c, m, y, k] = [reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?];
+            cmyk_to_linear_srgb(c, m, y, k)
+        }
+        // lightness is stored between 0 and 1
+        b"LAB " => {
+            let [l, a, b] = [reader.f32()?, reader.f32()?, reader.f32()?];
+            Lab::new(l * 100.0, a, b).to_linear_srgb()
+        }
+        b"Gray" => {
+            let v = reader.f32()?;
+            NonlinearSrgb::new(v, v, v).to_linear_srgb()
+        }
+        _ => {
+            let model = String::from_utf8_lossy(&model).trim_end().to_owned();
+            return Err(PaletteError::UnsupportedColorModel(model));
+        }
+    };
+
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../../fixtures/palettes/sample.ase");
+
+    fn srgb8(swatch: &Swatch) -> NonlinearSrgb<u8> {
+        NonlinearSrgb::from_linear_srgb(swatch.color.color)
+    }
+
+    #[test]
+    fn read_fixture() {
+        let palette = Palette::from_ase(FIXTURE).unwrap();
+        assert_eq!(palette.groups(), ["Brand"]);
+
+        let expected = [
+            ("Coral", NonlinearSrgb::new(255, 128, 64), Some(0)),
+            ("Ink", NonlinearSrgb::new(255, 0, 0), Some(0)),
+            ("Lab White", NonlinearSrgb::new(255, 255, 255), None),
+            ("Mid Gray", NonlinearSrgb::new(128, 128, 128), None),
+            ("Vermilion — 朱", NonlinearSrgb::new(227, 66, 52), None),
+        ];
+
+        assert_eq!(palette.len(), expected.len());
+        for (swatch, (name, color, group)) in palette.swatches().iter().zip(expected) {
+            assert_eq!(swatch.name, name);
+            assert_eq!(srgb8(swatch), color, "{name}");
+            assert_eq!(swatch.group, group, "{name}");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let palette = Palette::from_ase(FIXTURE).unwrap();
+        let read = Palette::from_ase(&palette.to_ase()).unwrap();
+
+        assert_eq!(read.groups(), palette.groups());
+        for (a, b) in read.swatches().iter().zip(palette.swatches()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.group, b.group);
+            assert_eq!(srgb8(a), srgb8(b));
+        }
+    }
+
+    #[test]
+    fn writes_consecutive_groups() {
+        let mut palette = Palette::default();
+        let warm = palette.add_group("Warm");
+        let cool = palette.add_group("Cool");
+        for (name, group) in [("a", Some(warm)), ("b", Some(cool)), ("c", None)] {
+            palette.add_swatch(Swatch {
+                group,
+                ..Swatch::new(name, Default::default())
+            });
+        }
+
+        let read = Palette::from_ase(&palette.to_ase()).unwrap();
+        assert_eq!(read, palette);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Palette::from_ase(b"8BPS"),
+            Err(PaletteError::InvalidSignature)
+        );
+        assert_eq!(Palette::from_ase(b"AS"), Err(PaletteError::UnexpectedEnd));
+        assert_eq!(
+            Palette::from_ase(b"ASEF\0\x02\0\0\0\0\0\0"),
+            Err(PaletteError::UnsupportedVersion(2))
+        );
+
+        // truncated in the middle of the last block
+        assert_eq!(
+            Palette::from_ase(&FIXTURE[..FIXTURE.len() - 3]),
+            Err(PaletteError::UnexpectedEnd)
+        );
+
+        // a group end without a start
+        let mut data = b"ASEF\0\x01\0\0\0\0\0\x01".to_vec();
+        data.extend(GROUP_END.to_be_bytes());
+        data.extend(0u32.to_be_bytes());
+        assert_eq!(Palette::from_ase(&data), Err(PaletteError::InvalidGroup));
+
+        // an unknown color model
+        let mut body = Vec::new();
+        write_name(&mut body, "x");
+        body.extend(b"HSV ");
+        let mut data = b"ASEF\0\x01\0\0\0\0\0\x01".to_vec();
+        data.extend(COLOR_ENTRY.to_be_bytes());
+        data.extend((body.len() as u32).to_be_bytes());
+        data.extend(body);
+        assert_eq!(
+            Palette::from_ase(&data),
+            Err(PaletteError::UnsupportedColorModel("HSV".into()))
+        );
+    }
+}
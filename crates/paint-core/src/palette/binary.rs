@@ -0,0 +1,69 @@
+//! Big-endian primitives shared by the binary palette formats.
+
+use super::PaletteError;
+
+/// Reads big-endian values from a byte slice.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], PaletteError> {
+        if self.data.len() < len {
+            return Err(PaletteError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], PaletteError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn u16(&mut self) -> Result<u16, PaletteError> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, PaletteError> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, PaletteError> {
+        self.array().map(f32::from_be_bytes)
+    }
+
+    /// Reads `len` UTF-16 code units, dropping the null terminator if present.
+    pub fn utf16(&mut self, len: usize) -> Result<String, PaletteError> {
+        let mut units = Vec::with_capacity(len);
+        for _ in 0..len {
+            units.push(self.u16()?);
+        }
+
+        if units.last() == Some(&0) {
+            units.pop();
+        }
+
+        String::from_utf16(&units).map_err(|_| PaletteError::InvalidName)
+    }
+}
+
+/// Appends a null-terminated UTF-16 string, returning its length in code
+/// units, including the terminator.
+pub fn write_utf16(out: &mut Vec<u8>, s: &str) -> usize {
+    let mut len = 0;
+    for unit in s.encode_utf16().chain([0]) {
+        out.extend(unit.to_be_bytes());
+        len += 1;
+    }
+    len
+}
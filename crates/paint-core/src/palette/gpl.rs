@@ -0,0 +1,161 @@
+//! GIMP palette (`.gpl`), a text format of 8-bit sRGB colors.
+
+use std::fmt::Write as _;
+
+use super::{Palette, PaletteError, Swatch};
+use crate::color::{Color, NonlinearSrgb};
+
+const SIGNATURE: &str = "GIMP Palette";
+
+impl Palette {
+    /// Reads a GIMP palette.
+    ///
+    /// Groups aren't part of the format, so all swatches are ungrouped.
+    pub fn from_gpl(text: &str) -> Result<Self, PaletteError> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, line)) if line.trim() == SIGNATURE => {}
+            _ => return Err(PaletteError::InvalidSignature),
+        }
+
+        let mut palette = Palette::default();
+
+        for (i, line) in lines {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix("Name:") {
+                palette.name = name.trim().to_owned();
+                continue;
+            }
+
+            let invalid = || PaletteError::InvalidLine(i + 1);
+
+            // the name is the rest of the line, and may contain spaces
+            let mut rest = line;
+            let mut rgb = [0u8; 3];
+            for c in &mut rgb {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                *c = rest[..end].parse().map_err(|_| invalid())?;
+                rest = rest[end..].trim_start();
+            }
+
+            let [r, g, b] = rgb;
+            let color = NonlinearSrgb::new(r, g, b).to_linear_srgb();
+            palette.add_swatch(Swatch::new(rest, color));
+        }
+
+        Ok(palette)
+    }
+
+    /// Writes a GIMP palette, with colors rounded to 8-bit sRGB.
+    ///
+    /// Groups are written as comments, which are lost when reading the file.
+    pub fn to_gpl(&self) -> String {
+        let single_line = |s: &str| s.replace(['\r', '\n'], " ");
+
+        let mut out = String::new();
+        writeln!(out, "{SIGNATURE}").unwrap();
+        writeln!(out, "Name: {}", single_line(&self.name)).unwrap();
+        writeln!(out, "Columns: 0").unwrap();
+        writeln!(out, "#").unwrap();
+
+        let mut group = None;
+        for swatch in &self.swatches {
+            if swatch.group != group {
+                group = swatch.group;
+                if let Some(group) = group {
+                    writeln!(out, "# {}", single_line(&self.groups[group])).unwrap();
+                }
+            }
+
+            let c = NonlinearSrgb::<u8>::from_linear_srgb(swatch.color.color);
+            writeln!(
+                out,
+                "{:3} {:3} {:3}\t{}",
+                c.r,
+                c.g,
+                c.b,
+                single_line(&swatch.name)
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../fixtures/palettes/sample.gpl");
+
+    fn srgb8(swatch: &Swatch) -> NonlinearSrgb<u8> {
+        NonlinearSrgb::from_linear_srgb(swatch.color.color)
+    }
+
+    #[test]
+    fn read_fixture() {
+        let palette = Palette::from_gpl(FIXTURE).unwrap();
+        assert_eq!(palette.name, "Sample Colors");
+        assert_eq!(palette.len(), 4);
+
+        let swatches = palette.swatches();
+        assert_eq!(swatches[0].name, "Red");
+        assert_eq!(srgb8(&swatches[0]), NonlinearSrgb::new(255, 0, 0));
+        assert_eq!(swatches[1].name, "Sky Blue");
+        assert_eq!(srgb8(&swatches[1]), NonlinearSrgb::new(135, 206, 235));
+        assert_eq!(swatches[2].name, "");
+        assert_eq!(srgb8(&swatches[2]), NonlinearSrgb::new(0, 128, 0));
+        assert_eq!(swatches[3].name, "Dark  Gray");
+        assert_eq!(srgb8(&swatches[3]), NonlinearSrgb::new(64, 64, 64));
+        assert!(swatches.iter().all(|s| s.color.alpha == 1.0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let palette = Palette::from_gpl(FIXTURE).unwrap();
+        let written = palette.to_gpl();
+        assert_eq!(Palette::from_gpl(&written).unwrap(), palette);
+    }
+
+    #[test]
+    fn groups_become_comments() {
+        let mut palette = Palette::new("Grouped");
+        let group = palette.add_group("Warm");
+        palette.add_swatch(Swatch {
+            group: Some(group),
+            ..Swatch::new("Red", NonlinearSrgb::<u8>::new(255, 0, 0).to_linear_srgb())
+        });
+
+        let written = palette.to_gpl();
+        assert!(written.contains("# Warm\n255   0   0\tRed\n"), "{written}");
+
+        let read = Palette::from_gpl(&written).unwrap();
+        assert!(read.groups().is_empty());
+        assert_eq!(read.swatches()[0].group, None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Palette::from_gpl("JASC-PAL\n0100\n"),
+            Err(PaletteError::InvalidSignature)
+        );
+        assert_eq!(Palette::from_gpl(""), Err(PaletteError::InvalidSignature));
+        assert_eq!(
+            Palette::from_gpl("GIMP Palette\nName: x\n255 0\n"),
+            Err(PaletteError::InvalidLine(3))
+        );
+        assert_eq!(
+            Palette::from_gpl("GIMP Palette\n256 0 0 Too bright\n"),
+            Err(PaletteError::InvalidLine(2))
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! Color palettes and their file formats.
+//!
+//! Supported formats are GIMP palettes (`.gpl`), Adobe Swatch Exchange
+//! (`.ase`) and Photoshop color swatches (`.aco`). None of them stores alpha,
+//! so imported colors are opaque and alpha is dropped on export.
+
+mod aco;
+mod ase;
+mod binary;
+mod gpl;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::color::{Color, DeltaE, LinearSrgb, NonlinearSrgb, Oklab, WithAlpha};
+
+/// Named color in a [`Palette`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swatch {
+    /// Name, which may be empty.
+    pub name: String,
+    /// The color.
+    pub color: WithAlpha<LinearSrgb>,
+    /// Index of the group in [`Palette::groups()`], if any.
+    pub group: Option<usize>,
+}
+
+impl Swatch {
+    /// Creates an opaque swatch outside of groups.
+    pub fn new(name: impl Into<String>, color: LinearSrgb) -> Self {
+        Self {
+            name: name.into(),
+            color: WithAlpha::opaque(color),
+            group: None,
+        }
+    }
+}
+
+/// List of named colors, optionally organized into groups, with nearest color
+/// search.
+///
+/// Colors are indexed by a k-d tree in Oklab, so [`nearest()`] stays fast for
+/// palettes of thousands of colors. The index is built on the first search
+/// after a change.
+///
+/// [`nearest()`]: Palette::nearest
+#[derive(Debug, Clone, Default)]
+pub struct Palette {
+    /// Name of the palette.
+    pub name: String,
+    groups: Vec<String>,
+    swatches: Vec<Swatch>,
+    /// Implicit k-d tree: the median of each range is the node, with the
+    /// lower half on the left and the upper half on the right. Levels split
+    /// the L, a and b axes in turn.
+    tree: OnceLock<Vec<Node>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    point: [f32; 3],
+    index: usize,
+}
+
+impl Palette {
+    /// Creates an empty palette.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates an unnamed palette of unnamed colors.
+    pub fn from_colors(colors: impl IntoIterator<Item = LinearSrgb>) -> Self {
+        let mut palette = Self::default();
+        for color in colors {
+            palette.add_swatch(Swatch::new("", color));
+        }
+        palette
+    }
+
+    /// Names of the groups.
+    pub fn groups(&self) -> &[String] {
+        &self.groups
+    }
+
+    /// Adds a group, returning its index.
+    pub fn add_group(&mut self, name: impl Into<String>) -> usize {
+        self.groups.push(name.into());
+        self.groups.len() - 1
+    }
+
+    /// Swatches in their original order.
+    pub fn swatches(&self) -> &[Swatch] {
+        &self.swatches
+    }
+
+    /// Adds a swatch, returning its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the swatch refers to a group which doesn't exist.
+    pub fn add_swatch(&mut self, swatch: Swatch) -> usize {
+        if let Some(group) = swatch.group {
+            assert!(group < self.groups.len(), "group {group} doesn't exist");
+        }
+
+        self.swatches.push(swatch);
+        self.tree = OnceLock::new();
+        self.swatches.len() - 1
+    }
+
+    /// Removes the swatch at the index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn remove_swatch(&mut self, index: usize) -> Swatch {
+        self.tree = OnceLock::new();
+        self.swatches.remove(index)
+    }
+
+    /// Number of swatches.
+    pub fn len(&self) -> usize {
+        self.swatches.len()
+    }
+
+    /// Whether the palette has no swatches.
+    pub fn is_empty(&self) -> bool {
+        self.swatches.is_empty()
+    }
+
+    /// Finds the index of the swatch closest to `color` by [`DeltaE::Ok`],
+    /// ignoring alpha.
+    ///
+    /// Returns `None` if the palette is empty.
+    pub fn nearest(&self, color: LinearSrgb) -> Option<usize> {
+        let tree = self.tree.get_or_init(|| build_tree(&self.swatches));
+        let lab = Oklab::from_linear_srgb(color);
+        let mut best = None;
+        search(tree, 0, [lab.l, lab.a, lab.b], &mut best);
+        best.map(|(_, index)| index)
+    }
+
+    /// Finds the index of the swatch closest to `color` by any formula,
+    /// ignoring alpha.
+    ///
+    /// Unlike [`nearest()`](Palette::nearest), this compares all colors, as
+    /// the CIE formulas aren't distances in any space that could be indexed.
+    pub fn nearest_by(&self, color: LinearSrgb, formula: DeltaE) -> Option<usize> {
+        self.swatches
+            .iter()
+            .map(|s| formula.difference(color, s.color.color))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
+impl PartialEq for Palette {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.groups == other.groups && self.swatches == other.swatches
+    }
+}
+
+/// Error returned when reading a palette file fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteError {
+    /// The file doesn't start with the signature of the format.
+    InvalidSignature,
+    /// Version of the format which isn't supported.
+    UnsupportedVersion(u32),
+    /// The file ends in the middle of a structure.
+    UnexpectedEnd,
+    /// Malformed line of a text format, counting from 1.
+    InvalidLine(usize),
+    /// Color model or color space which isn't supported.
+    UnsupportedColorModel(String),
+    /// Name which isn't valid UTF-16.
+    InvalidName,
+    /// Group which ends without starting, or starts inside another group.
+    InvalidGroup,
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::InvalidSignature => write!(f, "not a palette file of this format"),
+            PaletteError::UnsupportedVersion(v) => write!(f, "unsupported version {v}"),
+            PaletteError::UnexpectedEnd => write!(f, "unexpected end of file"),
+            PaletteError::InvalidLine(line) => write!(f, "invalid line {line}"),
+            PaletteError::UnsupportedColorModel(model) => {
+                write!(f, "unsupported color model {model:?}")
+            }
+            PaletteError::InvalidName => write!(f, "invalid UTF-16 in a name"),
+            PaletteError::InvalidGroup => write!(f, "mismatched group start and end"),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+/// Converts device CMYK to sRGB, ignoring any color profile.
+///
+/// Palette formats store CMYK for printing, which depends on the press and
+/// paper, so this is only an approximation.
+fn cmyk_to_linear_srgb(c: f32, m: f32, y: f32, k: f32) -> LinearSrgb {
+    let f = |x: f32| (1.0 - x) * (1.0 - k);
+    NonlinearSrgb::new(f(c), f(m), f(y)).to_linear_srgb()
+}
+
+fn build_tree(swatches: &[Swatch]) -> Vec<Node> {
+    let mut tree: Vec<Node> = swatches
+        .iter()
+        .enumerate()
+        .map(|(index, s)| {
+            let lab = Oklab::from_linear_srgb(s.color.color);
+            Node {
+                point: [lab.l, lab.a, lab.b],
+                index,
+            }
+        })
+        .collect();
+
+    build(&mut tree, 0);
+    tree
+}
+
+fn build(nodes: &mut [Node], depth: usize) {
+    if nodes.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 3;
+    let mid = nodes.len() / 2;
+    nodes.select_nth_unstable_by(mid, |a, b| compare(a, b, axis));
+
+    let (left, right) = nodes.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+fn compare(a: &Node, b: &Node, axis: usize) -> Ordering {
+    a.point[axis].total_cmp(&b.point[axis])
+}
+
+/// Updates `best` with the closest node as (squared distance, index).
+fn search(nodes: &[Node], depth: usize, target: [f32; 3], best: &mut Option<(f32, usize)>) {
+    if nodes.is_empty() {
+        return;
+    }
+
+    let mid = nodes.len() / 2;
+    let node = nodes[mid];
+
+    let distance: f32 = (0..3).map(|i| (node.point[i] - target[i]).powi(2)).sum();
+    // prefer earlier colors on ties, so duplicates resolve predictably
+    let is_better = match *best {
+        None => true,
+        Some((d, index)) => distance < d || (distance == d && node.index < index),
+    };
+    if is_better {
+        *best = Some((distance, node.index));
+    }
+
+    let axis = depth % 3;
+    let offset = target[axis] - node.point[axis];
+    let (near, far) = if offset < 0.0 {
+        (&nodes[..mid], &nodes[mid + 1..])
+    } else {
+        (&nodes[mid + 1..], &nodes[..mid])
+    };
+
+    search(near, depth + 1, target, best);
+
+    // the other side can only be closer than the splitting plane
+    if best.is_none_or(|(d, _)| offset * offset <= d) {
+        search(far, depth + 1, target, best);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random colors.
+    fn random_colors(count: usize, mut seed: u32) -> Vec<LinearSrgb> {
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        (0..count)
+            .map(|_| LinearSrgb::new(next(), next(), next()))
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        let palette = Palette::default();
+        assert!(palette.is_empty());
+        assert_eq!(palette.nearest(LinearSrgb::new(0.5, 0.5, 0.5)), None);
+        assert_eq!(
+            palette.nearest_by(LinearSrgb::new(0.5, 0.5, 0.5), DeltaE::Ciede2000),
+            None
+        );
+    }
+
+    #[test]
+    fn exact_match() {
+        let colors = random_colors(100, 1);
+        let palette = Palette::from_colors(colors.clone());
+
+        for (i, c) in colors.iter().enumerate() {
+            assert_eq!(palette.nearest(*c), Some(i));
+            assert_eq!(palette.nearest_by(*c, DeltaE::Ciede2000), Some(i));
+        }
+    }
+
+    #[test]
+    fn duplicates_resolve_to_first() {
+        let gray = LinearSrgb::new(0.5, 0.5, 0.5);
+        let palette = Palette::from_colors([LinearSrgb::new(1.0, 0.0, 0.0), gray, gray, gray]);
+        assert_eq!(palette.nearest(LinearSrgb::new(0.4, 0.4, 0.4)), Some(1));
+    }
+
+    #[test]
+    fn matches_linear_search() {
+        let palette = Palette::from_colors(random_colors(5000, 2));
+
+        for target in random_colors(500, 3) {
+            let nearest = palette.nearest(target).unwrap();
+            let expected = palette.nearest_by(target, DeltaE::Ok).unwrap();
+
+            let distance =
+                |i: usize| DeltaE::Ok.difference(target, palette.swatches()[i].color.color);
+            assert_eq!(distance(nearest), distance(expected), "{target:?}");
+        }
+    }
+
+    #[test]
+    fn basic_colors() {
+        let palette = Palette::from_colors([
+            LinearSrgb::new(0.0, 0.0, 0.0),
+            LinearSrgb::new(1.0, 1.0, 1.0),
+            LinearSrgb::new(1.0, 0.0, 0.0),
+            LinearSrgb::new(0.0, 1.0, 0.0),
+            LinearSrgb::new(0.0, 0.0, 1.0),
+        ]);
+
+        let cases = [
+            (LinearSrgb::new(0.01, 0.01, 0.01), 0),
+            (LinearSrgb::new(0.9, 0.8, 0.9), 1),
+            (LinearSrgb::new(0.6, 0.1, 0.05), 2),
+            (LinearSrgb::new(0.1, 0.4, 0.1), 3),
+            (LinearSrgb::new(0.1, 0.1, 0.7), 4),
+        ];
+
+        for (color, expected) in cases {
+            assert_eq!(palette.nearest(color), Some(expected), "{color:?}");
+        }
+    }
+
+    #[test]
+    fn index_follows_changes() {
+        let mut palette = Palette::from_colors([LinearSrgb::new(0.0, 0.0, 0.0)]);
+        let white = LinearSrgb::new(1.0, 1.0, 1.0);
+        assert_eq!(palette.nearest(white), Some(0));
+
+        palette.add_swatch(Swatch::new("White", white));
+        assert_eq!(palette.nearest(white), Some(1));
+
+        palette.remove_swatch(0);
+        assert_eq!(palette.nearest(LinearSrgb::new(0.0, 0.0, 0.0)), Some(0));
+    }
+
+    #[test]
+    fn cmyk() {
+        let to_u8 = |c: LinearSrgb| NonlinearSrgb::<u8>::from_linear_srgb(c);
+        assert_eq!(
+            to_u8(cmyk_to_linear_srgb(0.0, 0.0, 0.0, 0.0)),
+            NonlinearSrgb::new(255, 255, 255)
+        );
+        assert_eq!(
+            to_u8(cmyk_to_linear_srgb(0.0, 1.0, 1.0, 0.0)),
+            NonlinearSrgb::new(255, 0, 0)
+        );
+        assert_eq!(
+            to_u8(cmyk_to_linear_srgb(0.0, 0.0, 0.0, 0.5)),
+            NonlinearSrgb::new(128, 128, 128)
+        );
+    }
+}
@@ -0,0 +1,625 @@
+//! Projects and their layers, as stored on disk and exchanged with other
+//! painting software.
+//!
+//! Supported exchange formats are OpenRaster (`.ora`).
+
+mod openraster;
+
+use std::borrow::Cow;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use glam::{IVec2, UVec2};
+use half::f16;
+
+use crate::adjustment::AdjustmentLayer;
+use crate::color::{BlendingSpace, Color, Component, LinearSrgb, NonlinearSrgb, WithAlpha};
+use crate::geometry::Rect;
+use crate::text::TextLayer;
+
+#[derive(Debug, Clone)]
+pub struct ProjectMetadata {
+    pub creation_time: DateTime<Utc>,
+    pub modify_time: DateTime<Utc>,
+    pub resolution: UVec2,
+    /// Format of the canvas and its layers.
+    pub format: TextureFormat,
+    /// Color space in which layers and brush strokes are blended.
+    pub blending_space: BlendingSpace,
+    /// Adjustment layers above the canvas, from bottom to top.
+    pub adjustment_layers: Vec<AdjustmentLayer>,
+    /// Text layers above the adjustment layers, from bottom to top.
+    pub text_layers: Vec<TextLayer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Texture<'a> {
+    pub resolution: UVec2,
+    pub format: TextureFormat,
+    pub data: Cow<'a, [u8]>,
+    /// Distance between the starts of consecutive rows, in bytes.
+    pub row_stride: usize,
+}
+
+impl Texture<'_> {
+    /// Creates a texture from pixels in row-major order.
+    ///
+    /// Panics if the number of pixels doesn't match the resolution.
+    pub fn from_pixels(
+        resolution: UVec2,
+        format: TextureFormat,
+        pixels: &[WithAlpha<LinearSrgb>],
+    ) -> Texture<'static> {
+        assert_eq!(pixels.len(), (resolution.x * resolution.y) as usize);
+
+        let mut data = Vec::with_capacity(pixels.len() * format.bytes_per_pixel());
+        for c in pixels {
+            match format {
+                TextureFormat::Rgba8NonlinearSrgb => {
+                    let NonlinearSrgb { r, g, b } = NonlinearSrgb::<u8>::from_linear_srgb(c.color);
+                    data.extend([r, g, b, u8::from_f32(c.alpha)]);
+                }
+                TextureFormat::Rgba16LinearSrgb => {
+                    let LinearSrgb { r, g, b } = LinearSrgb::<u16>::from_linear_srgb(c.color);
+                    for v in [r, g, b, u16::from_f32(c.alpha)] {
+                        data.extend(v.to_le_bytes());
+                    }
+                }
+                TextureFormat::Rgba16FloatLinearSrgb => {
+                    let LinearSrgb { r, g, b } = LinearSrgb::<f16>::from_linear_srgb(c.color);
+                    for v in [r, g, b, f16::from_f32(c.alpha)] {
+                        data.extend(v.to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        Texture {
+            resolution,
+            format,
+            data: Cow::Owned(data),
+            row_stride: format.bytes_per_pixel() * resolution.x as usize,
+        }
+    }
+
+    /// Pixels in row-major order.
+    pub fn to_pixels(&self) -> Vec<WithAlpha<LinearSrgb>> {
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let row_len = bytes_per_pixel * self.resolution.x as usize;
+
+        (0..self.resolution.y as usize)
+            .flat_map(|y| self.data[y * self.row_stride..][..row_len].chunks_exact(bytes_per_pixel))
+            .map(|p| match self.format {
+                TextureFormat::Rgba8NonlinearSrgb => WithAlpha::new(
+                    NonlinearSrgb::new(p[0], p[1], p[2]).to_linear_srgb(),
+                    p[3].as_f32(),
+                ),
+                TextureFormat::Rgba16LinearSrgb => {
+                    let v = |i: usize| u16::from_le_bytes([p[2 * i], p[2 * i + 1]]);
+                    WithAlpha::new(
+                        LinearSrgb::new(v(0), v(1), v(2)).to_linear_srgb(),
+                        v(3).as_f32(),
+                    )
+                }
+                TextureFormat::Rgba16FloatLinearSrgb => {
+                    let v = |i: usize| f16::from_le_bytes([p[2 * i], p[2 * i + 1]]);
+                    WithAlpha::new(
+                        LinearSrgb::new(v(0), v(1), v(2)).to_linear_srgb(),
+                        v(3).as_f32(),
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
+/// Pixel format of a texture. All formats use straight alpha.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum TextureFormat {
+    /// 8-bit sRGB-encoded color, 8-bit linear alpha.
+    #[default]
+    Rgba8NonlinearSrgb,
+    /// 16-bit normalized linear sRGB color and alpha.
+    Rgba16LinearSrgb,
+    /// 16-bit floating point linear sRGB color and alpha.
+    Rgba16FloatLinearSrgb,
+}
+
+impl TextureFormat {
+    /// All texture formats.
+    pub const ALL: [TextureFormat; 3] = [
+        TextureFormat::Rgba8NonlinearSrgb,
+        TextureFormat::Rgba16LinearSrgb,
+        TextureFormat::Rgba16FloatLinearSrgb,
+    ];
+
+    /// Size of a single pixel in bytes.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            TextureFormat::Rgba8NonlinearSrgb => 4,
+            TextureFormat::Rgba16LinearSrgb | TextureFormat::Rgba16FloatLinearSrgb => 8,
+        }
+    }
+
+    /// Whether color components are stored in linear light.
+    pub fn is_linear(self) -> bool {
+        match self {
+            TextureFormat::Rgba8NonlinearSrgb => false,
+            TextureFormat::Rgba16LinearSrgb | TextureFormat::Rgba16FloatLinearSrgb => true,
+        }
+    }
+}
+
+/// Raster layers of a project, as exchanged with other painting software.
+#[derive(Debug, Clone)]
+pub struct LayerStack {
+    pub metadata: ProjectMetadata,
+    /// Layers from bottom to top.
+    pub layers: Vec<RasterLayer>,
+}
+
+/// A layer of pixels in a [`LayerStack`].
+#[derive(Debug, Clone)]
+pub struct RasterLayer {
+    /// Name, which may be empty.
+    pub name: String,
+    /// The pixels, which don't have to cover the whole canvas.
+    pub texture: Texture<'static>,
+    /// Canvas pixel of the top left corner, which may be outside the canvas.
+    pub offset: IVec2,
+    /// Multiplies the alpha of the layer, between 0 and 1.
+    pub opacity: f32,
+    /// Hidden layers aren't merged.
+    pub visible: bool,
+    pub blend_mode: BlendMode,
+}
+
+impl RasterLayer {
+    /// Creates a visible, fully opaque layer at the top left corner of the
+    /// canvas.
+    pub fn new(name: impl Into<String>, texture: Texture<'static>) -> Self {
+        Self {
+            name: name.into(),
+            texture,
+            offset: IVec2::ZERO,
+            opacity: 1.0,
+            visible: true,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+}
+
+/// How the colors of a layer combine with the colors below it, as defined by
+/// the W3C compositing specification.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+}
+
+impl BlendMode {
+    /// All blend modes.
+    pub const ALL: [BlendMode; 11] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::HardLight,
+        BlendMode::SoftLight,
+        BlendMode::Difference,
+    ];
+
+    /// Composites `src` over `dst` with this blend mode.
+    ///
+    /// Normal layers are blended in `space`, like brush strokes. The other
+    /// modes are defined on sRGB-encoded components, which is what other
+    /// painting software does as well.
+    pub fn blend(
+        self,
+        space: BlendingSpace,
+        dst: WithAlpha<LinearSrgb>,
+        src: WithAlpha<LinearSrgb>,
+    ) -> WithAlpha<LinearSrgb> {
+        if self == BlendMode::Normal {
+            return space.source_over(dst, src);
+        }
+
+        let backdrop = NonlinearSrgb::<f32>::from_linear_srgb(dst.color);
+        let source = NonlinearSrgb::<f32>::from_linear_srgb(src.color);
+        // where the backdrop is transparent, the source shows as is
+        let dst_alpha = dst.alpha.clamp(0.0, 1.0);
+        let mix = |b: f32, s: f32| {
+            let s = s.clamp(0.0, 1.0);
+            s + (self.blend_component(b.clamp(0.0, 1.0), s) - s) * dst_alpha
+        };
+
+        let color = NonlinearSrgb::new(
+            mix(backdrop.r, source.r),
+            mix(backdrop.g, source.g),
+            mix(backdrop.b, source.b),
+        );
+        BlendingSpace::GammaSrgb.source_over(dst, WithAlpha::new(color.to_linear_srgb(), src.alpha))
+    }
+
+    /// The blend function of a single component of the backdrop `b` and the
+    /// source `s`.
+    fn blend_component(self, b: f32, s: f32) -> f32 {
+        let multiply = |b: f32, s: f32| b * s;
+        let screen = |b: f32, s: f32| b + s - b * s;
+        let hard_light = |b: f32, s: f32| {
+            if s <= 0.5 {
+                multiply(b, 2.0 * s)
+            } else {
+                screen(b, 2.0 * s - 1.0)
+            }
+        };
+
+        match self {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => multiply(b, s),
+            BlendMode::Screen => screen(b, s),
+            BlendMode::Overlay => hard_light(s, b),
+            BlendMode::Darken => b.min(s),
+            BlendMode::Lighten => b.max(s),
+            BlendMode::ColorDodge if b <= 0.0 => 0.0,
+            BlendMode::ColorDodge if s >= 1.0 => 1.0,
+            BlendMode::ColorDodge => (b / (1.0 - s)).min(1.0),
+            BlendMode::ColorBurn if b >= 1.0 => 1.0,
+            BlendMode::ColorBurn if s <= 0.0 => 0.0,
+            BlendMode::ColorBurn => 1.0 - ((1.0 - b) / s).min(1.0),
+            BlendMode::HardLight => hard_light(b, s),
+            BlendMode::SoftLight if s <= 0.5 => b - (1.0 - 2.0 * s) * b * (1.0 - b),
+            BlendMode::SoftLight => {
+                let d = if b <= 0.25 {
+                    ((16.0 * b - 12.0) * b + 4.0) * b
+                } else {
+                    b.sqrt()
+                };
+                b + (2.0 * s - 1.0) * (d - b)
+            }
+            BlendMode::Difference => (b - s).abs(),
+        }
+    }
+}
+
+impl LayerStack {
+    /// Flattens the visible layers onto a transparent canvas, applies the
+    /// adjustment layers and draws the text layers on top, in the format of
+    /// the project.
+    pub fn merge(&self) -> Texture<'static> {
+        Texture::from_pixels(
+            self.metadata.resolution,
+            self.metadata.format,
+            &self.merged_pixels(),
+        )
+    }
+
+    fn merged_pixels(&self) -> Vec<WithAlpha<LinearSrgb>> {
+        let resolution = self.metadata.resolution;
+        let mut merged = vec![
+            WithAlpha::transparent(LinearSrgb::default());
+            resolution.x as usize * resolution.y as usize
+        ];
+
+        for layer in &self.layers {
+            if !layer.visible || layer.opacity <= 0.0 {
+                continue;
+            }
+
+            let layer_resolution = layer.texture.resolution;
+            let rect = Rect::from_min_size(layer.offset.as_vec2(), layer_resolution.as_vec2());
+            let Some((min, size)) = rect.to_pixels(resolution) else {
+                continue;
+            };
+
+            let pixels = layer.texture.to_pixels();
+            let opacity = layer.opacity.min(1.0);
+            for y in min.y..min.y + size.y {
+                for x in min.x..min.x + size.x {
+                    let p = (UVec2::new(x, y).as_ivec2() - layer.offset).as_uvec2();
+                    let src = pixels[p.y as usize * layer_resolution.x as usize + p.x as usize];
+                    let dst = &mut merged[y as usize * resolution.x as usize + x as usize];
+                    *dst = layer.blend_mode.blend(
+                        self.metadata.blending_space,
+                        *dst,
+                        WithAlpha::new(src.color, src.alpha * opacity),
+                    );
+                }
+            }
+        }
+
+        for adjustment in &self.metadata.adjustment_layers {
+            if adjustment.is_active() {
+                for pixel in &mut merged {
+                    pixel.color = adjustment.apply(pixel.color);
+                }
+            }
+        }
+
+        for text in self
+            .metadata
+            .text_layers
+            .iter()
+            .filter_map(|text| text.rasterize(resolution))
+        {
+            let Some((min, size)) = text.rect().to_pixels(resolution) else {
+                continue;
+            };
+
+            let pixels = text.as_persistence().to_pixels();
+            for y in min.y..min.y + size.y {
+                for x in min.x..min.x + size.x {
+                    let p = (UVec2::new(x, y).as_ivec2() - text.origin).as_uvec2();
+                    let src = pixels[(p.y * text.resolution.x + p.x) as usize];
+                    let dst = &mut merged[(y * resolution.x + x) as usize];
+                    *dst = self.metadata.blending_space.source_over(*dst, src);
+                }
+            }
+        }
+
+        merged
+    }
+}
+
+/// Error reading an OpenRaster image.
+#[derive(Debug)]
+pub enum OpenRasterError {
+    /// Not a zip archive, or a damaged one.
+    Archive(zip::result::ZipError),
+    /// The archive doesn't declare the OpenRaster MIME type.
+    InvalidSignature,
+    /// A file which the image refers to isn't in the archive.
+    MissingFile(String),
+    /// `stack.xml` isn't well-formed XML.
+    Xml(quick_xml::Error),
+    /// `stack.xml` is well-formed, but doesn't describe an image.
+    InvalidStack(String),
+    /// A layer which isn't a valid PNG image.
+    InvalidPng {
+        path: String,
+        error: png::DecodingError,
+    },
+}
+
+impl fmt::Display for OpenRasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenRasterError::Archive(e) => write!(f, "invalid archive: {e}"),
+            OpenRasterError::InvalidSignature => write!(f, "not an OpenRaster image"),
+            OpenRasterError::MissingFile(path) => write!(f, "missing file {path:?}"),
+            OpenRasterError::Xml(e) => write!(f, "invalid stack.xml: {e}"),
+            OpenRasterError::InvalidStack(reason) => write!(f, "invalid stack.xml: {reason}"),
+            OpenRasterError::InvalidPng { path, error } => {
+                write!(f, "invalid PNG {path:?}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpenRasterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpenRasterError::Archive(e) => Some(e),
+            OpenRasterError::Xml(e) => Some(e),
+            OpenRasterError::InvalidPng { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use glam::Vec2;
+
+    use super::*;
+    use crate::adjustment::Adjustment;
+    use crate::text::{Font, TextAlignment};
+
+    const RED: NonlinearSrgb<u8> = NonlinearSrgb::new(255, 0, 0);
+    const GREY: NonlinearSrgb<u8> = NonlinearSrgb::new(128, 128, 128);
+
+    fn solid(resolution: UVec2, color: NonlinearSrgb<u8>, alpha: u8) -> Texture<'static> {
+        let data =
+            [color.r, color.g, color.b, alpha].repeat((resolution.x * resolution.y) as usize);
+        Texture {
+            resolution,
+            format: TextureFormat::Rgba8NonlinearSrgb,
+            data: Cow::Owned(data),
+            row_stride: 4 * resolution.x as usize,
+        }
+    }
+
+    fn stack(layers: Vec<RasterLayer>) -> LayerStack {
+        LayerStack {
+            metadata: ProjectMetadata {
+                creation_time: Utc::now(),
+                modify_time: Utc::now(),
+                resolution: UVec2::new(4, 2),
+                format: TextureFormat::Rgba8NonlinearSrgb,
+                blending_space: BlendingSpace::LinearLight,
+                adjustment_layers: Vec::new(),
+                text_layers: Vec::new(),
+            },
+            layers,
+        }
+    }
+
+    fn rgba(texture: &Texture<'_>, x: u32, y: u32) -> [u8; 4] {
+        let i = y as usize * texture.row_stride + 4 * x as usize;
+        texture.data[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn pixels_round_trip_in_every_format() {
+        let pixels = [
+            WithAlpha::new(LinearSrgb::new(1.0, 0.5, 0.0), 1.0),
+            WithAlpha::new(LinearSrgb::new(0.25, 0.75, 0.125), 0.5),
+        ];
+        for format in [
+            TextureFormat::Rgba8NonlinearSrgb,
+            TextureFormat::Rgba16LinearSrgb,
+            TextureFormat::Rgba16FloatLinearSrgb,
+        ] {
+            let texture = Texture::from_pixels(UVec2::new(2, 1), format, &pixels);
+            assert_eq!(texture.data.len(), 2 * format.bytes_per_pixel());
+
+            for (actual, expected) in texture.to_pixels().iter().zip(&pixels) {
+                let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+                assert!(
+                    close(actual.color.r, expected.color.r)
+                        && close(actual.color.g, expected.color.g)
+                        && close(actual.color.b, expected.color.b)
+                        && close(actual.alpha, expected.alpha),
+                    "{format:?}: {actual:?}, expected {expected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn layers_are_merged_where_they_cover_the_canvas() {
+        let mut red = RasterLayer::new("red", solid(UVec2::new(2, 2), RED, 255));
+        red.offset = IVec2::new(-1, 1);
+        let mut hidden = RasterLayer::new("hidden", solid(UVec2::new(4, 2), GREY, 255));
+        hidden.visible = false;
+
+        let merged = stack(vec![red, hidden]).merge();
+        assert_eq!(merged.resolution, UVec2::new(4, 2));
+
+        // only the right column of the red layer is on the canvas
+        assert_eq!(rgba(&merged, 0, 1), [255, 0, 0, 255]);
+        for (x, y) in [(0, 0), (1, 0), (1, 1), (3, 1)] {
+            assert_eq!(rgba(&merged, x, y), [0, 0, 0, 0], "{x} {y}");
+        }
+    }
+
+    #[test]
+    fn normal_layers_blend_in_the_blending_space() {
+        let mut black = RasterLayer::new(
+            "",
+            solid(UVec2::new(4, 2), NonlinearSrgb::new(0, 0, 0), 255),
+        );
+        black.opacity = 0.5;
+        let white = RasterLayer::new(
+            "",
+            solid(UVec2::new(4, 2), NonlinearSrgb::new(255, 255, 255), 255),
+        );
+
+        let mut stack = stack(vec![white, black]);
+        for space in BlendingSpace::ALL {
+            stack.metadata.blending_space = space;
+            let expected = space.source_over(
+                WithAlpha::opaque(LinearSrgb::new(1.0, 1.0, 1.0)),
+                WithAlpha::new(LinearSrgb::new(0.0, 0.0, 0.0), 0.5),
+            );
+            let expected = NonlinearSrgb::<u8>::from_linear_srgb(expected.color);
+            assert_eq!(
+                rgba(&stack.merge(), 2, 1),
+                [expected.r, expected.g, expected.b, 255],
+                "{space:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn blend_modes_use_encoded_components() {
+        let backdrop = WithAlpha::opaque(GREY.to_linear_srgb());
+        let blend = |mode: BlendMode, source: NonlinearSrgb<u8>| {
+            let result = mode.blend(
+                BlendingSpace::LinearLight,
+                backdrop,
+                WithAlpha::opaque(source.to_linear_srgb()),
+            );
+            NonlinearSrgb::<u8>::from_linear_srgb(result.color)
+        };
+
+        let white = NonlinearSrgb::new(255, 255, 255);
+        let black = NonlinearSrgb::new(0, 0, 0);
+        assert_eq!(blend(BlendMode::Multiply, white), GREY);
+        assert_eq!(
+            blend(BlendMode::Multiply, GREY),
+            NonlinearSrgb::new(64, 64, 64)
+        );
+        assert_eq!(blend(BlendMode::Screen, black), GREY);
+        assert_eq!(
+            blend(BlendMode::Screen, GREY),
+            NonlinearSrgb::new(192, 192, 192)
+        );
+        assert_eq!(blend(BlendMode::Darken, RED), NonlinearSrgb::new(128, 0, 0));
+        assert_eq!(
+            blend(BlendMode::Lighten, RED),
+            NonlinearSrgb::new(255, 128, 128)
+        );
+        assert_eq!(
+            blend(BlendMode::Difference, white),
+            NonlinearSrgb::new(127, 127, 127)
+        );
+        assert_eq!(blend(BlendMode::Difference, GREY), black);
+
+        // blend modes don't change the alpha compositing
+        for mode in BlendMode::ALL {
+            let result = mode.blend(
+                BlendingSpace::LinearLight,
+                WithAlpha::new(LinearSrgb::new(0.5, 0.5, 0.5), 0.5),
+                WithAlpha::new(LinearSrgb::new(1.0, 0.0, 0.0), 0.5),
+            );
+            assert!((result.alpha - 0.75).abs() < 1e-6, "{mode:?}");
+        }
+
+        // over nothing, the source shows as is
+        for mode in BlendMode::ALL {
+            let result = mode.blend(
+                BlendingSpace::LinearLight,
+                WithAlpha::transparent(LinearSrgb::default()),
+                WithAlpha::opaque(RED.to_linear_srgb()),
+            );
+            assert_eq!(
+                NonlinearSrgb::<u8>::from_linear_srgb(result.color),
+                RED,
+                "{mode:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn adjustment_layers_apply_to_the_merged_image() {
+        let layer = RasterLayer::new("", solid(UVec2::new(4, 2), RED, 128));
+        let mut stack = stack(vec![layer]);
+        stack.metadata.adjustment_layers = vec![AdjustmentLayer::new(Adjustment::Invert)];
+
+        assert_eq!(rgba(&stack.merge(), 0, 0), [0, 255, 255, 128]);
+    }
+
+    #[test]
+    fn text_layers_are_drawn_above_the_adjustment_layers() {
+        let layer = RasterLayer::new("", solid(UVec2::new(4, 2), RED, 255));
+        let mut stack = stack(vec![layer]);
+        stack.metadata.adjustment_layers = vec![AdjustmentLayer::new(Adjustment::Invert)];
+        // a full block, covering the whole canvas
+        stack.metadata.text_layers = vec![TextLayer {
+            text: "\u{2588}".to_owned(),
+            font: Font::Sans,
+            size: 40.0,
+            color: NonlinearSrgb::new(10, 20, 30),
+            alignment: TextAlignment::Left,
+            position: Vec2::new(-4.0, -8.0),
+        }];
+
+        assert_eq!(rgba(&stack.merge(), 1, 1), [10, 20, 30, 255]);
+    }
+}
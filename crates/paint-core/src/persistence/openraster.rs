@@ -0,0 +1,1285 @@
+//! OpenRaster (`.ora`), the layered image format of Krita, MyPaint and GIMP.
+//!
+//! An image is a zip archive of PNG layers, arranged by `stack.xml`, with a
+//! merged image and a thumbnail for viewers which don't composite layers.
+//! PNGs are sRGB-encoded: layers in linear formats are written with 16 bits
+//! per component, so they don't lose precision in the shadows.
+//!
+//! What OpenRaster can't express, such as the blending space, adjustment
+//! layers and text layers, is stored in `stack.xml` under the [`NAMESPACE`] of this app,
+//! which other software ignores.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{self, Cursor, Read as _, Write as _};
+
+use chrono::Utc;
+use glam::{IVec2, UVec2, Vec2, Vec3};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::{Namespace, NamespaceResolver, ResolveResult};
+use zip::result::{ZipError, ZipResult};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{
+    BlendMode, LayerStack, OpenRasterError, ProjectMetadata, RasterLayer, Texture, TextureFormat,
+};
+use crate::adjustment::{Adjustment, AdjustmentLayer, Curve, Curves, Levels};
+use crate::color::{BlendingSpace, Color, Component, LinearSrgb, NonlinearSrgb, WithAlpha};
+use crate::text::{Font, TextAlignment, TextLayer};
+
+const MIMETYPE: &str = "image/openraster";
+const STACK: &str = "stack.xml";
+const MERGED_IMAGE: &str = "mergedimage.png";
+const THUMBNAIL: &str = "Thumbnail/thumbnail.png";
+/// Maximum width and height of the thumbnail.
+const THUMBNAIL_SIZE: u32 = 256;
+/// Maximum width and height of an image which is read, as the merged image
+/// is allocated at its full size.
+const MAX_SIZE: u32 = 16384;
+/// XML namespace of the extensions to `stack.xml`.
+const NAMESPACE: &str = "https://nyaalex.site/paint/openraster";
+/// Prefix which [`NAMESPACE`] is bound to when writing.
+const PREFIX: &str = "paint";
+
+impl LayerStack {
+    /// Reads an OpenRaster image.
+    ///
+    /// Layers are 8-bit sRGB, unless any of them has 16 bits per component,
+    /// in which case all of them are converted to 16-bit linear. Nested stacks
+    /// are flattened, combining their offsets, opacity and visibility with
+    /// those of their layers, but their blend modes are lost. Unknown blend
+    /// modes read as [`BlendMode::Normal`].
+    ///
+    /// Images from other software are blended in gamma-encoded sRGB, like
+    /// that software does.
+    pub fn from_ora(data: &[u8]) -> Result<Self, OpenRasterError> {
+        let mut archive = ZipArchive::new(Cursor::new(data)).map_err(OpenRasterError::Archive)?;
+
+        match read_file(&mut archive, "mimetype") {
+            Ok(mimetype) if mimetype.trim_ascii() == MIMETYPE.as_bytes() => {}
+            Ok(_) | Err(OpenRasterError::MissingFile(_)) => {
+                return Err(OpenRasterError::InvalidSignature);
+            }
+            Err(e) => return Err(e),
+        }
+
+        let stack = read_file(&mut archive, STACK)?;
+        let stack = std::str::from_utf8(&stack)
+            .map_err(|_| OpenRasterError::InvalidStack("not UTF-8".to_owned()))?;
+        let ParsedStack {
+            resolution,
+            blending_space,
+            adjustment_layers,
+            text_layers,
+            layers: entries,
+        } = parse_stack(stack)?;
+
+        let images = entries
+            .iter()
+            .map(|entry| decode_png(&read_file(&mut archive, &entry.src)?, &entry.src))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let format = if images.iter().any(|image| image.sixteen_bit) {
+            TextureFormat::Rgba16LinearSrgb
+        } else {
+            TextureFormat::Rgba8NonlinearSrgb
+        };
+
+        // the stack lists the topmost layer first
+        let layers = entries
+            .into_iter()
+            .zip(images)
+            .rev()
+            .map(|(entry, image)| RasterLayer {
+                name: entry.name,
+                texture: image.to_texture(format),
+                offset: entry.offset,
+                opacity: entry.opacity,
+                visible: entry.visible,
+                blend_mode: entry.blend_mode,
+            })
+            .collect();
+
+        let now = Utc::now();
+        Ok(Self {
+            metadata: ProjectMetadata {
+                creation_time: now,
+                modify_time: now,
+                resolution,
+                format,
+                blending_space,
+                adjustment_layers,
+                text_layers,
+            },
+            layers,
+        })
+    }
+
+    /// Writes an OpenRaster image.
+    ///
+    /// Other software doesn't know the adjustment and text layers, so they
+    /// are also applied to the merged image and the thumbnail.
+    pub fn to_ora(&self) -> Vec<u8> {
+        self.write_ora().expect("writing to memory should not fail")
+    }
+
+    fn write_ora(&self) -> ZipResult<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        // first and uncompressed, so that the format can be recognized from
+        // the start of the file
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(MIMETYPE.as_bytes())?;
+
+        zip.start_file(STACK, deflated)?;
+        zip.write_all(self.stack_xml().as_bytes())?;
+
+        // PNGs are compressed already
+        for (i, layer) in self.layers.iter().enumerate() {
+            zip.start_file(layer_path(i), stored)?;
+            zip.write_all(&encode_png(&layer.texture))?;
+        }
+
+        let merged = self.merged_pixels();
+        let resolution = self.metadata.resolution;
+        let merged_image = Texture::from_pixels(resolution, self.metadata.format, &merged);
+        zip.start_file(MERGED_IMAGE, stored)?;
+        zip.write_all(&encode_png(&merged_image))?;
+
+        zip.start_file(THUMBNAIL, stored)?;
+        zip.write_all(&encode_png(&thumbnail(resolution, &merged)))?;
+
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn stack_xml(&self) -> String {
+        let UVec2 { x: w, y: h } = self.metadata.resolution;
+
+        let mut xml = String::new();
+        writeln!(xml, "<?xml version='1.0' encoding='UTF-8'?>").unwrap();
+        writeln!(
+            xml,
+            r#"<image version="0.0.5" w="{w}" h="{h}" xmlns:{PREFIX}="{NAMESPACE}" {PREFIX}:blending-space="{}">"#,
+            blending_space_name(self.metadata.blending_space),
+        )
+        .unwrap();
+        // topmost first, like the stack
+        if !self.metadata.text_layers.is_empty() {
+            writeln!(xml, "  <{PREFIX}:text-layers>").unwrap();
+            for layer in self.metadata.text_layers.iter().rev() {
+                let NonlinearSrgb { r, g, b } = layer.color;
+                writeln!(
+                    xml,
+                    r##"    <{PREFIX}:text text="{}" font="{}" size="{}" color="#{r:02x}{g:02x}{b:02x}" alignment="{}" x="{}" y="{}"/>"##,
+                    escape_text(&layer.text),
+                    font_name(layer.font),
+                    layer.size,
+                    alignment_name(layer.alignment),
+                    layer.position.x,
+                    layer.position.y,
+                )
+                .unwrap();
+            }
+            writeln!(xml, "  </{PREFIX}:text-layers>").unwrap();
+        }
+        if !self.metadata.adjustment_layers.is_empty() {
+            writeln!(xml, "  <{PREFIX}:adjustments>").unwrap();
+            for layer in self.metadata.adjustment_layers.iter().rev() {
+                let (name, parameters) = adjustment_element(&layer.adjustment);
+                writeln!(
+                    xml,
+                    r#"    <{PREFIX}:{name} opacity="{}" visibility="{}"{parameters}/>"#,
+                    layer.opacity.clamp(0.0, 1.0),
+                    if layer.visible { "visible" } else { "hidden" },
+                )
+                .unwrap();
+            }
+            writeln!(xml, "  </{PREFIX}:adjustments>").unwrap();
+        }
+        writeln!(xml, "  <stack>").unwrap();
+        for (i, layer) in self.layers.iter().enumerate().rev() {
+            writeln!(
+                xml,
+                r#"    <layer name="{}" src="{}" x="{}" y="{}" opacity="{}" visibility="{}" composite-op="{}"/>"#,
+                escape(layer.name.as_str()),
+                layer_path(i),
+                layer.offset.x,
+                layer.offset.y,
+                layer.opacity.clamp(0.0, 1.0),
+                if layer.visible { "visible" } else { "hidden" },
+                composite_op(layer.blend_mode),
+            )
+            .unwrap();
+        }
+        writeln!(xml, "  </stack>").unwrap();
+        writeln!(xml, "</image>").unwrap();
+        xml
+    }
+}
+
+fn layer_path(index: usize) -> String {
+    format!("data/layer{index}.png")
+}
+
+fn composite_op(mode: BlendMode) -> &'static str {
+    match mode {
+        BlendMode::Normal => "svg:src-over",
+        BlendMode::Multiply => "svg:multiply",
+        BlendMode::Screen => "svg:screen",
+        BlendMode::Overlay => "svg:overlay",
+        BlendMode::Darken => "svg:darken",
+        BlendMode::Lighten => "svg:lighten",
+        BlendMode::ColorDodge => "svg:color-dodge",
+        BlendMode::ColorBurn => "svg:color-burn",
+        BlendMode::HardLight => "svg:hard-light",
+        BlendMode::SoftLight => "svg:soft-light",
+        BlendMode::Difference => "svg:difference",
+    }
+}
+
+fn blending_space_name(space: BlendingSpace) -> &'static str {
+    match space {
+        BlendingSpace::LinearLight => "linear-light",
+        BlendingSpace::GammaSrgb => "gamma-srgb",
+        BlendingSpace::Oklab => "oklab",
+    }
+}
+
+/// Name and parameter attributes of the element of an adjustment layer.
+fn adjustment_element(adjustment: &Adjustment) -> (&'static str, String) {
+    let points = |curve: &Curve| {
+        let points: Vec<_> = curve
+            .points()
+            .iter()
+            .map(|p| format!("{},{}", p.x, p.y))
+            .collect();
+        points.join(" ")
+    };
+    let vec3 = |v: Vec3| format!("{} {} {}", v.x, v.y, v.z);
+
+    match adjustment {
+        Adjustment::Levels(levels) => (
+            "levels",
+            format!(
+                r#" input-black="{}" input-white="{}" gamma="{}" output-black="{}" output-white="{}""#,
+                levels.input_black,
+                levels.input_white,
+                levels.gamma,
+                levels.output_black,
+                levels.output_white,
+            ),
+        ),
+        Adjustment::Curves(curves) => (
+            "curves",
+            format!(
+                r#" red="{}" green="{}" blue="{}" lightness="{}""#,
+                points(&curves.red),
+                points(&curves.green),
+                points(&curves.blue),
+                points(&curves.lightness),
+            ),
+        ),
+        Adjustment::HueChromaLightness {
+            hue,
+            chroma,
+            lightness,
+        } => (
+            "hue-chroma-lightness",
+            format!(r#" hue="{hue}" chroma="{chroma}" lightness="{lightness}""#),
+        ),
+        Adjustment::ColorBalance {
+            shadows,
+            midtones,
+            highlights,
+        } => (
+            "color-balance",
+            format!(
+                r#" shadows="{}" midtones="{}" highlights="{}""#,
+                vec3(*shadows),
+                vec3(*midtones),
+                vec3(*highlights),
+            ),
+        ),
+        Adjustment::Invert => ("invert", String::new()),
+    }
+}
+
+fn font_name(font: Font) -> &'static str {
+    match font {
+        Font::Sans => "sans",
+        Font::Serif => "serif",
+        Font::Mono => "mono",
+    }
+}
+
+fn alignment_name(alignment: TextAlignment) -> &'static str {
+    match alignment {
+        TextAlignment::Left => "left",
+        TextAlignment::Center => "center",
+        TextAlignment::Right => "right",
+    }
+}
+
+/// Escapes text for an attribute value, including the whitespace which
+/// attribute value normalization would turn into spaces.
+fn escape_text(text: &str) -> String {
+    escape(text)
+        .replace('\t', "&#9;")
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+}
+
+fn read_file(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    path: &str,
+) -> Result<Vec<u8>, OpenRasterError> {
+    let mut file = match archive.by_name(path) {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Err(OpenRasterError::MissingFile(path.to_owned())),
+        Err(e) => return Err(OpenRasterError::Archive(e)),
+    };
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)
+        .map_err(|e| OpenRasterError::Archive(e.into()))?;
+    Ok(data)
+}
+
+/// A `<layer>` of `stack.xml`, combined with the stacks it's nested in.
+struct LayerEntry {
+    name: String,
+    src: String,
+    offset: IVec2,
+    opacity: f32,
+    visible: bool,
+    blend_mode: BlendMode,
+}
+
+/// Offset, opacity and visibility of a `<stack>`, combined with the stacks
+/// it's nested in.
+#[derive(Clone, Copy)]
+struct StackContext {
+    offset: IVec2,
+    opacity: f32,
+    visible: bool,
+}
+
+/// Contents of `stack.xml`.
+struct ParsedStack {
+    resolution: UVec2,
+    blending_space: BlendingSpace,
+    /// Adjustment layers from bottom to top.
+    adjustment_layers: Vec<AdjustmentLayer>,
+    /// Text layers from bottom to top.
+    text_layers: Vec<TextLayer>,
+    /// Layers from top to bottom.
+    layers: Vec<LayerEntry>,
+}
+
+fn parse_stack(xml: &str) -> Result<ParsedStack, OpenRasterError> {
+    let mut reader = quick_xml::NsReader::from_str(xml);
+    let mut resolution = None;
+    // how other painting software blends normal layers
+    let mut blending_space = BlendingSpace::GammaSrgb;
+    let mut stacks = vec![StackContext {
+        offset: IVec2::ZERO,
+        opacity: 1.0,
+        visible: true,
+    }];
+    let mut adjustment_layers = Vec::new();
+    let mut text_layers = Vec::new();
+    let mut layers = Vec::new();
+
+    loop {
+        let (element, empty) = match reader.read_event().map_err(OpenRasterError::Xml)? {
+            Event::Start(element) => (element, false),
+            Event::Empty(element) => (element, true),
+            Event::End(element) if element.local_name().as_ref() == "stack" => {
+                // the outermost stack is never popped, so a stray end tag
+                // can't remove it
+                if stacks.len() > 1 {
+                    stacks.pop();
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        let (namespace, name) = reader.resolver().resolve_element(element.name());
+        if namespace == ResolveResult::Bound(Namespace(NAMESPACE)) {
+            if name.as_ref() == "text" {
+                text_layers.push(parse_text_layer(&element)?);
+            } else if let Some(adjustment) = parse_adjustment(&element, name.as_ref())? {
+                let visibility: Option<String> = attribute(&element, "visibility")?;
+                adjustment_layers.push(AdjustmentLayer {
+                    adjustment,
+                    opacity: attribute::<f32>(&element, "opacity")?
+                        .unwrap_or(1.0)
+                        .clamp(0.0, 1.0),
+                    visible: visibility.as_deref() != Some("hidden"),
+                });
+            }
+            continue;
+        }
+
+        match name.as_ref() {
+            "image" => {
+                let w = attribute(&element, "w")?;
+                let h = attribute(&element, "h")?;
+                resolution = w.zip(h).map(|(w, h)| UVec2::new(w, h));
+                if let Some(size) = resolution
+                    && (size.cmpeq(UVec2::ZERO).any() || size.cmpgt(UVec2::splat(MAX_SIZE)).any())
+                {
+                    return Err(OpenRasterError::InvalidStack(format!(
+                        "invalid image size {}x{}",
+                        size.x, size.y
+                    )));
+                }
+
+                let space =
+                    extension_attribute::<String>(reader.resolver(), &element, "blending-space")?;
+                if let Some(space) = space {
+                    blending_space = BlendingSpace::ALL
+                        .into_iter()
+                        .find(|&s| blending_space_name(s) == space)
+                        .ok_or_else(|| {
+                            OpenRasterError::InvalidStack(format!(
+                                "unknown blending space {space:?}"
+                            ))
+                        })?;
+                }
+            }
+            "stack" if !empty => {
+                let parent = stacks[stacks.len() - 1];
+                stacks.push(nested_context(parent, &element)?);
+            }
+            "layer" => {
+                let parent = stacks[stacks.len() - 1];
+                let context = nested_context(parent, &element)?;
+                let src = attribute::<String>(&element, "src")?
+                    .ok_or_else(|| OpenRasterError::InvalidStack("layer without src".to_owned()))?;
+                let blend_mode = attribute::<String>(&element, "composite-op")?
+                    .and_then(|op| {
+                        BlendMode::ALL
+                            .into_iter()
+                            .find(|&mode| composite_op(mode) == op)
+                    })
+                    .unwrap_or_default();
+
+                layers.push(LayerEntry {
+                    name: attribute(&element, "name")?.unwrap_or_default(),
+                    src,
+                    offset: context.offset,
+                    opacity: context.opacity,
+                    visible: context.visible,
+                    blend_mode,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let resolution =
+        resolution.ok_or_else(|| OpenRasterError::InvalidStack("missing image size".to_owned()))?;
+    // topmost first, like the stack
+    adjustment_layers.reverse();
+    text_layers.reverse();
+
+    Ok(ParsedStack {
+        resolution,
+        blending_space,
+        adjustment_layers,
+        text_layers,
+        layers,
+    })
+}
+
+/// Reads a `text` element in [`NAMESPACE`].
+fn parse_text_layer(element: &BytesStart<'_>) -> Result<TextLayer, OpenRasterError> {
+    let invalid = |name: &str, value: &str| {
+        OpenRasterError::InvalidStack(format!("unknown {name} {value:?}"))
+    };
+
+    let font = match attribute::<String>(element, "font")?.as_deref() {
+        None => Font::default(),
+        Some(name) => Font::ALL
+            .into_iter()
+            .find(|&font| font_name(font) == name)
+            .ok_or_else(|| invalid("font", name))?,
+    };
+    let alignment = match attribute::<String>(element, "alignment")?.as_deref() {
+        None | Some("left") => TextAlignment::Left,
+        Some("center") => TextAlignment::Center,
+        Some("right") => TextAlignment::Right,
+        Some(name) => return Err(invalid("alignment", name)),
+    };
+    let color = attribute::<HexColor>(element, "color")?
+        .map(|HexColor(color)| color)
+        .unwrap_or_default();
+
+    Ok(TextLayer {
+        text: raw_attribute(element, "text")?.unwrap_or_default(),
+        font,
+        size: attribute(element, "size")?.unwrap_or(0.0),
+        color,
+        alignment,
+        position: Vec2::new(
+            attribute(element, "x")?.unwrap_or(0.0),
+            attribute(element, "y")?.unwrap_or(0.0),
+        ),
+    })
+}
+
+/// Reads an element in [`NAMESPACE`] as an adjustment, if it is one.
+///
+/// Missing parameters don't change anything.
+fn parse_adjustment(
+    element: &BytesStart<'_>,
+    name: &str,
+) -> Result<Option<Adjustment>, OpenRasterError> {
+    let curve = |name| {
+        Ok::<_, OpenRasterError>(
+            attribute::<Points>(element, name)?
+                .map(|Points(points)| Curve::new(points))
+                .unwrap_or_default(),
+        )
+    };
+    let vec3 = |name| {
+        Ok::<_, OpenRasterError>(
+            attribute::<Components>(element, name)?
+                .map(|Components(v)| v)
+                .unwrap_or(Vec3::ZERO),
+        )
+    };
+
+    let adjustment = match name {
+        "levels" => {
+            let default = Levels::default();
+            Adjustment::Levels(Levels {
+                input_black: attribute(element, "input-black")?.unwrap_or(default.input_black),
+                input_white: attribute(element, "input-white")?.unwrap_or(default.input_white),
+                gamma: attribute(element, "gamma")?.unwrap_or(default.gamma),
+                output_black: attribute(element, "output-black")?.unwrap_or(default.output_black),
+                output_white: attribute(element, "output-white")?.unwrap_or(default.output_white),
+            })
+        }
+        "curves" => Adjustment::Curves(Curves {
+            red: curve("red")?,
+            green: curve("green")?,
+            blue: curve("blue")?,
+            lightness: curve("lightness")?,
+        }),
+        "hue-chroma-lightness" => Adjustment::HueChromaLightness {
+            hue: attribute(element, "hue")?.unwrap_or(0.0),
+            chroma: attribute(element, "chroma")?.unwrap_or(1.0),
+            lightness: attribute(element, "lightness")?.unwrap_or(0.0),
+        },
+        "color-balance" => Adjustment::ColorBalance {
+            shadows: vec3("shadows")?,
+            midtones: vec3("midtones")?,
+            highlights: vec3("highlights")?,
+        },
+        "invert" => Adjustment::Invert,
+        _ => return Ok(None),
+    };
+    Ok(Some(adjustment))
+}
+
+/// Points of a curve, as `x,y` pairs separated by whitespace.
+struct Points(Vec<Vec2>);
+
+impl std::str::FromStr for Points {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        s.split_whitespace()
+            .map(|point| {
+                let (x, y) = point.split_once(',').ok_or(())?;
+                Ok(Vec2::new(
+                    x.parse().map_err(|_| ())?,
+                    y.parse().map_err(|_| ())?,
+                ))
+            })
+            .collect::<Result<_, _>>()
+            .map(Points)
+    }
+}
+
+/// An sRGB color as `#rrggbb`.
+struct HexColor(NonlinearSrgb<u8>);
+
+impl std::str::FromStr for HexColor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let hex = s.strip_prefix('#').filter(|hex| hex.len() == 6).ok_or(())?;
+        let component =
+            |i: usize| u8::from_str_radix(hex.get(i..i + 2).ok_or(())?, 16).map_err(|_| ());
+        Ok(HexColor(NonlinearSrgb::new(
+            component(0)?,
+            component(2)?,
+            component(4)?,
+        )))
+    }
+}
+
+/// RGB components, separated by whitespace.
+struct Components(Vec3);
+
+impl std::str::FromStr for Components {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let components = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|_| ())?;
+        match components[..] {
+            [r, g, b] => Ok(Components(Vec3::new(r, g, b))),
+            _ => Err(()),
+        }
+    }
+}
+
+fn nested_context(
+    parent: StackContext,
+    element: &BytesStart<'_>,
+) -> Result<StackContext, OpenRasterError> {
+    // some software writes fractional offsets
+    let x: f32 = attribute(element, "x")?.unwrap_or(0.0);
+    let y: f32 = attribute(element, "y")?.unwrap_or(0.0);
+    let opacity: f32 = attribute(element, "opacity")?.unwrap_or(1.0);
+    let visibility: Option<String> = attribute(element, "visibility")?;
+
+    let add = |parent: i32, offset: f32| {
+        // `as` would saturate, silently moving the layer
+        (offset.abs() < i32::MAX as f32)
+            .then(|| parent.checked_add(offset.round() as i32))
+            .flatten()
+    };
+    let offset = add(parent.offset.x, x)
+        .zip(add(parent.offset.y, y))
+        .map(|(x, y)| IVec2::new(x, y))
+        .ok_or_else(|| OpenRasterError::InvalidStack(format!("invalid offset {x}, {y}")))?;
+
+    Ok(StackContext {
+        offset,
+        opacity: parent.opacity * opacity.clamp(0.0, 1.0),
+        visible: parent.visible && visibility.as_deref() != Some("hidden"),
+    })
+}
+
+/// Parses an attribute, if it's present.
+fn attribute<T: std::str::FromStr>(
+    element: &BytesStart<'_>,
+    name: &str,
+) -> Result<Option<T>, OpenRasterError> {
+    let Some(value) = raw_attribute(element, name)? else {
+        return Ok(None);
+    };
+
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| OpenRasterError::InvalidStack(format!("invalid value {value:?} of {name}")))
+}
+
+/// Reads an attribute without trimming it, if it's present.
+fn raw_attribute(element: &BytesStart<'_>, name: &str) -> Result<Option<String>, OpenRasterError> {
+    let Some(attribute) = element
+        .try_get_attribute(name)
+        .map_err(|e| OpenRasterError::Xml(e.into()))?
+    else {
+        return Ok(None);
+    };
+
+    let value = attribute
+        .normalized_value(quick_xml::XmlVersion::Implicit1_0)
+        .map_err(OpenRasterError::Xml)?;
+    Ok(Some(value.into_owned()))
+}
+
+/// Parses an attribute in [`NAMESPACE`], if it's present.
+fn extension_attribute<T: std::str::FromStr>(
+    resolver: &NamespaceResolver,
+    element: &BytesStart<'_>,
+    name: &str,
+) -> Result<Option<T>, OpenRasterError> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|e| OpenRasterError::Xml(e.into()))?;
+        let (namespace, local_name) = resolver.resolve_attribute(attribute.key);
+        if namespace != ResolveResult::Bound(Namespace(NAMESPACE)) || local_name.as_ref() != name {
+            continue;
+        }
+
+        let value = attribute
+            .normalized_value(quick_xml::XmlVersion::Implicit1_0)
+            .map_err(OpenRasterError::Xml)?;
+        return value.trim().parse().map(Some).map_err(|_| {
+            OpenRasterError::InvalidStack(format!("invalid value {value:?} of {name}"))
+        });
+    }
+
+    Ok(None)
+}
+
+/// A decoded PNG, with sRGB-encoded RGBA components.
+struct PngImage {
+    resolution: UVec2,
+    sixteen_bit: bool,
+    /// Components, scaled to 16 bits if `sixteen_bit`, 8 bits otherwise.
+    rgba: Vec<u16>,
+}
+
+impl PngImage {
+    fn to_texture(&self, format: TextureFormat) -> Texture<'static> {
+        if format == TextureFormat::Rgba8NonlinearSrgb && !self.sixteen_bit {
+            return Texture {
+                resolution: self.resolution,
+                format,
+                data: Cow::Owned(self.rgba.iter().map(|&v| v as u8).collect()),
+                row_stride: 4 * self.resolution.x as usize,
+            };
+        }
+
+        let max = if self.sixteen_bit {
+            u16::MAX
+        } else {
+            u8::MAX.into()
+        };
+        let v = |v: u16| v as f32 / max as f32;
+        let pixels: Vec<_> = self
+            .rgba
+            .chunks_exact(4)
+            .map(|p| {
+                WithAlpha::new(
+                    NonlinearSrgb::new(v(p[0]), v(p[1]), v(p[2])).to_linear_srgb(),
+                    v(p[3]),
+                )
+            })
+            .collect();
+        Texture::from_pixels(self.resolution, format, &pixels)
+    }
+}
+
+fn decode_png(data: &[u8], path: &str) -> Result<PngImage, OpenRasterError> {
+    let invalid = |error| OpenRasterError::InvalidPng {
+        path: path.to_owned(),
+        error,
+    };
+
+    // layers of large canvases exceed the default limit
+    let mut decoder =
+        png::Decoder::new_with_limits(Cursor::new(data), png::Limits { bytes: usize::MAX });
+    // palettes and fewer than 8 bits per component expand to 8 bits
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let size = reader.output_buffer_size().ok_or_else(|| {
+        invalid(png::DecodingError::IoError(
+            io::ErrorKind::OutOfMemory.into(),
+        ))
+    })?;
+    let mut buffer = vec![0; size];
+    let info = reader.next_frame(&mut buffer).map_err(invalid)?;
+
+    let sixteen_bit = info.bit_depth == png::BitDepth::Sixteen;
+    let components: Vec<u16> = if sixteen_bit {
+        buffer
+            .chunks_exact(2)
+            .map(|v| u16::from_be_bytes([v[0], v[1]]))
+            .collect()
+    } else {
+        buffer.iter().map(|&v| v.into()).collect()
+    };
+    let opaque = if sixteen_bit {
+        u16::MAX
+    } else {
+        u8::MAX.into()
+    };
+
+    let channels = info.color_type.samples();
+    let row_len = channels * info.width as usize;
+    let row_stride = info.line_size / if sixteen_bit { 2 } else { 1 };
+    let mut rgba = Vec::with_capacity(4 * (info.width * info.height) as usize);
+    for row in components.chunks(row_stride).take(info.height as usize) {
+        for p in row[..row_len].chunks_exact(channels) {
+            rgba.extend(match *p {
+                [v] => [v, v, v, opaque],
+                [v, a] => [v, v, v, a],
+                [r, g, b] => [r, g, b, opaque],
+                [r, g, b, a] => [r, g, b, a],
+                _ => unreachable!("PNGs have 1 to 4 channels"),
+            });
+        }
+    }
+
+    Ok(PngImage {
+        resolution: UVec2::new(info.width, info.height),
+        sixteen_bit,
+        rgba,
+    })
+}
+
+/// Encodes 8-bit sRGB textures as 8-bit PNGs and linear ones as 16-bit PNGs.
+fn encode_png(texture: &Texture<'_>) -> Vec<u8> {
+    // PNGs can't be empty
+    let empty;
+    let texture = if texture.resolution.cmpeq(UVec2::ZERO).any() {
+        empty = Texture::from_pixels(
+            UVec2::ONE,
+            texture.format,
+            &[WithAlpha::transparent(LinearSrgb::default())],
+        );
+        &empty
+    } else {
+        texture
+    };
+
+    let UVec2 { x: w, y: h } = texture.resolution;
+    let (depth, data) = if texture.format == TextureFormat::Rgba8NonlinearSrgb {
+        let row_len = 4 * w as usize;
+        let data: Vec<u8> = (0..h as usize)
+            .flat_map(|y| &texture.data[y * texture.row_stride..][..row_len])
+            .copied()
+            .collect();
+        (png::BitDepth::Eight, data)
+    } else {
+        let data = texture
+            .to_pixels()
+            .iter()
+            .flat_map(|c| {
+                let NonlinearSrgb { r, g, b } = NonlinearSrgb::<u16>::from_linear_srgb(c.color);
+                [r, g, b, u16::from_f32(c.alpha)]
+            })
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        (png::BitDepth::Sixteen, data)
+    };
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, w, h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(depth);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header().expect("the header should be valid");
+    writer
+        .write_image_data(&data)
+        .expect("the data should match the header");
+    writer.finish().expect("writing to memory should not fail");
+    png
+}
+
+/// Downscales the merged image to fit [`THUMBNAIL_SIZE`], averaging the
+/// covered pixels.
+fn thumbnail(resolution: UVec2, merged: &[WithAlpha<LinearSrgb>]) -> Texture<'static> {
+    let scale = (THUMBNAIL_SIZE as f32 / resolution.max_element() as f32).min(1.0);
+    let size = (resolution.as_vec2() * scale)
+        .round()
+        .as_uvec2()
+        .max(UVec2::ONE)
+        .min(resolution.max(UVec2::ONE));
+
+    let mut pixels = Vec::with_capacity((size.x * size.y) as usize);
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let min = UVec2::new(x, y) * resolution / size;
+            let max = ((UVec2::new(x, y) + 1) * resolution / size).max(min + 1);
+
+            // premultiplied, so that transparent colors don't bleed in
+            let (mut sum, mut alpha, mut count) = ([0.0; 3], 0.0, 0.0);
+            for sy in min.y..max.y.min(resolution.y) {
+                for sx in min.x..max.x.min(resolution.x) {
+                    let c = merged[sy as usize * resolution.x as usize + sx as usize];
+                    sum[0] += c.color.r * c.alpha;
+                    sum[1] += c.color.g * c.alpha;
+                    sum[2] += c.color.b * c.alpha;
+                    alpha += c.alpha;
+                    count += 1.0;
+                }
+            }
+
+            pixels.push(if alpha > 0.0 {
+                WithAlpha::new(
+                    LinearSrgb::new(sum[0] / alpha, sum[1] / alpha, sum[2] / alpha),
+                    alpha / count,
+                )
+            } else {
+                WithAlpha::transparent(LinearSrgb::default())
+            });
+        }
+    }
+
+    Texture::from_pixels(size, TextureFormat::Rgba8NonlinearSrgb, &pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(resolution: UVec2, seed: u8) -> Texture<'static> {
+        let data = (0..4 * resolution.x * resolution.y)
+            .map(|i| (i as u8).wrapping_mul(37).wrapping_add(seed))
+            .collect();
+        Texture {
+            resolution,
+            format: TextureFormat::Rgba8NonlinearSrgb,
+            data: Cow::Owned(data),
+            row_stride: 4 * resolution.x as usize,
+        }
+    }
+
+    fn stack(resolution: UVec2, layers: Vec<RasterLayer>) -> LayerStack {
+        LayerStack {
+            metadata: ProjectMetadata {
+                creation_time: Utc::now(),
+                modify_time: Utc::now(),
+                resolution,
+                format: TextureFormat::Rgba8NonlinearSrgb,
+                blending_space: BlendingSpace::GammaSrgb,
+                adjustment_layers: Vec::new(),
+                text_layers: Vec::new(),
+            },
+            layers,
+        }
+    }
+
+    /// Builds an archive from files, in order.
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, data) in files {
+            zip.start_file(*path, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn layers_round_trip() {
+        let mut background = RasterLayer::new("Background", texture(UVec2::new(6, 4), 0));
+        background.opacity = 0.5;
+        let mut sketch = RasterLayer::new(r#"<Sketch> & "ink""#, texture(UVec2::new(3, 2), 100));
+        sketch.offset = IVec2::new(-1, 2);
+        sketch.visible = false;
+        sketch.blend_mode = BlendMode::Multiply;
+
+        let original = stack(UVec2::new(6, 4), vec![background, sketch]);
+        let read = LayerStack::from_ora(&original.to_ora()).unwrap();
+
+        assert_eq!(read.metadata.resolution, UVec2::new(6, 4));
+        assert_eq!(read.metadata.format, TextureFormat::Rgba8NonlinearSrgb);
+        assert_eq!(read.layers.len(), 2);
+        for (read, original) in read.layers.iter().zip(&original.layers) {
+            assert_eq!(read.name, original.name);
+            assert_eq!(read.offset, original.offset);
+            assert_eq!(read.opacity, original.opacity);
+            assert_eq!(read.visible, original.visible);
+            assert_eq!(read.blend_mode, original.blend_mode);
+            assert_eq!(read.texture.resolution, original.texture.resolution);
+            assert_eq!(read.texture.data, original.texture.data);
+        }
+    }
+
+    #[test]
+    fn linear_layers_round_trip_through_16_bits() {
+        let pixels: Vec<_> = (0..16)
+            .map(|i| {
+                let v = i as f32 / 15.0;
+                WithAlpha::new(LinearSrgb::new(v, v * v, 1.0 - v), v)
+            })
+            .collect();
+        let texture =
+            Texture::from_pixels(UVec2::new(4, 4), TextureFormat::Rgba16LinearSrgb, &pixels);
+        let mut original = stack(UVec2::new(4, 4), vec![RasterLayer::new("", texture)]);
+        original.metadata.format = TextureFormat::Rgba16LinearSrgb;
+
+        let read = LayerStack::from_ora(&original.to_ora()).unwrap();
+        assert_eq!(read.metadata.format, TextureFormat::Rgba16LinearSrgb);
+
+        let texture = &read.layers[0].texture;
+        assert_eq!(texture.format, TextureFormat::Rgba16LinearSrgb);
+        for (actual, expected) in texture.to_pixels().iter().zip(&pixels) {
+            let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+            assert!(
+                close(actual.color.r, expected.color.r)
+                    && close(actual.color.g, expected.color.g)
+                    && close(actual.color.b, expected.color.b)
+                    && close(actual.alpha, expected.alpha),
+                "{actual:?}, expected {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn blending_space_round_trips() {
+        for space in BlendingSpace::ALL {
+            let mut original = stack(UVec2::ONE, Vec::new());
+            original.metadata.blending_space = space;
+
+            let read = LayerStack::from_ora(&original.to_ora()).unwrap();
+            assert_eq!(read.metadata.blending_space, space);
+        }
+
+        // the prefix is up to the writer
+        let stack_xml = format!(
+            r#"<image w="1" h="1" xmlns:p="{NAMESPACE}" p:blending-space="oklab"><stack/></image>"#
+        );
+        let data = archive(&[
+            ("mimetype", MIMETYPE.as_bytes()),
+            ("stack.xml", stack_xml.as_bytes()),
+        ]);
+        let read = LayerStack::from_ora(&data).unwrap();
+        assert_eq!(read.metadata.blending_space, BlendingSpace::Oklab);
+    }
+
+    #[test]
+    fn adjustment_layers_round_trip() {
+        let mut hidden = AdjustmentLayer::new(Adjustment::Invert);
+        hidden.visible = false;
+        let mut curves = AdjustmentLayer::new(Adjustment::Curves(Curves {
+            red: Curve::new([Vec2::new(0.0, 0.1), Vec2::new(0.4, 0.6), Vec2::ONE]),
+            lightness: Curve::new([Vec2::new(0.2, 0.0), Vec2::new(0.8, 1.0)]),
+            ..Default::default()
+        }));
+        curves.opacity = 0.3;
+
+        let mut original = stack(UVec2::ONE, Vec::new());
+        original.metadata.adjustment_layers = vec![
+            AdjustmentLayer::new(Adjustment::Levels(Levels {
+                input_black: 0.1,
+                input_white: 0.9,
+                gamma: 1.3,
+                output_black: 0.05,
+                output_white: 0.95,
+            })),
+            curves,
+            AdjustmentLayer::new(Adjustment::HueChromaLightness {
+                hue: -0.5,
+                chroma: 1.25,
+                lightness: 0.1,
+            }),
+            AdjustmentLayer::new(Adjustment::ColorBalance {
+                shadows: Vec3::new(0.1, 0.0, -0.1),
+                midtones: Vec3::ZERO,
+                highlights: Vec3::new(-0.05, 0.02, 0.3),
+            }),
+            hidden,
+        ];
+
+        let read = LayerStack::from_ora(&original.to_ora()).unwrap();
+        assert_eq!(
+            read.metadata.adjustment_layers,
+            original.metadata.adjustment_layers
+        );
+    }
+
+    #[test]
+    fn text_layers_round_trip() {
+        let mut original = stack(UVec2::ONE, Vec::new());
+        original.metadata.text_layers = vec![
+            TextLayer {
+                text: "  Title\n\t<b> & \"quotes\"\r\n".to_owned(),
+                font: Font::Serif,
+                size: 31.5,
+                color: NonlinearSrgb::new(200, 20, 10),
+                alignment: TextAlignment::Center,
+                position: Vec2::new(-4.25, 12.0),
+            },
+            TextLayer {
+                text: String::new(),
+                font: Font::Mono,
+                size: 8.0,
+                color: NonlinearSrgb::new(0, 255, 171),
+                alignment: TextAlignment::Right,
+                position: Vec2::ZERO,
+            },
+        ];
+
+        let read = LayerStack::from_ora(&original.to_ora()).unwrap();
+        assert_eq!(read.metadata.text_layers, original.metadata.text_layers);
+    }
+
+    #[test]
+    fn unknown_extension_elements_are_ignored() {
+        let stack_xml = format!(
+            r#"<image w="1" h="1" xmlns:paint="{NAMESPACE}">
+                 <paint:adjustments>
+                   <paint:posterize levels="4"/>
+                   <paint:hue-chroma-lightness hue="0.5" opacity="0.5"/>
+                 </paint:adjustments>
+                 <stack/>
+               </image>"#
+        );
+        let data = archive(&[
+            ("mimetype", MIMETYPE.as_bytes()),
+            ("stack.xml", stack_xml.as_bytes()),
+        ]);
+
+        let read = LayerStack::from_ora(&data).unwrap();
+        let [layer] = read.metadata.adjustment_layers.as_slice() else {
+            panic!("expected 1 adjustment layer");
+        };
+        assert_eq!(layer.opacity, 0.5);
+        assert_eq!(
+            layer.adjustment,
+            Adjustment::HueChromaLightness {
+                hue: 0.5,
+                chroma: 1.0,
+                lightness: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn archive_has_merged_image_and_thumbnail() {
+        let layer = RasterLayer::new("", texture(UVec2::new(1024, 512), 0));
+        let data = stack(UVec2::new(1024, 512), vec![layer]).to_ora();
+
+        // the MIME type comes first and uncompressed, at a fixed offset
+        assert_eq!(&data[30..38], b"mimetype");
+        assert_eq!(&data[38..54], MIMETYPE.as_bytes());
+
+        let mut archive = ZipArchive::new(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(
+            archive.by_index(0).unwrap().compression(),
+            CompressionMethod::Stored
+        );
+
+        let merged = decode_png(
+            &read_file(&mut archive, MERGED_IMAGE).unwrap(),
+            MERGED_IMAGE,
+        )
+        .unwrap();
+        assert_eq!(merged.resolution, UVec2::new(1024, 512));
+
+        let thumbnail =
+            decode_png(&read_file(&mut archive, THUMBNAIL).unwrap(), THUMBNAIL).unwrap();
+        assert_eq!(thumbnail.resolution, UVec2::new(256, 128));
+    }
+
+    #[test]
+    fn nested_stacks_are_flattened() {
+        let grey = [128u8, 128, 128, 255];
+        let grey = Texture {
+            resolution: UVec2::ONE,
+            format: TextureFormat::Rgba8NonlinearSrgb,
+            data: Cow::Borrowed(&grey),
+            row_stride: 4,
+        };
+        let png = encode_png(&grey);
+
+        let stack_xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <image w="8" h="6" xres="72" yres="72">
+              <stack>
+                <stack x="2" y="1" opacity="0.5" visibility="hidden" name="group">
+                  <layer src="data/top.png" x="1" y="1.4" opacity="0.5" composite-op="krita:unknown"/>
+                </stack>
+                <!-- comments are fine -->
+                <layer src="data/bottom.png" name="bottom" composite-op="svg:screen"/>
+              </stack>
+            </image>"#;
+        let data = archive(&[
+            ("mimetype", MIMETYPE.as_bytes()),
+            ("stack.xml", stack_xml.as_bytes()),
+            ("data/top.png", &png),
+            ("data/bottom.png", &png),
+        ]);
+
+        let read = LayerStack::from_ora(&data).unwrap();
+        assert_eq!(read.metadata.resolution, UVec2::new(8, 6));
+        assert_eq!(read.metadata.blending_space, BlendingSpace::GammaSrgb);
+
+        let [bottom, top] = read.layers.as_slice() else {
+            panic!("expected 2 layers");
+        };
+        assert_eq!(bottom.name, "bottom");
+        assert_eq!(bottom.offset, IVec2::ZERO);
+        assert_eq!(bottom.opacity, 1.0);
+        assert!(bottom.visible);
+        assert_eq!(bottom.blend_mode, BlendMode::Screen);
+        assert_eq!(bottom.texture.data, grey.data);
+
+        assert_eq!(top.name, "");
+        assert_eq!(top.offset, IVec2::new(3, 2));
+        assert_eq!(top.opacity, 0.25);
+        assert!(!top.visible);
+        assert_eq!(top.blend_mode, BlendMode::Normal);
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        assert!(matches!(
+            LayerStack::from_ora(b"not a zip"),
+            Err(OpenRasterError::Archive(_))
+        ));
+        assert!(matches!(
+            LayerStack::from_ora(&archive(&[("mimetype", b"image/png")])),
+            Err(OpenRasterError::InvalidSignature)
+        ));
+        assert!(matches!(
+            LayerStack::from_ora(&archive(&[("mimetype", MIMETYPE.as_bytes())])),
+            Err(OpenRasterError::MissingFile(path)) if path == STACK
+        ));
+
+        let missing_layer = archive(&[
+            ("mimetype", MIMETYPE.as_bytes()),
+            (
+                "stack.xml",
+                br#"<image w="1" h="1"><stack><layer src="a.png"/></stack></image>"#,
+            ),
+        ]);
+        assert!(matches!(
+            LayerStack::from_ora(&missing_layer),
+            Err(OpenRasterError::MissingFile(path)) if path == "a.png"
+        ));
+
+        let invalid_png = archive(&[
+            ("mimetype", MIMETYPE.as_bytes()),
+            (
+                "stack.xml",
+                br#"<image w="1" h="1"><stack><layer src="a.png"/></stack></image>"#,
+            ),
+            ("a.png", b"not a png"),
+        ]);
+        assert!(matches!(
+            LayerStack::from_ora(&invalid_png),
+            Err(OpenRasterError::InvalidPng { path, .. }) if path == "a.png"
+        ));
+
+        let invalid_size = archive(&[
+            ("mimetype", MIMETYPE.as_bytes()),
+            ("stack.xml", br#"<image w="wide" h="1"><stack/></image>"#),
+        ]);
+        assert!(matches!(
+            LayerStack::from_ora(&invalid_size),
+            Err(OpenRasterError::InvalidStack(_))
+        ));
+    }
+
+    #[test]
+    fn malicious_stacks_are_rejected() {
+        let stacks: [&[u8]; 5] = [
+            br#"<image w="0" h="1"><stack/></image>"#,
+            br#"<image w="4294967295" h="4294967295"><stack/></image>"#,
+            br#"<image w="1" h="1"><stack><layer src="a.png" x="1e30"/></stack></image>"#,
+            br#"<image w="1" h="1"><stack><layer src="a.png" y="NaN"/></stack></image>"#,
+            br#"<image w="1" h="1"><stack x="2000000000"><stack x="2000000000">
+                <layer src="a.png"/>
+            </stack></stack></image>"#,
+        ];
+
+        for stack in stacks {
+            let ora = archive(&[("mimetype", MIMETYPE.as_bytes()), ("stack.xml", stack)]);
+            assert!(matches!(
+                LayerStack::from_ora(&ora),
+                Err(OpenRasterError::InvalidStack(_))
+            ));
+        }
+    }
+}
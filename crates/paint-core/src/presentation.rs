@@ -1,9 +1,22 @@
-use glam::{Affine2, UVec2};
+use std::f32::consts::TAU;
+
+use glam::{Affine2, UVec2, Vec2};
+
+use crate::color::{Color, LinearSrgb, OkLch, Okhsl, Okhsv, Oklab};
+use crate::geometry::{DamageRegion, Rect};
 
 #[derive(Debug, Clone)]
 pub struct Viewport<T> {
     pub transform: Affine2,
     pub canvas: Canvas<T>,
+    /// Filter applied to what is shown on the screen, not to the canvas.
+    pub filter: ViewFilter,
+    /// Area of the canvas which has changed since the previous viewport, or
+    /// [`None`] if anything may have changed.
+    ///
+    /// Only meaningful when the transform, resolution and filter are the same
+    /// as in the previous viewport.
+    pub damage: Option<DamageRegion>,
 }
 
 #[derive(Debug, Clone)]
@@ -14,7 +27,109 @@ pub struct Canvas<T> {
 
 #[derive(Debug, Clone)]
 pub enum Layer<T> {
+    /// A texture covering the whole canvas.
     Texture(T),
+    /// A texture covering only part of the canvas, such as a text layer.
+    Placed {
+        texture: T,
+        /// Area covered by the texture, in canvas pixels.
+        rect: Rect,
+    },
+}
+
+/// Post-processing of the viewport, for checking values and accessibility of
+/// the painting without changing it.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum ViewFilter {
+    #[default]
+    None,
+    /// Gray with the Oklab lightness of the color.
+    Luminance,
+    /// Simulated red-blind vision (Viénot et al. 1999).
+    Protanopia,
+    /// Simulated green-blind vision (Viénot et al. 1999).
+    Deuteranopia,
+    /// Simulated blue-blind vision (Brettel et al. 1997), which the single
+    /// plane projection of Viénot models poorly.
+    Tritanopia,
+    /// Oklab lightness quantized into `bands` evenly spaced grays, from black
+    /// to white.
+    ValueBands { bands: u32 },
+}
+
+impl ViewFilter {
+    /// Smallest number of [`ViewFilter::ValueBands`], fewer are clamped.
+    pub const MIN_VALUE_BANDS: u32 = 2;
+
+    // Simulation matrices in linear sRGB, as derived in DaltonLens
+    // (https://daltonlens.org/opensource-cvd-simulation/).
+    const PROTANOPIA: [[f32; 3]; 3] = [
+        [0.11238, 0.88762, 0.0],
+        [0.11238, 0.88762, 0.0],
+        [0.00401, -0.00401, 1.0],
+    ];
+    const DEUTERANOPIA: [[f32; 3]; 3] = [
+        [0.29275, 0.70725, 0.0],
+        [0.29275, 0.70725, 0.0],
+        [-0.02234, 0.02234, 1.0],
+    ];
+    const TRITANOPIA_1: [[f32; 3]; 3] = [
+        [1.01277, 0.13548, -0.14826],
+        [-0.01243, 0.86812, 0.14431],
+        [0.07589, 0.805, 0.11911],
+    ];
+    const TRITANOPIA_2: [[f32; 3]; 3] = [
+        [0.93678, 0.18979, -0.12657],
+        [0.06154, 0.81526, 0.1232],
+        [-0.37562, 1.12767, 0.24796],
+    ];
+    /// Normal of the plane separating the two Brettel half-planes.
+    const TRITANOPIA_SEPARATION: [f32; 3] = [0.03901, -0.02788, -0.01113];
+
+    /// Filters a color in the sRGB gamut, as the viewport would show it.
+    ///
+    /// This is the reference implementation, which the GPU filter must match.
+    pub fn apply(self, c: LinearSrgb) -> LinearSrgb {
+        let rgb = [c.r, c.g, c.b];
+
+        let filtered = match self {
+            ViewFilter::None => return c,
+            ViewFilter::Luminance => {
+                let l = Oklab::from_linear_srgb(c).l;
+                return gray(l);
+            }
+            ViewFilter::ValueBands { bands } => {
+                let bands = bands.max(Self::MIN_VALUE_BANDS) as f32;
+                let l = Oklab::from_linear_srgb(c).l.clamp(0.0, 1.0);
+                let band = (l * bands).floor().min(bands - 1.0);
+                return gray(band / (bands - 1.0));
+            }
+            ViewFilter::Protanopia => mul(Self::PROTANOPIA, rgb),
+            ViewFilter::Deuteranopia => mul(Self::DEUTERANOPIA, rgb),
+            ViewFilter::Tritanopia => {
+                let n = Self::TRITANOPIA_SEPARATION;
+                if n[0] * rgb[0] + n[1] * rgb[1] + n[2] * rgb[2] >= 0.0 {
+                    mul(Self::TRITANOPIA_1, rgb)
+                } else {
+                    mul(Self::TRITANOPIA_2, rgb)
+                }
+            }
+        };
+
+        let [r, g, b] = filtered.map(|x| x.clamp(0.0, 1.0));
+        LinearSrgb::new(r, g, b)
+    }
+}
+
+/// Gray with the Oklab lightness `l`.
+fn gray(l: f32) -> LinearSrgb {
+    let l = l.clamp(0.0, 1.0);
+    let y = l * l * l;
+    LinearSrgb::new(y, y, y)
+}
+
+fn mul(m: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
 }
 
 #[derive(Debug, Clone)]
@@ -24,5 +139,341 @@ pub enum ColorPickerSlice {
         /// Hue in radians, between 0 and 2π.
         hue: f32,
     },
-    OkhslHueVerticalGradient,
+    /// Okhsl hue gradient, hue increasing from top to bottom.
+    OkhslHueVerticalGradient {
+        /// Hues to mark on the gradient in radians, between 0 and 2π, for
+        /// example from [`crate::color::Harmony::hues`].
+        markers: Vec<f32>,
+    },
+    /// Okhsl color space slice with constant hue, saturation increasing from
+    /// left to right and lightness from bottom to top.
+    OkhslHueSlice {
+        /// Hue in radians, between 0 and 2π.
+        hue: f32,
+    },
+    /// Okhsl color wheel with constant lightness, hue increasing
+    /// counterclockwise from the right and saturation outwards.
+    OkhslLightnessWheel {
+        /// Lightness, between 0 and 1.
+        lightness: f32,
+    },
+    /// Okhsv hue ring at full saturation and value, hue increasing
+    /// counterclockwise from the right.
+    OkhsvHueRing,
+    /// OkLCh color space slice with constant hue, chroma increasing from left
+    /// to right and lightness from bottom to top.
+    OkLchChromaLightnessPlane {
+        /// Hue in radians, between 0 and 2π.
+        hue: f32,
+    },
+    /// Vertical strip of `color` over a checkerboard, opaque at the top and
+    /// transparent at the bottom.
+    AlphaStrip { color: LinearSrgb },
+}
+
+/// A value picked from a [`ColorPickerSlice`], in the color space of the
+/// slice.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorPickerValue {
+    Okhsv(Okhsv),
+    Okhsl(Okhsl),
+    OkLch(OkLch),
+    /// Opacity, between 0 and 1.
+    Alpha(f32),
+}
+
+impl ColorPickerValue {
+    /// Converts the color to linear sRGB, or returns `None` for alpha.
+    fn to_linear_srgb(self) -> Option<LinearSrgb> {
+        match self {
+            ColorPickerValue::Okhsv(c) => Some(c.to_linear_srgb()),
+            ColorPickerValue::Okhsl(c) => Some(c.to_linear_srgb()),
+            ColorPickerValue::OkLch(c) => Some(c.to_linear_srgb()),
+            ColorPickerValue::Alpha(_) => None,
+        }
+    }
+
+    fn to_okhsv(self) -> Option<Okhsv> {
+        match self {
+            ColorPickerValue::Okhsv(c) => Some(c),
+            _ => self.to_linear_srgb().map(Okhsv::from_linear_srgb),
+        }
+    }
+
+    fn to_okhsl(self) -> Option<Okhsl> {
+        match self {
+            ColorPickerValue::Okhsl(c) => Some(c),
+            _ => self.to_linear_srgb().map(Okhsl::from_linear_srgb),
+        }
+    }
+
+    fn to_oklch(self) -> Option<OkLch> {
+        match self {
+            ColorPickerValue::OkLch(c) => Some(c),
+            _ => self.to_linear_srgb().map(OkLch::from_linear_srgb),
+        }
+    }
+}
+
+impl ColorPickerSlice {
+    /// Chroma at the right edge of [`ColorPickerSlice::OkLchChromaLightnessPlane`],
+    /// the reference for 100% chroma in CSS, which covers sRGB and Display P3.
+    pub const OKLCH_MAX_CHROMA: f32 = 0.4;
+
+    /// Radius of the marker on [`ColorPickerSlice::OkhsvHueRing`], relative to
+    /// the radius of the ring, which hosts usually show from 80% outwards.
+    pub const HUE_RING_MARKER_RADIUS: f32 = 0.9;
+
+    /// Whether the slice is a vertical strip, whose value only depends on the
+    /// Y coordinate.
+    pub fn is_vertical_strip(&self) -> bool {
+        matches!(
+            self,
+            ColorPickerSlice::OkhslHueVerticalGradient { .. } | ColorPickerSlice::AlphaStrip { .. }
+        )
+    }
+
+    /// Returns the value at `point`, in coordinates normalized between 0 and
+    /// 1, Y down. Points outside of the slice are clamped to its edge.
+    ///
+    /// [`ColorPickerSlice::OkhslHueVerticalGradient`] only picks the hue,
+    /// saturation and lightness are those of the gradient.
+    pub fn value_at(&self, point: Vec2) -> ColorPickerValue {
+        let Vec2 { x, y } = point.clamp(Vec2::ZERO, Vec2::ONE);
+
+        // polar coordinates around the center, counterclockwise from the right
+        let polar = || {
+            let v = Vec2::new(2.0 * x - 1.0, 1.0 - 2.0 * y);
+            (v.y.atan2(v.x).rem_euclid(TAU), v.length().min(1.0))
+        };
+
+        match *self {
+            ColorPickerSlice::OkhsvHueSlice { hue } => {
+                ColorPickerValue::Okhsv(Okhsv::new(hue, x, 1.0 - y))
+            }
+            ColorPickerSlice::OkhslHueVerticalGradient { .. } => {
+                ColorPickerValue::Okhsl(Okhsl::new((y * TAU).rem_euclid(TAU), 1.0, 0.62))
+            }
+            ColorPickerSlice::OkhslHueSlice { hue } => {
+                ColorPickerValue::Okhsl(Okhsl::new(hue, x, 1.0 - y))
+            }
+            ColorPickerSlice::OkhslLightnessWheel { lightness } => {
+                let (angle, radius) = polar();
+                ColorPickerValue::Okhsl(Okhsl::new(angle, radius, lightness))
+            }
+            ColorPickerSlice::OkhsvHueRing => {
+                let (angle, _) = polar();
+                ColorPickerValue::Okhsv(Okhsv::new(angle, 1.0, 1.0))
+            }
+            ColorPickerSlice::OkLchChromaLightnessPlane { hue } => {
+                ColorPickerValue::OkLch(OkLch::new(1.0 - y, x * Self::OKLCH_MAX_CHROMA, hue))
+            }
+            ColorPickerSlice::AlphaStrip { .. } => ColorPickerValue::Alpha(1.0 - y),
+        }
+    }
+
+    /// Returns the point where `value` lies, in coordinates normalized between
+    /// 0 and 1, Y down, clamped to the slice.
+    ///
+    /// Colors in another color space are converted, which loses the hue of
+    /// grays. Returns `None` for alpha on color slices and vice versa.
+    pub fn point_of(&self, value: ColorPickerValue) -> Option<Vec2> {
+        let polar = |angle: f32, radius: f32| {
+            let (sin, cos) = angle.sin_cos();
+            Vec2::new(0.5 + 0.5 * radius * cos, 0.5 - 0.5 * radius * sin)
+        };
+
+        let point = match *self {
+            ColorPickerSlice::OkhsvHueSlice { .. } => {
+                let c = value.to_okhsv()?;
+                Vec2::new(c.s, 1.0 - c.v)
+            }
+            ColorPickerSlice::OkhslHueVerticalGradient { .. } => {
+                let c = value.to_okhsl()?;
+                Vec2::new(0.5, c.h.rem_euclid(TAU) / TAU)
+            }
+            ColorPickerSlice::OkhslHueSlice { .. } => {
+                let c = value.to_okhsl()?;
+                Vec2::new(c.s, 1.0 - c.l)
+            }
+            ColorPickerSlice::OkhslLightnessWheel { .. } => {
+                let c = value.to_okhsl()?;
+                polar(c.h, c.s.clamp(0.0, 1.0))
+            }
+            ColorPickerSlice::OkhsvHueRing => {
+                let c = value.to_okhsv()?;
+                polar(c.h, Self::HUE_RING_MARKER_RADIUS)
+            }
+            ColorPickerSlice::OkLchChromaLightnessPlane { .. } => {
+                let c = value.to_oklch()?;
+                Vec2::new(c.c / Self::OKLCH_MAX_CHROMA, 1.0 - c.l)
+            }
+            ColorPickerSlice::AlphaStrip { .. } => match value {
+                ColorPickerValue::Alpha(alpha) => Vec2::new(0.5, 1.0 - alpha),
+                _ => return None,
+            },
+        };
+
+        Some(point.clamp(Vec2::ZERO, Vec2::ONE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slices() -> Vec<ColorPickerSlice> {
+        vec![
+            ColorPickerSlice::OkhsvHueSlice { hue: 1.0 },
+            ColorPickerSlice::OkhslHueVerticalGradient { markers: vec![] },
+            ColorPickerSlice::OkhslHueSlice { hue: 4.0 },
+            ColorPickerSlice::OkhslLightnessWheel { lightness: 0.7 },
+            ColorPickerSlice::OkhsvHueRing,
+            ColorPickerSlice::OkLchChromaLightnessPlane { hue: 2.5 },
+            ColorPickerSlice::AlphaStrip {
+                color: LinearSrgb::new(1.0, 0.0, 0.0),
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for slice in slices() {
+            for point in [
+                Vec2::new(0.3, 0.2),
+                Vec2::new(0.5, 0.8),
+                Vec2::new(0.9, 0.6),
+                Vec2::new(0.6, 0.4),
+            ] {
+                let value = slice.value_at(point);
+                let back = slice.point_of(value).unwrap();
+
+                // only the point's direction matters on the ring, and only Y
+                // on the strips
+                let expected = match slice {
+                    ColorPickerSlice::OkhsvHueRing => {
+                        let d = (point - 0.5).normalize();
+                        0.5 + d * 0.5 * ColorPickerSlice::HUE_RING_MARKER_RADIUS
+                    }
+                    _ if slice.is_vertical_strip() => Vec2::new(0.5, point.y),
+                    _ => point,
+                };
+
+                assert!(back.distance(expected) < 1e-4, "{slice:?} {point}: {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn value_orientation() {
+        // saturation to the right, value up
+        let slice = ColorPickerSlice::OkhsvHueSlice { hue: 1.0 };
+        assert_eq!(
+            slice.value_at(Vec2::new(1.0, 0.0)),
+            ColorPickerValue::Okhsv(Okhsv::new(1.0, 1.0, 1.0))
+        );
+
+        // hue 0 to the right, increasing counterclockwise
+        let slice = ColorPickerSlice::OkhsvHueRing;
+        let ColorPickerValue::Okhsv(right) = slice.value_at(Vec2::new(1.0, 0.5)) else {
+            panic!();
+        };
+        let ColorPickerValue::Okhsv(top) = slice.value_at(Vec2::new(0.5, 0.0)) else {
+            panic!();
+        };
+        assert_eq!(right.h, 0.0);
+        assert!((top.h - TAU / 4.0).abs() < 1e-6);
+
+        // opaque at the top, clamped outside
+        let slice = ColorPickerSlice::AlphaStrip {
+            color: LinearSrgb::new(1.0, 1.0, 1.0),
+        };
+        assert_eq!(
+            slice.value_at(Vec2::new(0.5, -1.0)),
+            ColorPickerValue::Alpha(1.0)
+        );
+    }
+
+    #[test]
+    fn converts_between_spaces() {
+        let color = LinearSrgb::new(0.2, 0.5, 0.1);
+        let okhsv = ColorPickerValue::Okhsv(Okhsv::from_linear_srgb(color));
+
+        let slice = ColorPickerSlice::OkhslHueSlice { hue: 0.0 };
+        let point = slice.point_of(okhsv).unwrap();
+        let ColorPickerValue::Okhsl(okhsl) = slice.value_at(point) else {
+            panic!();
+        };
+
+        let expected = Okhsl::from_linear_srgb(color);
+        assert!((okhsl.s - expected.s).abs() < 1e-4);
+        assert!((okhsl.l - expected.l).abs() < 1e-4);
+
+        assert_eq!(slice.point_of(ColorPickerValue::Alpha(0.5)), None);
+        assert_eq!(ColorPickerSlice::AlphaStrip { color }.point_of(okhsv), None);
+    }
+
+    #[test]
+    fn view_filters_keep_grays() {
+        let filters = [
+            ViewFilter::None,
+            ViewFilter::Luminance,
+            ViewFilter::Protanopia,
+            ViewFilter::Deuteranopia,
+            ViewFilter::Tritanopia,
+        ];
+
+        for filter in filters {
+            for y in [0.0, 0.18, 0.5, 1.0] {
+                let filtered = filter.apply(LinearSrgb::new(y, y, y));
+                for c in [filtered.r, filtered.g, filtered.b] {
+                    assert!((c - y).abs() < 1e-4, "{filter:?} {y}: {filtered:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn view_filters_simulate_confusion() {
+        let red = LinearSrgb::new(1.0, 0.0, 0.0);
+        let green = LinearSrgb::new(0.0, 1.0, 0.0);
+        let blue = LinearSrgb::new(0.0, 0.0, 1.0);
+
+        // protanopes and deuteranopes can't tell red from green, only the
+        // yellow-blue axis is left
+        for filter in [ViewFilter::Protanopia, ViewFilter::Deuteranopia] {
+            for c in [red, green] {
+                let c = filter.apply(c);
+                assert!((c.r - c.g).abs() < 1e-4, "{filter:?}: {c:?}");
+                assert!(c.b < 0.05, "{filter:?}: {c:?}");
+            }
+        }
+
+        // tritanopes see blue as cyan-ish and yellow as pink-ish
+        let c = ViewFilter::Tritanopia.apply(blue);
+        assert!(c.g > c.r && c.b > c.r, "{c:?}");
+
+        let lightness = |c: LinearSrgb| Oklab::from_linear_srgb(c).l;
+        let c = ViewFilter::Luminance.apply(green);
+        assert!((lightness(c) - lightness(green)).abs() < 1e-4);
+        assert!((c.r - c.g).abs() < 1e-6 && (c.g - c.b).abs() < 1e-6);
+    }
+
+    #[test]
+    fn value_bands() {
+        let filter = ViewFilter::ValueBands { bands: 3 };
+        let band = |l: f32| {
+            let gray = filter.apply(OkLch::new(l, 0.05, 1.0).to_linear_srgb());
+            Oklab::from_linear_srgb(gray).l
+        };
+
+        assert!(band(0.1).abs() < 1e-4);
+        assert!((band(0.5) - 0.5).abs() < 1e-4);
+        assert!((band(0.9) - 1.0).abs() < 1e-4);
+        assert_eq!(band(0.4), band(0.6));
+
+        // a single band would have no values at all
+        let white = LinearSrgb::new(1.0, 1.0, 1.0);
+        assert_eq!(ViewFilter::ValueBands { bands: 0 }.apply(white), white);
+    }
 }
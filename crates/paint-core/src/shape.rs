@@ -0,0 +1,430 @@
+//! Geometric shapes, drawn by dragging from one point to another.
+//!
+//! Filled shapes are rasterized from their signed distance, which gives
+//! analytic antialiasing. Stroked shapes are stamped with brush dabs along
+//! their outline, so they look like brush strokes.
+
+use std::f32::consts::PI;
+
+use glam::{Mat2, UVec2, Vec2};
+
+use crate::geometry::Rect;
+
+/// Distance between stroke dabs, relative to the stroke width.
+const DAB_SPACING: f32 = 0.05;
+
+/// Smallest distance between stroke dabs in pixels, which keeps the number of
+/// dabs reasonable for thin strokes.
+const MIN_DAB_SPACING: f32 = 0.25;
+
+/// Length of the outline segments approximating an ellipse, in pixels.
+const ELLIPSE_SEGMENT_LENGTH: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapeKind {
+    /// Line from the start to the end.
+    Line,
+    /// Axis-aligned rectangle with opposite corners at the start and the end.
+    Rectangle,
+    /// Ellipse inscribed in the rectangle from the start to the end.
+    Ellipse,
+    /// Regular polygon centered at the start, with a corner at the end.
+    Polygon { sides: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShapeStyle {
+    /// Brush dabs along the outline, `width` pixels wide.
+    Stroke { width: f32 },
+    /// The inside of the shape. Lines have no inside, so they're drawn as
+    /// hairlines.
+    Fill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeSettings {
+    pub kind: ShapeKind,
+    pub style: ShapeStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shape {
+    pub settings: ShapeSettings,
+    /// Where dragging started, in canvas pixels.
+    pub start: Vec2,
+    /// Where dragging ended, in canvas pixels.
+    pub end: Vec2,
+}
+
+impl Shape {
+    /// Creates an empty shape at the position, which dragging grows.
+    pub fn new(settings: ShapeSettings, position: Vec2) -> Self {
+        Self {
+            settings,
+            start: position,
+            end: position,
+        }
+    }
+
+    /// Number of sides of a polygon, at least 3.
+    pub fn sides(&self) -> u32 {
+        match self.settings.kind {
+            ShapeKind::Polygon { sides } => sides.max(3),
+            _ => 4,
+        }
+    }
+
+    /// Width of the stroke, at least a pixel.
+    pub fn stroke_width(&self) -> Option<f32> {
+        match self.settings.style {
+            ShapeStyle::Stroke { width } => Some(width.max(1.0)),
+            ShapeStyle::Fill => None,
+        }
+    }
+
+    /// Area of the canvas which drawing the shape touches.
+    pub fn bounds(&self) -> Rect {
+        let rect = match self.settings.kind {
+            ShapeKind::Line | ShapeKind::Rectangle | ShapeKind::Ellipse => {
+                Rect::new(self.start.min(self.end), self.start.max(self.end))
+            }
+            ShapeKind::Polygon { .. } => {
+                Rect::from_center_half_size(self.start, Vec2::splat(self.start.distance(self.end)))
+            }
+        };
+
+        // plus a pixel for antialiasing
+        let padding = self.stroke_width().map_or(0.0, |width| 0.5 * width);
+        rect.expand(padding + 1.0)
+    }
+
+    /// Points along the outline, where closed outlines end at their first
+    /// point.
+    pub fn outline(&self) -> Vec<Vec2> {
+        match self.settings.kind {
+            ShapeKind::Line => vec![self.start, self.end],
+            ShapeKind::Rectangle => {
+                let (min, max) = (self.start.min(self.end), self.start.max(self.end));
+                vec![
+                    min,
+                    Vec2::new(max.x, min.y),
+                    max,
+                    Vec2::new(min.x, max.y),
+                    min,
+                ]
+            }
+            ShapeKind::Ellipse => {
+                let center = (self.start + self.end) / 2.0;
+                let radii = (self.end - self.start).abs() / 2.0;
+                // Ramanujan's approximation is plenty for choosing a count
+                let (a, b) = (radii.x, radii.y);
+                let perimeter = PI * (3.0 * (a + b) - ((3.0 * a + b) * (a + 3.0 * b)).sqrt());
+                let count = (perimeter / ELLIPSE_SEGMENT_LENGTH).ceil().max(16.0) as u32;
+
+                (0..=count)
+                    .map(|i| {
+                        let angle = 2.0 * PI * (i % count) as f32 / count as f32;
+                        center + radii * Vec2::from_angle(angle)
+                    })
+                    .collect()
+            }
+            ShapeKind::Polygon { .. } => {
+                let sides = self.sides();
+                (0..=sides)
+                    .map(|i| {
+                        let angle = 2.0 * PI * (i % sides) as f32 / sides as f32;
+                        self.start + Mat2::from_angle(angle) * (self.end - self.start)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Centers of the brush dabs stroking the outline, or nothing for filled
+    /// shapes.
+    pub fn dabs(&self) -> Vec<Vec2> {
+        let Some(width) = self.stroke_width() else {
+            return Vec::new();
+        };
+
+        let spacing = (width * DAB_SPACING).max(MIN_DAB_SPACING);
+        let outline = self.outline();
+
+        let mut dabs = vec![outline[0]];
+        // distance from the last dab, carried over segments
+        let mut traveled = 0.0;
+        for segment in outline.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let length = a.distance(b);
+            if length == 0.0 {
+                continue;
+            }
+
+            let mut along = spacing - traveled;
+            while along <= length {
+                dabs.push(a.lerp(b, along / length));
+                along += spacing;
+            }
+            traveled = length - (along - spacing);
+        }
+
+        dabs
+    }
+
+    /// Signed distance to the edge of the filled shape, negative inside.
+    pub fn signed_distance(&self, p: Vec2) -> f32 {
+        match self.settings.kind {
+            // the hairline is a pixel wide
+            ShapeKind::Line => segment_distance(p, self.start, self.end) - 0.5,
+            ShapeKind::Rectangle => {
+                let center = (self.start + self.end) / 2.0;
+                let half_size = (self.end - self.start).abs() / 2.0;
+                let q = (p - center).abs() - half_size;
+                q.max(Vec2::ZERO).length() + q.x.max(q.y).min(0.0)
+            }
+            ShapeKind::Ellipse => {
+                let center = (self.start + self.end) / 2.0;
+                let radii = (self.end - self.start).abs() / 2.0;
+                ellipse_distance(p - center, radii)
+            }
+            ShapeKind::Polygon { .. } => {
+                polygon_distance(p - self.start, self.end - self.start, self.sides())
+            }
+        }
+    }
+
+    /// Alpha of every pixel of a texture of the given resolution, row-major.
+    ///
+    /// This is the reference implementation, which the GPU rendering must
+    /// match.
+    pub fn rasterize(&self, resolution: UVec2) -> Vec<f32> {
+        let dabs = self.dabs();
+
+        (0..resolution.y)
+            .flat_map(|y| (0..resolution.x).map(move |x| Vec2::new(x as f32, y as f32) + 0.5))
+            .map(|p| match self.stroke_width() {
+                Some(width) => dabs
+                    .iter()
+                    .map(|&dab| dab_alpha(p.distance(dab), width))
+                    // dabs are blended over each other, like brush dabs
+                    .fold(0.0, |alpha, dab| dab + alpha * (1.0 - dab)),
+                None => (0.5 - self.signed_distance(p)).clamp(0.0, 1.0),
+            })
+            .collect()
+    }
+}
+
+/// Alpha of a brush dab `width` pixels wide at a distance from its center,
+/// as the stamped brush draws it.
+pub fn dab_alpha(distance: f32, width: f32) -> f32 {
+    1.0 - smoothstep(-0.5, 0.5, distance - 0.5 * width)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn segment_distance(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let (pa, ba) = (p - a, b - a);
+    let h = if ba == Vec2::ZERO {
+        0.0
+    } else {
+        (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0)
+    };
+    (pa - ba * h).length()
+}
+
+/// Approximate signed distance to an ellipse centered at the origin, which is
+/// exact enough near the edge for antialiasing.
+fn ellipse_distance(p: Vec2, radii: Vec2) -> f32 {
+    let radii = radii.max(Vec2::splat(1e-3));
+    let k1 = (p / radii).length();
+    let k2 = (p / (radii * radii)).length();
+    if k2 == 0.0 {
+        return -radii.min_element();
+    }
+
+    k1 * (k1 - 1.0) / k2
+}
+
+/// Signed distance to a regular polygon centered at the origin, with a corner
+/// at `corner`.
+///
+/// From "2D distance functions" by Inigo Quilez.
+fn polygon_distance(p: Vec2, corner: Vec2, sides: u32) -> f32 {
+    let radius = corner.length();
+    if radius == 0.0 {
+        return p.length();
+    }
+
+    // rotated so the corner is on the positive Y axis
+    let d = corner / radius;
+    let p = Vec2::new(d.x * p.y - d.y * p.x, d.dot(p));
+
+    let half_angle = PI / sides as f32;
+    let angle = p.x.atan2(p.y).rem_euclid(2.0 * half_angle) - half_angle;
+    let mut q = p.length() * Vec2::new(angle.cos(), angle.sin().abs());
+    q -= radius * Vec2::new(half_angle.cos(), half_angle.sin());
+    q.y += (-q.y).clamp(0.0, radius * half_angle.sin());
+    q.length() * q.x.signum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(kind: ShapeKind, style: ShapeStyle, start: Vec2, end: Vec2) -> Shape {
+        Shape {
+            settings: ShapeSettings { kind, style },
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn filled_shapes_have_signed_distances() {
+        let rectangle = shape(
+            ShapeKind::Rectangle,
+            ShapeStyle::Fill,
+            Vec2::new(10.0, 20.0),
+            Vec2::new(2.0, 4.0),
+        );
+        assert_eq!(rectangle.signed_distance(Vec2::new(6.0, 12.0)), -4.0);
+        assert_eq!(rectangle.signed_distance(Vec2::new(13.0, 12.0)), 3.0);
+        assert_eq!(rectangle.signed_distance(Vec2::new(6.0, 3.0)), 1.0);
+
+        let ellipse = shape(
+            ShapeKind::Ellipse,
+            ShapeStyle::Fill,
+            Vec2::ZERO,
+            Vec2::new(20.0, 10.0),
+        );
+        for (p, expected) in [
+            (Vec2::new(10.0, 5.0), -5.0),
+            (Vec2::new(22.0, 5.0), 2.0),
+            (Vec2::new(10.0, 9.0), -1.0),
+            (Vec2::new(19.0, 5.0), -1.0),
+        ] {
+            let distance = ellipse.signed_distance(p);
+            assert!((distance - expected).abs() < 0.1, "{p}: {distance}");
+        }
+
+        // a square with its corners on the axes
+        let square = shape(
+            ShapeKind::Polygon { sides: 4 },
+            ShapeStyle::Fill,
+            Vec2::ZERO,
+            Vec2::new(0.0, 10.0),
+        );
+        let apothem = 10.0 / 2f32.sqrt();
+        for p in [
+            Vec2::new(5.0, 5.0),
+            Vec2::new(-5.0, 5.0),
+            Vec2::new(5.0, -5.0),
+        ] {
+            let distance = square.signed_distance(p * 2.0);
+            assert!((distance - apothem).abs() < 1e-4, "{p}: {distance}");
+        }
+        assert!((square.signed_distance(Vec2::new(0.0, 12.0)) - 2.0).abs() < 1e-4);
+        assert!((square.signed_distance(Vec2::ZERO) + apothem).abs() < 1e-4);
+    }
+
+    #[test]
+    fn outlines_are_closed() {
+        for kind in [
+            ShapeKind::Rectangle,
+            ShapeKind::Ellipse,
+            ShapeKind::Polygon { sides: 5 },
+        ] {
+            let shape = shape(kind, ShapeStyle::Fill, Vec2::ZERO, Vec2::new(30.0, 20.0));
+            let outline = shape.outline();
+            assert!(outline.first().unwrap().distance(*outline.last().unwrap()) < 1e-3);
+
+            // every point of the outline is on the edge
+            for p in outline {
+                let distance = shape.signed_distance(p);
+                assert!(distance.abs() < 0.1, "{kind:?} {p}: {distance}");
+            }
+        }
+
+        let polygon = shape(
+            ShapeKind::Polygon { sides: 1 },
+            ShapeStyle::Fill,
+            Vec2::ZERO,
+            Vec2::X,
+        );
+        assert_eq!(polygon.outline().len(), 4);
+    }
+
+    #[test]
+    fn dabs_are_spaced_along_the_outline() {
+        let line = shape(
+            ShapeKind::Line,
+            ShapeStyle::Stroke { width: 20.0 },
+            Vec2::ZERO,
+            Vec2::new(10.0, 0.0),
+        );
+        let dabs = line.dabs();
+        assert_eq!(dabs.len(), 11);
+        assert_eq!(*dabs.last().unwrap(), Vec2::new(10.0, 0.0));
+
+        // spacing carries over corners
+        let rectangle = shape(
+            ShapeKind::Rectangle,
+            ShapeStyle::Stroke { width: 2.0 },
+            Vec2::ZERO,
+            Vec2::new(3.1, 3.1),
+        );
+        let dabs = rectangle.dabs();
+        for pair in dabs.windows(2) {
+            let distance = pair[0].distance(pair[1]);
+            assert!(distance <= MIN_DAB_SPACING + 1e-4, "{distance}");
+        }
+
+        let filled = Shape {
+            settings: ShapeSettings {
+                style: ShapeStyle::Fill,
+                ..line.settings
+            },
+            ..line
+        };
+        assert!(filled.dabs().is_empty());
+    }
+
+    #[test]
+    fn rasterized_shapes_are_antialiased() {
+        let resolution = UVec2::new(8, 8);
+        let filled = shape(
+            ShapeKind::Rectangle,
+            ShapeStyle::Fill,
+            Vec2::new(2.0, 2.0),
+            Vec2::new(5.5, 6.0),
+        );
+        let alpha = filled.rasterize(resolution);
+        assert_eq!(alpha[3 * 8 + 3], 1.0);
+        assert_eq!(alpha[3 * 8 + 5], 0.5);
+        assert_eq!(alpha[3 * 8 + 6], 0.0);
+        assert_eq!(alpha[7 * 8 + 3], 0.0);
+
+        let stroked = Shape {
+            settings: ShapeSettings {
+                kind: ShapeKind::Line,
+                style: ShapeStyle::Stroke { width: 2.0 },
+            },
+            ..filled
+        };
+        let alpha = stroked.rasterize(resolution);
+        assert_eq!(alpha[0], 0.0);
+        assert!(alpha[3 * 8 + 3] > 0.9);
+
+        // everything drawn is within the bounds
+        let (min, size) = stroked.bounds().to_pixels(resolution).unwrap();
+        for (i, alpha) in alpha.iter().enumerate() {
+            let p = UVec2::new(i as u32 % 8, i as u32 / 8);
+            if p.cmplt(min).any() || p.cmpge(min + size).any() {
+                assert_eq!(*alpha, 0.0, "{p}");
+            }
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Text layers, rasterized on the CPU with bundled fonts, so they work offline
+//! and look the same on every device.
+
+use std::borrow::Cow;
+
+use ab_glyph::{Font as _, FontRef, Glyph, PxScale, ScaleFont as _, point};
+use glam::{IVec2, UVec2, Vec2};
+
+use crate::color::NonlinearSrgb;
+use crate::geometry::Rect;
+use crate::persistence;
+
+/// Bundled fonts, see `fonts/LICENSE`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Font {
+    /// DejaVu Sans.
+    #[default]
+    Sans,
+    /// DejaVu Serif.
+    Serif,
+    /// DejaVu Sans Mono.
+    Mono,
+}
+
+impl Font {
+    pub const ALL: [Font; 3] = [Font::Sans, Font::Serif, Font::Mono];
+
+    fn data(self) -> &'static [u8] {
+        match self {
+            Font::Sans => include_bytes!("../fonts/DejaVuSans.ttf"),
+            Font::Serif => include_bytes!("../fonts/DejaVuSerif.ttf"),
+            Font::Mono => include_bytes!("../fonts/DejaVuSansMono.ttf"),
+        }
+    }
+
+    fn font_ref(self) -> FontRef<'static> {
+        FontRef::try_from_slice(self.data()).expect("bundled fonts should be valid")
+    }
+}
+
+/// Horizontal alignment of the lines of a text relative to each other.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum TextAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A text above the canvas, which stays editable until it's drawn onto the
+/// canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayer {
+    /// The text, where `\n` starts a new line.
+    pub text: String,
+    pub font: Font,
+    /// Height of a line from the highest ascender to the lowest descender,
+    /// in canvas pixels.
+    pub size: f32,
+    pub color: NonlinearSrgb<u8>,
+    pub alignment: TextAlignment,
+    /// Top left corner of the text, in canvas pixels.
+    pub position: Vec2,
+}
+
+impl TextLayer {
+    /// Largest size which is rasterized, larger sizes are clamped, as every
+    /// glyph is rasterized in full before it's clipped.
+    pub const MAX_SIZE: f32 = 2048.0;
+
+    /// Glyphs of every line, positioned on the canvas.
+    fn layout(&self, font: &FontRef<'static>) -> Vec<Glyph> {
+        let font = font.as_scaled(PxScale::from(self.size.min(Self::MAX_SIZE)));
+        let line_height = font.height() + font.line_gap();
+
+        let lines: Vec<(Vec<Glyph>, f32)> = self
+            .text
+            .lines()
+            .map(|line| {
+                let mut glyphs: Vec<Glyph> = Vec::new();
+                let mut x = 0.0;
+                for c in line.chars() {
+                    let mut glyph = font.scaled_glyph(c);
+                    if let Some(previous) = glyphs.last() {
+                        x += font.kern(previous.id, glyph.id);
+                    }
+                    glyph.position = point(x, 0.0);
+                    x += font.h_advance(glyph.id);
+                    glyphs.push(glyph);
+                }
+                (glyphs, x)
+            })
+            .collect();
+
+        let width = lines.iter().map(|(_, width)| *width).fold(0.0, f32::max);
+
+        lines
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, (glyphs, line_width))| {
+                let x = match self.alignment {
+                    TextAlignment::Left => 0.0,
+                    TextAlignment::Center => (width - line_width) / 2.0,
+                    TextAlignment::Right => width - line_width,
+                };
+                let origin = self.position + Vec2::new(x, font.ascent() + line_height * i as f32);
+
+                glyphs.into_iter().map(move |mut glyph| {
+                    glyph.position.x += origin.x;
+                    glyph.position.y += origin.y;
+                    glyph
+                })
+            })
+            .collect()
+    }
+
+    /// Rasterizes the part of the text within a canvas of the resolution, or
+    /// returns [`None`] if nothing would be visible.
+    pub fn rasterize(&self, canvas_resolution: UVec2) -> Option<RasterizedText> {
+        if self.size.is_nan() || self.size <= 0.0 {
+            return None;
+        }
+
+        let font = self.font.font_ref();
+        let canvas = Rect::from_resolution(canvas_resolution);
+        let outlines: Vec<_> = self
+            .layout(&font)
+            .into_iter()
+            .filter_map(|glyph| font.outline_glyph(glyph))
+            .map(|outline| {
+                let bounds = outline.px_bounds();
+                let rect = Rect::new(
+                    Vec2::new(bounds.min.x, bounds.min.y),
+                    Vec2::new(bounds.max.x, bounds.max.y),
+                );
+                (outline, rect)
+            })
+            // glyphs outside the canvas aren't rasterized at all
+            .filter(|(_, rect)| rect.intersects(&canvas))
+            .collect();
+
+        let bounds = outlines
+            .iter()
+            .map(|(_, rect)| *rect)
+            .fold(Rect::EMPTY, Rect::union)
+            .round_out()
+            .intersection(canvas);
+
+        if bounds.is_empty() {
+            return None;
+        }
+
+        // within the canvas, so the size fits
+        let origin = bounds.min.as_ivec2();
+        let resolution = bounds.size().as_uvec2();
+        let (width, height) = (resolution.x as usize, resolution.y as usize);
+
+        let mut coverage = vec![0.0f32; width * height];
+        for (outline, rect) in &outlines {
+            let offset = rect.min.as_ivec2() - origin;
+            outline.draw(|x, y, c| {
+                let p = offset + UVec2::new(x, y).as_ivec2();
+                if p.cmpge(IVec2::ZERO).all() && p.cmplt(resolution.as_ivec2()).all() {
+                    let i = p.y as usize * width + p.x as usize;
+                    // overlapping glyphs, such as accents, add up
+                    coverage[i] = (coverage[i] + c).min(1.0);
+                }
+            });
+        }
+
+        let NonlinearSrgb { r, g, b } = self.color;
+        let data = coverage
+            .iter()
+            .flat_map(|c| [r, g, b, (c * 255.0).round() as u8])
+            .collect();
+
+        Some(RasterizedText {
+            origin,
+            resolution,
+            data,
+        })
+    }
+}
+
+/// A rasterized text, covering only the area of the glyphs within the canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterizedText {
+    /// Canvas pixel of the top left corner.
+    pub origin: IVec2,
+    pub resolution: UVec2,
+    /// Pixels in [`persistence::TextureFormat::Rgba8NonlinearSrgb`].
+    pub data: Vec<u8>,
+}
+
+impl RasterizedText {
+    /// Area of the canvas covered by the text.
+    pub fn rect(&self) -> Rect {
+        Rect::from_min_size(self.origin.as_vec2(), self.resolution.as_vec2())
+    }
+
+    pub fn as_persistence(&self) -> persistence::Texture<'_> {
+        persistence::Texture {
+            resolution: self.resolution,
+            format: persistence::TextureFormat::Rgba8NonlinearSrgb,
+            data: Cow::Borrowed(&self.data),
+            row_stride: 4 * self.resolution.x as usize,
+        }
+    }
+
+    /// The text on a transparent texture of the canvas resolution, clipped to
+    /// the canvas, as brush strokes are drawn onto the canvas.
+    pub fn to_canvas(&self, resolution: UVec2) -> persistence::Texture<'static> {
+        let row_stride = 4 * resolution.x as usize;
+        let mut data = vec![0; row_stride * resolution.y as usize];
+
+        if let Some((min, size)) = self.rect().to_pixels(resolution) {
+            let source_min = (min.as_ivec2() - self.origin).as_uvec2();
+            for y in 0..size.y {
+                let source = 4 * ((source_min.y + y) * self.resolution.x + source_min.x) as usize;
+                let target = 4 * ((min.y + y) * resolution.x + min.x) as usize;
+                let len = 4 * size.x as usize;
+                data[target..target + len].copy_from_slice(&self.data[source..source + len]);
+            }
+        }
+
+        persistence::Texture {
+            resolution,
+            format: persistence::TextureFormat::Rgba8NonlinearSrgb,
+            data: Cow::Owned(data),
+            row_stride,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANVAS: UVec2 = UVec2::new(1024, 512);
+
+    fn layer(text: &str) -> TextLayer {
+        TextLayer {
+            text: text.to_owned(),
+            font: Font::Sans,
+            size: 32.0,
+            color: NonlinearSrgb::new(200, 20, 10),
+            alignment: TextAlignment::Left,
+            position: Vec2::new(10.0, 20.0),
+        }
+    }
+
+    fn alpha(text: &RasterizedText) -> impl Iterator<Item = u8> + '_ {
+        text.data.chunks(4).map(|c| c[3])
+    }
+
+    #[test]
+    fn text_is_rasterized_in_its_color() {
+        for font in Font::ALL {
+            let text = TextLayer {
+                font,
+                ..layer("Hello")
+            }
+            .rasterize(CANVAS)
+            .unwrap();
+
+            // the glyphs sit below the position, around the size
+            let rect = text.rect();
+            assert!(rect.min.cmpge(Vec2::new(10.0, 20.0)).all(), "{rect:?}");
+            assert!(rect.size().y > 16.0 && rect.size().y <= 32.0, "{rect:?}");
+            assert!(rect.size().x > 50.0, "{rect:?}");
+
+            assert!(alpha(&text).any(|a| a == 255));
+            assert!(alpha(&text).any(|a| a > 0 && a < 255), "not antialiased");
+            assert!(text.data.chunks(4).all(|c| c[..3] == [200, 20, 10]));
+        }
+    }
+
+    #[test]
+    fn empty_text_is_not_rasterized() {
+        assert_eq!(layer("").rasterize(CANVAS), None);
+        assert_eq!(layer(" \n ").rasterize(CANVAS), None);
+        assert_eq!(
+            TextLayer {
+                size: 0.0,
+                ..layer("Hello")
+            }
+            .rasterize(CANVAS),
+            None
+        );
+    }
+
+    #[test]
+    fn lines_are_aligned() {
+        let text = "Wide line\nI";
+        let rect = |alignment| {
+            TextLayer {
+                alignment,
+                ..layer(text)
+            }
+            .rasterize(CANVAS)
+            .unwrap()
+            .rect()
+        };
+
+        // the wide line determines the bounds
+        let left = rect(TextAlignment::Left);
+        assert_eq!(left, rect(TextAlignment::Center));
+        assert_eq!(left, rect(TextAlignment::Right));
+        assert!(left.size().y > 50.0, "{left:?}");
+
+        // so compare where the short line ends up
+        let short_line_x = |alignment| {
+            let text = TextLayer {
+                alignment,
+                ..layer(text)
+            }
+            .rasterize(CANVAS)
+            .unwrap();
+            let last_row = text.resolution.y - 1;
+            (0..text.resolution.x)
+                .find(|x| text.data[(4 * (last_row * text.resolution.x + x) + 3) as usize] > 0)
+                .unwrap()
+        };
+        let (left, center, right) = (
+            short_line_x(TextAlignment::Left),
+            short_line_x(TextAlignment::Center),
+            short_line_x(TextAlignment::Right),
+        );
+        assert!(left < center && center < right, "{left} {center} {right}");
+    }
+
+    #[test]
+    fn text_is_clipped_to_the_canvas() {
+        let layer = layer("Hello");
+        let full = layer.rasterize(CANVAS).unwrap();
+
+        let resolution = UVec2::new(40, 30);
+        let clipped = layer.rasterize(resolution).unwrap();
+        assert!(Rect::from_resolution(resolution).contains_rect(&clipped.rect()));
+        assert!(clipped.resolution.cmplt(full.resolution).all());
+
+        // the same coverage, only fewer pixels of it
+        let alpha = |text: &RasterizedText| -> Vec<u8> {
+            text.to_canvas(resolution)
+                .data
+                .chunks(4)
+                .map(|c| c[3])
+                .collect()
+        };
+        assert_eq!(alpha(&clipped), alpha(&full));
+    }
+
+    #[test]
+    fn oversized_text_is_limited_to_the_canvas() {
+        let huge = TextLayer {
+            size: 1e9,
+            position: Vec2::new(-1e6, -100.0),
+            ..layer(&"W".repeat(10_000))
+        };
+        let text = huge.rasterize(CANVAS).unwrap();
+        assert!(Rect::from_resolution(CANVAS).contains_rect(&text.rect()));
+        assert_eq!(
+            text.data.len(),
+            4 * (text.resolution.x * text.resolution.y) as usize
+        );
+
+        let far_away = TextLayer {
+            position: Vec2::splat(1e30),
+            ..huge
+        };
+        assert_eq!(far_away.rasterize(CANVAS), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Compares compositing a small brush stroke with and without damage tracking
+//! on a large canvas.
+//!
+//! Run with `cargo bench -p paint-wgpu --bench damage`. Requires a GPU adapter
+//! supporting the features from [`paint_wgpu::get_required_wgpu_features()`].
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_lite::future::block_on;
+use glam::{UVec2, Vec2};
+use paint_core::behaviour::{
+    BrushEngine as _, BrushState, BrushStroke as _, Compositor as _, StrokeSettings,
+};
+use paint_core::color::BlendingSpace;
+use paint_core::geometry::{DamageRegion, Rect};
+use paint_core::persistence::TextureFormat;
+use paint_wgpu::{BrushEngine, Compositor, FrameContext, GlobalContext};
+
+const CANVAS_RESOLUTION: UVec2 = UVec2::new(8192, 8192);
+const ITERATIONS: u32 = 50;
+
+fn main() {
+    let Some(context) = create_context() else {
+        eprintln!("no suitable GPU adapter, skipping");
+        return;
+    };
+
+    let mut compositor = Compositor::new(
+        context.clone(),
+        CANVAS_RESOLUTION,
+        TextureFormat::Rgba8NonlinearSrgb,
+    );
+    let brush_engine = BrushEngine::new(context.clone());
+
+    let mut stroke = brush_engine.begin_stroke(&StrokeSettings {
+        canvas_resolution: CANVAS_RESOLUTION,
+        canvas_format: compositor.format(),
+        blending_space: BlendingSpace::LinearLight,
+    });
+
+    for i in 0..20 {
+        stroke.update(&BrushState {
+            position: Vec2::new(1000.0 + 10.0 * i as f32, 1000.0 + 5.0 * i as f32),
+            pressure: 0.5,
+        });
+    }
+
+    let mut ctx = FrameContext::new(&context);
+    let texture = stroke.render(&mut ctx);
+    submit(&context, ctx);
+
+    let damaged = stroke.damage().clone();
+    let full = DamageRegion::from_rect(Rect::from_resolution(CANVAS_RESOLUTION));
+
+    // warm up pipelines and clear the canvas
+    let mut ctx = FrameContext::new(&context);
+    compositor.put_texture(&mut ctx, texture.clone(), &full, BlendingSpace::LinearLight);
+    submit(&context, ctx);
+
+    let full_time = measure(&context, |ctx| {
+        compositor.put_texture(ctx, texture.clone(), &full, BlendingSpace::LinearLight)
+    });
+    let damaged_time = measure(&context, |ctx| {
+        compositor.put_texture(ctx, texture.clone(), &damaged, BlendingSpace::LinearLight)
+    });
+
+    println!(
+        "canvas {}x{}, stroke covers {:.2}% of it",
+        CANVAS_RESOLUTION.x,
+        CANVAS_RESOLUTION.y,
+        100.0 * damaged.area() / full.area()
+    );
+    println!("full canvas:  {full_time:?} per stroke");
+    println!("damage rects: {damaged_time:?} per stroke");
+}
+
+fn measure(context: &GlobalContext, mut f: impl FnMut(&mut FrameContext)) -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        let mut ctx = FrameContext::new(context);
+        f(&mut ctx);
+        submit(context, ctx);
+    }
+
+    start.elapsed() / ITERATIONS
+}
+
+fn submit(context: &GlobalContext, ctx: FrameContext) {
+    context.submit(ctx);
+    context
+        .device()
+        .poll(wgpu::PollType::wait_indefinitely())
+        .expect("device should not be lost");
+}
+
+fn create_context() -> Option<Arc<GlobalContext>> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = block_on(instance.request_adapter(&Default::default())).ok()?;
+
+    let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        required_features: paint_wgpu::get_required_wgpu_features(),
+        required_limits: paint_wgpu::get_required_wgpu_limits().using_resolution(adapter.limits()),
+        ..Default::default()
+    }))
+    .ok()?;
+
+    Some(Arc::new(GlobalContext::new(&adapter, device, queue, None)))
+}
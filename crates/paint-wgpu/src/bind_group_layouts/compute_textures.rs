@@ -0,0 +1,68 @@
+/// Creates a layout of textures, which compute passes only load from, so they
+/// don't have to be filterable, followed by a storage texture.
+pub fn create(
+    device: &wgpu::Device,
+    count: usize,
+    storage_format: wgpu::TextureFormat,
+) -> wgpu::BindGroupLayout {
+    let mut entries = Vec::with_capacity(count + 1);
+
+    for i in 0..count {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: i as u32,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        })
+    }
+
+    entries.push(wgpu::BindGroupLayoutEntry {
+        binding: count as u32,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access: wgpu::StorageTextureAccess::WriteOnly,
+            format: storage_format,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    });
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Compute Textures Bind Group Layout"),
+        entries: &entries,
+    })
+}
+
+pub fn create_bind_group(
+    device: &wgpu::Device,
+    bind_group_layouts: &super::Storage,
+    texture_views: &[&wgpu::TextureView],
+    storage_texture_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    let mut entries = Vec::with_capacity(texture_views.len() + 1);
+
+    for (i, texture_view) in texture_views.iter().enumerate() {
+        entries.push(wgpu::BindGroupEntry {
+            binding: i as u32,
+            resource: wgpu::BindingResource::TextureView(texture_view),
+        });
+    }
+
+    entries.push(wgpu::BindGroupEntry {
+        binding: texture_views.len() as u32,
+        resource: wgpu::BindingResource::TextureView(storage_texture_view),
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Compute Textures Bind Group"),
+        layout: &bind_group_layouts.get(super::Key::ComputeTextures {
+            num_texture_bindings: texture_views.len(),
+            storage_format: storage_texture_view.texture().format(),
+        }),
+        entries: &entries,
+    })
+}
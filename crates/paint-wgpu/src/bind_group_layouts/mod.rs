@@ -1,3 +1,4 @@
+pub mod compute_textures;
 pub mod sampled_textures;
 
 use std::collections::HashMap;
@@ -5,7 +6,14 @@ use std::sync::Mutex;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Key {
-    SampledTextures { num_texture_bindings: usize },
+    SampledTextures {
+        num_texture_bindings: usize,
+    },
+    /// Textures followed by a storage texture, for compute passes.
+    ComputeTextures {
+        num_texture_bindings: usize,
+        storage_format: wgpu::TextureFormat,
+    },
 }
 
 impl Key {
@@ -14,6 +22,10 @@ impl Key {
             Key::SampledTextures {
                 num_texture_bindings,
             } => self::sampled_textures::create(device, num_texture_bindings),
+            Key::ComputeTextures {
+                num_texture_bindings,
+                storage_format,
+            } => self::compute_textures::create(device, num_texture_bindings, storage_format),
         }
     }
 }
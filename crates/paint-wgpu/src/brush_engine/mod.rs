@@ -1,24 +1,42 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use glam::{Affine2, Vec2};
+use glam::{UVec2, Vec2, Vec3};
 use paint_core::behaviour::{BrushState, StrokeSettings};
+use paint_core::color::{BlendingSpace, LinearSrgb};
+use paint_core::geometry::{DamageRegion, Rect};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use wgpu::util::DeviceExt;
 use zerocopy::IntoBytes as _;
 
 use crate::context::{FrameContext, GlobalContext};
-use crate::render_pipelines;
-use crate::render_pipelines::stamped_brush::{Immediates, Instance};
-use crate::texture::Texture;
+use crate::render_pipelines::stamped_brush::{self, Immediates, Instance};
+use crate::resources::ring_buffer::RingBuffer;
+use crate::resources::texture_pool::{self, PooledTexture};
+use crate::texture::{self, Texture};
+use crate::{mipmaps, render_pipelines};
+
+/// Color of the brush, until brushes have a color setting.
+pub const BRUSH_COLOR: LinearSrgb = LinearSrgb::new(0.0, 0.0, 0.0);
 
 pub struct BrushEngine {
     context: Arc<GlobalContext>,
+    instance_buffer: Arc<Mutex<RingBuffer>>,
 }
 
 impl BrushEngine {
     pub fn new(context: Arc<GlobalContext>) -> Self {
-        Self { context }
+        let instance_buffer = Arc::new(Mutex::new(RingBuffer::new(
+            context.device.clone(),
+            context.queue.clone(),
+            "Brush Stroke Instance Buffer",
+            wgpu::BufferUsages::VERTEX,
+            64 * 1024,
+        )));
+
+        Self {
+            context,
+            instance_buffer,
+        }
     }
 }
 
@@ -26,55 +44,240 @@ impl paint_core::behaviour::BrushEngine for BrushEngine {
     type Stroke = BrushStroke;
 
     fn begin_stroke(&self, settings: &StrokeSettings) -> Self::Stroke {
-        BrushStroke::new(self.context.clone(), settings)
+        BrushStroke::new(&self.context, self.instance_buffer.clone(), settings)
     }
 }
 
-pub struct BrushStroke {
+/// Dabs of [`BRUSH_COLOR`], blended over each other in the blending space of
+/// the document, which brush strokes and stroked shapes are drawn with.
+///
+/// Dabs are accumulated premultiplied in the blending space first, and the
+/// accumulated colors are resolved into a texture of the canvas format.
+pub struct DabAccumulator {
     context: Arc<GlobalContext>,
     render_pipeline: wgpu::RenderPipeline,
-    preview_texture: wgpu::Texture,
+    resolve_pipeline: wgpu::RenderPipeline,
+    blending_space: BlendingSpace,
+    resolution: UVec2,
+    /// Only held to keep it out of the pool until the accumulator is dropped.
+    _texture: PooledTexture,
+    view: wgpu::TextureView,
+}
+
+impl DabAccumulator {
+    pub fn new(context: &Arc<GlobalContext>, settings: &StrokeSettings) -> Self {
+        let format = texture::wgpu_format(settings.canvas_format);
+
+        let render_pipeline = context
+            .render_pipelines
+            .get(render_pipelines::Key::StampedBrush);
+        let resolve_pipeline = context
+            .render_pipelines
+            .get(render_pipelines::Key::BlendResolve(format));
+
+        // only the base level is resolved, so there are no mipmaps
+        let texture = context.texture_pool.acquire(
+            "Dab Accumulation Texture",
+            texture_pool::Key {
+                width: settings.canvas_resolution.x,
+                height: settings.canvas_resolution.y,
+                mip_level_count: 1,
+                format: stamped_brush::ACCUMULATION_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        );
+        let view = texture.create_view(&Default::default());
+
+        Self {
+            context: context.clone(),
+            render_pipeline,
+            resolve_pipeline,
+            blending_space: settings.blending_space,
+            resolution: settings.canvas_resolution,
+            _texture: texture,
+            view,
+        }
+    }
+
+    /// Draws the dabs over the accumulated ones, which `load` may clear.
+    pub fn accumulate(
+        &self,
+        ctx: &mut FrameContext,
+        instance_buffer: &Mutex<RingBuffer>,
+        instances: &[Instance],
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let color = Vec3::from(self.blending_space.encode(BRUSH_COLOR)).extend(1.0);
+        let immediates = Immediates::new(self.resolution.as_vec2(), color);
+
+        let buffer = if instances.is_empty() {
+            None
+        } else {
+            let mut instance_buffer = instance_buffer.lock().unwrap();
+            Some(instance_buffer.write(ctx, instances.as_bytes()))
+        };
+
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        if let Some(buffer) = buffer {
+            pass.set_pipeline(&self.render_pipeline);
+            pass.set_immediates(0, immediates.as_bytes());
+            pass.set_vertex_buffer(0, buffer.slice());
+            pass.draw(0..6, 0..instances.len() as u32);
+        }
+    }
+
+    /// Resolves the accumulated colors within the damage into the view,
+    /// which `load` may clear.
+    pub fn resolve(
+        &self,
+        ctx: &mut FrameContext,
+        view: &wgpu::TextureView,
+        damage: &DamageRegion,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let bind_group = self
+            .context
+            .bind_group_cache
+            .get_sampled_textures(&self.context.default_sampler, &[&self.view]);
+        let immediates = render_pipelines::blend::Immediates::new(self.blending_space);
+
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        pass.set_pipeline(&self.resolve_pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_immediates(0, immediates.as_bytes());
+
+        for rect in damage.rects() {
+            let Some((offset, size)) = rect.to_pixels(self.resolution) else {
+                continue;
+            };
+
+            pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+            pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+/// A brush stroke, whose dabs are blended over each other in the blending
+/// space of the document, see [`DabAccumulator`].
+pub struct BrushStroke {
+    dabs: DabAccumulator,
+    instance_buffer: Arc<Mutex<RingBuffer>>,
+    preview_texture: PooledTexture,
+    preview_render_view: wgpu::TextureView,
     preview_texture_view: wgpu::TextureView,
     instances: Vec<Instance>,
     last_instance: Option<Instance>,
+    /// Area touched by the whole stroke.
+    damage: DamageRegion,
+    /// Area touched by instances which haven't been rendered yet.
+    pending_damage: DamageRegion,
     should_clear: bool,
     rng: SmallRng,
 }
 
 impl BrushStroke {
-    pub fn new(context: Arc<GlobalContext>, settings: &StrokeSettings) -> Self {
-        let render_pipeline = context
-            .render_pipelines
-            .get(render_pipelines::Key::StampedBrush);
+    pub fn new(
+        context: &Arc<GlobalContext>,
+        instance_buffer: Arc<Mutex<RingBuffer>>,
+        settings: &StrokeSettings,
+    ) -> Self {
+        let format = texture::wgpu_format(settings.canvas_format);
 
-        let preview_texture = context.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Brush Stroke Preview Texture"),
-            size: wgpu::Extent3d {
+        let preview_texture = context.texture_pool.acquire(
+            "Brush Stroke Preview Texture",
+            texture_pool::Key {
                 width: settings.canvas_resolution.x,
                 height: settings.canvas_resolution.y,
-                depth_or_array_layers: 1,
+                mip_level_count: mipmaps::mip_level_count(
+                    settings.canvas_resolution.x,
+                    settings.canvas_resolution.y,
+                ),
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
             },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
+        );
 
+        // rendering is only possible into a single mip level
+        let preview_render_view = preview_texture.create_view(&wgpu::TextureViewDescriptor {
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
         let preview_texture_view = preview_texture.create_view(&Default::default());
 
         Self {
-            context,
-            render_pipeline,
+            dabs: DabAccumulator::new(context, settings),
+            instance_buffer,
             preview_texture,
+            preview_render_view,
             preview_texture_view,
             instances: Vec::new(),
             last_instance: None,
+            damage: DamageRegion::new(),
+            pending_damage: DamageRegion::new(),
             should_clear: true,
             rng: SmallRng::from_os_rng(),
         }
     }
+
+    fn push_instance(&mut self, instance: Instance) {
+        // matches the quad size in the shader, plus a pixel for antialiasing
+        let half_size = Vec2::splat(instance.radius + 2.0);
+        let rect = Rect::from_center_half_size(instance.pos, half_size);
+        self.damage.add(rect);
+        self.pending_damage.add(rect);
+        self.instances.push(instance);
+    }
+
+    fn clear_mip_levels(&self, ctx: &mut FrameContext) {
+        for level in 1..self.preview_texture.mip_level_count() {
+            let view = self
+                .preview_texture
+                .create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                });
+
+            ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+        }
+    }
 }
 
 impl paint_core::behaviour::BrushStroke for BrushStroke {
@@ -98,7 +301,7 @@ impl paint_core::behaviour::BrushStroke for BrushStroke {
                     let jitter_x = self.rng.random_range(-1.0..1.0) * 0.5;
                     let jitter_y = self.rng.random_range(-1.0..1.0) * 0.5;
                     let pos = pos + Vec2::new(jitter_x, jitter_y);
-                    self.instances.push(Instance { pos, radius });
+                    self.push_instance(Instance { pos, radius });
                     dist_along_dir += spacing;
                 }
             }
@@ -107,65 +310,105 @@ impl paint_core::behaviour::BrushStroke for BrushStroke {
         let pos = state.position;
         let instance = Instance { pos, radius };
 
-        self.instances.push(instance);
+        self.push_instance(instance);
         self.last_instance = Some(instance);
     }
 
-    fn render(&mut self, ctx: &mut FrameContext) -> Texture {
-        let resolution = self.preview_texture.size();
-        let resolution = Vec2::new(resolution.width as f32, resolution.height as f32);
-
-        let pixel_to_ndc = Affine2::from_translation(Vec2::new(-1.0, 1.0))
-            * Affine2::from_scale(Vec2::new(2.0, -2.0) / resolution);
+    fn damage(&self) -> &DamageRegion {
+        &self.damage
+    }
 
-        let immediates = Immediates {
-            transform: pixel_to_ndc.matrix2,
-            translation: pixel_to_ndc.translation,
-        };
+    fn render(&mut self, ctx: &mut FrameContext) -> Texture {
+        if self.should_clear {
+            // the pooled texture may contain an older stroke in any mip level
+            self.clear_mip_levels(ctx);
+        }
 
-        let buffer = if self.instances.is_empty() {
-            None
+        let load = if self.should_clear {
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
         } else {
-            let buffer =
-                self.context
-                    .device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: None,
-                        contents: self.instances.as_slice().as_bytes(),
-                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-                    });
-            Some(buffer)
+            wgpu::LoadOp::Load
         };
 
-        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.preview_texture_view,
-                depth_slice: None,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: if self.should_clear {
-                        wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
-                    } else {
-                        wgpu::LoadOp::Load
-                    },
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            ..Default::default()
-        });
-
-        if let Some(buffer) = buffer {
-            pass.set_pipeline(&self.render_pipeline);
-            pass.set_immediates(0, immediates.as_bytes());
-            pass.set_vertex_buffer(0, buffer.slice(..));
-            pass.draw(0..6, 0..self.instances.len() as u32);
-        }
+        self.dabs
+            .accumulate(ctx, &self.instance_buffer, &self.instances, load);
+        self.dabs
+            .resolve(ctx, &self.preview_render_view, &self.pending_damage, load);
 
-        drop(pass);
+        ctx.mipmap_generator.generate_region(
+            &mut ctx.encoder,
+            &self.preview_texture,
+            &self.pending_damage,
+        );
 
         self.instances.clear();
+        self.pending_damage.clear();
         self.should_clear = false;
 
         Texture(self.preview_texture_view.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use paint_core::behaviour::{
+        BrushEngine as _, BrushStroke as _, Compositor as _, ShapeDrawing as _, ShapeEngine as _,
+    };
+    use paint_core::persistence;
+    use paint_core::shape::{Shape, ShapeKind, ShapeSettings, ShapeStyle};
+
+    use super::*;
+    use crate::utils::testing;
+    use crate::{Compositor, ShapeEngine};
+
+    #[test]
+    fn stroked_line_matches_brush_stroke() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(32, 24);
+        let line = Shape {
+            settings: ShapeSettings {
+                kind: ShapeKind::Line,
+                style: ShapeStyle::Stroke { width: 4.0 },
+            },
+            start: Vec2::new(3.5, 4.0),
+            end: Vec2::new(27.0, 19.5),
+        };
+
+        for blending_space in BlendingSpace::ALL {
+            let settings = StrokeSettings {
+                canvas_resolution: resolution,
+                canvas_format: persistence::TextureFormat::Rgba8NonlinearSrgb,
+                blending_space,
+            };
+            let mut compositor =
+                Compositor::new(context.clone(), resolution, settings.canvas_format);
+
+            // the brush stamps the dabs of the line, without any jitter
+            let mut stroke = BrushEngine::new(context.clone()).begin_stroke(&settings);
+            for pos in line.dabs() {
+                stroke.push_instance(Instance { pos, radius: 4.0 });
+            }
+
+            let mut drawing = ShapeEngine::new(context.clone()).begin_drawing(&settings);
+            drawing.set_shape(&line);
+
+            let mut ctx = FrameContext::new(&context);
+            let texture = stroke.render(&mut ctx);
+            let brush =
+                compositor.preview_texture(&mut ctx, texture, stroke.damage(), blending_space);
+            context.submit(ctx);
+            let brush = testing::download(&context, &brush);
+
+            let mut ctx = FrameContext::new(&context);
+            let texture = drawing.render(&mut ctx);
+            let shape =
+                compositor.preview_texture(&mut ctx, texture, drawing.damage(), blending_space);
+            context.submit(ctx);
+            let shape = testing::download(&context, &shape);
+
+            assert!(brush.chunks(4).any(|c| c[0] == 0), "{blending_space:?}");
+            assert_eq!(shape, brush, "{blending_space:?}");
+        }
+    }
+}
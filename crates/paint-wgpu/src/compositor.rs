@@ -1,96 +1,1325 @@
 use std::sync::Arc;
 
-use glam::{Affine2, Vec2};
+use glam::UVec2;
+use half::f16;
+use paint_core::adjustment::{Adjustment, AdjustmentLayer, Curves};
+use paint_core::color::BlendingSpace;
+use paint_core::filter::Filter;
+use paint_core::geometry::{DamageRegion, Rect};
+use paint_core::persistence;
 use zerocopy::IntoBytes as _;
 
-use crate::{FrameContext, GlobalContext, Texture, bind_group_layouts, render_pipelines};
+use crate::compute_pipelines::WORKGROUP_SIZE;
+use crate::compute_pipelines::filter::{Immediates as FilterImmediates, Pass};
+use crate::compute_pipelines::liquify::ResampleImmediates;
+use crate::resources::texture_pool;
+use crate::utils::copy_region;
+use crate::{
+    FrameContext, GlobalContext, Texture, bind_group_layouts, compute_pipelines, mipmaps,
+    render_pipelines, texture,
+};
 
+/// Number of samples in the curve lookup tables of curves adjustments, must
+/// match `CURVE_SAMPLES` in the shader.
+const CURVE_SAMPLES: u32 = 256;
+
+/// Composites textures onto the canvas.
+///
+/// Blending reads the canvas while writing the result, which isn't possible
+/// within a single texture. So textures are always blended into the preview
+/// texture first, and the result is copied back to the canvas on commit.
+///
+/// Filters and liquify strokes are previewed the same way, with the filtered
+/// or resampled canvas in the preview texture.
+///
+/// Adjustment layers are applied on top, into a separate texture, so the
+/// canvas keeps the painted colors.
 pub struct Compositor {
     context: Arc<GlobalContext>,
     pipeline: wgpu::RenderPipeline,
-    canvas_texture_view: wgpu::TextureView,
+    canvas: CanvasTexture,
+    preview: CanvasTexture,
+    /// Area where the preview differs from the canvas.
+    preview_divergence: DamageRegion,
+    /// Filter which the preview shows, if any.
+    previewed_filter: Option<Filter>,
+    resolution: UVec2,
+    format: persistence::TextureFormat,
     should_clear: bool,
+    /// Active adjustment layers, from bottom to top.
+    adjustments: Vec<AdjustmentPass>,
+    /// The canvas or the preview with the adjustments applied, allocated on
+    /// first use.
+    adjusted: Option<CanvasTexture>,
+    /// Area where the adjusted texture doesn't show the adjusted canvas.
+    adjusted_divergence: DamageRegion,
 }
 
-impl Compositor {
-    pub fn new(context: Arc<GlobalContext>) -> Self {
-        let pipeline = context
-            .render_pipelines
-            .get(render_pipelines::Key::SingleQuad);
+/// An adjustment layer, with the lookup table of its curves.
+struct AdjustmentPass {
+    layer: AdjustmentLayer,
+    curves: Option<wgpu::TextureView>,
+}
 
-        let canvas_texture = context.device.create_texture(&wgpu::TextureDescriptor {
-            label: None,
+struct CanvasTexture {
+    texture: wgpu::Texture,
+    render_view: wgpu::TextureView,
+    texture_view: wgpu::TextureView,
+}
+
+impl CanvasTexture {
+    fn new(
+        device: &wgpu::Device,
+        label: &str,
+        resolution: UVec2,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
             size: wgpu::Extent3d {
-                width: 2304,
-                height: 1440,
+                width: resolution.x,
+                height: resolution.y,
                 depth_or_array_layers: 1,
             },
-            mip_level_count: 1,
+            mip_level_count: mipmaps::mip_level_count(resolution.x, resolution.y),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::COPY_SRC,
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
 
-        let canvas_texture_view = canvas_texture.create_view(&Default::default());
+        // rendering is only possible into a single mip level
+        let render_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
+        let texture_view = texture.create_view(&Default::default());
+
+        Self {
+            texture,
+            render_view,
+            texture_view,
+        }
+    }
+
+    fn clear(&self, ctx: &mut FrameContext, color: wgpu::Color) {
+        for level in 0..self.texture.mip_level_count() {
+            let view = self.texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            });
+
+            ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+        }
+    }
+}
+
+impl Compositor {
+    /// Creates a compositor with a blank canvas.
+    ///
+    /// The format must be supported by the device, see
+    /// [`is_canvas_format_supported()`](crate::is_canvas_format_supported).
+    pub fn new(
+        context: Arc<GlobalContext>,
+        resolution: UVec2,
+        format: persistence::TextureFormat,
+    ) -> Self {
+        let wgpu_format = texture::wgpu_format(format);
+
+        let pipeline = context
+            .render_pipelines
+            .get(render_pipelines::Key::Blend(wgpu_format));
+
+        let canvas = CanvasTexture::new(&context.device, "Canvas", resolution, wgpu_format);
+        let preview =
+            CanvasTexture::new(&context.device, "Canvas Preview", resolution, wgpu_format);
 
         Self {
             context,
             pipeline,
-            canvas_texture_view,
+            canvas,
+            preview,
+            preview_divergence: DamageRegion::new(),
+            previewed_filter: None,
+            resolution,
+            format,
             should_clear: true,
+            adjustments: Vec::new(),
+            adjusted: None,
+            adjusted_divergence: DamageRegion::new(),
         }
     }
-}
 
-impl paint_core::behaviour::Compositor for Compositor {
-    type Texture = Texture;
-    type Context = FrameContext;
+    fn clear_if_needed(&mut self, ctx: &mut FrameContext) {
+        if self.should_clear {
+            self.canvas.clear(ctx, wgpu::Color::WHITE);
+            self.preview.clear(ctx, wgpu::Color::WHITE);
+            self.preview_divergence.clear();
+            self.previewed_filter = None;
+            self.invalidate_adjusted();
+            self.should_clear = false;
+        }
+    }
+
+    fn invalidate_adjusted(&mut self) {
+        self.adjusted_divergence = DamageRegion::from_rect(Rect::from_resolution(self.resolution));
+    }
+
+    /// Makes the preview show the canvas with the texture on top.
+    ///
+    /// Returns the area of the preview which has changed.
+    fn update_preview(
+        &mut self,
+        ctx: &mut FrameContext,
+        texture: &Texture,
+        damage: &DamageRegion,
+        blending_space: BlendingSpace,
+    ) -> DamageRegion {
+        self.clear_if_needed(ctx);
+
+        // restore the areas of the previous preview, which the new damage
+        // doesn't necessarily cover
+        copy_region(
+            &mut ctx.encoder,
+            &self.canvas.texture,
+            &self.preview.texture,
+            &self.preview_divergence,
+            self.resolution,
+        );
 
-    fn put_texture(&mut self, ctx: &mut Self::Context, texture: Self::Texture) {
-        let transform = Affine2::from_translation(Vec2::new(-1.0, 1.0))
-            * Affine2::from_scale(Vec2::new(2.0, -2.0));
+        let bind_group = self.context.bind_group_cache.get_sampled_textures(
+            &self.context.default_sampler,
+            &[&texture.0, &self.canvas.texture_view],
+        );
+
+        let immediates = render_pipelines::blend::Immediates::new(blending_space);
+
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.preview.render_view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_immediates(0, immediates.as_bytes());
+
+        for rect in damage.rects() {
+            let Some((offset, size)) = rect.to_pixels(self.resolution) else {
+                continue;
+            };
 
-        let immediates = render_pipelines::single_quad::Immediates {
-            transform: transform.matrix2,
-            translation: transform.translation,
+            pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+            pass.draw(0..3, 0..1);
+        }
+
+        drop(pass);
+
+        self.previewed_filter = None;
+        let mut changed = std::mem::replace(&mut self.preview_divergence, damage.clone());
+        changed.add_region(damage);
+
+        ctx.mipmap_generator
+            .generate_region(&mut ctx.encoder, &self.preview.texture, &changed);
+
+        changed
+    }
+
+    /// Makes the preview show the filtered canvas, unless it already does.
+    ///
+    /// Returns the area of the preview which has changed.
+    fn update_filter_preview(&mut self, ctx: &mut FrameContext, filter: &Filter) -> DamageRegion {
+        self.clear_if_needed(ctx);
+
+        if self.previewed_filter.as_ref() == Some(filter) {
+            return DamageRegion::new();
+        }
+
+        let key = texture_pool::Key {
+            width: self.resolution.x,
+            height: self.resolution.y,
+            mip_level_count: 1,
+            format: compute_pipelines::filter::FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
         };
 
+        // passes read what the previous one wrote, so after premultiplying,
+        // they alternate between two textures
+        let pool = &self.context.texture_pool;
+        let original = pool.acquire("Filter Texture", key);
+        let intermediates = [
+            pool.acquire("Filter Texture", key),
+            pool.acquire("Filter Texture", key),
+        ];
+        let original = original.create_view(&Default::default());
+        let intermediates = intermediates.map(|texture| texture.create_view(&Default::default()));
+
+        let blur_passes = filter.blur_passes();
+
+        let mut passes = vec![(
+            Pass::Premultiply,
+            &self.canvas.texture_view,
+            &original,
+            FilterImmediates::default(),
+        )];
+        let mut input = &original;
+        for (i, blur_pass) in blur_passes.iter().enumerate() {
+            let output = &intermediates[i % 2];
+            passes.push((Pass::Blur, input, output, FilterImmediates::blur(blur_pass)));
+            input = output;
+        }
+        let output = &intermediates[blur_passes.len() % 2];
+        passes.push((
+            Pass::Finish,
+            input,
+            output,
+            FilterImmediates::finish(filter),
+        ));
+
+        let workgroups = (self.resolution + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+        let mut compute_pass = ctx.encoder.begin_compute_pass(&Default::default());
+
+        for (pass, input, pass_output, immediates) in passes {
+            let pipeline = self
+                .context
+                .compute_pipelines
+                .get(compute_pipelines::Key::Filter(pass));
+
+            // the original is only read once it's written
+            let original = match pass {
+                Pass::Premultiply => &self.context.default_texture_view,
+                Pass::Blur | Pass::Finish => &original,
+            };
+
+            // the pooled views are new every time, so the bind groups
+            // wouldn't be reused from the cache
+            let bind_group = bind_group_layouts::compute_textures::create_bind_group(
+                &self.context.device,
+                &self.context.bind_group_layouts,
+                &[input, original],
+                pass_output,
+            );
+
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.set_immediates(0, immediates.as_bytes());
+            compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        drop(compute_pass);
+
+        let full = DamageRegion::from_rect(Rect::from_resolution(self.resolution));
+        self.draw_into_preview(ctx, output, &full);
+
+        ctx.mipmap_generator
+            .generate(&mut ctx.encoder, &self.preview.texture);
+
+        self.previewed_filter = Some(*filter);
+        self.preview_divergence = full.clone();
+        full
+    }
+
+    /// Makes the preview show the canvas resampled through the displacement
+    /// field within the damage.
+    ///
+    /// Returns the area of the preview which has changed.
+    fn update_displacement_preview(
+        &mut self,
+        ctx: &mut FrameContext,
+        field: &Texture,
+        damage: &DamageRegion,
+    ) -> DamageRegion {
+        self.clear_if_needed(ctx);
+
+        copy_region(
+            &mut ctx.encoder,
+            &self.canvas.texture,
+            &self.preview.texture,
+            &self.preview_divergence,
+            self.resolution,
+        );
+
+        let resampled = self.context.texture_pool.acquire(
+            "Resampled Canvas Texture",
+            texture_pool::Key {
+                width: self.resolution.x,
+                height: self.resolution.y,
+                mip_level_count: 1,
+                format: compute_pipelines::liquify::RESAMPLED_FORMAT,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        );
+        let resampled = resampled.create_view(&Default::default());
+
+        let pipeline = self
+            .context
+            .compute_pipelines
+            .get(compute_pipelines::Key::LiquifyResample);
+
+        // the field is a new texture for every stroke, so caching would only
+        // evict useful bind groups
+        let bind_group = bind_group_layouts::compute_textures::create_bind_group(
+            &self.context.device,
+            &self.context.bind_group_layouts,
+            &[&field.0, &self.canvas.texture_view],
+            &resampled,
+        );
+
+        let mut compute_pass = ctx.encoder.begin_compute_pass(&Default::default());
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+
+        for rect in damage.rects() {
+            let Some((origin, size)) = rect.to_pixels(self.resolution) else {
+                continue;
+            };
+
+            let immediates = ResampleImmediates { origin };
+            let workgroups = (size + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+            compute_pass.set_immediates(0, immediates.as_bytes());
+            compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        drop(compute_pass);
+
+        self.draw_into_preview(ctx, &resampled, damage);
+
+        self.previewed_filter = None;
+        let mut changed = std::mem::replace(&mut self.preview_divergence, damage.clone());
+        changed.add_region(damage);
+
+        ctx.mipmap_generator
+            .generate_region(&mut ctx.encoder, &self.preview.texture, &changed);
+
+        changed
+    }
+
+    /// Draws a texture of the canvas size into the preview, within the
+    /// region.
+    ///
+    /// Storage textures can't have the canvas formats, so this is how the
+    /// results of compute passes get into the preview.
+    fn draw_into_preview(
+        &self,
+        ctx: &mut FrameContext,
+        source: &wgpu::TextureView,
+        region: &DamageRegion,
+    ) {
         let bind_group = bind_group_layouts::sampled_textures::create_bind_group(
             &self.context.device,
             &self.context.bind_group_layouts,
-            &self.context.default_sampler,
-            &[&texture.0],
+            &self.context.nearest_sampler,
+            &[source],
         );
 
+        let pipeline =
+            self.context
+                .render_pipelines
+                .get(render_pipelines::Key::FullscreenTriangle(
+                    texture::wgpu_format(self.format),
+                ));
+
         let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.canvas_texture_view,
+                view: &self.preview.render_view,
                 depth_slice: None,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: if self.should_clear {
-                        self.should_clear = false;
-                        wgpu::LoadOp::Clear(wgpu::Color::WHITE)
-                    } else {
-                        wgpu::LoadOp::Load
-                    },
+                    load: wgpu::LoadOp::Load,
                     store: wgpu::StoreOp::Store,
                 },
             })],
             ..Default::default()
         });
 
-        pass.set_pipeline(&self.pipeline);
+        pass.set_pipeline(&pipeline);
         pass.set_bind_group(0, &bind_group, &[]);
-        pass.set_immediates(0, immediates.as_bytes());
-        pass.draw(0..6, 0..1);
+
+        for rect in region.rects() {
+            let Some((offset, size)) = rect.to_pixels(self.resolution) else {
+                continue;
+            };
+
+            pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+            pass.draw(0..3, 0..1);
+        }
     }
 
-    fn render(&mut self, _ctx: &mut Self::Context) -> Self::Texture {
-        Texture(self.canvas_texture_view.clone())
+    /// Copies the preview to the canvas within the region, which must cover
+    /// the whole divergence.
+    fn commit_preview(&mut self, ctx: &mut FrameContext, region: &DamageRegion) {
+        copy_region(
+            &mut ctx.encoder,
+            &self.preview.texture,
+            &self.canvas.texture,
+            region,
+            self.resolution,
+        );
+
+        ctx.mipmap_generator
+            .generate_region(&mut ctx.encoder, &self.canvas.texture, region);
+
+        self.preview_divergence.clear();
+        self.previewed_filter = None;
+        self.adjusted_divergence.add_region(region);
+    }
+
+    /// Returns the preview, with the adjustments applied.
+    fn render_preview(&mut self, ctx: &mut FrameContext, changed: &DamageRegion) -> Texture {
+        if self.adjustments.is_empty() {
+            return Texture(self.preview.texture_view.clone());
+        }
+
+        // outside of the divergence, the preview shows the canvas, so once
+        // the changed area is adjusted, the adjusted texture shows the
+        // adjusted preview everywhere
+        let mut region = std::mem::take(&mut self.adjusted_divergence);
+        region.add_region(changed);
+        let adjusted = self.adjust(ctx, true, &region);
+        self.adjusted_divergence = self.preview_divergence.clone();
+
+        adjusted
+    }
+
+    /// Applies the adjustments to the canvas, or the preview, within the
+    /// region, and returns the adjusted texture.
+    fn adjust(&mut self, ctx: &mut FrameContext, preview: bool, region: &DamageRegion) -> Texture {
+        let adjusted = self.adjusted.get_or_insert_with(|| {
+            CanvasTexture::new(
+                &self.context.device,
+                "Adjusted Canvas",
+                self.resolution,
+                texture::wgpu_format(self.format),
+            )
+        });
+
+        let source = if preview {
+            &self.preview.texture_view
+        } else {
+            &self.canvas.texture_view
+        };
+
+        let wgpu_format = texture::wgpu_format(self.format);
+
+        // adjustments read what the previous one wrote, so they alternate
+        // between two textures, ending with the adjusted texture
+        let intermediate = (self.adjustments.len() > 1).then(|| {
+            let texture = self.context.texture_pool.acquire(
+                "Adjustment Intermediate Texture",
+                texture_pool::Key {
+                    width: self.resolution.x,
+                    height: self.resolution.y,
+                    mip_level_count: 1,
+                    format: wgpu_format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                },
+            );
+            let view = texture.create_view(&Default::default());
+            (texture, view)
+        });
+
+        let pipeline = self
+            .context
+            .render_pipelines
+            .get(render_pipelines::Key::Adjustment(wgpu_format));
+
+        let mut input = source;
+        for (i, adjustment) in self.adjustments.iter().enumerate() {
+            let output = match &intermediate {
+                Some((_, view)) if !(self.adjustments.len() - 1 - i).is_multiple_of(2) => view,
+                _ => &adjusted.render_view,
+            };
+
+            // intermediate views are new every time, so the bind groups
+            // wouldn't be reused from the cache
+            let curves = adjustment
+                .curves
+                .as_ref()
+                .unwrap_or(&self.context.default_texture_view);
+            let bind_group = bind_group_layouts::sampled_textures::create_bind_group(
+                &self.context.device,
+                &self.context.bind_group_layouts,
+                &self.context.default_sampler,
+                &[input, curves],
+            );
+
+            let immediates = render_pipelines::adjustment::Immediates::new(&adjustment.layer);
+
+            let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_immediates(0, immediates.as_bytes());
+
+            for rect in region.rects() {
+                let Some((offset, size)) = rect.to_pixels(self.resolution) else {
+                    continue;
+                };
+
+                pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+                pass.draw(0..3, 0..1);
+            }
+
+            input = output;
+        }
+
+        ctx.mipmap_generator
+            .generate_region(&mut ctx.encoder, &adjusted.texture, region);
+
+        Texture(adjusted.texture_view.clone())
+    }
+}
+
+/// Creates the lookup table of the curves, holding the red, green, blue and
+/// lightness curves in the RGBA channels.
+fn create_curves_lut(ctx: &FrameContext, curves: &Curves) -> wgpu::TextureView {
+    let size = wgpu::Extent3d {
+        width: CURVE_SAMPLES,
+        height: 1,
+        depth_or_array_layers: 1,
+    };
+
+    let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Curves Lookup Table"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    let data: Vec<u16> = (0..CURVE_SAMPLES)
+        .flat_map(|i| {
+            let x = i as f32 / (CURVE_SAMPLES - 1) as f32;
+            [&curves.red, &curves.green, &curves.blue, &curves.lightness]
+                .map(|curve| f16::from_f32(curve.evaluate(x)).to_bits())
+        })
+        .collect();
+
+    ctx.queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data.as_bytes(),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(CURVE_SAMPLES * 8),
+            rows_per_image: Some(1),
+        },
+        size,
+    );
+
+    texture.create_view(&Default::default())
+}
+
+impl paint_core::behaviour::Compositor for Compositor {
+    type Texture = Texture;
+    type Context = FrameContext;
+
+    fn put_texture(
+        &mut self,
+        ctx: &mut Self::Context,
+        texture: Self::Texture,
+        damage: &DamageRegion,
+        blending_space: BlendingSpace,
+    ) {
+        self.update_preview(ctx, &texture, damage, blending_space);
+        self.commit_preview(ctx, damage);
+    }
+
+    fn preview_texture(
+        &mut self,
+        ctx: &mut Self::Context,
+        texture: Self::Texture,
+        damage: &DamageRegion,
+        blending_space: BlendingSpace,
+    ) -> Self::Texture {
+        let changed = self.update_preview(ctx, &texture, damage, blending_space);
+        self.render_preview(ctx, &changed)
+    }
+
+    fn put_displacement(
+        &mut self,
+        ctx: &mut Self::Context,
+        field: Self::Texture,
+        damage: &DamageRegion,
+    ) {
+        self.update_displacement_preview(ctx, &field, damage);
+        self.commit_preview(ctx, damage);
+    }
+
+    fn preview_displacement(
+        &mut self,
+        ctx: &mut Self::Context,
+        field: Self::Texture,
+        damage: &DamageRegion,
+    ) -> Self::Texture {
+        let changed = self.update_displacement_preview(ctx, &field, damage);
+        self.render_preview(ctx, &changed)
+    }
+
+    fn set_adjustment_layers(&mut self, ctx: &mut Self::Context, layers: &[AdjustmentLayer]) {
+        self.adjustments = layers
+            .iter()
+            .filter(|layer| layer.is_active())
+            .map(|layer| AdjustmentPass {
+                layer: layer.clone(),
+                curves: match &layer.adjustment {
+                    Adjustment::Curves(curves) => Some(create_curves_lut(ctx, curves)),
+                    _ => None,
+                },
+            })
+            .collect();
+
+        self.invalidate_adjusted();
+    }
+
+    fn preview_filter(&mut self, ctx: &mut Self::Context, filter: &Filter) -> Self::Texture {
+        let changed = self.update_filter_preview(ctx, filter);
+        self.render_preview(ctx, &changed)
+    }
+
+    fn apply_filter(&mut self, ctx: &mut Self::Context, filter: &Filter) {
+        self.update_filter_preview(ctx, filter);
+
+        let region = self.preview_divergence.clone();
+        self.commit_preview(ctx, &region);
+    }
+
+    fn format(&self) -> persistence::TextureFormat {
+        self.format
+    }
+
+    fn is_format_supported(&self, format: persistence::TextureFormat) -> bool {
+        self.context.is_canvas_format_supported(format)
+    }
+
+    fn set_format(&mut self, ctx: &mut Self::Context, format: persistence::TextureFormat) {
+        if format == self.format {
+            return;
+        }
+
+        let wgpu_format = texture::wgpu_format(format);
+        let device = &self.context.device;
+
+        let previous = std::mem::replace(
+            &mut self.canvas,
+            CanvasTexture::new(device, "Canvas", self.resolution, wgpu_format),
+        );
+        self.preview = CanvasTexture::new(device, "Canvas Preview", self.resolution, wgpu_format);
+        self.pipeline = self
+            .context
+            .render_pipelines
+            .get(render_pipelines::Key::Blend(wgpu_format));
+        self.format = format;
+
+        self.preview_divergence.clear();
+        self.previewed_filter = None;
+        self.adjusted = None;
+        self.invalidate_adjusted();
+
+        // a blank canvas is cleared in the new format anyway
+        if self.should_clear {
+            return;
+        }
+
+        // drawing converts the colors, unlike copying
+        let full = DamageRegion::from_rect(Rect::from_resolution(self.resolution));
+        self.draw_into_preview(ctx, &previous.texture_view, &full);
+        self.commit_preview(ctx, &full);
+        ctx.mipmap_generator
+            .generate(&mut ctx.encoder, &self.preview.texture);
+    }
+
+    fn render(&mut self, ctx: &mut Self::Context) -> Self::Texture {
+        self.clear_if_needed(ctx);
+
+        if self.adjustments.is_empty() {
+            return Texture(self.canvas.texture_view.clone());
+        }
+
+        let region = std::mem::take(&mut self.adjusted_divergence);
+        self.adjust(ctx, false, &region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec3};
+    use paint_core::adjustment::{Curve, Levels};
+    use paint_core::behaviour::{
+        BrushEngine as _, BrushState, BrushStroke as _, Compositor as _, LiquifyEngine as _,
+        ShapeDrawing as _, ShapeEngine as _, StrokeSettings, Texture as _,
+    };
+    use paint_core::color::{Color, NonlinearSrgb, WithAlpha};
+    use paint_core::liquify::{DisplacementField, LiquifyMode, LiquifySettings};
+    use paint_core::shape::{Shape, ShapeKind, ShapeSettings, ShapeStyle};
+
+    use super::*;
+    use crate::utils::testing;
+    use crate::{BrushEngine, LiquifyEngine, ShapeEngine};
+
+    /// Largest allowed difference of an 8-bit component, as the GPU computes
+    /// in `f32` while the reference uses `f64` intermediates.
+    const TOLERANCE: u8 = 2;
+
+    fn download(context: &Arc<GlobalContext>, texture: &Texture) -> Vec<NonlinearSrgb<u8>> {
+        testing::download(context, texture)
+            .chunks(4)
+            .map(|c| NonlinearSrgb::new(c[0], c[1], c[2]))
+            .collect()
+    }
+
+    fn assert_close(actual: &[NonlinearSrgb<u8>], expected: &[NonlinearSrgb<u8>], what: &str) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                [(a.r, e.r), (a.g, e.g), (a.b, e.b)]
+                    .iter()
+                    .all(|(a, e)| a.abs_diff(*e) <= TOLERANCE),
+                "{what}: {a:?} != {e:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn gpu_filters_match_cpu_reference() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(16, 8);
+        let colors: Vec<NonlinearSrgb<u8>> = (0..128u8)
+            .map(|i| NonlinearSrgb::new(i % 3 * 120, i / 4 % 4 * 85, i.wrapping_mul(37)))
+            .collect();
+
+        let mut compositor = Compositor::new(
+            context.clone(),
+            resolution,
+            persistence::TextureFormat::Rgba8NonlinearSrgb,
+        );
+
+        let texture = testing::upload(&context, resolution, &colors);
+        let mut ctx = FrameContext::new(&context);
+        compositor.put_texture(
+            &mut ctx,
+            texture,
+            &DamageRegion::from_rect(Rect::from_resolution(resolution)),
+            BlendingSpace::LinearLight,
+        );
+        context.submit(ctx);
+
+        let filters = [
+            Filter::GaussianBlur { radius: 2.5 },
+            // clamped to the largest radius
+            Filter::GaussianBlur { radius: 1e9 },
+            Filter::BoxBlur { radius: 1 },
+            Filter::MotionBlur {
+                angle: 0.5,
+                length: 5.0,
+            },
+            Filter::UnsharpMask {
+                radius: 2.0,
+                amount: 0.8,
+                threshold: 0.02,
+            },
+            Filter::Noise {
+                amount: 0.1,
+                seed: 7,
+                monochrome: false,
+            },
+            Filter::Noise {
+                amount: 0.1,
+                seed: 7,
+                monochrome: true,
+            },
+        ];
+
+        let mut canvas = colors;
+        for filter in filters {
+            let mut ctx = FrameContext::new(&context);
+            let preview = compositor.preview_filter(&mut ctx, &filter);
+            context.submit(ctx);
+
+            let pixels: Vec<_> = canvas
+                .iter()
+                .map(|c| WithAlpha {
+                    color: c.to_linear_srgb(),
+                    alpha: 1.0,
+                })
+                .collect();
+            let expected: Vec<NonlinearSrgb<u8>> = filter
+                .apply(resolution, &pixels)
+                .iter()
+                .map(|c| NonlinearSrgb::from_linear_srgb(c.color))
+                .collect();
+
+            let what = format!("{filter:?}");
+            assert_close(&download(&context, &preview), &expected, &what);
+
+            // the canvas is only changed on commit
+            let mut ctx = FrameContext::new(&context);
+            let rendered = compositor.render(&mut ctx);
+            context.submit(ctx);
+            assert_eq!(download(&context, &rendered), canvas, "{what}");
+
+            let mut ctx = FrameContext::new(&context);
+            compositor.apply_filter(&mut ctx, &filter);
+            let rendered = compositor.render(&mut ctx);
+            context.submit(ctx);
+
+            canvas = download(&context, &rendered);
+            assert_close(&canvas, &expected, &what);
+        }
+    }
+
+    #[test]
+    fn gpu_liquify_matches_cpu_reference() {
+        let context = testing::create_context();
+
+        // gradients, as offsets would amplify differences at sharp edges
+        let resolution = UVec2::new(32, 32);
+        let colors: Vec<NonlinearSrgb<u8>> = (0..32 * 32u32)
+            .map(|i| NonlinearSrgb::new((i % 32 * 8) as u8, (i / 32 * 8) as u8, 128))
+            .collect();
+
+        let mut compositor = Compositor::new(
+            context.clone(),
+            resolution,
+            persistence::TextureFormat::Rgba8NonlinearSrgb,
+        );
+
+        let texture = testing::upload(&context, resolution, &colors);
+        let mut ctx = FrameContext::new(&context);
+        compositor.put_texture(
+            &mut ctx,
+            texture,
+            &DamageRegion::from_rect(Rect::from_resolution(resolution)),
+            BlendingSpace::LinearLight,
+        );
+        context.submit(ctx);
+
+        let engine = LiquifyEngine::new(context.clone());
+        let states = [
+            (Vec2::new(8.0, 16.0), 1.0),
+            (Vec2::new(13.0, 15.0), 0.8),
+            (Vec2::new(20.0, 17.5), 0.6),
+        ]
+        .map(|(position, pressure)| BrushState { position, pressure });
+
+        let mut canvas = colors;
+        for mode in [
+            LiquifyMode::Push,
+            LiquifyMode::Twirl { clockwise: true },
+            LiquifyMode::Pinch,
+            LiquifyMode::Bloat,
+            LiquifyMode::Reconstruct,
+        ] {
+            let settings = LiquifySettings {
+                mode,
+                radius: 10.0,
+                strength: 0.9,
+            };
+            let mut stroke = engine.begin_stroke(
+                &StrokeSettings {
+                    canvas_resolution: resolution,
+                    canvas_format: compositor.format(),
+                    blending_space: BlendingSpace::LinearLight,
+                },
+                &settings,
+            );
+
+            let mut field = DisplacementField::new(resolution);
+            let mut last_position = None;
+            for state in &states {
+                stroke.update(state);
+                for dab in settings.dabs(last_position, state) {
+                    field.apply(mode, &dab);
+                }
+                last_position = Some(state.position);
+            }
+
+            let pixels: Vec<_> = canvas
+                .iter()
+                .map(|c| WithAlpha {
+                    color: c.to_linear_srgb(),
+                    alpha: 1.0,
+                })
+                .collect();
+            let expected: Vec<NonlinearSrgb<u8>> = field
+                .resample(&pixels)
+                .iter()
+                .map(|c| NonlinearSrgb::from_linear_srgb(c.color))
+                .collect();
+
+            let mut ctx = FrameContext::new(&context);
+            let texture = stroke.render(&mut ctx);
+            let preview = compositor.preview_displacement(&mut ctx, texture, stroke.damage());
+            context.submit(ctx);
+
+            let what = format!("{mode:?}");
+            assert_close(&download(&context, &preview), &expected, &what);
+
+            let mut ctx = FrameContext::new(&context);
+            let texture = stroke.render(&mut ctx);
+            compositor.put_displacement(&mut ctx, texture, stroke.damage());
+            let rendered = compositor.render(&mut ctx);
+            context.submit(ctx);
+
+            let rendered = download(&context, &rendered);
+            assert_close(&rendered, &expected, &what);
+            if mode == LiquifyMode::Reconstruct {
+                // every stroke starts from a cleared field, so there's
+                // nothing to reconstruct
+                assert_eq!(rendered, canvas, "{what}");
+            } else {
+                assert_ne!(rendered, canvas, "{what}");
+            }
+            canvas = rendered;
+        }
+    }
+
+    #[test]
+    fn gpu_brush_strokes_are_opaque_in_every_blending_space() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(32, 16);
+        let engine = BrushEngine::new(context.clone());
+
+        for space in BlendingSpace::ALL {
+            let mut compositor = Compositor::new(
+                context.clone(),
+                resolution,
+                persistence::TextureFormat::Rgba8NonlinearSrgb,
+            );
+
+            let mut stroke = engine.begin_stroke(&StrokeSettings {
+                canvas_resolution: resolution,
+                canvas_format: compositor.format(),
+                blending_space: space,
+            });
+            for x in [8.0, 24.0] {
+                stroke.update(&BrushState {
+                    position: Vec2::new(x, 8.0),
+                    pressure: 0.4,
+                });
+            }
+
+            let mut ctx = FrameContext::new(&context);
+            let texture = stroke.render(&mut ctx);
+            compositor.put_texture(&mut ctx, texture, stroke.damage(), space);
+            let rendered = compositor.render(&mut ctx);
+            context.submit(ctx);
+
+            let rendered = download(&context, &rendered);
+            let at = |x: u32, y: u32| rendered[(y * resolution.x + x) as usize];
+            let what = format!("{space:?}");
+            assert_close(&[at(16, 8)], &[NonlinearSrgb::new(0, 0, 0)], &what);
+            assert_close(&[at(16, 0)], &[NonlinearSrgb::new(255, 255, 255)], &what);
+        }
+    }
+
+    #[test]
+    fn gpu_blending_matches_cpu_reference() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(8, 8);
+        let canvas: Vec<NonlinearSrgb<u8>> = (0..64)
+            .map(|i| NonlinearSrgb::new(i % 4 * 85, i / 4 % 4 * 85, i / 16 * 85))
+            .collect();
+        // a half-transparent stroke, in other colors than the canvas below
+        let stroke: Vec<NonlinearSrgb<u8>> = canvas
+            .iter()
+            .map(|c| NonlinearSrgb::new(c.g, 255 - c.b, c.r / 2 + 40))
+            .collect();
+        let stroke_alpha = 128;
+
+        for space in BlendingSpace::ALL {
+            let mut compositor = Compositor::new(
+                context.clone(),
+                resolution,
+                persistence::TextureFormat::Rgba8NonlinearSrgb,
+            );
+            let everything = DamageRegion::from_rect(Rect::from_resolution(resolution));
+
+            let texture = testing::upload(&context, resolution, &canvas);
+            let mut ctx = FrameContext::new(&context);
+            compositor.put_texture(&mut ctx, texture, &everything, space);
+
+            let texture = Texture::upload(
+                &mut ctx,
+                persistence::Texture {
+                    resolution,
+                    format: persistence::TextureFormat::Rgba8NonlinearSrgb,
+                    data: stroke
+                        .iter()
+                        .flat_map(|c| [c.r, c.g, c.b, stroke_alpha])
+                        .collect::<Vec<_>>()
+                        .into(),
+                    row_stride: 4 * resolution.x as usize,
+                },
+            );
+            let preview = compositor.preview_texture(&mut ctx, texture, &everything, space);
+            context.submit(ctx);
+
+            let expected: Vec<NonlinearSrgb<u8>> = canvas
+                .iter()
+                .zip(&stroke)
+                .map(|(dst, src)| {
+                    let dst = WithAlpha::new(dst.to_linear_srgb(), 1.0);
+                    let src = WithAlpha::new(src.to_linear_srgb(), stroke_alpha as f32 / 255.0);
+                    NonlinearSrgb::from_linear_srgb(space.source_over(dst, src).color)
+                })
+                .collect();
+
+            assert_close(
+                &download(&context, &preview),
+                &expected,
+                &format!("{space:?}"),
+            );
+        }
+    }
+
+    #[test]
+    fn gpu_shapes_match_cpu_reference() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(32, 24);
+        let mut compositor = Compositor::new(
+            context.clone(),
+            resolution,
+            persistence::TextureFormat::Rgba8NonlinearSrgb,
+        );
+
+        let engine = ShapeEngine::new(context.clone());
+        let mut drawing = engine.begin_drawing(&StrokeSettings {
+            canvas_resolution: resolution,
+            canvas_format: compositor.format(),
+            blending_space: BlendingSpace::GammaSrgb,
+        });
+
+        let kinds = [
+            ShapeKind::Line,
+            ShapeKind::Rectangle,
+            ShapeKind::Ellipse,
+            ShapeKind::Polygon { sides: 5 },
+        ];
+        let styles = [ShapeStyle::Fill, ShapeStyle::Stroke { width: 3.0 }];
+
+        // the same drawing is edited, so every shape replaces the previous
+        let mut shape = None;
+        for kind in kinds {
+            for style in styles {
+                let edited = Shape {
+                    settings: ShapeSettings { kind, style },
+                    start: Vec2::new(15.3, 11.0),
+                    end: Vec2::new(27.0, 20.6),
+                };
+                drawing.set_shape(&edited);
+                shape = Some(edited);
+
+                // black over the white canvas, blended in gamma space so the
+                // 8-bit alpha of the texture is precise enough
+                let expected: Vec<NonlinearSrgb<u8>> = edited
+                    .rasterize(resolution)
+                    .iter()
+                    .map(|alpha| {
+                        let value = ((1.0 - alpha) * 255.0).round() as u8;
+                        NonlinearSrgb::new(value, value, value)
+                    })
+                    .collect();
+
+                let mut ctx = FrameContext::new(&context);
+                let texture = drawing.render(&mut ctx);
+                let preview = compositor.preview_texture(
+                    &mut ctx,
+                    texture,
+                    drawing.damage(),
+                    BlendingSpace::GammaSrgb,
+                );
+                context.submit(ctx);
+
+                let what = format!("{kind:?} {style:?}");
+                assert_close(&download(&context, &preview), &expected, &what);
+            }
+        }
+
+        // the last shape is drawn onto the canvas
+        let mut ctx = FrameContext::new(&context);
+        let texture = drawing.render(&mut ctx);
+        compositor.put_texture(
+            &mut ctx,
+            texture,
+            drawing.damage(),
+            BlendingSpace::GammaSrgb,
+        );
+        let rendered = compositor.render(&mut ctx);
+        context.submit(ctx);
+
+        let alpha = shape.unwrap().rasterize(resolution);
+        let rendered = download(&context, &rendered);
+        assert!(
+            rendered
+                .iter()
+                .zip(alpha)
+                .all(|(c, alpha)| alpha > 0.5 || c.r > 127)
+        );
+        assert!(rendered.iter().any(|c| c.r == 0));
+    }
+
+    #[test]
+    fn gpu_adjustments_match_cpu_reference() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(8, 8);
+        let colors: Vec<NonlinearSrgb<u8>> = (0..64)
+            .map(|i| NonlinearSrgb::new(i % 4 * 85, i / 4 % 4 * 85, i / 16 * 85))
+            .collect();
+
+        let mut compositor = Compositor::new(
+            context.clone(),
+            resolution,
+            persistence::TextureFormat::Rgba8NonlinearSrgb,
+        );
+
+        let texture = testing::upload(&context, resolution, &colors);
+        let mut ctx = FrameContext::new(&context);
+        compositor.put_texture(
+            &mut ctx,
+            texture,
+            &DamageRegion::from_rect(Rect::from_resolution(resolution)),
+            BlendingSpace::LinearLight,
+        );
+        context.submit(ctx);
+
+        let levels = AdjustmentLayer::new(Adjustment::Levels(Levels {
+            input_black: 0.1,
+            input_white: 0.9,
+            gamma: 1.4,
+            output_black: 0.05,
+            output_white: 0.95,
+        }));
+        let curves = AdjustmentLayer::new(Adjustment::Curves(Curves {
+            lightness: Curve::new([Vec2::new(0.0, 0.1), Vec2::new(0.6, 0.7), Vec2::ONE]),
+            ..Curves::rgb(Curve::new([Vec2::ZERO, Vec2::new(0.4, 0.6), Vec2::ONE]))
+        }));
+        let hue_chroma_lightness = AdjustmentLayer {
+            opacity: 0.6,
+            ..AdjustmentLayer::new(Adjustment::HueChromaLightness {
+                hue: 1.0,
+                chroma: 0.7,
+                lightness: -0.05,
+            })
+        };
+        let color_balance = AdjustmentLayer::new(Adjustment::ColorBalance {
+            shadows: Vec3::new(0.1, 0.0, -0.1),
+            midtones: Vec3::new(0.0, 0.1, 0.0),
+            highlights: Vec3::new(-0.1, 0.0, 0.05),
+        });
+        let invert = AdjustmentLayer::new(Adjustment::Invert);
+
+        let stacks = [
+            vec![levels.clone()],
+            vec![curves],
+            vec![hue_chroma_lightness.clone()],
+            vec![color_balance],
+            vec![invert.clone()],
+            vec![levels, hue_chroma_lightness, invert],
+            vec![],
+        ];
+
+        for layers in stacks {
+            let mut ctx = FrameContext::new(&context);
+            compositor.set_adjustment_layers(&mut ctx, &layers);
+            let adjusted = compositor.render(&mut ctx);
+            context.submit(ctx);
+
+            let actual = download(&context, &adjusted);
+
+            // every adjustment is written into an 8-bit texture
+            let expected = colors.iter().map(|&c| {
+                layers.iter().fold(c, |c, layer| {
+                    NonlinearSrgb::from_linear_srgb(layer.apply(c.to_linear_srgb()))
+                })
+            });
+
+            let expected: Vec<_> = expected.collect();
+            assert_close(&actual, &expected, &format!("{layers:?}"));
+        }
+    }
+
+    #[test]
+    fn gpu_format_conversion_keeps_the_canvas() {
+        let context = testing::create_context();
+
+        let resolution = UVec2::new(16, 8);
+        let colors: Vec<NonlinearSrgb<u8>> = (0..128u8)
+            .map(|i| NonlinearSrgb::new(i * 2, 255 - i, i.wrapping_mul(37)))
+            .collect();
+
+        let mut compositor = Compositor::new(
+            context.clone(),
+            resolution,
+            persistence::TextureFormat::Rgba8NonlinearSrgb,
+        );
+
+        let texture = testing::upload(&context, resolution, &colors);
+        let mut ctx = FrameContext::new(&context);
+        compositor.put_texture(
+            &mut ctx,
+            texture,
+            &DamageRegion::from_rect(Rect::from_resolution(resolution)),
+            BlendingSpace::LinearLight,
+        );
+        context.submit(ctx);
+
+        let format = persistence::TextureFormat::Rgba16FloatLinearSrgb;
+        assert!(compositor.is_format_supported(format));
+
+        // there and back, as only 8-bit textures can be downloaded
+        let mut ctx = FrameContext::new(&context);
+        compositor.set_format(&mut ctx, format);
+        compositor.set_format(&mut ctx, persistence::TextureFormat::Rgba8NonlinearSrgb);
+        let rendered = compositor.render(&mut ctx);
+        context.submit(ctx);
+
+        assert_eq!(download(&context, &rendered), colors);
     }
 }
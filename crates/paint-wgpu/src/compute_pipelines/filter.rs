@@ -0,0 +1,105 @@
+use std::mem;
+
+use glam::Vec2;
+use paint_core::filter::{BlurPass, Filter};
+
+use crate::{bind_group_layouts, pipeline_layouts, shaders};
+
+/// Format of the textures which filters write.
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Pass of a filter, see `filter.wgsl`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Pass {
+    Premultiply,
+    Blur,
+    Finish,
+}
+
+impl Pass {
+    fn entry_point(self) -> &'static str {
+        match self {
+            Pass::Premultiply => "premultiply",
+            Pass::Blur => "blur",
+            Pass::Finish => "finish",
+        }
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Default, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct Immediates {
+    pub step: Vec2,
+    pub radius: u32,
+    pub sigma: f32,
+    pub amount: f32,
+    pub threshold: f32,
+    pub seed: u32,
+    pub mode: u32,
+}
+
+impl Immediates {
+    pub fn blur(pass: &BlurPass) -> Self {
+        Self {
+            step: pass.step,
+            radius: pass.radius,
+            sigma: pass.sigma,
+            ..Default::default()
+        }
+    }
+
+    pub fn finish(filter: &Filter) -> Self {
+        // must match the constants in the shader
+        match *filter {
+            Filter::GaussianBlur { .. } | Filter::BoxBlur { .. } | Filter::MotionBlur { .. } => {
+                Self::default()
+            }
+            Filter::UnsharpMask {
+                amount, threshold, ..
+            } => Self {
+                amount,
+                threshold,
+                mode: 1,
+                ..Default::default()
+            },
+            Filter::Noise {
+                amount,
+                seed,
+                monochrome,
+            } => Self {
+                amount,
+                seed,
+                mode: if monochrome { 3 } else { 2 },
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Pipeline writing a pass of a filter into a 16-bit float storage texture.
+pub fn compile(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    pass: Pass,
+) -> wgpu::ComputePipeline {
+    let shader = shaders.get(shaders::Key::Filter);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::ComputeTextures {
+            num_texture_bindings: 2,
+            storage_format: FORMAT,
+        }],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(&format!("Filter {pass:?} Compute Pipeline")),
+        layout: Some(&layout),
+        module: &shader,
+        entry_point: Some(pass.entry_point()),
+        compilation_options: Default::default(),
+        cache,
+    })
+}
@@ -0,0 +1,132 @@
+use std::mem;
+
+use glam::{UVec2, Vec2};
+use paint_core::liquify::{Dab, LiquifyMode, MAX_SCALE, MAX_TWIRL_ANGLE};
+
+use crate::{bind_group_layouts, pipeline_layouts, shaders};
+
+/// Format of displacement fields, holding the offsets in the red and green
+/// channels.
+///
+/// 16-bit floats would lose subpixel precision of larger offsets, and
+/// two-channel formats can't be storage textures on every backend.
+pub const FIELD_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// Format of the resampled canvas.
+pub const RESAMPLED_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Pass writing a displacement field, see `liquify_dab.wgsl`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum FieldPass {
+    Clear,
+    Dab,
+}
+
+impl FieldPass {
+    fn entry_point(self) -> &'static str {
+        match self {
+            FieldPass::Clear => "clear",
+            FieldPass::Dab => "dab",
+        }
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, Default, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct DabImmediates {
+    pub position: Vec2,
+    pub delta: Vec2,
+    pub origin: UVec2,
+    pub radius: f32,
+    pub strength: f32,
+    pub mode: u32,
+    pub amount: f32,
+}
+
+impl DabImmediates {
+    pub fn new(mode: LiquifyMode, dab: &Dab, origin: UVec2) -> Self {
+        // must match the constants in the shader
+        let (mode, amount) = match mode {
+            LiquifyMode::Push => (0, 0.0),
+            LiquifyMode::Twirl { clockwise } => {
+                (1, if clockwise { -1.0 } else { 1.0 } * MAX_TWIRL_ANGLE)
+            }
+            LiquifyMode::Pinch => (2, MAX_SCALE),
+            LiquifyMode::Bloat => (2, -MAX_SCALE),
+            LiquifyMode::Reconstruct => (3, 0.0),
+        };
+
+        Self {
+            position: dab.position,
+            delta: dab.delta,
+            origin,
+            radius: dab.radius,
+            strength: dab.strength,
+            mode,
+            amount,
+        }
+    }
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct ResampleImmediates {
+    pub origin: UVec2,
+}
+
+/// Pipeline writing a displacement field, within the dispatched area starting
+/// at the origin.
+pub fn compile_field(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    pass: FieldPass,
+) -> wgpu::ComputePipeline {
+    let shader = shaders.get(shaders::Key::LiquifyDab);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::ComputeTextures {
+            num_texture_bindings: 1,
+            storage_format: FIELD_FORMAT,
+        }],
+        immediate_size: mem::size_of::<DabImmediates>() as u32,
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(&format!("Liquify {pass:?} Compute Pipeline")),
+        layout: Some(&layout),
+        module: &shader,
+        entry_point: Some(pass.entry_point()),
+        compilation_options: Default::default(),
+        cache,
+    })
+}
+
+/// Pipeline resampling a texture through a displacement field, within the
+/// dispatched area starting at the origin.
+pub fn compile_resample(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+) -> wgpu::ComputePipeline {
+    let shader = shaders.get(shaders::Key::LiquifyResample);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::ComputeTextures {
+            num_texture_bindings: 2,
+            storage_format: RESAMPLED_FORMAT,
+        }],
+        immediate_size: mem::size_of::<ResampleImmediates>() as u32,
+    });
+
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("LiquifyResample Compute Pipeline"),
+        layout: Some(&layout),
+        module: &shader,
+        entry_point: Some("resample"),
+        compilation_options: Default::default(),
+        cache,
+    })
+}
@@ -0,0 +1,96 @@
+pub mod filter;
+pub mod liquify;
+
+use std::sync::Arc;
+
+use crate::resources::OnceMap;
+use crate::{pipeline_layouts, shaders};
+
+/// Side of the square workgroups, must match `@workgroup_size` in the shaders.
+pub const WORKGROUP_SIZE: u32 = 8;
+
+/// Compute pipeline key.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Key {
+    Filter(filter::Pass),
+    LiquifyField(liquify::FieldPass),
+    LiquifyResample,
+}
+
+impl Key {
+    /// Pipelines compiled ahead of time.
+    pub const WARM_UP: [Key; 6] = [
+        Key::Filter(filter::Pass::Premultiply),
+        Key::Filter(filter::Pass::Blur),
+        Key::Filter(filter::Pass::Finish),
+        Key::LiquifyField(liquify::FieldPass::Clear),
+        Key::LiquifyField(liquify::FieldPass::Dab),
+        Key::LiquifyResample,
+    ];
+
+    pub fn compile(
+        self,
+        device: &wgpu::Device,
+        shaders: &shaders::Storage,
+        pipeline_layouts: &pipeline_layouts::Storage,
+        cache: Option<&wgpu::PipelineCache>,
+    ) -> wgpu::ComputePipeline {
+        match self {
+            Key::Filter(pass) => {
+                self::filter::compile(device, shaders, pipeline_layouts, cache, pass)
+            }
+            Key::LiquifyField(pass) => {
+                self::liquify::compile_field(device, shaders, pipeline_layouts, cache, pass)
+            }
+            Key::LiquifyResample => {
+                self::liquify::compile_resample(device, shaders, pipeline_layouts, cache)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Storage {
+    device: wgpu::Device,
+    shaders: Arc<shaders::Storage>,
+    pipeline_layouts: Arc<pipeline_layouts::Storage>,
+    cache: Option<wgpu::PipelineCache>,
+    pipelines: OnceMap<Key, wgpu::ComputePipeline>,
+}
+
+impl Storage {
+    pub fn new(
+        device: wgpu::Device,
+        shaders: Arc<shaders::Storage>,
+        pipeline_layouts: Arc<pipeline_layouts::Storage>,
+        cache: Option<wgpu::PipelineCache>,
+    ) -> Self {
+        Self {
+            device,
+            shaders,
+            pipeline_layouts,
+            cache,
+            pipelines: OnceMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: Key) -> wgpu::ComputePipeline {
+        // compiling doesn't block other pipelines, so the warm up doesn't
+        // stall rendering with pipelines which are already compiled
+        self.pipelines.get_or_init(key, || {
+            key.compile(
+                &self.device,
+                &self.shaders,
+                &self.pipeline_layouts,
+                self.cache.as_ref(),
+            )
+        })
+    }
+
+    /// Compiles all commonly used pipelines which haven't been used yet.
+    pub fn warm_up(&self) {
+        for key in Key::WARM_UP {
+            self.get(key);
+        }
+    }
+}
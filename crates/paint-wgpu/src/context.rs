@@ -1,6 +1,14 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::{bind_group_layouts, pipeline_layouts, render_pipelines, shaders};
+use paint_core::persistence;
+
+use crate::pipeline_cache::{PipelineCache, PipelineCacheSettings};
+use crate::resources::bind_group_cache::BindGroupCache;
+use crate::resources::texture_pool::TexturePool;
+use crate::{
+    bind_group_layouts, compute_pipelines, mipmaps, pipeline_layouts, render_pipelines, shaders,
+};
 
 #[derive(Debug)]
 pub struct GlobalContext {
@@ -8,12 +16,37 @@ pub struct GlobalContext {
     pub(crate) queue: wgpu::Queue,
     pub(crate) bind_group_layouts: Arc<bind_group_layouts::Storage>,
     pub(crate) render_pipelines: Arc<render_pipelines::Storage>,
+    pub(crate) compute_pipelines: Arc<compute_pipelines::Storage>,
     pub(crate) default_texture_view: wgpu::TextureView,
     pub(crate) default_sampler: wgpu::Sampler,
+    pub(crate) trilinear_sampler: wgpu::Sampler,
+    pub(crate) nearest_sampler: wgpu::Sampler,
+    pub(crate) mipmap_generator: Arc<mipmaps::Generator>,
+    pub(crate) texture_pool: Arc<TexturePool>,
+    pub(crate) bind_group_cache: Arc<BindGroupCache>,
+    pipeline_cache: Option<Arc<PipelineCache>>,
+    /// Formats which canvases can use on this device.
+    canvas_formats: Vec<persistence::TextureFormat>,
 }
 
 impl GlobalContext {
-    pub fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+    /// Creates the context.
+    ///
+    /// With `pipeline_cache` settings, compiled pipelines are persisted across
+    /// launches where supported. Either way, all pipelines are compiled
+    /// in a background thread, so they are ready by the time they're needed.
+    ///
+    /// The adapter has to be the one which the device was requested from.
+    pub fn new(
+        adapter: &wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        pipeline_cache: Option<PipelineCacheSettings>,
+    ) -> Self {
+        let pipeline_cache = pipeline_cache
+            .and_then(|settings| PipelineCache::load(&device, &settings))
+            .map(Arc::new);
+
         let shaders = Arc::new(shaders::Storage::new(device.clone()));
         let bind_group_layouts = Arc::new(bind_group_layouts::Storage::new(device.clone()));
 
@@ -23,24 +56,125 @@ impl GlobalContext {
         ));
 
         let render_pipelines = Arc::new(render_pipelines::Storage::new(
+            device.clone(),
+            shaders.clone(),
+            pipeline_layouts.clone(),
+            pipeline_cache.as_ref().map(|cache| cache.get().clone()),
+        ));
+
+        let compute_pipelines = Arc::new(compute_pipelines::Storage::new(
             device.clone(),
             shaders,
             pipeline_layouts,
+            pipeline_cache.as_ref().map(|cache| cache.get().clone()),
         ));
 
+        spawn_warm_up(
+            render_pipelines.clone(),
+            compute_pipelines.clone(),
+            pipeline_cache.clone(),
+        );
+
         let default_texture = crate::utils::create_default_texture(&device, &queue);
         let default_texture_view = default_texture.create_view(&Default::default());
         let default_sampler = crate::utils::create_default_sampler(&device);
+        let trilinear_sampler = crate::utils::create_trilinear_sampler(&device);
+        let nearest_sampler = crate::utils::create_nearest_sampler(&device);
+
+        let mipmap_generator = Arc::new(mipmaps::Generator::new(
+            device.clone(),
+            bind_group_layouts.clone(),
+            render_pipelines.clone(),
+        ));
+
+        let texture_pool = Arc::new(TexturePool::new(device.clone(), 3));
+        let bind_group_cache = Arc::new(BindGroupCache::new(
+            device.clone(),
+            bind_group_layouts.clone(),
+            64,
+        ));
+
+        let canvas_formats = persistence::TextureFormat::ALL
+            .into_iter()
+            .filter(|&format| crate::is_canvas_format_supported(adapter, &device, format))
+            .collect();
 
         Self {
             device,
             queue,
             bind_group_layouts,
             render_pipelines,
+            compute_pipelines,
             default_texture_view,
             default_sampler,
+            trilinear_sampler,
+            nearest_sampler,
+            mipmap_generator,
+            texture_pool,
+            bind_group_cache,
+            pipeline_cache,
+            canvas_formats,
         }
     }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Whether canvases can use the format on this device, see
+    /// [`is_canvas_format_supported()`](crate::is_canvas_format_supported).
+    pub fn is_canvas_format_supported(&self, format: persistence::TextureFormat) -> bool {
+        self.canvas_formats.contains(&format)
+    }
+
+    /// Persists compiled pipelines, if pipeline caching is enabled.
+    ///
+    /// Meant to be called when the app goes to background, as pipelines
+    /// compiled after the initial warm up are only saved this way.
+    pub fn save_pipeline_cache(&self) {
+        if let Some(cache) = &self.pipeline_cache
+            && let Err(e) = cache.save()
+        {
+            tracing::warn!("Failed to save pipeline cache: {e}");
+        }
+    }
+
+    /// Finishes recording the frame and submits its commands to the queue.
+    pub fn submit(&self, ctx: FrameContext) {
+        self.queue.submit(std::iter::once(ctx.encoder.finish()));
+        ctx.submitted.store(true, Ordering::Release);
+    }
+}
+
+/// Compiles all render and compute pipelines in a background thread, then
+/// saves the cache.
+fn spawn_warm_up(
+    render_pipelines: Arc<render_pipelines::Storage>,
+    compute_pipelines: Arc<compute_pipelines::Storage>,
+    pipeline_cache: Option<Arc<PipelineCache>>,
+) {
+    let result = std::thread::Builder::new()
+        .name("pipeline-warm-up".into())
+        .spawn(move || {
+            let start_time = std::time::Instant::now();
+            render_pipelines.warm_up();
+            compute_pipelines.warm_up();
+            tracing::debug!("Warmed up pipelines in {:?}", start_time.elapsed());
+
+            if let Some(cache) = pipeline_cache
+                && let Err(e) = cache.save()
+            {
+                tracing::warn!("Failed to save pipeline cache: {e}");
+            }
+        });
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to spawn pipeline warm up thread: {e}");
+    }
 }
 
 #[derive(Debug)]
@@ -48,6 +182,9 @@ pub struct FrameContext {
     pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
     pub(crate) encoder: wgpu::CommandEncoder,
+    pub(crate) mipmap_generator: Arc<mipmaps::Generator>,
+    /// Set once the commands are submitted, see [`GlobalContext::submit()`].
+    pub(crate) submitted: Arc<AtomicBool>,
 }
 
 impl FrameContext {
@@ -56,6 +193,8 @@ impl FrameContext {
             device: ctx.device.clone(),
             queue: ctx.queue.clone(),
             encoder: ctx.device.create_command_encoder(&Default::default()),
+            mipmap_generator: ctx.mipmap_generator.clone(),
+            submitted: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -1,26 +1,77 @@
 mod bind_group_layouts;
+mod compute_pipelines;
+mod mipmaps;
+mod pipeline_cache;
 mod pipeline_layouts;
 mod render_pipelines;
+mod resources;
 mod shaders;
 mod utils;
 
 mod brush_engine;
 mod compositor;
 mod context;
+mod liquify;
 mod renderer;
+mod shape;
 mod texture;
 
 pub use self::brush_engine::{BrushEngine, BrushStroke};
 pub use self::compositor::Compositor;
-pub use self::context::{GlobalContext, FrameContext};
+pub use self::context::{FrameContext, GlobalContext};
+pub use self::liquify::{LiquifyEngine, LiquifyStroke};
+pub use self::pipeline_cache::PipelineCacheSettings;
 pub use self::renderer::color_picker::ColorPickerRenderer;
-pub use self::renderer::viewport::ViewportRenderer;
+pub use self::renderer::viewport::{ViewportRenderer, ViewportSettings};
+pub use self::shape::{ShapeDrawing, ShapeEngine};
 pub use self::texture::Texture;
 
 pub fn get_required_wgpu_features() -> wgpu::Features {
     wgpu::Features::IMMEDIATES
 }
 
+/// Features which are used when available.
+pub fn get_optional_wgpu_features() -> wgpu::Features {
+    wgpu::Features::PIPELINE_CACHE
+        | wgpu::Features::TEXTURE_FORMAT_16BIT_NORM
+        | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+}
+
+/// Whether canvases and layers can use the format on this device.
+///
+/// 8-bit sRGB and 16-bit float formats are always supported, while 16-bit
+/// normalized textures have to be renderable, blendable and filterable, which
+/// not all (mostly mobile) GPUs can do.
+pub fn is_canvas_format_supported(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: paint_core::persistence::TextureFormat,
+) -> bool {
+    let format = texture::wgpu_format(format);
+
+    let required_features = format.required_features();
+    if !device.features().contains(required_features) {
+        return false;
+    }
+
+    let format_features = if device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        adapter.get_texture_format_features(format)
+    } else {
+        format.guaranteed_format_features(device.features())
+    };
+
+    format_features
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        && format_features.flags.contains(
+            wgpu::TextureFormatFeatureFlags::BLENDABLE
+                | wgpu::TextureFormatFeatureFlags::FILTERABLE,
+        )
+}
+
 pub fn get_required_wgpu_limits() -> wgpu::Limits {
     wgpu::Limits {
         max_immediate_size: 128,
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use glam::{UVec2, Vec2};
+use paint_core::behaviour::{BrushState, StrokeSettings};
+use paint_core::geometry::DamageRegion;
+use paint_core::liquify::{Dab, LiquifySettings};
+use zerocopy::IntoBytes as _;
+
+use crate::bind_group_layouts;
+use crate::compute_pipelines::liquify::{DabImmediates, FIELD_FORMAT, FieldPass};
+use crate::compute_pipelines::{self, WORKGROUP_SIZE};
+use crate::context::{FrameContext, GlobalContext};
+use crate::resources::texture_pool::{self, PooledTexture};
+use crate::texture::Texture;
+use crate::utils::copy_region;
+
+pub struct LiquifyEngine {
+    context: Arc<GlobalContext>,
+}
+
+impl LiquifyEngine {
+    pub fn new(context: Arc<GlobalContext>) -> Self {
+        Self { context }
+    }
+}
+
+impl paint_core::behaviour::LiquifyEngine for LiquifyEngine {
+    type Stroke = LiquifyStroke;
+
+    fn begin_stroke(&self, settings: &StrokeSettings, liquify: &LiquifySettings) -> Self::Stroke {
+        LiquifyStroke::new(&self.context, settings, liquify)
+    }
+}
+
+/// A liquify stroke, which renders into a displacement field.
+///
+/// Dabs read the field around them while writing it, so they're applied into
+/// a scratch texture, and copied back into the field.
+pub struct LiquifyStroke {
+    context: Arc<GlobalContext>,
+    settings: LiquifySettings,
+    resolution: UVec2,
+    field: PooledTexture,
+    field_view: wgpu::TextureView,
+    scratch: PooledTexture,
+    scratch_view: wgpu::TextureView,
+    last_position: Option<Vec2>,
+    /// Dabs which haven't been rendered yet.
+    dabs: Vec<Dab>,
+    /// Area displaced by the whole stroke.
+    damage: DamageRegion,
+    should_clear: bool,
+}
+
+impl LiquifyStroke {
+    pub fn new(
+        context: &Arc<GlobalContext>,
+        settings: &StrokeSettings,
+        liquify: &LiquifySettings,
+    ) -> Self {
+        let key = texture_pool::Key {
+            width: settings.canvas_resolution.x,
+            height: settings.canvas_resolution.y,
+            mip_level_count: 1,
+            format: FIELD_FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+        };
+
+        let field = context
+            .texture_pool
+            .acquire("Displacement Field Texture", key);
+        let field_view = field.create_view(&Default::default());
+        let scratch = context
+            .texture_pool
+            .acquire("Displacement Field Texture", key);
+        let scratch_view = scratch.create_view(&Default::default());
+
+        Self {
+            context: context.clone(),
+            settings: *liquify,
+            resolution: settings.canvas_resolution,
+            field,
+            field_view,
+            scratch,
+            scratch_view,
+            last_position: None,
+            dabs: Vec::new(),
+            damage: DamageRegion::new(),
+            should_clear: true,
+        }
+    }
+
+    fn bind_group(
+        &self,
+        texture: &wgpu::TextureView,
+        storage_texture: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        // the pooled views are new for every stroke, so caching would only
+        // evict useful bind groups
+        bind_group_layouts::compute_textures::create_bind_group(
+            &self.context.device,
+            &self.context.bind_group_layouts,
+            &[texture],
+            storage_texture,
+        )
+    }
+
+    /// Clears the field, which may contain an older stroke.
+    ///
+    /// Rendering into 32-bit float textures isn't possible on every device,
+    /// so this is a compute pass as well.
+    fn clear(&self, ctx: &mut FrameContext) {
+        let pipeline = self
+            .context
+            .compute_pipelines
+            .get(compute_pipelines::Key::LiquifyField(FieldPass::Clear));
+        let workgroups = (self.resolution + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+        let mut pass = ctx.encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(
+            0,
+            &self.bind_group(&self.scratch_view, &self.field_view),
+            &[],
+        );
+        pass.set_immediates(0, DabImmediates::default().as_bytes());
+        pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+    }
+}
+
+impl paint_core::behaviour::BrushStroke for LiquifyStroke {
+    type Texture = Texture;
+    type Context = FrameContext;
+
+    fn update(&mut self, state: &BrushState) {
+        for dab in self.settings.dabs(self.last_position, state) {
+            self.damage.add(dab.bounds());
+            self.dabs.push(dab);
+        }
+
+        self.last_position = Some(state.position);
+    }
+
+    fn damage(&self) -> &DamageRegion {
+        &self.damage
+    }
+
+    fn render(&mut self, ctx: &mut FrameContext) -> Texture {
+        if self.should_clear {
+            self.clear(ctx);
+            self.should_clear = false;
+        }
+
+        let pipeline = self
+            .context
+            .compute_pipelines
+            .get(compute_pipelines::Key::LiquifyField(FieldPass::Dab));
+        let bind_group = self.bind_group(&self.field_view, &self.scratch_view);
+
+        for dab in self.dabs.drain(..) {
+            let Some((origin, size)) = dab.bounds().to_pixels(self.resolution) else {
+                continue;
+            };
+
+            let immediates = DabImmediates::new(self.settings.mode, &dab, origin);
+            let workgroups = (size + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+            let mut pass = ctx.encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_immediates(0, immediates.as_bytes());
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            drop(pass);
+
+            copy_region(
+                &mut ctx.encoder,
+                &self.scratch,
+                &self.field,
+                &DamageRegion::from_rect(dab.bounds()),
+                self.resolution,
+            );
+        }
+
+        Texture(self.field_view.clone())
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex};
+
+use glam::UVec2;
+use paint_core::geometry::{DamageRegion, Rect};
+
+use crate::resources::LruCache;
+use crate::{bind_group_layouts, render_pipelines};
+
+/// Returns the number of mip levels in a full mip chain for the given size.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    }
+    .max_mips(wgpu::TextureDimension::D2)
+}
+
+/// Generates mip chains by repeatedly downsampling the previous level.
+///
+/// Downsampling happens in linear light, since the sRGB texture formats are
+/// decoded on sampling and encoded on writing.
+///
+/// Views and bind groups of individual levels are cached for the most recently
+/// used textures. Any renderable and filterable texture format is supported.
+#[derive(Debug)]
+pub struct Generator {
+    device: wgpu::Device,
+    bind_group_layouts: Arc<bind_group_layouts::Storage>,
+    render_pipelines: Arc<render_pipelines::Storage>,
+    sampler: wgpu::Sampler,
+    levels: Mutex<LruCache<wgpu::Texture, Arc<[Level]>>>,
+}
+
+/// Resources for rendering a single mip level.
+#[derive(Debug)]
+struct Level {
+    /// View of this level, used as a render target.
+    view: wgpu::TextureView,
+    /// Bind group sampling the previous level.
+    src_bind_group: wgpu::BindGroup,
+}
+
+impl Generator {
+    pub fn new(
+        device: wgpu::Device,
+        bind_group_layouts: Arc<bind_group_layouts::Storage>,
+        render_pipelines: Arc<render_pipelines::Storage>,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Mipmap Generator Sampler"),
+            min_filter: wgpu::FilterMode::Linear,
+            mag_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            device,
+            bind_group_layouts,
+            render_pipelines,
+            sampler,
+            levels: Mutex::new(LruCache::new(16)),
+        }
+    }
+
+    /// Records commands regenerating all mip levels of the texture from the
+    /// base level.
+    ///
+    /// The texture must have `TEXTURE_BINDING` and `RENDER_ATTACHMENT` usages.
+    pub fn generate(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        let resolution = UVec2::new(texture.width(), texture.height());
+        let region = DamageRegion::from_rect(Rect::from_resolution(resolution));
+        self.generate_region(encoder, texture, &region);
+    }
+
+    /// Same as [`Generator::generate()`], but only updates the parts of the mip
+    /// levels derived from the damaged area of the base level.
+    pub fn generate_region(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        damage: &DamageRegion,
+    ) {
+        if damage.is_empty() {
+            return;
+        }
+
+        let levels = self
+            .levels
+            .lock()
+            .unwrap()
+            .get_or_insert_with(texture.clone(), |texture| self.create_levels(texture))
+            .clone();
+
+        let pipeline = self
+            .render_pipelines
+            .get(render_pipelines::Key::FullscreenTriangle(texture.format()));
+
+        for (level, resources) in (1..).zip(levels.iter()) {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Mipmap Generation Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &resources.view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &resources.src_bind_group, &[]);
+
+            let size = texture
+                .size()
+                .mip_level_size(level, wgpu::TextureDimension::D2);
+            let level_resolution = UVec2::new(size.width, size.height);
+            let scale = 1.0 / (1u32 << level) as f32;
+
+            for rect in damage.rects() {
+                let Some((offset, size)) = rect.scale(scale).to_pixels(level_resolution) else {
+                    continue;
+                };
+
+                pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+                pass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    fn create_levels(&self, texture: &wgpu::Texture) -> Arc<[Level]> {
+        let level_view = |level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        };
+
+        (1..texture.mip_level_count())
+            .map(|level| Level {
+                view: level_view(level),
+                src_bind_group: bind_group_layouts::sampled_textures::create_bind_group(
+                    &self.device,
+                    &self.bind_group_layouts,
+                    &self.sampler,
+                    &[&level_view(level - 1)],
+                ),
+            })
+            .collect()
+    }
+}
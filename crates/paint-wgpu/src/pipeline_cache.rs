@@ -0,0 +1,128 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::shaders;
+
+const FILE_PREFIX: &str = "paint-pipeline-cache-";
+
+/// Where, and for which adapter, to persist the pipeline cache.
+#[derive(Debug, Clone)]
+pub struct PipelineCacheSettings {
+    /// Directory for cache files, such as the app's cache directory.
+    pub directory: PathBuf,
+    /// Adapter which the device was created from.
+    pub adapter_info: wgpu::AdapterInfo,
+}
+
+/// Pipeline cache backed by a file.
+///
+/// The file name includes the adapter (driver) and a hash of all shader
+/// sources, so changes to either start a fresh cache.
+#[derive(Debug)]
+pub struct PipelineCache {
+    cache: wgpu::PipelineCache,
+    path: PathBuf,
+}
+
+impl PipelineCache {
+    /// Creates the pipeline cache, initialized from the file if it exists.
+    ///
+    /// Returns [`None`] if the device or backend doesn't support pipeline
+    /// caching.
+    pub fn load(device: &wgpu::Device, settings: &PipelineCacheSettings) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return None;
+        }
+
+        let adapter_key = wgpu::util::pipeline_cache_key(&settings.adapter_info)?;
+        let file_name = format!("{FILE_PREFIX}{adapter_key}-{:016x}", shader_sources_hash());
+        let path = settings.directory.join(file_name);
+
+        remove_stale_files(&settings.directory, &path);
+
+        let data = match fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                tracing::warn!("Failed to read pipeline cache from {path:?}: {e}");
+                None
+            }
+        };
+
+        tracing::debug!(
+            "Loaded pipeline cache from {path:?}: {} bytes",
+            data.as_ref().map_or(0, Vec::len)
+        );
+
+        // SAFETY: the data was previously returned by `PipelineCache::get_data`
+        // for the same adapter, as the file name includes the adapter key
+        let cache = unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("Pipeline Cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+
+        Some(Self { cache, path })
+    }
+
+    pub fn get(&self) -> &wgpu::PipelineCache {
+        &self.cache
+    }
+
+    /// Writes the cache contents to the file.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(data) = self.cache.get_data() else {
+            return Ok(());
+        };
+
+        // write to a temporary file first, so that a crash never leaves a
+        // partially written cache behind
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, &data)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        tracing::debug!(
+            "Saved pipeline cache to {:?}: {} bytes",
+            self.path,
+            data.len()
+        );
+        Ok(())
+    }
+}
+
+/// Removes cache files written by other app or driver versions.
+fn remove_stale_files(directory: &std::path::Path, current: &std::path::Path) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_cache_file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(FILE_PREFIX));
+
+        if is_cache_file && path != current {
+            tracing::debug!("Removing stale pipeline cache {path:?}");
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Stable (FNV-1a) hash of all shader sources.
+fn shader_sources_hash() -> u64 {
+    let mut hash = 0xcbf29ce484222325_u64;
+
+    for key in shaders::Key::ALL {
+        for byte in key.source().bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    hash
+}
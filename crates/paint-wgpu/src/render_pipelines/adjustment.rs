@@ -0,0 +1,113 @@
+use std::mem;
+
+use glam::Vec4;
+use paint_core::adjustment::{Adjustment, AdjustmentLayer};
+
+use crate::{bind_group_layouts, pipeline_layouts, shaders};
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct Immediates {
+    /// Parameters, whose meaning depends on the kind.
+    pub params: [Vec4; 3],
+    /// The adjustment kind, see the constants in the shader.
+    pub kind: u32,
+    pub opacity: f32,
+    /// WGSL rounds the struct size up to the `vec4` alignment.
+    pub _padding: [u32; 2],
+}
+
+impl Immediates {
+    pub fn new(layer: &AdjustmentLayer) -> Self {
+        // must match the constants in the shader
+        let (kind, params) = match &layer.adjustment {
+            Adjustment::Levels(levels) => (
+                0,
+                [
+                    Vec4::new(levels.input_black, levels.input_white, levels.gamma, 0.0),
+                    Vec4::new(levels.output_black, levels.output_white, 0.0, 0.0),
+                    Vec4::ZERO,
+                ],
+            ),
+            // the curves are passed as a lookup table
+            Adjustment::Curves(_) => (1, [Vec4::ZERO; 3]),
+            Adjustment::HueChromaLightness {
+                hue,
+                chroma,
+                lightness,
+            } => (
+                2,
+                [
+                    Vec4::new(*hue, *chroma, *lightness, 0.0),
+                    Vec4::ZERO,
+                    Vec4::ZERO,
+                ],
+            ),
+            Adjustment::ColorBalance {
+                shadows,
+                midtones,
+                highlights,
+            } => (
+                3,
+                [
+                    shadows.extend(0.0),
+                    midtones.extend(0.0),
+                    highlights.extend(0.0),
+                ],
+            ),
+            Adjustment::Invert => (4, [Vec4::ZERO; 3]),
+        };
+
+        Self {
+            params,
+            kind,
+            opacity: layer.opacity,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Pipeline adjusting the first texture, read at the target's pixel
+/// coordinates. The second texture is the curve lookup table.
+pub fn compile(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = shaders.get(shaders::Key::Adjustment);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::SampledTextures {
+            num_texture_bindings: 2,
+        }],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Adjustment Render Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fragment"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::all(),
+            })],
+        }),
+        multiview_mask: None,
+        cache,
+    })
+}
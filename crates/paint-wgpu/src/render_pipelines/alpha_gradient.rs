@@ -1,29 +1,31 @@
 use std::mem;
 
-use crate::{bind_group_layouts, pipeline_layouts, shaders};
+use glam::Vec4;
+
+use crate::{pipeline_layouts, shaders};
 
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
 pub struct Immediates {
-    pub alpha: f32,
+    /// Linear color, its alpha fading to 0 from top to bottom.
+    pub color: Vec4,
 }
 
 pub fn compile(
     device: &wgpu::Device,
     shaders: &shaders::Storage,
     pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
 ) -> wgpu::RenderPipeline {
-    let shader = shaders.get(shaders::Key::FullscreenTriangleInterpolateTwoTextures);
+    let shader = shaders.get(shaders::Key::AlphaGradient);
 
     let layout = pipeline_layouts.get(pipeline_layouts::Key {
-        bind_group_layouts: vec![bind_group_layouts::Key::SampledTextures {
-            num_texture_bindings: 2,
-        }],
+        bind_group_layouts: vec![],
         immediate_size: mem::size_of::<Immediates>() as u32,
     });
 
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("FullscreenTriangleInterpolateTwoTextures Render Pipeline"),
+        label: Some("AlphaGradient Render Pipeline"),
         layout: Some(&layout),
         vertex: wgpu::VertexState {
             module: &shader,
@@ -40,11 +42,11 @@ pub fn compile(
             compilation_options: Default::default(),
             targets: &[Some(wgpu::ColorTargetState {
                 format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                blend: None,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                 write_mask: wgpu::ColorWrites::all(),
             })],
         }),
         multiview_mask: None,
-        cache: None,
+        cache,
     })
 }
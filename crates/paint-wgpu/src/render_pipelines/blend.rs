@@ -0,0 +1,115 @@
+use std::mem;
+
+use paint_core::color::BlendingSpace;
+
+use crate::{bind_group_layouts, pipeline_layouts, shaders};
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct Immediates {
+    pub space: u32,
+}
+
+impl Immediates {
+    pub fn new(space: BlendingSpace) -> Self {
+        // must match the constants in the shader
+        let space = match space {
+            BlendingSpace::LinearLight => 0,
+            BlendingSpace::GammaSrgb => 1,
+            BlendingSpace::Oklab => 2,
+        };
+
+        Self { space }
+    }
+}
+
+/// Pipeline compositing the first texture over the second one, writing the
+/// result as is. Both textures are read at the target's pixel coordinates.
+pub fn compile(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = shaders.get(shaders::Key::Blend);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::SampledTextures {
+            num_texture_bindings: 2,
+        }],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Blend Render Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fragment"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::all(),
+            })],
+        }),
+        multiview_mask: None,
+        cache,
+    })
+}
+
+/// Pipeline writing the colors of a texture accumulated premultiplied in the
+/// blending space, such as brush dabs, as linear colors. The texture is read
+/// at the target's pixel coordinates.
+pub fn compile_resolve(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = shaders.get(shaders::Key::Blend);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::SampledTextures {
+            num_texture_bindings: 1,
+        }],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Blend Resolve Render Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("resolve"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::all(),
+            })],
+        }),
+        multiview_mask: None,
+        cache,
+    })
+}
@@ -15,6 +15,7 @@ pub fn compile(
     device: &wgpu::Device,
     shaders: &shaders::Storage,
     pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
 ) -> wgpu::RenderPipeline {
     let shader = shaders.get(shaders::Key::CanvasBorder);
 
@@ -64,6 +65,6 @@ pub fn compile(
             })],
         }),
         multiview_mask: None,
-        cache: None,
+        cache,
     })
 }
@@ -0,0 +1,60 @@
+use std::mem;
+
+use glam::Vec2;
+
+use crate::{pipeline_layouts, shaders};
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct Immediates {
+    /// The slice kind, see `renderer::color_picker::slice::Kind`.
+    pub kind: u32,
+    /// The constant coordinate, whose meaning depends on the kind.
+    pub constant: f32,
+    /// Resolution of the target in pixels.
+    pub resolution: Vec2,
+    /// The gamut mapping, see `gamut_map` in `oklab.wgsl`.
+    pub gamut_mapping: u32,
+    /// Alpha of the adaptive L0 gamut mapping.
+    pub gamut_mapping_alpha: f32,
+}
+
+pub fn compile(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+) -> wgpu::RenderPipeline {
+    let shader = shaders.get(shaders::Key::ColorPickerSlice);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("ColorPickerSlice Render Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fragment"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                blend: None,
+                write_mask: wgpu::ColorWrites::all(),
+            })],
+        }),
+        multiview_mask: None,
+        cache,
+    })
+}
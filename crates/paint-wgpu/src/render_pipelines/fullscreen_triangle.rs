@@ -4,6 +4,8 @@ pub fn compile(
     device: &wgpu::Device,
     shaders: &shaders::Storage,
     pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    format: wgpu::TextureFormat,
 ) -> wgpu::RenderPipeline {
     let shader = shaders.get(shaders::Key::FullscreenTriangle);
 
@@ -31,12 +33,12 @@ pub fn compile(
             entry_point: Some("fragment"),
             compilation_options: Default::default(),
             targets: &[Some(wgpu::ColorTargetState {
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 blend: None,
                 write_mask: wgpu::ColorWrites::all(),
             })],
         }),
         multiview_mask: None,
-        cache: None,
+        cache,
     })
 }
@@ -1,46 +1,118 @@
+pub mod adjustment;
+pub mod alpha_gradient;
+pub mod blend;
 pub mod canvas_border;
+pub mod color_picker_slice;
 pub mod fullscreen_triangle;
-pub mod fullscreen_triangle_interpolate_two_textures;
+pub mod gamut_boundary;
+pub mod hue_marker;
+pub mod pixel_grid;
+pub mod selection_marker;
+pub mod shape_fill;
 pub mod single_quad;
 pub mod stamped_brush;
+pub mod view_filter;
 
-// TODO: add pipeline cache
-
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
+use crate::resources::OnceMap;
 use crate::{pipeline_layouts, shaders};
 
+/// Pipeline key. Pipelines which render into canvas textures are keyed by the
+/// target format as well.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Key {
-    FullscreenTriangle,
-    FullscreenTriangleInterpolateTwoTextures,
-    SingleQuad,
+    FullscreenTriangle(wgpu::TextureFormat),
+    SingleQuad(wgpu::TextureFormat),
     StampedBrush,
     CanvasBorder,
+    PixelGrid,
+    ColorPickerSlice,
+    HueMarker,
+    AlphaGradient,
+    GamutBoundary,
+    SelectionMarker,
+    Blend(wgpu::TextureFormat),
+    BlendResolve(wgpu::TextureFormat),
+    ViewFilter,
+    Adjustment(wgpu::TextureFormat),
+    ShapeFill(wgpu::TextureFormat),
 }
 
 impl Key {
+    /// Pipelines compiled ahead of time, for the formats which are supported
+    /// on every device.
+    pub const WARM_UP: [Key; 21] = [
+        Key::FullscreenTriangle(wgpu::TextureFormat::Rgba8UnormSrgb),
+        Key::FullscreenTriangle(wgpu::TextureFormat::Rgba16Float),
+        Key::SingleQuad(wgpu::TextureFormat::Rgba8UnormSrgb),
+        Key::SingleQuad(wgpu::TextureFormat::Rgba16Float),
+        Key::StampedBrush,
+        Key::CanvasBorder,
+        Key::PixelGrid,
+        Key::ColorPickerSlice,
+        Key::HueMarker,
+        Key::AlphaGradient,
+        Key::GamutBoundary,
+        Key::SelectionMarker,
+        Key::Blend(wgpu::TextureFormat::Rgba8UnormSrgb),
+        Key::Blend(wgpu::TextureFormat::Rgba16Float),
+        Key::BlendResolve(wgpu::TextureFormat::Rgba8UnormSrgb),
+        Key::BlendResolve(wgpu::TextureFormat::Rgba16Float),
+        Key::ViewFilter,
+        Key::Adjustment(wgpu::TextureFormat::Rgba8UnormSrgb),
+        Key::Adjustment(wgpu::TextureFormat::Rgba16Float),
+        Key::ShapeFill(wgpu::TextureFormat::Rgba8UnormSrgb),
+        Key::ShapeFill(wgpu::TextureFormat::Rgba16Float),
+    ];
+
     pub fn compile(
         self,
         device: &wgpu::Device,
         shaders: &shaders::Storage,
         pipeline_layouts: &pipeline_layouts::Storage,
+        cache: Option<&wgpu::PipelineCache>,
     ) -> wgpu::RenderPipeline {
         match self {
-            Key::FullscreenTriangle => {
-                self::fullscreen_triangle::compile(device, shaders, pipeline_layouts)
+            Key::FullscreenTriangle(format) => {
+                self::fullscreen_triangle::compile(device, shaders, pipeline_layouts, cache, format)
+            }
+            Key::SingleQuad(format) => {
+                self::single_quad::compile(device, shaders, pipeline_layouts, cache, format)
+            }
+            Key::StampedBrush => {
+                self::stamped_brush::compile(device, shaders, pipeline_layouts, cache)
+            }
+            Key::CanvasBorder => {
+                self::canvas_border::compile(device, shaders, pipeline_layouts, cache)
+            }
+            Key::PixelGrid => self::pixel_grid::compile(device, shaders, pipeline_layouts, cache),
+            Key::ColorPickerSlice => {
+                self::color_picker_slice::compile(device, shaders, pipeline_layouts, cache)
+            }
+            Key::HueMarker => self::hue_marker::compile(device, shaders, pipeline_layouts, cache),
+            Key::AlphaGradient => {
+                self::alpha_gradient::compile(device, shaders, pipeline_layouts, cache)
+            }
+            Key::GamutBoundary => {
+                self::gamut_boundary::compile(device, shaders, pipeline_layouts, cache)
             }
-            Key::FullscreenTriangleInterpolateTwoTextures => {
-                self::fullscreen_triangle_interpolate_two_textures::compile(
-                    device,
-                    shaders,
-                    pipeline_layouts,
-                )
+            Key::SelectionMarker => {
+                self::selection_marker::compile(device, shaders, pipeline_layouts, cache)
+            }
+            Key::Blend(format) => {
+                self::blend::compile(device, shaders, pipeline_layouts, cache, format)
+            }
+            Key::BlendResolve(format) => {
+                self::blend::compile_resolve(device, shaders, pipeline_layouts, cache, format)
+            }
+            Key::ViewFilter => self::view_filter::compile(device, shaders, pipeline_layouts, cache),
+            Key::Adjustment(format) => {
+                self::adjustment::compile(device, shaders, pipeline_layouts, cache, format)
+            }
+            Key::ShapeFill(format) => {
+                self::shape_fill::compile(device, shaders, pipeline_layouts, cache, format)
             }
-            Key::SingleQuad => self::single_quad::compile(device, shaders, pipeline_layouts),
-            Key::StampedBrush => self::stamped_brush::compile(device, shaders, pipeline_layouts),
-            Key::CanvasBorder => self::canvas_border::compile(device, shaders, pipeline_layouts),
         }
     }
 }
@@ -50,7 +122,8 @@ pub struct Storage {
     device: wgpu::Device,
     shaders: Arc<shaders::Storage>,
     pipeline_layouts: Arc<pipeline_layouts::Storage>,
-    pipelines: Mutex<HashMap<Key, wgpu::RenderPipeline>>,
+    cache: Option<wgpu::PipelineCache>,
+    pipelines: OnceMap<Key, wgpu::RenderPipeline>,
 }
 
 impl Storage {
@@ -58,20 +131,34 @@ impl Storage {
         device: wgpu::Device,
         shaders: Arc<shaders::Storage>,
         pipeline_layouts: Arc<pipeline_layouts::Storage>,
+        cache: Option<wgpu::PipelineCache>,
     ) -> Self {
         Self {
             device,
             shaders,
             pipeline_layouts,
-            pipelines: Mutex::new(HashMap::new()),
+            cache,
+            pipelines: OnceMap::new(),
         }
     }
 
     pub fn get(&self, key: Key) -> wgpu::RenderPipeline {
-        let mut pipelines = self.pipelines.lock().unwrap();
-        pipelines
-            .entry(key)
-            .or_insert_with(|| key.compile(&self.device, &self.shaders, &self.pipeline_layouts))
-            .clone()
+        // compiling doesn't block other pipelines, so the warm up doesn't
+        // stall rendering with pipelines which are already compiled
+        self.pipelines.get_or_init(key, || {
+            key.compile(
+                &self.device,
+                &self.shaders,
+                &self.pipeline_layouts,
+                self.cache.as_ref(),
+            )
+        })
+    }
+
+    /// Compiles all commonly used pipelines which haven't been used yet.
+    pub fn warm_up(&self) {
+        for key in Key::WARM_UP {
+            self.get(key);
+        }
     }
 }
@@ -0,0 +1,81 @@
+use std::mem;
+
+use glam::{Vec2, Vec4};
+use paint_core::shape::{Shape, ShapeKind};
+
+use crate::{pipeline_layouts, shaders};
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct Immediates {
+    /// Linear color of the shape.
+    pub color: Vec4,
+    pub start: Vec2,
+    pub end: Vec2,
+    pub kind: u32,
+    pub sides: u32,
+    /// WGSL rounds the struct size up to the `vec4` alignment.
+    pub _padding: [u32; 2],
+}
+
+impl Immediates {
+    pub fn new(shape: &Shape, color: Vec4) -> Self {
+        // must match the constants in the shader
+        let kind = match shape.settings.kind {
+            ShapeKind::Line => 0,
+            ShapeKind::Rectangle => 1,
+            ShapeKind::Ellipse => 2,
+            ShapeKind::Polygon { .. } => 3,
+        };
+
+        Self {
+            color,
+            start: shape.start,
+            end: shape.end,
+            kind,
+            sides: shape.sides(),
+            _padding: [0; 2],
+        }
+    }
+}
+
+pub fn compile(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = shaders.get(shaders::Key::ShapeFill);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("ShapeFill Render Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fragment"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::all(),
+            })],
+        }),
+        multiview_mask: None,
+        cache,
+    })
+}
@@ -1,14 +1,39 @@
 use std::mem;
 
-use glam::{Mat2, Vec2};
+use glam::{Affine2, Mat2, Vec2, Vec4};
 
 use crate::{pipeline_layouts, shaders};
 
+/// Format which dabs are accumulated in, premultiplied in the blending space.
+///
+/// Oklab coordinates can be negative, so this has to be a float format.
+pub const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
 pub struct Immediates {
+    /// Brush color in the blending space, see
+    /// [`BlendingSpace::encode()`](paint_core::color::BlendingSpace::encode).
+    pub color: Vec4,
     pub transform: Mat2,
     pub translation: Vec2,
+    /// WGSL rounds the struct size up to the `vec4` alignment.
+    pub _padding: [u32; 2],
+}
+
+impl Immediates {
+    /// Immediates for drawing in pixels into a texture of the resolution.
+    pub fn new(resolution: Vec2, color: Vec4) -> Self {
+        let pixel_to_ndc = Affine2::from_translation(Vec2::new(-1.0, 1.0))
+            * Affine2::from_scale(Vec2::new(2.0, -2.0) / resolution);
+
+        Self {
+            color,
+            transform: pixel_to_ndc.matrix2,
+            translation: pixel_to_ndc.translation,
+            _padding: [0; 2],
+        }
+    }
 }
 
 #[repr(C, packed)]
@@ -22,6 +47,7 @@ pub fn compile(
     device: &wgpu::Device,
     shaders: &shaders::Storage,
     pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
 ) -> wgpu::RenderPipeline {
     let shader = shaders.get(shaders::Key::StampedBrush);
 
@@ -62,19 +88,14 @@ pub fn compile(
             entry_point: Some("fragment"),
             compilation_options: Default::default(),
             targets: &[Some(wgpu::ColorTargetState {
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent::REPLACE,
-                    alpha: wgpu::BlendComponent {
-                        src_factor: wgpu::BlendFactor::One,
-                        dst_factor: wgpu::BlendFactor::One,
-                        operation: wgpu::BlendOperation::Max,
-                    },
-                }),
+                format: ACCUMULATION_FORMAT,
+                // "source over" of premultiplied colors is the same in every
+                // space, as long as the colors are in the blending space
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                 write_mask: wgpu::ColorWrites::all(),
             })],
         }),
         multiview_mask: None,
-        cache: None,
+        cache,
     })
 }
@@ -0,0 +1,72 @@
+use std::mem;
+
+use paint_core::presentation::ViewFilter;
+
+use crate::{bind_group_layouts, pipeline_layouts, shaders};
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, zerocopy::IntoBytes, zerocopy::Immutable)]
+pub struct Immediates {
+    pub filter: u32,
+    pub bands: u32,
+}
+
+impl Immediates {
+    pub fn new(filter: ViewFilter) -> Self {
+        // must match the constants in the shader
+        let (filter, bands) = match filter {
+            ViewFilter::None => (0, 0),
+            ViewFilter::Luminance => (1, 0),
+            ViewFilter::Protanopia => (2, 0),
+            ViewFilter::Deuteranopia => (3, 0),
+            ViewFilter::Tritanopia => (4, 0),
+            ViewFilter::ValueBands { bands } => (5, bands),
+        };
+
+        Self { filter, bands }
+    }
+}
+
+/// Pipeline filtering a texture into an sRGB target, reading it at the
+/// target's pixel coordinates.
+pub fn compile(
+    device: &wgpu::Device,
+    shaders: &shaders::Storage,
+    pipeline_layouts: &pipeline_layouts::Storage,
+    cache: Option<&wgpu::PipelineCache>,
+) -> wgpu::RenderPipeline {
+    let shader = shaders.get(shaders::Key::ViewFilter);
+
+    let layout = pipeline_layouts.get(pipeline_layouts::Key {
+        bind_group_layouts: vec![bind_group_layouts::Key::SampledTextures {
+            num_texture_bindings: 1,
+        }],
+        immediate_size: mem::size_of::<Immediates>() as u32,
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("ViewFilter Render Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vertex"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        primitive: Default::default(),
+        depth_stencil: None,
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fragment"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                blend: None,
+                write_mask: wgpu::ColorWrites::all(),
+            })],
+        }),
+        multiview_mask: None,
+        cache,
+    })
+}
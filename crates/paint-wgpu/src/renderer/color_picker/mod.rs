@@ -1,38 +1,46 @@
 mod slice;
 
+use std::f32::consts::TAU;
 use std::sync::Arc;
 
+use glam::{Vec2, Vec4};
+use paint_core::color::GamutMapping;
 use paint_core::presentation;
 use zerocopy::IntoBytes;
 
 use crate::context::GlobalContext;
-use crate::{FrameContext, bind_group_layouts, render_pipelines};
+use crate::{FrameContext, render_pipelines};
 
+/// Width of hue markers in physical pixels, excluding the outline.
+const HUE_MARKER_WIDTH: f32 = 3.0;
+
+/// Width of the selection ring in physical pixels, excluding the outline.
+const SELECTION_MARKER_WIDTH: f32 = 2.0;
+
+/// Radius of the selection ring on 2D slices, relative to the shorter side.
+const SELECTION_MARKER_RADIUS: f32 = 0.04;
+
+/// Width of the sRGB gamut boundary line in physical pixels.
+const GAMUT_BOUNDARY_WIDTH: f32 = 1.5;
+
+/// Renders color picker slices, computing every pixel exactly on the GPU.
 #[derive(Debug)]
 pub struct ColorPickerRenderer {
     context: Arc<GlobalContext>,
-    sampler: wgpu::Sampler,
-    slice_cache: slice::Cache,
+    gamut_mapping: GamutMapping,
 }
 
 impl ColorPickerRenderer {
     pub fn new(context: Arc<GlobalContext>) -> Self {
-        let sampler = create_sampler(&context.device);
-
-        let slice_cache = slice::Cache::new(
-            context.device.clone(),
-            context.queue.clone(),
-            slice::CacheSettings {
-                width: 256,
-                height: 256,
-                num_fixed_slices: 64,
-            },
-        );
+        Self::with_gamut_mapping(context, GamutMapping::default())
+    }
 
+    /// Creates a renderer mapping out of gamut colors of the slices with
+    /// `gamut_mapping`, rather than the default.
+    pub fn with_gamut_mapping(context: Arc<GlobalContext>, gamut_mapping: GamutMapping) -> Self {
         Self {
             context,
-            sampler,
-            slice_cache,
+            gamut_mapping,
         }
     }
 
@@ -41,7 +49,26 @@ impl ColorPickerRenderer {
         mut ctx: FrameContext,
         target: &wgpu::Texture,
         slice: presentation::ColorPickerSlice,
+        selection: Option<presentation::ColorPickerValue>,
     ) {
+        let resolution = Vec2::new(target.width() as f32, target.height() as f32);
+
+        // the ring surrounds the selected point, centered on vertical strips
+        let selection_marker = selection
+            .and_then(|value| slice.point_of(value))
+            .map(|point| {
+                let radius = if slice.is_vertical_strip() {
+                    0.4 * resolution.x
+                } else {
+                    SELECTION_MARKER_RADIUS * resolution.min_element()
+                };
+                render_pipelines::selection_marker::Immediates {
+                    position: point * resolution,
+                    radius,
+                    width: SELECTION_MARKER_WIDTH,
+                }
+            });
+
         let target_view = target.create_view(&wgpu::TextureViewDescriptor {
             format: Some(wgpu::TextureFormat::Rgba8UnormSrgb),
             ..Default::default()
@@ -60,80 +87,122 @@ impl ColorPickerRenderer {
             ..Default::default()
         });
 
+        let mut markers = Vec::new();
+        let mut alpha_gradient = None;
+        let mut gamut_boundary = None;
+
         let (slice_kind, constant) = match slice {
             presentation::ColorPickerSlice::OkhsvHueSlice { hue } => {
                 (slice::Kind::OkhsvHueSlice, hue)
             }
-            presentation::ColorPickerSlice::OkhslHueVerticalGradient => {
+            presentation::ColorPickerSlice::OkhslHueVerticalGradient { markers: hues } => {
+                markers = hues;
                 (slice::Kind::OkhslHueVerticalGradient, 0.0)
             }
+            presentation::ColorPickerSlice::OkhslHueSlice { hue } => {
+                (slice::Kind::OkhslHueSlice, hue)
+            }
+            presentation::ColorPickerSlice::OkhslLightnessWheel { lightness } => {
+                (slice::Kind::OkhslLightnessWheel, lightness)
+            }
+            presentation::ColorPickerSlice::OkhsvHueRing => (slice::Kind::OkhsvHueRing, 0.0),
+            presentation::ColorPickerSlice::OkLchChromaLightnessPlane { hue } => {
+                gamut_boundary = Some(hue);
+                (slice::Kind::OkLchChromaLightnessPlane, hue)
+            }
+            presentation::ColorPickerSlice::AlphaStrip { color } => {
+                alpha_gradient = Some(color);
+                (slice::Kind::AlphaStrip, 0.0)
+            }
         };
 
-        let hit = self.slice_cache.get(slice_kind, constant);
-
-        match hit {
-            Some(slice::CacheHit::Exact { texture_view }) => {
-                let pipeline = self
-                    .context
-                    .render_pipelines
-                    .get(render_pipelines::Key::FullscreenTriangle);
-                pass.set_pipeline(&pipeline);
-
-                let bind_group = bind_group_layouts::sampled_textures::create_bind_group(
-                    &self.context.device,
-                    &self.context.bind_group_layouts,
-                    &self.sampler,
-                    &[&texture_view],
-                );
-                pass.set_bind_group(0, &bind_group, &[]);
-                pass.draw(0..3, 0..1);
-            }
+        // values of the constants in `oklab.wgsl`
+        let (gamut_mapping_id, gamut_mapping_alpha) = match self.gamut_mapping {
+            GamutMapping::Clip => (0, 0.0),
+            GamutMapping::PreserveChroma => (1, 0.0),
+            GamutMapping::ProjectToMidLightness => (2, 0.0),
+            GamutMapping::ProjectToCusp => (3, 0.0),
+            GamutMapping::AdaptiveL0 { alpha } => (4, alpha),
+            GamutMapping::Css => (5, 0.0),
+        };
+
+        let pipeline = self
+            .context
+            .render_pipelines
+            .get(render_pipelines::Key::ColorPickerSlice);
+        pass.set_pipeline(&pipeline);
+
+        let immediates = render_pipelines::color_picker_slice::Immediates {
+            kind: slice_kind as u32,
+            constant,
+            resolution,
+            gamut_mapping: gamut_mapping_id,
+            gamut_mapping_alpha,
+        };
+        pass.set_immediates(0, immediates.as_bytes());
+        pass.draw(0..3, 0..1);
+
+        if let Some(color) = alpha_gradient {
+            let pipeline = self
+                .context
+                .render_pipelines
+                .get(render_pipelines::Key::AlphaGradient);
+            pass.set_pipeline(&pipeline);
+
+            let immediates = render_pipelines::alpha_gradient::Immediates {
+                color: Vec4::new(color.r, color.g, color.b, 1.0),
+            };
+            pass.set_immediates(0, immediates.as_bytes());
+            pass.draw(0..3, 0..1);
+        }
 
-            Some(slice::CacheHit::Interpolated {
-                texture_view_a,
-                texture_view_b,
-                alpha,
-            }) => {
-                let pipeline = self
-                    .context
-                    .render_pipelines
-                    .get(render_pipelines::Key::FullscreenTriangleInterpolateTwoTextures);
-                pass.set_pipeline(&pipeline);
-
-                pass.set_immediates(
-                    0,
-                    render_pipelines::fullscreen_triangle_interpolate_two_textures::Immediates {
-                        alpha,
-                    }
-                    .as_bytes(),
-                );
-
-                let bind_group = bind_group_layouts::sampled_textures::create_bind_group(
-                    &self.context.device,
-                    &self.context.bind_group_layouts,
-                    &self.sampler,
-                    &[&texture_view_a, &texture_view_b],
-                );
-                pass.set_bind_group(0, &bind_group, &[]);
+        if let Some(hue) = gamut_boundary {
+            let pipeline = self
+                .context
+                .render_pipelines
+                .get(render_pipelines::Key::GamutBoundary);
+            pass.set_pipeline(&pipeline);
+
+            let immediates = render_pipelines::gamut_boundary::Immediates {
+                hue,
+                max_chroma: presentation::ColorPickerSlice::OKLCH_MAX_CHROMA,
+                line_width: GAMUT_BOUNDARY_WIDTH,
+            };
+            pass.set_immediates(0, immediates.as_bytes());
+            pass.draw(0..3, 0..1);
+        }
+
+        if !markers.is_empty() {
+            let pipeline = self
+                .context
+                .render_pipelines
+                .get(render_pipelines::Key::HueMarker);
+            pass.set_pipeline(&pipeline);
+
+            for hue in markers {
+                // the gradient starts at hue 0 at the top
+                let immediates = render_pipelines::hue_marker::Immediates {
+                    position: hue / TAU,
+                    width: HUE_MARKER_WIDTH,
+                };
+                pass.set_immediates(0, immediates.as_bytes());
                 pass.draw(0..3, 0..1);
             }
+        }
 
-            _ => (),
+        if let Some(immediates) = selection_marker {
+            let pipeline = self
+                .context
+                .render_pipelines
+                .get(render_pipelines::Key::SelectionMarker);
+            pass.set_pipeline(&pipeline);
+            pass.set_immediates(0, immediates.as_bytes());
+            pass.draw(0..3, 0..1);
         }
 
         drop(pass);
 
         // TODO: this shouldn't be here
-        let command_buffer = ctx.encoder.finish();
-        self.context.queue.submit(std::iter::once(command_buffer));
+        self.context.submit(ctx);
     }
 }
-
-fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
-    device.create_sampler(&wgpu::SamplerDescriptor {
-        label: None,
-        min_filter: wgpu::FilterMode::Linear,
-        mag_filter: wgpu::FilterMode::Linear,
-        ..Default::default()
-    })
-}
@@ -1,227 +1,389 @@
-use std::collections::HashMap;
-use std::f32::consts::PI;
-
-use paint_core::color::{Color, NonlinearSrgb, Okhsl, Okhsv};
-use rayon::iter::{IntoParallelIterator, ParallelIterator as _};
-use wgpu::util::DeviceExt as _;
-
 /// Kind of 3D color space and a 2D (or 1D) slice of that space.
+///
+/// Values are passed to `color_picker_slice.wgsl`, which renders the slices.
+#[repr(u32)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Kind {
     /// Constant hue Okhsv slice.
-    OkhsvHueSlice,
+    OkhsvHueSlice = 0,
     /// Okhsl hue vertical gradient.
-    OkhslHueVerticalGradient,
+    OkhslHueVerticalGradient = 1,
+    /// Constant hue Okhsl slice, saturation along X and lightness along Y.
+    OkhslHueSlice = 2,
+    /// Constant lightness Okhsl wheel, hue as angle and saturation as radius.
+    OkhslLightnessWheel = 3,
+    /// Fully saturated Okhsv hue ring, hue as angle.
+    OkhsvHueRing = 4,
+    /// Constant hue OkLCh slice, chroma along X and lightness along Y.
+    OkLchChromaLightnessPlane = 5,
+    /// Checkerboard background of a vertical alpha strip, with square cells.
+    AlphaStrip = 6,
 }
 
-fn create_fixed_slice_jobs(num_slices: u32) -> Vec<(Kind, f32)> {
-    (0..num_slices)
-        .flat_map(|i| {
-            let constant = (i as f32) / (num_slices as f32 - 1.0);
-            [(Kind::OkhsvHueSlice, constant * 2.0 * PI)]
-        })
-        .chain([(Kind::OkhslHueVerticalGradient, 0.0)])
-        .collect()
-}
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::sync::Arc;
 
-/// Represents a 2D slice of a 3D color space.
-#[derive(Debug)]
-pub struct Slice {
-    /// The constant coordinate (the exact kind depends on [`SliceKind`]).
-    pub constant: f32,
-    /// Underlying texture.
-    pub texture_view: wgpu::TextureView,
-}
+    use glam::UVec2;
+    use paint_core::color::{
+        Color, GamutMapping, LinearSrgb, NonlinearSrgb, OkLch, Okhsl, Okhsv, batch,
+    };
 
-struct TextureData {
-    width: u32,
-    height: u32,
-    data: Vec<u8>,
-}
+    use super::*;
+    use crate::utils::testing;
+    use crate::{ColorPickerRenderer, FrameContext, GlobalContext};
 
-/// Uploads a texture storing a rasterized slice, returning its view.
-fn create_slice_texture(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
-    data: TextureData,
-) -> wgpu::TextureView {
-    let texture = device.create_texture_with_data(
-        queue,
-        &wgpu::TextureDescriptor {
-            label: None,
-            size: wgpu::Extent3d {
-                width: data.width,
-                height: data.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        },
-        wgpu::util::TextureDataOrder::LayerMajor,
-        &data.data,
-    );
-
-    texture.create_view(&Default::default())
-}
+    /// Chroma at the right edge of [`Kind::OkLchChromaLightnessPlane`].
+    const OKLCH_MAX_CHROMA: f32 = 0.4;
 
-/// Rasterizes a 2D slice of a 3D color space.
-///
-/// Returns a row-major sRGBA texture, 4 bytes per pixel.
-fn rasterize_slice(width: u32, height: u32, kind: Kind, constant: f32) -> TextureData {
-    match kind {
-        Kind::OkhsvHueSlice => {
-            rasterize_rectangular_plot(width, height, |x, y| Okhsv::new(constant, x, y))
-        }
-        Kind::OkhslHueVerticalGradient => {
-            rasterize_vertical_gradient(height, |y| Okhsl::new((1.0 - y) * 2.0 * PI, 1.0, 0.62))
+    /// Number of checkerboard cells across the alpha strip.
+    const ALPHA_STRIP_CELLS: f32 = 2.0;
+
+    /// Largest allowed difference of an 8-bit component, as the GPU computes
+    /// in `f32` while the reference uses `f64` intermediates.
+    const TOLERANCE: u8 = 2;
+
+    /// Linear opacity of black over out of gamut colors, see
+    /// `gamut_boundary.wgsl`.
+    const GAMUT_BOUNDARY_DIMMING: f32 = 0.35;
+
+    /// Rasterizes a 2D slice of a 3D color space on the CPU, mapping out of
+    /// gamut colors with `mapping`.
+    ///
+    /// Returns a row-major sRGBA texture, 4 bytes per pixel.
+    fn rasterize_slice(
+        width: u32,
+        height: u32,
+        kind: Kind,
+        constant: f32,
+        mapping: GamutMapping,
+    ) -> Vec<u8> {
+        match kind {
+            Kind::OkhsvHueSlice => rasterize_rectangular_plot(width, height, mapping, |x, y| {
+                Okhsv::new(constant, x, y)
+            }),
+            Kind::OkhslHueVerticalGradient => {
+                rasterize_rectangular_plot(width, height, mapping, |_x, y| {
+                    Okhsl::new((1.0 - y) * 2.0 * PI, 1.0, 0.62)
+                })
+            }
+            Kind::OkhslHueSlice => rasterize_rectangular_plot(width, height, mapping, |x, y| {
+                Okhsl::new(constant, x, y)
+            }),
+            Kind::OkhslLightnessWheel => {
+                rasterize_circular_plot(width, height, mapping, |angle, radius| {
+                    Okhsl::new(angle, radius, constant)
+                })
+            }
+            Kind::OkhsvHueRing => {
+                rasterize_circular_plot(width, height, mapping, |angle, _radius| {
+                    Okhsv::new(angle, 1.0, 1.0)
+                })
+            }
+            Kind::OkLchChromaLightnessPlane => {
+                rasterize_rectangular_plot(width, height, mapping, |x, y| {
+                    OkLch::new(y, x * OKLCH_MAX_CHROMA, constant)
+                })
+            }
+            // with a black alpha gradient over the checkerboard
+            Kind::AlphaStrip => {
+                let rows = ALPHA_STRIP_CELLS * height as f32 / width as f32;
+                rasterize_rectangular_plot(width, height, mapping, |x, y| {
+                    let column = (x * ALPHA_STRIP_CELLS).floor() as u32;
+                    let row = (y * rows).floor() as u32;
+                    let v = if (column + row).is_multiple_of(2) {
+                        1.0
+                    } else {
+                        0.8
+                    };
+                    let v = NonlinearSrgb::new(v, v, v).to_linear_srgb().r * (1.0 - y);
+                    LinearSrgb::new(v, v, v)
+                })
+            }
         }
     }
-}
 
-/// Rasterizes a rectangular plot of `f(x, y)`.
-///
-/// Coordinates are normalized between 0 and 1, Y up.
-///
-/// Returns a row-major sRGBA texture, 4 bytes per pixel.
-fn rasterize_rectangular_plot<C: Color, F: Fn(f32, f32) -> C>(
-    width: u32,
-    height: u32,
-    f: F,
-) -> TextureData {
-    let width = width as usize;
-    let height = height as usize;
-    let mut data = vec![0; 4 * width * height];
-
-    for x in 0..width {
-        for y in 0..height {
-            let fx = (x as f32) / (width as f32 - 1.0);
-            let fy = 1.0 - (y as f32) / (height as f32 - 1.0);
-            let color = f(fx, fy).to_linear_srgb_clamped();
-            let rgb = NonlinearSrgb::<u8>::from_linear_srgb(color);
-            data[4 * (y * width + x)] = rgb.r;
-            data[4 * (y * width + x) + 1] = rgb.g;
-            data[4 * (y * width + x) + 2] = rgb.b;
-            data[4 * (y * width + x) + 3] = 255;
-        }
+    /// Largest in gamut chroma of a [`Kind::OkLchChromaLightnessPlane`]
+    /// slice within `margin` pixels of each row, in pixels from the left.
+    ///
+    /// Returns the smallest and the largest for each row, sampling lightness
+    /// finely enough not to miss the sharp cusp of the gamut.
+    fn oklch_gamut_rows(resolution: UVec2, hue: f32, margin: f32) -> Vec<(f32, f32)> {
+        let max_chroma = |l: f32| {
+            let mut low = 0.0;
+            let mut high = OKLCH_MAX_CHROMA;
+            for _ in 0..24 {
+                let mid = 0.5 * (low + high);
+                let rgb = OkLch::new(l, mid, hue).to_linear_srgb();
+                if [rgb.r, rgb.g, rgb.b]
+                    .iter()
+                    .all(|c| (0.0..=1.0).contains(c))
+                {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            low / OKLCH_MAX_CHROMA * resolution.x as f32
+        };
+
+        (0..resolution.y)
+            .map(|y| {
+                let steps = (8.0 * margin) as i32;
+                (-steps..=steps)
+                    .map(|i| {
+                        let py = y as f32 + 0.5 + margin * i as f32 / steps as f32;
+                        let l = 1.0 - py / resolution.y as f32;
+                        // the boundary meets the top and bottom edges
+                        if (0.0..=1.0).contains(&l) {
+                            max_chroma(l)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .fold((f32::INFINITY, 0.0f32), |(min, max), c| {
+                        (min.min(c), max.max(c))
+                    })
+            })
+            .collect()
     }
 
-    TextureData {
-        width: width as u32,
-        height: height as u32,
-        data,
+    /// Rasterizes a rectangular plot of `f(x, y)`, sampled at pixel centers.
+    ///
+    /// Coordinates are normalized between 0 and 1, Y up.
+    fn rasterize_rectangular_plot<C: Color>(
+        width: u32,
+        height: u32,
+        mapping: GamutMapping,
+        f: impl Fn(f32, f32) -> C,
+    ) -> Vec<u8> {
+        let colors: Vec<_> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let fx = (x as f32 + 0.5) / width as f32;
+                let fy = 1.0 - (y as f32 + 0.5) / height as f32;
+                f(fx, fy)
+            })
+            .collect();
+
+        let mut rgb = vec![NonlinearSrgb::default(); colors.len()];
+        batch::to_srgb_u8(&colors, &mut rgb, mapping);
+
+        rgb.iter().flat_map(|c| [c.r, c.g, c.b, 255]).collect()
     }
-}
 
-/// Rasterizes a radial plot of `f(angle, radius)`.
-///
-/// Angle is between 0 and 2π. Radius is between 0 and 1.
-///
-/// Returns a row-major sRGBA texture, 4 bytes per pixel.
-#[allow(unused)] // we'll need this soon for more slice kinds
-fn rasterize_circular_plot<C: Color, F: Fn(f32, f32) -> C>(
-    width: u32,
-    height: u32,
-    f: F,
-) -> TextureData {
-    rasterize_rectangular_plot(width, height, |x, y| {
-        let x = 2.0 * x - 1.0;
-        let y = 2.0 * y - 1.0;
-        let angle = PI + f32::atan2(-y, -x);
-        let radius = f32::hypot(x, y).clamp(0.0, 1.0);
-        f(angle, radius)
-    })
-}
+    /// Rasterizes a radial plot of `f(angle, radius)`.
+    ///
+    /// Angle is between 0 and 2π. Radius is between 0 and 1.
+    fn rasterize_circular_plot<C: Color>(
+        width: u32,
+        height: u32,
+        mapping: GamutMapping,
+        f: impl Fn(f32, f32) -> C,
+    ) -> Vec<u8> {
+        rasterize_rectangular_plot(width, height, mapping, |x, y| {
+            let x = 2.0 * x - 1.0;
+            let y = 2.0 * y - 1.0;
+            let angle = PI + f32::atan2(-y, -x);
+            let radius = f32::hypot(x, y).clamp(0.0, 1.0);
+            f(angle, radius)
+        })
+    }
 
-/// Rasterizes a plot of `f(y)`.
-///
-/// Returns a row-major sRGBA texture, 4 bytes per pixel. Width is set to 1.
-fn rasterize_vertical_gradient<C: Color, F: Fn(f32) -> C>(height: u32, f: F) -> TextureData {
-    rasterize_rectangular_plot(1, height, |_x, y| f(y))
-}
+    /// Renders the slice on the GPU, returning tightly packed sRGBA pixels.
+    fn render_slice(
+        context: &Arc<GlobalContext>,
+        renderer: &ColorPickerRenderer,
+        resolution: UVec2,
+        slice: paint_core::presentation::ColorPickerSlice,
+        selection: Option<paint_core::presentation::ColorPickerValue>,
+    ) -> Vec<u8> {
+        let target = testing::create_target(context, resolution);
 
-/// [`Cache`] settings.
-#[derive(Debug, Clone, Copy)]
-pub struct CacheSettings {
-    /// Width of the slices.
-    pub width: u32,
-    /// Height of the slices.
-    pub height: u32,
-    /// Number of fixed slices per slice kind.
-    pub num_fixed_slices: u32,
-}
+        renderer.render(
+            FrameContext::new(context),
+            target.0.texture(),
+            slice,
+            selection,
+        );
 
-/// Result of [`Cache`] slice retrieval.
-#[derive(Debug)]
-pub enum CacheHit {
-    /// Interpolation between two nearest slices.
-    Interpolated {
-        texture_view_a: wgpu::TextureView,
-        texture_view_b: wgpu::TextureView,
-        alpha: f32,
-    },
-
-    /// Exact slice for that constant exists.
-    Exact { texture_view: wgpu::TextureView },
-}
+        testing::download(context, &target)
+    }
 
-#[derive(Debug)]
-pub struct Cache {
-    kinds: HashMap<Kind, Vec<Slice>>,
-}
+    #[test]
+    fn gpu_slices_match_cpu_reference() {
+        use paint_core::presentation::ColorPickerSlice;
+
+        let context = testing::create_context();
+
+        let renderer = ColorPickerRenderer::new(context.clone());
+        let mapping = GamutMapping::default();
+
+        let square = UVec2::new(64, 64);
+        let strip = UVec2::new(16, 128);
 
-impl Cache {
-    pub fn new(device: wgpu::Device, queue: wgpu::Queue, settings: CacheSettings) -> Self {
-        let fixed_slices = create_fixed_slice_jobs(settings.num_fixed_slices)
-            .into_par_iter()
-            .map(|(kind, constant)| {
-                let data = rasterize_slice(settings.width, settings.height, kind, constant);
-                let texture_view = create_slice_texture(&device, &queue, data);
+        let mut cases = vec![
+            (
+                square,
+                ColorPickerSlice::OkhsvHueRing,
+                Kind::OkhsvHueRing,
+                0.0,
+            ),
+            (
+                strip,
+                ColorPickerSlice::OkhslHueVerticalGradient { markers: vec![] },
+                Kind::OkhslHueVerticalGradient,
+                0.0,
+            ),
+            (
+                strip,
+                ColorPickerSlice::AlphaStrip {
+                    color: Default::default(),
+                },
+                Kind::AlphaStrip,
+                0.0,
+            ),
+        ];
+
+        // hues near the cusps of the primaries and secondaries
+        for hue in [0.0, 0.5, 1.9, 2.5, 3.6, 4.6, 5.8] {
+            cases.extend([
                 (
-                    kind,
-                    Slice {
-                        constant,
-                        texture_view,
-                    },
-                )
-            })
-            .collect::<Vec<_>>();
+                    square,
+                    ColorPickerSlice::OkhsvHueSlice { hue },
+                    Kind::OkhsvHueSlice,
+                    hue,
+                ),
+                (
+                    square,
+                    ColorPickerSlice::OkhslHueSlice { hue },
+                    Kind::OkhslHueSlice,
+                    hue,
+                ),
+                (
+                    square,
+                    ColorPickerSlice::OkLchChromaLightnessPlane { hue },
+                    Kind::OkLchChromaLightnessPlane,
+                    hue,
+                ),
+            ]);
+        }
 
-        let mut kinds = HashMap::<Kind, Vec<Slice>>::new();
-        for (kind, slice) in fixed_slices {
-            let list = kinds.entry(kind).or_default();
-            list.push(slice);
+        for lightness in [0.0, 0.1, 0.5, 0.9, 1.0] {
+            cases.push((
+                square,
+                ColorPickerSlice::OkhslLightnessWheel { lightness },
+                Kind::OkhslLightnessWheel,
+                lightness,
+            ));
         }
 
-        Self { kinds }
+        for (resolution, slice, kind, constant) in cases {
+            let expected = rasterize_slice(resolution.x, resolution.y, kind, constant, mapping);
+            let actual = render_slice(&context, &renderer, resolution, slice, None);
+            assert_matches_reference(&actual, &expected, resolution, kind, constant);
+        }
     }
 
-    pub fn get(&self, kind: Kind, constant: f32) -> Option<CacheHit> {
-        let list = self.kinds.get(&kind)?;
+    #[test]
+    fn gpu_gamut_mappings_match_cpu_reference() {
+        use paint_core::presentation::ColorPickerSlice;
+
+        let context = testing::create_context();
 
-        // find slices to interpolate from using the sorted list
-        let slice_b_idx = list.iter().position(|slice| slice.constant >= constant)?;
+        let resolution = UVec2::new(64, 64);
 
-        let slice_b = &list[slice_b_idx];
+        for mapping in [
+            GamutMapping::Clip,
+            GamutMapping::PreserveChroma,
+            GamutMapping::ProjectToMidLightness,
+            GamutMapping::ProjectToCusp,
+            GamutMapping::AdaptiveL0 { alpha: 0.5 },
+            GamutMapping::Css,
+        ] {
+            let renderer = ColorPickerRenderer::with_gamut_mapping(context.clone(), mapping);
 
-        if slice_b.constant == constant {
-            return Some(CacheHit::Exact {
-                texture_view: slice_b.texture_view.clone(),
-            });
+            for hue in [0.5, 2.5, 4.6] {
+                let kind = Kind::OkLchChromaLightnessPlane;
+                let slice = ColorPickerSlice::OkLchChromaLightnessPlane { hue };
+
+                let expected = rasterize_slice(resolution.x, resolution.y, kind, hue, mapping);
+                let actual = render_slice(&context, &renderer, resolution, slice, None);
+                assert_matches_reference(&actual, &expected, resolution, kind, hue);
+            }
         }
+    }
 
-        let slice_a_idx = slice_b_idx.checked_sub(1)?;
-        let slice_a = &list[slice_a_idx];
+    /// Compares every component within [`TOLERANCE`], accounting for the
+    /// gamut boundary drawn over [`Kind::OkLchChromaLightnessPlane`].
+    fn assert_matches_reference(
+        actual: &[u8],
+        expected: &[u8],
+        resolution: UVec2,
+        kind: Kind,
+        constant: f32,
+    ) {
+        // pixels away from the boundary on either side, generously as the line
+        // is drawn with a first-order distance, which overshoots the cusp
+        let margin = 8.0;
+        let gamut_rows = oklch_gamut_rows(resolution, constant, margin);
 
-        let alpha = (constant - slice_a.constant) / (slice_b.constant - slice_a.constant);
+        for (i, (a, e)) in actual.chunks(4).zip(expected.chunks(4)).enumerate() {
+            let (x, y) = (i as u32 % resolution.x, i as u32 / resolution.x);
+            let mut e = [e[0], e[1], e[2], e[3]];
 
-        Some(CacheHit::Interpolated {
-            texture_view_a: slice_a.texture_view.clone(),
-            texture_view_b: slice_b.texture_view.clone(),
-            alpha,
-        })
+            if kind == Kind::OkLchChromaLightnessPlane {
+                let (min, max) = gamut_rows[y as usize];
+                let center = x as f32 + 0.5;
+
+                if center > max + margin {
+                    // out of gamut colors are dimmed
+                    let rgb = NonlinearSrgb::<u8>::new(e[0], e[1], e[2]).to_linear_srgb();
+                    let rgb = NonlinearSrgb::<u8>::from_linear_srgb(LinearSrgb::new(
+                        rgb.r * (1.0 - GAMUT_BOUNDARY_DIMMING),
+                        rgb.g * (1.0 - GAMUT_BOUNDARY_DIMMING),
+                        rgb.b * (1.0 - GAMUT_BOUNDARY_DIMMING),
+                    ));
+                    e = [rgb.r, rgb.g, rgb.b, e[3]];
+                } else if center > min - margin {
+                    continue;
+                }
+            }
+
+            for (a, e) in a.iter().zip(e) {
+                assert!(
+                    a.abs_diff(e) <= TOLERANCE,
+                    "{kind:?} {constant} at ({x}, {y}): GPU {a:?}, CPU {e:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn selection_marker_surrounds_selected_point() {
+        use paint_core::presentation::{ColorPickerSlice, ColorPickerValue};
+
+        let context = testing::create_context();
+
+        let renderer = ColorPickerRenderer::new(context.clone());
+        let resolution = UVec2::new(64, 64);
+        let slice = ColorPickerSlice::OkhsvHueSlice { hue: 1.0 };
+        let selection = ColorPickerValue::Okhsv(Okhsv::new(1.0, 0.5, 0.5));
+
+        let plain = render_slice(&context, &renderer, resolution, slice.clone(), None);
+        let marked = render_slice(&context, &renderer, resolution, slice, Some(selection));
+
+        let pixel = |data: &[u8], x: u32, y: u32| {
+            let i = 4 * (y * resolution.x + x) as usize;
+            [data[i], data[i + 1], data[i + 2]]
+        };
+
+        // the ring has a radius of 2.56 pixels around (32, 32)
+        assert_eq!(pixel(&marked, 34, 32), [255, 255, 255]);
+        assert_ne!(pixel(&plain, 34, 32), [255, 255, 255]);
+
+        assert_eq!(pixel(&marked, 10, 10), pixel(&plain, 10, 10));
+        assert_eq!(pixel(&marked, 50, 40), pixel(&plain, 50, 40));
     }
 }
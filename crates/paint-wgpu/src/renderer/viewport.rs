@@ -1,22 +1,90 @@
-use std::sync::Arc;
+use std::mem;
+use std::sync::{Arc, Mutex};
 
-use glam::{Affine2, Vec2};
+use glam::{Affine2, UVec2, Vec2};
+use paint_core::geometry::{DamageRegion, Rect};
 use paint_core::presentation;
-use wgpu::util::DeviceExt;
 use zerocopy::IntoBytes;
 
 use crate::context::GlobalContext;
+use crate::render_pipelines::canvas_border::Vertex;
+use crate::resources::ring_buffer::{Allocation, RingBuffer};
 use crate::texture::Texture;
 use crate::{FrameContext, bind_group_layouts, render_pipelines};
 
+/// [`ViewportRenderer`] settings.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewportSettings {
+    /// Zoom level at which canvas pixels stop being smoothed and are shown as
+    /// sharp squares instead.
+    pub nearest_filtering_min_scale: f32,
+    /// Whether to outline canvas pixels when nearest filtering is active.
+    pub show_pixel_grid: bool,
+}
+
+impl Default for ViewportSettings {
+    fn default() -> Self {
+        Self {
+            nearest_filtering_min_scale: 4.0,
+            show_pixel_grid: true,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ViewportRenderer {
     context: Arc<GlobalContext>,
+    settings: ViewportSettings,
     default_bind_group: wgpu::BindGroup,
+    background_bind_group: wgpu::BindGroup,
+    vertex_buffer: Mutex<RingBuffer>,
+    frame: Mutex<Option<Frame>>,
+}
+
+/// The last rendered frame, which is presented again with only the damaged
+/// area redrawn, as long as the view stays the same.
+#[derive(Debug)]
+struct Frame {
+    size: UVec2,
+    /// Transform, canvas resolution and filter of the viewport shown.
+    view: Option<(Affine2, UVec2, presentation::ViewFilter)>,
+    texture_view: wgpu::TextureView,
+    /// The frame before the view filter, if there is one.
+    unfiltered_view: Option<wgpu::TextureView>,
+}
+
+impl Frame {
+    fn new(device: &wgpu::Device, size: UVec2, filtered: bool) -> Self {
+        let create_view = |label| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            texture.create_view(&Default::default())
+        };
+
+        Self {
+            size,
+            view: None,
+            texture_view: create_view("Viewport Frame Texture"),
+            unfiltered_view: filtered.then(|| create_view("Viewport Unfiltered Texture")),
+        }
+    }
 }
 
 impl ViewportRenderer {
-    pub fn new(context: Arc<GlobalContext>) -> Self {
+    pub fn new(context: Arc<GlobalContext>, settings: ViewportSettings) -> Self {
         let default_bind_group = bind_group_layouts::sampled_textures::create_bind_group(
             &context.device,
             &context.bind_group_layouts,
@@ -24,9 +92,34 @@ impl ViewportRenderer {
             &[&context.default_texture_view],
         );
 
+        let background_texture = crate::utils::create_solid_texture(
+            &context.device,
+            &context.queue,
+            "Viewport Background Texture",
+            [0, 0, 0, 255],
+        );
+        let background_bind_group = bind_group_layouts::sampled_textures::create_bind_group(
+            &context.device,
+            &context.bind_group_layouts,
+            &context.default_sampler,
+            &[&background_texture.create_view(&Default::default())],
+        );
+
+        let vertex_buffer = Mutex::new(RingBuffer::new(
+            context.device.clone(),
+            context.queue.clone(),
+            "Viewport Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            4096,
+        ));
+
         Self {
             context,
+            settings,
             default_bind_group,
+            background_bind_group,
+            vertex_buffer,
+            frame: Mutex::new(None),
         }
     }
 
@@ -38,19 +131,128 @@ impl ViewportRenderer {
     ) {
         let start_time = std::time::Instant::now();
 
-        let resolution = Vec2::new(target.width() as f32, target.height() as f32);
+        let size = UVec2::new(target.width(), target.height());
 
         let pixel_to_ndc = Affine2::from_translation(Vec2::new(-1.0, 1.0))
-            * Affine2::from_scale(Vec2::new(2.0, -2.0) / resolution);
+            * Affine2::from_scale(Vec2::new(2.0, -2.0) / size.as_vec2());
 
         let target_view = target.create_view(&wgpu::TextureViewDescriptor {
             format: Some(wgpu::TextureFormat::Rgba8UnormSrgb),
             ..Default::default()
         });
 
+        // filters need the whole viewport, so it's rendered into an
+        // intermediate texture first
+        let filtered = viewport.filter != presentation::ViewFilter::None;
+
+        let mut frame = self.frame.lock().unwrap();
+        let frame = match &mut *frame {
+            Some(frame) if frame.size == size && frame.unfiltered_view.is_some() == filtered => {
+                frame
+            }
+            frame => frame.insert(Frame::new(&self.context.device, size, filtered)),
+        };
+
+        // the whole frame is redrawn, unless only part of the canvas has
+        // changed
+        let view = (
+            viewport.transform,
+            viewport.canvas.resolution,
+            viewport.filter,
+        );
+        let redrawn = match &viewport.damage {
+            Some(damage) if frame.view == Some(view) => damaged_area(viewport, damage),
+            _ => Rect::from_resolution(size),
+        };
+        frame.view = Some(view);
+
+        if let Some(scissor) = redrawn.to_pixels(size) {
+            let border_vertices = self.canvas_border_vertices(pixel_to_ndc, viewport);
+            let border_vertices = self
+                .vertex_buffer
+                .lock()
+                .unwrap()
+                .write(&ctx, border_vertices.as_bytes());
+
+            let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame
+                        .unfiltered_view
+                        .as_ref()
+                        .unwrap_or(&frame.texture_view),
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            let (offset, scissor_size) = scissor;
+            pass.set_scissor_rect(offset.x, offset.y, scissor_size.x, scissor_size.y);
+
+            self.render_background(&mut pass);
+            self.render_canvas_layers(&mut pass, pixel_to_ndc, viewport);
+            self.render_pixel_grid(&mut pass, pixel_to_ndc, viewport);
+            self.render_canvas_border(&mut pass, &border_vertices);
+            drop(pass);
+
+            if let Some(unfiltered_view) = &frame.unfiltered_view {
+                self.render_filter(
+                    &mut ctx,
+                    unfiltered_view,
+                    &frame.texture_view,
+                    viewport.filter,
+                    scissor,
+                );
+            }
+        }
+
+        self.present_frame(&mut ctx, &frame.texture_view, &target_view);
+
+        // TODO: should submit somewhere outside
+        self.context.submit(ctx);
+
+        tracing::trace!("viewport rendering CPU time is {:?}", start_time.elapsed());
+    }
+
+    /// Clears the scissor rect, which a load operation can't do.
+    fn render_background(&self, pass: &mut wgpu::RenderPass) {
+        let pipeline =
+            self.context
+                .render_pipelines
+                .get(render_pipelines::Key::FullscreenTriangle(
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                ));
+
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &self.background_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn present_frame(
+        &self,
+        ctx: &mut FrameContext,
+        frame_view: &wgpu::TextureView,
+        target_view: &wgpu::TextureView,
+    ) {
+        let bind_group = self
+            .context
+            .bind_group_cache
+            .get_sampled_textures(&self.context.default_sampler, &[frame_view]);
+
+        let pipeline =
+            self.context
+                .render_pipelines
+                .get(render_pipelines::Key::FullscreenTriangle(
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                ));
+
         let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &target_view,
+                view: target_view,
                 depth_slice: None,
                 resolve_target: None,
                 ops: wgpu::Operations {
@@ -61,23 +263,70 @@ impl ViewportRenderer {
             ..Default::default()
         });
 
-        self.render_canvas_layers(&mut pass, pixel_to_ndc, viewport);
-        self.render_canvas_border(&mut pass, pixel_to_ndc, viewport);
-        drop(pass);
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
 
-        // TODO: should finish and submit somewhere outside
-        let command_buffer = ctx.encoder.finish();
-        self.context.queue.submit(std::iter::once(command_buffer));
+    fn render_filter(
+        &self,
+        ctx: &mut FrameContext,
+        source: &wgpu::TextureView,
+        target_view: &wgpu::TextureView,
+        filter: presentation::ViewFilter,
+        (offset, size): (UVec2, UVec2),
+    ) {
+        let bind_group = self
+            .context
+            .bind_group_cache
+            .get_sampled_textures(&self.context.nearest_sampler, &[source]);
 
-        tracing::trace!("viewport rendering CPU time is {:?}", start_time.elapsed());
+        let pipeline = self
+            .context
+            .render_pipelines
+            .get(render_pipelines::Key::ViewFilter);
+
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target_view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+
+        let immediates = render_pipelines::view_filter::Immediates::new(filter);
+
+        pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_immediates(0, immediates.as_bytes());
+        pass.draw(0..3, 0..1);
+    }
+
+    fn render_canvas_border(&self, pass: &mut wgpu::RenderPass, vertices: &Allocation) {
+        let pipeline = self
+            .context
+            .render_pipelines
+            .get(render_pipelines::Key::CanvasBorder);
+
+        let num_vertices =
+            (vertices.range.end - vertices.range.start) / mem::size_of::<Vertex>() as u64;
+
+        pass.set_pipeline(&pipeline);
+        pass.set_vertex_buffer(0, vertices.slice());
+        pass.draw(0..(num_vertices as u32), 0..1);
     }
 
-    fn render_canvas_border(
+    fn canvas_border_vertices(
         &self,
-        pass: &mut wgpu::RenderPass,
         pixel_to_ndc: Affine2,
         viewport: &presentation::Viewport<Texture>,
-    ) {
+    ) -> [Vertex; 20] {
         // assuming scale is uniform
         let scale = viewport.transform.to_scale_angle_translation().0.x;
 
@@ -122,7 +371,7 @@ impl ViewportRenderer {
             Vec2::new(size.x, size.y),
         ];
 
-        let vertices = positions.map(|pos| {
+        positions.map(|pos| {
             // find nearest corner
             let (corner_pos, _) = corners
                 .iter()
@@ -136,29 +385,49 @@ impl ViewportRenderer {
             let corner_pos_px = viewport.transform.transform_point2(corner_pos);
             let corner_dist = (corner_pos_px - pos_px).length();
 
-            render_pipelines::canvas_border::Vertex {
+            Vertex {
                 pos_ndc,
                 corner_dist,
             }
-        });
+        })
+    }
 
-        let vertex_buffer =
-            self.context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: None,
-                    contents: vertices.as_bytes(),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
+    fn render_pixel_grid(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        pixel_to_ndc: Affine2,
+        viewport: &presentation::Viewport<Texture>,
+    ) {
+        // assuming scale is uniform
+        let scale = viewport.transform.to_scale_angle_translation().0.x;
+
+        if !self.settings.show_pixel_grid || scale < self.settings.nearest_filtering_min_scale {
+            return;
+        }
+
+        let transform = pixel_to_ndc
+            * viewport.transform
+            * Affine2::from_scale(viewport.canvas.resolution.as_vec2());
+
+        // fade the grid in, so it doesn't pop up right at the threshold
+        let opacity = (scale / self.settings.nearest_filtering_min_scale - 1.0).clamp(0.0, 1.0);
+        let opacity = 0.1 + 0.2 * opacity;
+
+        let immediates = render_pipelines::pixel_grid::Immediates {
+            transform: transform.matrix2,
+            translation: transform.translation,
+            resolution: viewport.canvas.resolution.as_vec2(),
+            opacity,
+        };
 
         let pipeline = self
             .context
             .render_pipelines
-            .get(render_pipelines::Key::CanvasBorder);
+            .get(render_pipelines::Key::PixelGrid);
 
         pass.set_pipeline(&pipeline);
-        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-        pass.draw(0..(vertices.len() as u32), 0..1);
+        pass.set_immediates(0, immediates.as_bytes());
+        pass.draw(0..6, 0..1);
     }
 
     fn render_canvas_layers(
@@ -167,41 +436,280 @@ impl ViewportRenderer {
         pixel_to_ndc: Affine2,
         viewport: &presentation::Viewport<Texture>,
     ) {
-        let transform = pixel_to_ndc
-            * viewport.transform
-            * Affine2::from_scale(viewport.canvas.resolution.as_vec2());
+        let quad_immediates = |rect: Rect| {
+            let transform = pixel_to_ndc
+                * viewport.transform
+                * Affine2::from_translation(rect.min)
+                * Affine2::from_scale(rect.size());
+
+            render_pipelines::single_quad::Immediates {
+                transform: transform.matrix2,
+                translation: transform.translation,
+            }
+        };
+        let canvas_immediates = quad_immediates(Rect::from_resolution(viewport.canvas.resolution));
 
         let pipeline = self
             .context
             .render_pipelines
-            .get(render_pipelines::Key::SingleQuad);
+            .get(render_pipelines::Key::SingleQuad(
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+            ));
 
         pass.set_pipeline(&pipeline);
         pass.set_bind_group(0, &self.default_bind_group, &[]);
 
-        let immediates = render_pipelines::single_quad::Immediates {
-            transform: transform.matrix2,
-            translation: transform.translation,
-        };
-        pass.set_immediates(0, immediates.as_bytes());
-
+        pass.set_immediates(0, canvas_immediates.as_bytes());
         pass.draw(0..6, 0..1);
 
+        // assuming scale is uniform
+        let scale = viewport.transform.to_scale_angle_translation().0.x;
+
+        let sampler = if scale >= self.settings.nearest_filtering_min_scale {
+            &self.context.nearest_sampler
+        } else {
+            &self.context.trilinear_sampler
+        };
+
         for layer in &viewport.canvas.layers {
-            match layer {
-                presentation::Layer::Texture(texture) => {
-                    let bind_group = bind_group_layouts::sampled_textures::create_bind_group(
-                        &self.context.device,
-                        &self.context.bind_group_layouts,
-                        &self.context.default_sampler,
-                        &[&texture.0],
-                    );
-
-                    pass.set_bind_group(0, &bind_group, &[]);
-                }
+            let (texture, immediates) = match layer {
+                presentation::Layer::Texture(texture) => (texture, canvas_immediates),
+                presentation::Layer::Placed { texture, rect } => (texture, quad_immediates(*rect)),
+            };
+
+            let bind_group = self
+                .context
+                .bind_group_cache
+                .get_sampled_textures(sampler, &[&texture.0]);
+
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.set_immediates(0, immediates.as_bytes());
+            pass.draw(0..6, 0..1);
+        }
+    }
+}
+
+/// Area of the screen showing the damaged part of the canvas.
+fn damaged_area(viewport: &presentation::Viewport<Texture>, damage: &DamageRegion) -> Rect {
+    if damage.is_empty() {
+        return Rect::EMPTY;
+    }
+
+    // linear filtering blends canvas pixels with their neighbours
+    let rect = damage.bounds().expand(1.0);
+    let corners = [
+        rect.min,
+        Vec2::new(rect.max.x, rect.min.y),
+        rect.max,
+        Vec2::new(rect.min.x, rect.max.y),
+    ]
+    .map(|corner| viewport.transform.transform_point2(corner));
+
+    let min = corners.into_iter().fold(Vec2::INFINITY, Vec2::min);
+    let max = corners.into_iter().fold(Vec2::NEG_INFINITY, Vec2::max);
+
+    // a few screen pixels more for the mip levels of a minified canvas
+    Rect::new(min, max).expand(4.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec2;
+    use paint_core::color::{LinearSrgb, NonlinearSrgb, batch};
+    use paint_core::presentation::ViewFilter;
+
+    use super::*;
+    use crate::FrameContext;
+    use crate::utils::testing;
+
+    /// Size of a canvas pixel on the screen, large enough for the pixel
+    /// centers to stay clear of the canvas border.
+    const SCALE: u32 = 16;
+
+    /// Renders the canvas layers magnified by [`SCALE`], returning the sRGB
+    /// color at the center of every canvas pixel.
+    fn render_viewport(
+        context: &Arc<GlobalContext>,
+        renderer: &ViewportRenderer,
+        layers: &[presentation::Layer<Texture>],
+        resolution: UVec2,
+        filter: ViewFilter,
+        damage: Option<DamageRegion>,
+    ) -> Vec<NonlinearSrgb<u8>> {
+        let target = testing::create_target(context, resolution * SCALE);
+
+        let viewport = presentation::Viewport {
+            transform: Affine2::from_scale(Vec2::splat(SCALE as f32)),
+            canvas: presentation::Canvas {
+                resolution,
+                layers: layers.to_vec(),
+            },
+            filter,
+            damage,
+        };
+        renderer.render(FrameContext::new(context), target.0.texture(), &viewport);
+
+        let downloaded = testing::download(context, &target);
+        let row_size = 4 * (resolution.x * SCALE) as usize;
+
+        (0..resolution.y)
+            .flat_map(|y| (0..resolution.x).map(move |x| UVec2::new(x, y)))
+            .map(|pixel| {
+                let center = pixel * SCALE + SCALE / 2;
+                let offset = center.y as usize * row_size + 4 * center.x as usize;
+                let [r, g, b, _] = downloaded[offset..offset + 4] else {
+                    unreachable!();
+                };
+                NonlinearSrgb::new(r, g, b)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gpu_placed_layers_cover_their_rect() {
+        let context = testing::create_context();
+
+        let renderer = ViewportRenderer::new(
+            context.clone(),
+            ViewportSettings {
+                show_pixel_grid: false,
+                ..Default::default()
+            },
+        );
+
+        let resolution = UVec2::new(8, 4);
+        let white = NonlinearSrgb::new(255, 255, 255);
+        let red = NonlinearSrgb::new(255, 0, 0);
+        let canvas = testing::upload(&context, resolution, &[white; 32]);
+        let placed = testing::upload(&context, UVec2::new(3, 2), &[red; 6]);
+
+        let rect = Rect::from_min_size(Vec2::new(4.0, 1.0), Vec2::new(3.0, 2.0));
+        let layers = [
+            presentation::Layer::Texture(canvas),
+            presentation::Layer::Placed {
+                texture: placed,
+                rect,
+            },
+        ];
+        let actual = render_viewport(
+            &context,
+            &renderer,
+            &layers,
+            resolution,
+            ViewFilter::None,
+            None,
+        );
+
+        for (i, color) in actual.iter().enumerate() {
+            let (x, y) = (i as u32 % resolution.x, i as u32 / resolution.x);
+            let inside = (4..7).contains(&x) && (1..3).contains(&y);
+            assert_eq!(*color, if inside { red } else { white }, "{x} {y}");
+        }
+    }
+
+    #[test]
+    fn gpu_view_filters_match_cpu_reference() {
+        let context = testing::create_context();
+
+        let renderer = ViewportRenderer::new(
+            context.clone(),
+            ViewportSettings {
+                show_pixel_grid: false,
+                ..Default::default()
+            },
+        );
+
+        let colors = [
+            NonlinearSrgb::new(255, 0, 0),
+            NonlinearSrgb::new(0, 255, 0),
+            NonlinearSrgb::new(0, 0, 255),
+            NonlinearSrgb::new(255, 255, 0),
+            NonlinearSrgb::new(0, 255, 255),
+            NonlinearSrgb::new(255, 0, 255),
+            NonlinearSrgb::new(128, 128, 128),
+            NonlinearSrgb::new(230, 120, 40),
+        ];
+        let resolution = UVec2::new(4, 2);
+
+        let canvas = [presentation::Layer::Texture(testing::upload(
+            &context, resolution, &colors,
+        ))];
+
+        let mut linear = vec![LinearSrgb::new(0.0, 0.0, 0.0); colors.len()];
+        batch::decode_srgb_u8(&colors, &mut linear);
+
+        for filter in [
+            ViewFilter::None,
+            ViewFilter::Luminance,
+            ViewFilter::Protanopia,
+            ViewFilter::Deuteranopia,
+            ViewFilter::Tritanopia,
+            ViewFilter::ValueBands { bands: 4 },
+        ] {
+            let filtered: Vec<_> = linear.iter().map(|&c| filter.apply(c)).collect();
+            let mut expected = vec![NonlinearSrgb::new(0, 0, 0); colors.len()];
+            batch::encode_srgb_u8(&filtered, &mut expected);
+
+            let actual = render_viewport(&context, &renderer, &canvas, resolution, filter, None);
+
+            for (a, e) in actual.iter().zip(&expected) {
+                assert!(
+                    [(a.r, e.r), (a.g, e.g), (a.b, e.b)]
+                        .iter()
+                        .all(|(a, e)| a.abs_diff(*e) <= 2),
+                    "{filter:?}: {a:?} != {e:?}"
+                );
             }
+        }
+    }
 
-            pass.draw(0..6, 0..1);
+    #[test]
+    fn gpu_only_damaged_area_is_redrawn() {
+        let context = testing::create_context();
+
+        let renderer = ViewportRenderer::new(
+            context.clone(),
+            ViewportSettings {
+                show_pixel_grid: false,
+                ..Default::default()
+            },
+        );
+
+        let resolution = UVec2::new(8, 4);
+        let white = NonlinearSrgb::new(255, 255, 255);
+        let red = NonlinearSrgb::new(255, 0, 0);
+        let white_canvas = [presentation::Layer::Texture(testing::upload(
+            &context,
+            resolution,
+            &[white; 32],
+        ))];
+        let red_canvas = [presentation::Layer::Texture(testing::upload(
+            &context, resolution, &[red; 32],
+        ))];
+
+        let render = |layers: &[_], damage| {
+            render_viewport(
+                &context,
+                &renderer,
+                layers,
+                resolution,
+                ViewFilter::None,
+                damage,
+            )
+        };
+
+        assert_eq!(render(&white_canvas, None), [white; 32]);
+
+        // the redrawn area is a canvas pixel larger, for linear filtering
+        let damage = Rect::from_min_size(Vec2::new(2.0, 1.0), Vec2::new(2.0, 2.0));
+        let actual = render(&red_canvas, Some(DamageRegion::from_rect(damage)));
+        for (i, color) in actual.iter().enumerate() {
+            let x = i as u32 % resolution.x;
+            let redrawn = (1..5).contains(&x);
+            assert_eq!(*color, if redrawn { red } else { white }, "{x}");
         }
+
+        assert_eq!(render(&red_canvas, Some(DamageRegion::new())), actual);
+        assert_eq!(render(&red_canvas, None), [red; 32]);
     }
 }
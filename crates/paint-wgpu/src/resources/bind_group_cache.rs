@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+
+use super::LruCache;
+use crate::bind_group_layouts;
+
+/// Largest number of texture views in a cached bind group.
+const MAX_TEXTURE_VIEWS: usize = 2;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct Key {
+    sampler: wgpu::Sampler,
+    /// Inline, so that looking up a cached bind group doesn't allocate.
+    texture_views: [Option<wgpu::TextureView>; MAX_TEXTURE_VIEWS],
+}
+
+/// Caches [`bind_group_layouts::sampled_textures`] bind groups, keyed by the
+/// identity of the sampler and the texture views.
+///
+/// Cached bind groups keep their textures alive, so the number of entries is
+/// limited.
+#[derive(Debug)]
+pub struct BindGroupCache {
+    device: wgpu::Device,
+    bind_group_layouts: Arc<bind_group_layouts::Storage>,
+    cache: Mutex<LruCache<Key, wgpu::BindGroup>>,
+}
+
+impl BindGroupCache {
+    pub fn new(
+        device: wgpu::Device,
+        bind_group_layouts: Arc<bind_group_layouts::Storage>,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            device,
+            bind_group_layouts,
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    pub fn get_sampled_textures(
+        &self,
+        sampler: &wgpu::Sampler,
+        texture_views: &[&wgpu::TextureView],
+    ) -> wgpu::BindGroup {
+        assert!(
+            texture_views.len() <= MAX_TEXTURE_VIEWS,
+            "bind groups with more than {MAX_TEXTURE_VIEWS} texture views aren't cached"
+        );
+        let key = Key {
+            sampler: sampler.clone(),
+            texture_views: std::array::from_fn(|i| texture_views.get(i).copied().cloned()),
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        cache
+            .get_or_insert_with(key, |_| {
+                bind_group_layouts::sampled_textures::create_bind_group(
+                    &self.device,
+                    &self.bind_group_layouts,
+                    sampler,
+                    texture_views,
+                )
+            })
+            .clone()
+    }
+}
@@ -0,0 +1,123 @@
+//! Reusable GPU resources, so steady-state frames don't allocate.
+
+pub mod bind_group_cache;
+pub mod ring_buffer;
+pub mod texture_pool;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Map which evicts the least recently used entry when it's full.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    entries: HashMap<K, (V, u64)>,
+    capacity: usize,
+    clock: u64,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::with_capacity(capacity + 1),
+            capacity: capacity.max(1),
+            clock: 0,
+        }
+    }
+
+    /// Returns the value for the key, creating it with `create` if missing.
+    pub fn get_or_insert_with(&mut self, key: K, create: impl FnOnce(&K) -> V) -> &V {
+        self.clock += 1;
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.evict_oldest();
+        }
+
+        let clock = self.clock;
+        let entry = self
+            .entries
+            .entry(key)
+            .or_insert_with_key(|key| (create(key), clock));
+        entry.1 = clock;
+        &entry.0
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(key, _)| key.clone());
+
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Map of values which are expensive to create, such as pipelines, created
+/// once per key on first use.
+///
+/// The map is only locked to look up the entry, not while the value is
+/// created, so creating a value only blocks other threads asking for the same
+/// key.
+#[derive(Debug)]
+pub struct OnceMap<K, V> {
+    entries: Mutex<HashMap<K, Arc<OnceLock<V>>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> OnceMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the value for the key, creating it with `create` if missing.
+    pub fn get_or_init(&self, key: K, create: impl FnOnce() -> V) -> V {
+        let entry = self.entries.lock().unwrap().entry(key).or_default().clone();
+        entry.get_or_init(create).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn once_map_creates_each_value_once() {
+        let map = OnceMap::new();
+        assert_eq!(map.get_or_init(1, || "one"), "one");
+        assert_eq!(map.get_or_init(1, || unreachable!()), "one");
+        assert_eq!(map.get_or_init(2, || "two"), "two");
+    }
+
+    #[test]
+    fn once_map_creation_doesnt_block_other_keys() {
+        let map = Arc::new(OnceMap::new());
+        let (started_sender, started) = mpsc::channel();
+        let (finish, finish_receiver) = mpsc::channel::<()>();
+
+        let slow = std::thread::spawn({
+            let map = map.clone();
+            move || {
+                map.get_or_init(1, || {
+                    started_sender.send(()).unwrap();
+                    finish_receiver.recv().unwrap();
+                    "slow"
+                })
+            }
+        });
+
+        // while the slow value is being created, other keys are available
+        started.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(map.get_or_init(2, || "fast"), "fast");
+
+        finish.send(()).unwrap();
+        assert_eq!(slow.join().unwrap(), "slow");
+        assert_eq!(map.get_or_init(1, || unreachable!()), "slow");
+    }
+}
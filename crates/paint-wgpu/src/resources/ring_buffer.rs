@@ -0,0 +1,124 @@
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::FrameContext;
+
+/// A part of a [`RingBuffer`] holding data written for a single draw.
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub buffer: wgpu::Buffer,
+    pub range: Range<u64>,
+}
+
+impl Allocation {
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(self.range.clone())
+    }
+}
+
+/// Growable buffer for per-frame data, such as vertices and instances.
+///
+/// Writes go through [`wgpu::Queue::write_buffer()`], which takes effect on
+/// the next submission. Because of that, a region can only be overwritten
+/// once the frame that used it has been submitted. Until then, writes are
+/// appended, and if there is no space left, the buffer is reallocated with a
+/// bigger size.
+#[derive(Debug)]
+pub struct RingBuffer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    buffer: wgpu::Buffer,
+    head: u64,
+    /// Submission flags of frames which used the buffer since the last wrap.
+    in_flight: Vec<Arc<AtomicBool>>,
+}
+
+impl RingBuffer {
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: u64,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let buffer = create_buffer(&device, label, usage, capacity);
+
+        Self {
+            device,
+            queue,
+            label,
+            usage,
+            buffer,
+            head: 0,
+            in_flight: Vec::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.buffer.size()
+    }
+
+    /// Writes the data, returning where it's stored.
+    ///
+    /// The length of the data must be a multiple of
+    /// [`wgpu::COPY_BUFFER_ALIGNMENT`].
+    pub fn write(&mut self, ctx: &FrameContext, data: &[u8]) -> Allocation {
+        let size = data.len() as u64;
+        debug_assert!(size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT));
+
+        if self.head + size > self.capacity() {
+            let can_wrap = size <= self.capacity()
+                && self
+                    .in_flight
+                    .iter()
+                    .all(|submitted| submitted.load(Ordering::Acquire));
+
+            if !can_wrap {
+                let capacity = (2 * self.capacity()).max(size.next_power_of_two());
+                tracing::debug!("Growing {} to {capacity} bytes", self.label);
+                self.buffer = create_buffer(&self.device, self.label, self.usage, capacity);
+            }
+
+            self.head = 0;
+            self.in_flight.clear();
+        }
+
+        let range = self.head..(self.head + size);
+        if size > 0 {
+            self.queue.write_buffer(&self.buffer, range.start, data);
+        }
+
+        self.head = range.end;
+
+        if !self
+            .in_flight
+            .last()
+            .is_some_and(|submitted| Arc::ptr_eq(submitted, &ctx.submitted))
+        {
+            self.in_flight.push(ctx.submitted.clone());
+        }
+
+        Allocation {
+            buffer: self.buffer.clone(),
+            range,
+        }
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    capacity: u64,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: capacity.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+        usage,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// Properties which make textures interchangeable.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Key {
+    pub width: u32,
+    pub height: u32,
+    pub mip_level_count: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+}
+
+/// Recycles textures, so short-lived textures (such as brush stroke previews)
+/// don't allocate every time.
+///
+/// Textures go back to the pool when the [`PooledTexture`] is dropped. Their
+/// contents are left as is, so users have to clear them.
+#[derive(Debug)]
+pub struct TexturePool {
+    device: wgpu::Device,
+    free: Mutex<HashMap<Key, Vec<wgpu::Texture>>>,
+    max_free_per_key: usize,
+}
+
+impl TexturePool {
+    pub fn new(device: wgpu::Device, max_free_per_key: usize) -> Self {
+        Self {
+            device,
+            free: Mutex::new(HashMap::new()),
+            max_free_per_key,
+        }
+    }
+
+    pub fn acquire(self: &Arc<Self>, label: &'static str, key: Key) -> PooledTexture {
+        let texture = self
+            .free
+            .lock()
+            .unwrap()
+            .get_mut(&key)
+            .and_then(Vec::pop)
+            .unwrap_or_else(|| {
+                tracing::debug!("Allocating pooled texture {label:?}: {key:?}");
+                self.create(label, key)
+            });
+
+        PooledTexture {
+            pool: self.clone(),
+            key,
+            texture: Some(texture),
+        }
+    }
+
+    fn create(&self, label: &'static str, key: Key) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: key.width,
+                height: key.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: key.mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: key.format,
+            usage: key.usage,
+            view_formats: &[],
+        })
+    }
+
+    fn release(&self, key: Key, texture: wgpu::Texture) {
+        let mut free = self.free.lock().unwrap();
+        let list = free.entry(key).or_default();
+        if list.len() < self.max_free_per_key {
+            list.push(texture);
+        }
+    }
+}
+
+/// A texture borrowed from a [`TexturePool`].
+#[derive(Debug)]
+pub struct PooledTexture {
+    pool: Arc<TexturePool>,
+    key: Key,
+    texture: Option<wgpu::Texture>,
+}
+
+impl Deref for PooledTexture {
+    type Target = wgpu::Texture;
+
+    fn deref(&self) -> &Self::Target {
+        self.texture.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledTexture {
+    fn drop(&mut self) {
+        if let Some(texture) = self.texture.take() {
+            self.pool.release(self.key, texture);
+        }
+    }
+}
@@ -1,32 +1,97 @@
-// TODO: add spir-v cache
+// Compiled shaders are not cached separately, driver-level caching happens
+// through the pipeline cache, see `crate::pipeline_cache`.
 
-use std::collections::HashMap;
-use std::sync::Mutex;
+use crate::resources::OnceMap;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum Key {
     FullscreenTriangle,
-    FullscreenTriangleInterpolateTwoTextures,
     SingleQuad,
     StampedBrush,
     CanvasBorder,
+    PixelGrid,
+    ColorPickerSlice,
+    HueMarker,
+    AlphaGradient,
+    GamutBoundary,
+    SelectionMarker,
+    Blend,
+    ViewFilter,
+    Adjustment,
+    Filter,
+    LiquifyDab,
+    LiquifyResample,
+    ShapeFill,
 }
 
 impl Key {
-    pub fn compile(self, device: &wgpu::Device) -> wgpu::ShaderModule {
-        let wgsl = match self {
+    pub const ALL: [Key; 17] = [
+        Key::FullscreenTriangle,
+        Key::SingleQuad,
+        Key::StampedBrush,
+        Key::CanvasBorder,
+        Key::PixelGrid,
+        Key::ColorPickerSlice,
+        Key::HueMarker,
+        Key::AlphaGradient,
+        Key::GamutBoundary,
+        Key::SelectionMarker,
+        Key::Blend,
+        Key::ViewFilter,
+        Key::Adjustment,
+        Key::Filter,
+        Key::LiquifyDab,
+        Key::LiquifyResample,
+        Key::ShapeFill,
+    ];
+
+    /// WGSL source code of the shader.
+    ///
+    /// WGSL has no imports, so shared code such as `oklab.wgsl` is
+    /// concatenated.
+    pub fn source(self) -> &'static str {
+        match self {
             Key::FullscreenTriangle => include_str!("wgsl/fullscreen_triangle.wgsl"),
-            Key::FullscreenTriangleInterpolateTwoTextures => {
-                include_str!("wgsl/fullscreen_triangle_interpolate_two_textures.wgsl")
-            }
             Key::SingleQuad => include_str!("wgsl/single_quad.wgsl"),
             Key::StampedBrush => include_str!("wgsl/stamped_brush.wgsl"),
             Key::CanvasBorder => include_str!("wgsl/canvas_border.wgsl"),
-        };
+            Key::PixelGrid => include_str!("wgsl/pixel_grid.wgsl"),
+            Key::ColorPickerSlice => concat!(
+                include_str!("wgsl/oklab.wgsl"),
+                include_str!("wgsl/color_picker_slice.wgsl")
+            ),
+            Key::HueMarker => include_str!("wgsl/hue_marker.wgsl"),
+            Key::AlphaGradient => include_str!("wgsl/alpha_gradient.wgsl"),
+            Key::GamutBoundary => concat!(
+                include_str!("wgsl/oklab.wgsl"),
+                include_str!("wgsl/gamut_boundary.wgsl")
+            ),
+            Key::SelectionMarker => include_str!("wgsl/selection_marker.wgsl"),
+            // appended rather than prepended, unused types declared before
+            // the immediates break them on the GL backend
+            Key::ViewFilter => concat!(
+                include_str!("wgsl/view_filter.wgsl"),
+                include_str!("wgsl/oklab.wgsl")
+            ),
+            Key::Blend => concat!(
+                include_str!("wgsl/blend.wgsl"),
+                include_str!("wgsl/oklab.wgsl")
+            ),
+            Key::Adjustment => concat!(
+                include_str!("wgsl/adjustment.wgsl"),
+                include_str!("wgsl/oklab.wgsl")
+            ),
+            Key::Filter => include_str!("wgsl/filter.wgsl"),
+            Key::LiquifyDab => include_str!("wgsl/liquify_dab.wgsl"),
+            Key::LiquifyResample => include_str!("wgsl/liquify_resample.wgsl"),
+            Key::ShapeFill => include_str!("wgsl/shape_fill.wgsl"),
+        }
+    }
 
+    pub fn compile(self, device: &wgpu::Device) -> wgpu::ShaderModule {
         device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some(&format!("{self:?} Shader")),
-            source: wgpu::ShaderSource::Wgsl(wgsl.into()),
+            source: wgpu::ShaderSource::Wgsl(self.source().into()),
         })
     }
 }
@@ -34,22 +99,18 @@ impl Key {
 #[derive(Debug)]
 pub struct Storage {
     device: wgpu::Device,
-    shaders: Mutex<HashMap<Key, wgpu::ShaderModule>>,
+    shaders: OnceMap<Key, wgpu::ShaderModule>,
 }
 
 impl Storage {
     pub fn new(device: wgpu::Device) -> Self {
         Self {
             device,
-            shaders: Mutex::new(HashMap::new()),
+            shaders: OnceMap::new(),
         }
     }
 
     pub fn get(&self, key: Key) -> wgpu::ShaderModule {
-        let mut shaders = self.shaders.lock().unwrap();
-        shaders
-            .entry(key)
-            .or_insert_with(|| key.compile(&self.device))
-            .clone()
+        self.shaders.get_or_init(key, || key.compile(&self.device))
     }
 }
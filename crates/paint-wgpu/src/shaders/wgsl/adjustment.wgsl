@@ -0,0 +1,134 @@
+// Applies an adjustment layer to the texture below it. Must match
+// `paint_core::adjustment::AdjustmentLayer::apply()`.
+
+const KIND_LEVELS: u32 = 0u;
+const KIND_CURVES: u32 = 1u;
+const KIND_HUE_CHROMA_LIGHTNESS: u32 = 2u;
+const KIND_COLOR_BALANCE: u32 = 3u;
+const KIND_INVERT: u32 = 4u;
+
+// `GamutMapping::default()`
+const DEFAULT_GAMUT_MAPPING: u32 = GAMUT_MAPPING_ADAPTIVE_L0;
+const DEFAULT_GAMUT_MAPPING_ALPHA: f32 = 0.05;
+
+// number of entries of the curve lookup table
+const CURVE_SAMPLES: u32 = 256u;
+
+struct Immediates {
+    // parameters, whose meaning depends on the kind
+    p0: vec4<f32>,
+    p1: vec4<f32>,
+    p2: vec4<f32>,
+    kind: u32,
+    opacity: f32,
+}
+
+var<immediate> imm: Immediates;
+
+@group(0) @binding(0)
+var u_sampler: sampler;
+
+@group(0) @binding(1)
+var u_texture: texture_2d<f32>;
+
+// red, green, blue and Oklab lightness curves in the RGBA channels
+@group(0) @binding(2)
+var u_curves: texture_2d<f32>;
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    return vec4(positions[in_vertex_index], 0.0, 1.0);
+}
+
+fn levels(x: vec3<f32>) -> vec3<f32> {
+    let input_black = imm.p0.x;
+    let input_white = imm.p0.y;
+    let gamma = imm.p0.z;
+    let output_black = imm.p1.x;
+    let output_white = imm.p1.y;
+
+    let range = max(input_white - input_black, 1.1920929e-7);
+    let t = clamp((x - input_black) / range, vec3(0.0), vec3(1.0));
+    let t_ = select(pow(t, vec3(1.0 / max(gamma, 1.1920929e-7))), vec3(0.0), t <= vec3(0.0));
+    return output_black + t_ * (output_white - output_black);
+}
+
+// Evaluates a curve from the lookup table, linearly interpolating between
+// the samples.
+fn curve(x: f32, channel: u32) -> f32 {
+    let p = clamp(x, 0.0, 1.0) * f32(CURVE_SAMPLES - 1u);
+    let i = u32(floor(p));
+    let j = min(i + 1u, CURVE_SAMPLES - 1u);
+
+    let a = textureLoad(u_curves, vec2(i32(i), 0), 0)[channel];
+    let b = textureLoad(u_curves, vec2(i32(j), 0), 0)[channel];
+    return mix(a, b, p - floor(p));
+}
+
+fn curves(c: vec3<f32>) -> vec3<f32> {
+    let e = srgb_encode(c);
+    let e_ = vec3(curve(e.r, 0u), curve(e.g, 1u), curve(e.b, 2u));
+
+    var lab = linear_srgb_to_oklab(srgb_decode(clamp(e_, vec3(0.0), vec3(1.0))));
+    lab.x = curve(lab.x, 3u);
+    return gamut_map(lab, DEFAULT_GAMUT_MAPPING, DEFAULT_GAMUT_MAPPING_ALPHA);
+}
+
+fn hue_chroma_lightness(c: vec3<f32>) -> vec3<f32> {
+    let lab = linear_srgb_to_oklab(c);
+
+    let h = atan2(lab.z, lab.y) + imm.p0.x;
+    let chroma = max(length(lab.yz) * imm.p0.y, 0.0);
+    let l = clamp(lab.x + imm.p0.z, 0.0, 1.0);
+
+    let lab_ = vec3(l, chroma * cos(h), chroma * sin(h));
+    return gamut_map(lab_, DEFAULT_GAMUT_MAPPING, DEFAULT_GAMUT_MAPPING_ALPHA);
+}
+
+fn color_balance(c: vec3<f32>) -> vec3<f32> {
+    let l = clamp(linear_srgb_to_oklab(c).x, 0.0, 1.0);
+    let shadow_weight = (1.0 - l) * (1.0 - l);
+    let highlight_weight = l * l;
+    let midtone_weight = 1.0 - shadow_weight - highlight_weight;
+
+    let offset = imm.p0.xyz * shadow_weight
+        + imm.p1.xyz * midtone_weight
+        + imm.p2.xyz * highlight_weight;
+    return srgb_decode(clamp(srgb_encode(c) + offset, vec3(0.0), vec3(1.0)));
+}
+
+fn adjust(c: vec3<f32>) -> vec3<f32> {
+    switch imm.kind {
+        case KIND_LEVELS: {
+            return srgb_decode(clamp(levels(srgb_encode(c)), vec3(0.0), vec3(1.0)));
+        }
+        case KIND_CURVES: {
+            return curves(c);
+        }
+        case KIND_HUE_CHROMA_LIGHTNESS: {
+            return hue_chroma_lightness(c);
+        }
+        case KIND_COLOR_BALANCE: {
+            return color_balance(c);
+        }
+        case KIND_INVERT: {
+            return srgb_decode(1.0 - clamp(srgb_encode(c), vec3(0.0), vec3(1.0)));
+        }
+        default: {
+            return c;
+        }
+    }
+}
+
+@fragment
+fn fragment(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(u_texture, vec2<i32>(pos.xy), 0);
+    let adjusted = adjust(color.rgb);
+    return vec4(mix(color.rgb, adjusted, clamp(imm.opacity, 0.0, 1.0)), color.a);
+}
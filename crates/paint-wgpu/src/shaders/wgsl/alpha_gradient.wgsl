@@ -1,18 +1,9 @@
 struct Immediates {
-    alpha: f32,
+    color: vec4<f32>,
 }
 
 var<immediate> imm: Immediates;
 
-@group(0) @binding(0)
-var u_sampler: sampler;
-
-@group(0) @binding(1)
-var u_texture_a: texture_2d<f32>;
-
-@group(0) @binding(2)
-var u_texture_b: texture_2d<f32>;
-
 struct VertexOutput {
     @builtin(position) pos: vec4<f32>,
     @location(0) uv: vec2<f32>,
@@ -33,7 +24,7 @@ fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
     );
 
     var output: VertexOutput;
-    
+
     output.pos = vec4(positions[in_vertex_index], 0.0, 1.0);
     output.uv = uvs[in_vertex_index];
 
@@ -42,7 +33,5 @@ fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
 
 @fragment
 fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
-    let a = textureSample(u_texture_a, u_sampler, v.uv);
-    let b = textureSample(u_texture_b, u_sampler, v.uv);
-    return mix(a, b, imm.alpha);
+    return vec4(imm.color.rgb, imm.color.a * (1.0 - v.uv.y));
 }
@@ -0,0 +1,86 @@
+// Composites a source texture over a destination texture in a selectable
+// blending space. Must match `paint_core::color::BlendingSpace::source_over()`.
+//
+// `resolve` turns colors accumulated premultiplied in the blending space, such
+// as stamped brush dabs, back into linear colors, and only reads `u_src`.
+
+const SPACE_LINEAR_LIGHT: u32 = 0u;
+const SPACE_GAMMA_SRGB: u32 = 1u;
+const SPACE_OKLAB: u32 = 2u;
+
+struct Immediates {
+    space: u32,
+}
+
+var<immediate> imm: Immediates;
+
+@group(0) @binding(0)
+var u_sampler: sampler;
+
+@group(0) @binding(1)
+var u_src: texture_2d<f32>;
+
+@group(0) @binding(2)
+var u_dst: texture_2d<f32>;
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    return vec4(positions[in_vertex_index], 0.0, 1.0);
+}
+
+fn encode(c: vec3<f32>) -> vec3<f32> {
+    switch imm.space {
+        case SPACE_GAMMA_SRGB: { return srgb_encode(c); }
+        case SPACE_OKLAB: { return linear_srgb_to_oklab(c); }
+        case SPACE_LINEAR_LIGHT, default: { return c; }
+    }
+}
+
+fn decode(c: vec3<f32>) -> vec3<f32> {
+    switch imm.space {
+        case SPACE_GAMMA_SRGB: { return srgb_decode(c); }
+        case SPACE_OKLAB: { return oklab_to_linear_srgb(c); }
+        case SPACE_LINEAR_LIGHT, default: { return c; }
+    }
+}
+
+@fragment
+fn fragment(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(pos.xy);
+    let src = textureLoad(u_src, coords, 0);
+    let dst = textureLoad(u_dst, coords, 0);
+
+    let src_alpha = clamp(src.a, 0.0, 1.0);
+    let dst_alpha = clamp(dst.a, 0.0, 1.0);
+
+    let dst_weight = dst_alpha * (1.0 - src_alpha);
+    let alpha = src_alpha + dst_weight;
+
+    if alpha <= 0.0 {
+        return vec4(0.0);
+    }
+
+    let mixed = (encode(src.rgb) * src_alpha + encode(dst.rgb) * dst_weight) / alpha;
+    let color = clamp(decode(mixed), vec3(0.0), vec3(1.0));
+
+    return vec4(color, alpha);
+}
+
+@fragment
+fn resolve(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let src = textureLoad(u_src, vec2<i32>(pos.xy), 0);
+
+    if src.a <= 0.0 {
+        return vec4(0.0);
+    }
+
+    let color = clamp(decode(src.rgb / src.a), vec3(0.0), vec3(1.0));
+
+    return vec4(color, clamp(src.a, 0.0, 1.0));
+}
@@ -0,0 +1,114 @@
+// Color picker slices, the CPU reference being `paint_core::color`.
+// Requires `oklab.wgsl`.
+
+const PI: f32 = 3.14159265358979;
+
+// slice kinds, the values of `slice::Kind`
+const OKHSV_HUE_SLICE: u32 = 0u;
+const OKHSL_HUE_VERTICAL_GRADIENT: u32 = 1u;
+const OKHSL_HUE_SLICE: u32 = 2u;
+const OKHSL_LIGHTNESS_WHEEL: u32 = 3u;
+const OKHSV_HUE_RING: u32 = 4u;
+const OKLCH_CHROMA_LIGHTNESS_PLANE: u32 = 5u;
+const ALPHA_STRIP: u32 = 6u;
+
+const OKLCH_MAX_CHROMA: f32 = 0.4;
+const ALPHA_STRIP_CELLS: f32 = 2.0;
+
+struct Immediates {
+    kind: u32,
+    constant: f32,
+    resolution: vec2<f32>,
+    gamut_mapping: u32,
+    gamut_mapping_alpha: f32,
+}
+
+var<immediate> imm: Immediates;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    const uvs = array<vec2<f32>, 3>(
+        vec2(0.0,  1.0),
+        vec2(2.0,  1.0),
+        vec2(0.0, -1.0),
+    );
+
+    var output: VertexOutput;
+
+    output.pos = vec4(positions[in_vertex_index], 0.0, 1.0);
+    output.uv = uvs[in_vertex_index];
+
+    return output;
+}
+
+@fragment
+fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
+    // plot coordinates, Y up
+    let x = v.uv.x;
+    let y = 1.0 - v.uv.y;
+
+    // polar coordinates for the circular plots
+    let px = 2.0 * x - 1.0;
+    let py = 2.0 * y - 1.0;
+    let angle = PI + atan2(-py, -px);
+    let radius = clamp(length(vec2(px, py)), 0.0, 1.0);
+
+    var lab: vec3<f32>;
+
+    switch imm.kind {
+        case OKHSV_HUE_SLICE: {
+            lab = okhsv_to_oklab(vec3(imm.constant, x, y));
+        }
+        case OKHSL_HUE_VERTICAL_GRADIENT: {
+            lab = okhsl_to_oklab(vec3((1.0 - y) * 2.0 * PI, 1.0, 0.62));
+        }
+        case OKHSL_HUE_SLICE: {
+            lab = okhsl_to_oklab(vec3(imm.constant, x, y));
+        }
+        case OKHSL_LIGHTNESS_WHEEL: {
+            lab = okhsl_to_oklab(vec3(angle, radius, imm.constant));
+        }
+        case OKHSV_HUE_RING: {
+            lab = okhsv_to_oklab(vec3(angle, 1.0, 1.0));
+        }
+        case OKLCH_CHROMA_LIGHTNESS_PLANE: {
+            let c = x * OKLCH_MAX_CHROMA;
+            lab = vec3(y, c * cos(imm.constant), c * sin(imm.constant));
+        }
+        case ALPHA_STRIP: {
+            return vec4(vec3(checkerboard(v.pos.xy)), 1.0);
+        }
+        default: {
+            return vec4(0.0);
+        }
+    }
+
+    let rgb = gamut_map(lab, imm.gamut_mapping, imm.gamut_mapping_alpha);
+    return vec4(rgb, 1.0);
+}
+
+/// Linear gray level of the alpha strip checkerboard, with square cells and
+/// the bottom left cell being white.
+fn checkerboard(pos: vec2<f32>) -> f32 {
+    let cell = imm.resolution.x / ALPHA_STRIP_CELLS;
+    let column = min(floor(pos.x / cell), ALPHA_STRIP_CELLS - 1.0);
+    let row = floor((imm.resolution.y - pos.y) / cell);
+
+    if (u32(column + row) & 1u) == 0u {
+        return 1.0;
+    }
+
+    // 0.8 in sRGB
+    return 0.603827;
+}
@@ -0,0 +1,147 @@
+// Image filters. Must match `paint_core::filter::Filter::apply()`.
+//
+// Filters run on premultiplied colors in 16-bit float textures: the canvas is
+// premultiplied first, then blurred by any number of passes, and the result
+// is combined with the original and unpremultiplied.
+
+const MODE_BLUR: u32 = 0u;
+const MODE_UNSHARP_MASK: u32 = 1u;
+const MODE_NOISE: u32 = 2u;
+const MODE_MONOCHROME_NOISE: u32 = 3u;
+
+struct Immediates {
+    step: vec2<f32>,
+    radius: u32,
+    sigma: f32,
+    amount: f32,
+    threshold: f32,
+    seed: u32,
+    mode: u32,
+}
+
+var<immediate> imm: Immediates;
+
+// the input of the pass, the blurred image when finishing
+@group(0) @binding(0)
+var u_texture: texture_2d<f32>;
+
+// the premultiplied original when finishing
+@group(0) @binding(1)
+var u_original: texture_2d<f32>;
+
+@group(0) @binding(2)
+var u_output: texture_storage_2d<rgba16float, write>;
+
+fn load(p: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(u_texture));
+    return textureLoad(u_texture, clamp(p, vec2(0), size - 1), 0);
+}
+
+// bilinear interpolation between pixel centers, at integer coordinates
+fn load_bilinear(p: vec2<f32>) -> vec4<f32> {
+    let p0 = floor(p);
+    let f = p - p0;
+    let i = vec2<i32>(p0);
+
+    let top = mix(load(i), load(i + vec2(1, 0)), f.x);
+    let bottom = mix(load(i + vec2(0, 1)), load(i + vec2(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+fn in_bounds(id: vec3<u32>) -> bool {
+    return all(id.xy < textureDimensions(u_output));
+}
+
+@compute @workgroup_size(8, 8)
+fn premultiply(@builtin(global_invocation_id) id: vec3<u32>) {
+    if !in_bounds(id) {
+        return;
+    }
+
+    let c = textureLoad(u_texture, id.xy, 0);
+    let a = clamp(c.a, 0.0, 1.0);
+    textureStore(u_output, id.xy, vec4(c.rgb * a, a));
+}
+
+@compute @workgroup_size(8, 8)
+fn blur(@builtin(global_invocation_id) id: vec3<u32>) {
+    if !in_bounds(id) {
+        return;
+    }
+
+    let position = vec2<f32>(id.xy);
+    let r = i32(imm.radius);
+
+    var sum = vec4(0.0);
+    var weight_sum = 0.0;
+    for (var i = -r; i <= r; i++) {
+        var weight = 1.0;
+        if imm.sigma > 0.0 {
+            let x = f32(i) / imm.sigma;
+            weight = exp(-0.5 * x * x);
+        }
+
+        sum += load_bilinear(position + imm.step * f32(i)) * weight;
+        weight_sum += weight;
+    }
+
+    textureStore(u_output, id.xy, sum / weight_sum);
+}
+
+// PCG hash by Jarzynski and Olano, "Hash Functions for GPU Rendering"
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// uniform between -1 and 1
+fn to_noise(hash: u32) -> f32 {
+    return f32(hash >> 8u) / 8388608.0 - 1.0;
+}
+
+fn unsharp_mask(original: vec4<f32>, blurred: vec4<f32>) -> vec4<f32> {
+    let difference = original.rgb - blurred.rgb;
+    let masked = select(vec3(0.0), difference, abs(difference) >= vec3(imm.threshold));
+    let rgb = clamp(original.rgb + masked * imm.amount, vec3(0.0), vec3(original.a));
+    return vec4(rgb, original.a);
+}
+
+fn add_noise(position: vec2<u32>, c: vec4<f32>) -> vec4<f32> {
+    let hash = pcg(position.x + pcg(position.y + pcg(imm.seed)));
+
+    var noise = vec3(to_noise(hash));
+    if imm.mode == MODE_NOISE {
+        noise = vec3(to_noise(pcg(hash)), to_noise(pcg(hash + 1u)), to_noise(pcg(hash + 2u)));
+    }
+
+    let rgb = clamp(c.rgb + noise * imm.amount * c.a, vec3(0.0), vec3(c.a));
+    return vec4(rgb, c.a);
+}
+
+@compute @workgroup_size(8, 8)
+fn finish(@builtin(global_invocation_id) id: vec3<u32>) {
+    if !in_bounds(id) {
+        return;
+    }
+
+    let blurred = textureLoad(u_texture, id.xy, 0);
+    let original = textureLoad(u_original, id.xy, 0);
+
+    var c = blurred;
+    switch imm.mode {
+        case MODE_UNSHARP_MASK: {
+            c = unsharp_mask(original, blurred);
+        }
+        case MODE_NOISE, MODE_MONOCHROME_NOISE: {
+            c = add_noise(id.xy, original);
+        }
+        default: {}
+    }
+
+    var rgb = vec3(0.0);
+    if c.a > 0.0 {
+        rgb = c.rgb / c.a;
+    }
+    textureStore(u_output, id.xy, vec4(rgb, c.a));
+}
@@ -0,0 +1,62 @@
+// Gamut boundary over `OkLchChromaLightnessPlane` color picker slices,
+// dimming colors outside of sRGB. Requires `oklab.wgsl`.
+
+struct Immediates {
+    hue: f32,
+    max_chroma: f32,
+    line_width: f32,
+}
+
+var<immediate> imm: Immediates;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    const uvs = array<vec2<f32>, 3>(
+        vec2(0.0,  1.0),
+        vec2(2.0,  1.0),
+        vec2(0.0, -1.0),
+    );
+
+    var output: VertexOutput;
+
+    output.pos = vec4(positions[in_vertex_index], 0.0, 1.0);
+    output.uv = uvs[in_vertex_index];
+
+    return output;
+}
+
+@fragment
+fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
+    let l = clamp(1.0 - v.uv.y, 0.0, 1.0);
+    let c = v.uv.x * imm.max_chroma;
+
+    var c_max = 0.0;
+    if l > 0.0 && l < 1.0 {
+        let a_ = cos(imm.hue);
+        let b_ = sin(imm.hue);
+        c_max = find_gamut_intersection(a_, b_, l, 1.0, l, find_cusp(a_, b_));
+    }
+
+    // approximate distance to the boundary in pixels, positive outside
+    let f = c - c_max;
+    let dist = f / max(length(vec2(dpdx(f), dpdy(f))), 1e-6);
+
+    let half_width = 0.5 * imm.line_width;
+    let line = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, abs(dist));
+    let outside = smoothstep(-0.5, 0.5, dist);
+
+    // white line over dimmed out of gamut colors
+    let alpha = max(line, 0.35 * outside);
+    return vec4(vec3(line / max(alpha, 1e-6)), alpha);
+}
@@ -0,0 +1,49 @@
+struct Immediates {
+    position: f32,
+    width: f32,
+}
+
+var<immediate> imm: Immediates;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    const uvs = array<vec2<f32>, 3>(
+        vec2(0.0,  1.0),
+        vec2(2.0,  1.0),
+        vec2(0.0, -1.0),
+    );
+
+    var output: VertexOutput;
+
+    output.pos = vec4(positions[in_vertex_index], 0.0, 1.0);
+    output.uv = uvs[in_vertex_index];
+
+    return output;
+}
+
+@fragment
+fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
+    // distance to the marker in screen pixels, wrapping around since the
+    // gradient is a hue circle
+    let d = abs(v.uv.y - imm.position);
+    let dist = min(d, 1.0 - d) / fwidth(v.uv.y);
+
+    // white line with a dark outline, visible on any hue
+    let half_width = 0.5 * imm.width;
+    let line = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, dist);
+    let outline = 1.0 - smoothstep(half_width + 0.5, half_width + 1.5, dist);
+
+    let color = vec3(line);
+    return vec4(color, max(line, outline * 0.75));
+}
@@ -0,0 +1,111 @@
+// Clears and applies liquify dabs to a displacement field. Dabs must match
+// `paint_core::liquify::DisplacementField::apply()`.
+//
+// The field holds the offsets in pixels where the canvas is sampled from, in
+// the red and green channels.
+
+const MODE_PUSH: u32 = 0u;
+const MODE_TWIRL: u32 = 1u;
+const MODE_SCALE: u32 = 2u;
+const MODE_RECONSTRUCT: u32 = 3u;
+
+struct Immediates {
+    position: vec2<f32>,
+    delta: vec2<f32>,
+    // first pixel of the dispatch
+    origin: vec2<u32>,
+    radius: f32,
+    strength: f32,
+    mode: u32,
+    // twirl angle or scale of a full strength dab
+    amount: f32,
+}
+
+var<immediate> imm: Immediates;
+
+@group(0) @binding(0)
+var u_field: texture_2d<f32>;
+
+@group(0) @binding(1)
+var u_output: texture_storage_2d<rgba32float, write>;
+
+fn load(p: vec2<i32>) -> vec2<f32> {
+    let size = vec2<i32>(textureDimensions(u_field));
+    return textureLoad(u_field, clamp(p, vec2(0), size - 1), 0).xy;
+}
+
+// bilinear interpolation between pixel centers, at integer coordinates
+fn load_bilinear(p: vec2<f32>) -> vec2<f32> {
+    let p0 = floor(p);
+    let f = p - p0;
+    let i = vec2<i32>(p0);
+
+    let top = mix(load(i), load(i + vec2(1, 0)), f.x);
+    let bottom = mix(load(i + vec2(0, 1)), load(i + vec2(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+fn weight(distance: f32) -> f32 {
+    let d = distance / imm.radius;
+    if d >= 1.0 {
+        return 0.0;
+    }
+
+    let t = 1.0 - d * d;
+    return imm.strength * t * t;
+}
+
+@compute @workgroup_size(8, 8)
+fn dab(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.xy + imm.origin;
+    if any(i >= textureDimensions(u_output)) {
+        return;
+    }
+
+    // pixel centers are at half-integer canvas coordinates
+    let x = vec2<f32>(i) + 0.5;
+    let current = load(vec2<i32>(i));
+
+    let w = weight(distance(x, imm.position));
+    var offset = current;
+
+    if w > 0.0 {
+        // the point whose previous sample the pixel shows from now on
+        var y = x;
+        switch imm.mode {
+            case MODE_PUSH: {
+                y = x - imm.delta * w;
+            }
+            case MODE_TWIRL: {
+                let angle = w * imm.amount;
+                let v = x - imm.position;
+                let c = cos(angle);
+                let s = sin(angle);
+                y = imm.position + vec2(c * v.x - s * v.y, s * v.x + c * v.y);
+            }
+            case MODE_SCALE: {
+                y = imm.position + (x - imm.position) * (1.0 + w * imm.amount);
+            }
+            default: {}
+        }
+
+        if imm.mode == MODE_RECONSTRUCT {
+            offset = current * (1.0 - w);
+        } else {
+            offset = y - x + load_bilinear(y - 0.5);
+        }
+    }
+
+    textureStore(u_output, i, vec4(offset, 0.0, 0.0));
+}
+
+// clears the output, starting a new field
+@compute @workgroup_size(8, 8)
+fn clear(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.xy + imm.origin;
+    if any(i >= textureDimensions(u_output)) {
+        return;
+    }
+
+    textureStore(u_output, i, vec4(0.0));
+}
@@ -0,0 +1,54 @@
+// Resamples the canvas through a displacement field. Must match
+// `paint_core::liquify::DisplacementField::resample()`.
+
+struct Immediates {
+    // first pixel of the dispatch
+    origin: vec2<u32>,
+}
+
+var<immediate> imm: Immediates;
+
+@group(0) @binding(0)
+var u_field: texture_2d<f32>;
+
+@group(0) @binding(1)
+var u_texture: texture_2d<f32>;
+
+@group(0) @binding(2)
+var u_output: texture_storage_2d<rgba16float, write>;
+
+fn load(p: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(u_texture));
+    let c = textureLoad(u_texture, clamp(p, vec2(0), size - 1), 0);
+    let a = clamp(c.a, 0.0, 1.0);
+    return vec4(c.rgb * a, a);
+}
+
+// bilinear interpolation of premultiplied colors between pixel centers, at
+// integer coordinates
+fn load_bilinear(p: vec2<f32>) -> vec4<f32> {
+    let p0 = floor(p);
+    let f = p - p0;
+    let i = vec2<i32>(p0);
+
+    let top = mix(load(i), load(i + vec2(1, 0)), f.x);
+    let bottom = mix(load(i + vec2(0, 1)), load(i + vec2(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+@compute @workgroup_size(8, 8)
+fn resample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.xy + imm.origin;
+    if any(i >= textureDimensions(u_output)) {
+        return;
+    }
+
+    let offset = textureLoad(u_field, i, 0).xy;
+    let c = load_bilinear(vec2<f32>(i) + offset);
+
+    var rgb = vec3(0.0);
+    if c.a > 0.0 {
+        rgb = c.rgb / c.a;
+    }
+    textureStore(u_output, i, vec4(rgb, c.a));
+}
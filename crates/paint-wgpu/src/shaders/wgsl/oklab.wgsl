@@ -0,0 +1,464 @@
+// Okhsv, Okhsl and gamut clipping adopted from
+// https://bottosson.github.io/misc/ok_color.h
+// Copyright(c) 2021 Björn Ottosson. MIT License.
+//
+// Port of `paint_core::color::oklab::utils`, `GamutMapping` and the sRGB
+// transfer functions, concatenated with the shaders which need them, see
+// `shaders::Key::source`.
+
+fn srgb_encode(x: vec3<f32>) -> vec3<f32> {
+    let low = x * 12.92;
+    let high = 1.055 * pow(max(x, vec3(0.0)), vec3(1.0 / 2.4)) - 0.055;
+    return select(high, low, x <= vec3(0.0031308));
+}
+
+fn srgb_decode(x: vec3<f32>) -> vec3<f32> {
+    let low = x / 12.92;
+    let high = pow(max((x + 0.055) / 1.055, vec3(0.0)), vec3(2.4));
+    return select(high, low, x <= vec3(0.04045));
+}
+
+fn linear_srgb_to_oklab(rgb: vec3<f32>) -> vec3<f32> {
+    let l = 0.4122214708 * rgb.r + 0.5363325363 * rgb.g + 0.0514459929 * rgb.b;
+    let m = 0.2119034982 * rgb.r + 0.6806995451 * rgb.g + 0.1073969566 * rgb.b;
+    let s = 0.0883024619 * rgb.r + 0.2817188376 * rgb.g + 0.6299787005 * rgb.b;
+
+    let l_ = sign(l) * pow(abs(l), 1.0 / 3.0);
+    let m_ = sign(m) * pow(abs(m), 1.0 / 3.0);
+    let s_ = sign(s) * pow(abs(s), 1.0 / 3.0);
+
+    return vec3(
+        0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+        1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+        0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+    );
+}
+
+fn oklab_to_linear_srgb(lab: vec3<f32>) -> vec3<f32> {
+    let l_ = lab.x + 0.3963377774 * lab.y + 0.2158037573 * lab.z;
+    let m_ = lab.x - 0.1055613458 * lab.y - 0.0638541728 * lab.z;
+    let s_ = lab.x - 0.0894841775 * lab.y - 1.2914855480 * lab.z;
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    return vec3(
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    );
+}
+
+struct Cusp {
+    l: f32,
+    c: f32,
+}
+
+// Finds the maximum saturation possible for a given hue that fits in sRGB.
+// Saturation here is defined as S = C/L.
+// a and b must be normalized so a^2 + b^2 == 1.
+fn compute_max_saturation(a: f32, b: f32) -> f32 {
+    // Select different coefficients depending on which component goes below
+    // zero first
+    var k0: f32;
+    var k1: f32;
+    var k2: f32;
+    var k3: f32;
+    var k4: f32;
+    var wl: f32;
+    var wm: f32;
+    var ws: f32;
+
+    if -1.88170328 * a - 0.80936493 * b > 1.0 {
+        // red component
+        k0 = 1.19086277; k1 = 1.76576728; k2 = 0.59662641; k3 = 0.75515197; k4 = 0.56771245;
+        wl = 4.0767416621; wm = -3.3077115913; ws = 0.2309699292;
+    } else if 1.81444104 * a - 1.19445276 * b > 1.0 {
+        // green component
+        k0 = 0.73956515; k1 = -0.45954404; k2 = 0.08285427; k3 = 0.12541070; k4 = 0.14503204;
+        wl = -1.2684380046; wm = 2.6097574011; ws = -0.3413193965;
+    } else {
+        // blue component
+        k0 = 1.35733652; k1 = -0.00915799; k2 = -1.15130210; k3 = -0.50559606; k4 = 0.00692167;
+        wl = -0.0041960863; wm = -0.7034186147; ws = 1.7076147010;
+    }
+
+    // approximate max saturation using a polynomial
+    var saturation = k0 + k1 * a + k2 * b + k3 * a * a + k4 * a * b;
+
+    // one step of Halley's method to get closer
+    let k_l = 0.3963377774 * a + 0.2158037573 * b;
+    let k_m = -0.1055613458 * a - 0.0638541728 * b;
+    let k_s = -0.0894841775 * a - 1.2914855480 * b;
+
+    let l_ = 1.0 + saturation * k_l;
+    let m_ = 1.0 + saturation * k_m;
+    let s_ = 1.0 + saturation * k_s;
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    let l_ds = 3.0 * k_l * l_ * l_;
+    let m_ds = 3.0 * k_m * m_ * m_;
+    let s_ds = 3.0 * k_s * s_ * s_;
+
+    let l_ds2 = 6.0 * k_l * k_l * l_;
+    let m_ds2 = 6.0 * k_m * k_m * m_;
+    let s_ds2 = 6.0 * k_s * k_s * s_;
+
+    let f = wl * l + wm * m + ws * s;
+    let f1 = wl * l_ds + wm * m_ds + ws * s_ds;
+    let f2 = wl * l_ds2 + wm * m_ds2 + ws * s_ds2;
+
+    saturation = saturation - f * f1 / (f1 * f1 - 0.5 * f * f2);
+
+    return saturation;
+}
+
+fn scale_l(l_vt: f32, c_vt: f32, a_: f32, b_: f32) -> f32 {
+    let rgb_scale = oklab_to_linear_srgb(vec3(l_vt, a_ * c_vt, b_ * c_vt));
+    let rgb_max = max(max(rgb_scale.r, rgb_scale.g), max(rgb_scale.b, 0.0));
+    return pow(1.0 / rgb_max, 1.0 / 3.0);
+}
+
+// Finds L_cusp and C_cusp for a given hue.
+// a and b must be normalized so a^2 + b^2 == 1.
+fn find_cusp(a: f32, b: f32) -> Cusp {
+    let s_cusp = compute_max_saturation(a, b);
+    let l_cusp = scale_l(1.0, s_cusp, a, b);
+    return Cusp(l_cusp, l_cusp * s_cusp);
+}
+
+// Finds intersection of the line defined by
+// L = L0 * (1 - t) + t * L1;
+// C = t * C1;
+// a and b must be normalized so a^2 + b^2 == 1.
+fn find_gamut_intersection(a: f32, b: f32, l1: f32, c1: f32, l0: f32, cusp: Cusp) -> f32 {
+    var t: f32;
+
+    if (l1 - l0) * cusp.c - (cusp.l - l0) * c1 <= 0.0 {
+        // lower half
+        t = cusp.c * l0 / (c1 * cusp.l + cusp.c * (l0 - l1));
+    } else {
+        // upper half, first intersect with triangle
+        t = cusp.c * (l0 - 1.0) / (c1 * (cusp.l - 1.0) + cusp.c * (l0 - l1));
+
+        // then one step of Newton's method, like the reference
+        let dl = l1 - l0;
+        let dc = c1;
+
+        let k_l = 0.3963377774 * a + 0.2158037573 * b;
+        let k_m = -0.1055613458 * a - 0.0638541728 * b;
+        let k_s = -0.0894841775 * a - 1.2914855480 * b;
+
+        let l_dt = dl + dc * k_l;
+        let m_dt = dl + dc * k_m;
+        let s_dt = dl + dc * k_s;
+
+        let ll = l0 * (1.0 - t) + t * l1;
+        let cc = t * c1;
+
+        let l_ = ll + cc * k_l;
+        let m_ = ll + cc * k_m;
+        let s_ = ll + cc * k_s;
+
+        let l = l_ * l_ * l_;
+        let m = m_ * m_ * m_;
+        let s = s_ * s_ * s_;
+
+        let ldt = 3.0 * l_dt * l_ * l_;
+        let mdt = 3.0 * m_dt * m_ * m_;
+        let sdt = 3.0 * s_dt * s_ * s_;
+
+        let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s - 1.0;
+        let r1 = 4.0767416621 * ldt - 3.3077115913 * mdt + 0.2309699292 * sdt;
+
+        let u_r = 1.0 / r1;
+        var t_r = -r * u_r;
+
+        let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s - 1.0;
+        let g1 = -1.2684380046 * ldt + 2.6097574011 * mdt - 0.3413193965 * sdt;
+
+        let u_g = 1.0 / g1;
+        var t_g = -g * u_g;
+
+        let bb = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s - 1.0;
+        let b1 = -0.0041960863 * ldt - 0.7034186147 * mdt + 1.7076147010 * sdt;
+
+        let u_b = 1.0 / b1;
+        var t_b = -bb * u_b;
+
+        t_r = select(1e6, t_r, u_r >= 0.0);
+        t_g = select(1e6, t_g, u_g >= 0.0);
+        t_b = select(1e6, t_b, u_b >= 0.0);
+
+        t += min(t_r, min(t_g, t_b));
+    }
+
+    return t;
+}
+
+// gamut mappings, the values of `GamutMapping` in `renderer::color_picker`
+const GAMUT_MAPPING_CLIP: u32 = 0u;
+const GAMUT_MAPPING_PRESERVE_CHROMA: u32 = 1u;
+const GAMUT_MAPPING_PROJECT_TO_MID_LIGHTNESS: u32 = 2u;
+const GAMUT_MAPPING_PROJECT_TO_CUSP: u32 = 3u;
+const GAMUT_MAPPING_ADAPTIVE_L0: u32 = 4u;
+const GAMUT_MAPPING_CSS: u32 = 5u;
+
+// Converts Oklab to linear sRGB, mapping out of gamut colors with
+// `paint_core::color::GamutMapping`. `alpha` is only used by the adaptive L0.
+fn gamut_map(lab: vec3<f32>, mapping: u32, alpha: f32) -> vec3<f32> {
+    let rgb = oklab_to_linear_srgb(lab);
+
+    if mapping == GAMUT_MAPPING_CLIP {
+        return clamp(rgb, vec3(0.0), vec3(1.0));
+    }
+    if mapping == GAMUT_MAPPING_CSS {
+        return gamut_map_css(lab);
+    }
+
+    let eps = 1e-3;
+    if all(rgb <= vec3(1.0 + eps)) && all(rgb >= vec3(-eps)) {
+        return clamp(rgb, vec3(0.0), vec3(1.0));
+    }
+
+    let l = lab.x;
+    let c = max(0.00001, length(lab.yz));
+    let a_ = lab.y / c;
+    let b_ = lab.z / c;
+
+    let cusp = find_cusp(a_, b_);
+
+    var l0: f32;
+    switch mapping {
+        case GAMUT_MAPPING_PRESERVE_CHROMA: {
+            l0 = clamp(l, 0.0, 1.0);
+        }
+        case GAMUT_MAPPING_PROJECT_TO_MID_LIGHTNESS: {
+            l0 = 0.5;
+        }
+        case GAMUT_MAPPING_PROJECT_TO_CUSP: {
+            l0 = cusp.l;
+        }
+        default: {
+            let ld = l - 0.5;
+            let e1 = 0.5 + abs(ld) + alpha * c;
+            l0 = 0.5 * (1.0 + sign(ld) * (e1 - sqrt(e1 * e1 - 2.0 * abs(ld))));
+        }
+    }
+
+    let t = find_gamut_intersection(a_, b_, l, c, l0, cusp);
+    let l_clipped = l0 * (1.0 - t) + t * l;
+    let c_clipped = t * c;
+
+    let clipped = oklab_to_linear_srgb(vec3(l_clipped, c_clipped * a_, c_clipped * b_));
+    return clamp(clipped, vec3(0.0), vec3(1.0));
+}
+
+// CSS Color 4 binary search for the largest chroma whose clipped color is
+// less than a just noticeable difference away.
+fn gamut_map_css(lab: vec3<f32>) -> vec3<f32> {
+    let jnd = 0.02;
+    let eps = 0.0001;
+
+    if lab.x >= 1.0 {
+        return vec3(1.0);
+    }
+    if lab.x <= 0.0 {
+        return vec3(0.0);
+    }
+
+    let rgb = oklab_to_linear_srgb(lab);
+    if is_unit_cube(rgb) {
+        return clamp(rgb, vec3(0.0), vec3(1.0));
+    }
+
+    var clipped = clamp(rgb, vec3(0.0), vec3(1.0));
+    if distance(lab, linear_srgb_to_oklab(clipped)) < jnd {
+        return clipped;
+    }
+
+    let c0 = length(lab.yz);
+    let ab_ = lab.yz / c0;
+
+    var min_c = 0.0;
+    var max_c = c0;
+    var min_in_gamut = true;
+
+    while max_c - min_c > eps {
+        let c = 0.5 * (min_c + max_c);
+        let current = vec3(lab.x, c * ab_);
+        let rgb = oklab_to_linear_srgb(current);
+
+        if min_in_gamut && is_unit_cube(rgb) {
+            min_c = c;
+            continue;
+        }
+
+        clipped = clamp(rgb, vec3(0.0), vec3(1.0));
+        let e = distance(current, linear_srgb_to_oklab(clipped));
+        if e < jnd {
+            if jnd - e < eps {
+                break;
+            }
+            min_in_gamut = false;
+            min_c = c;
+        } else {
+            max_c = c;
+        }
+    }
+
+    return clipped;
+}
+
+fn is_unit_cube(rgb: vec3<f32>) -> bool {
+    let eps = 1e-5;
+    return all(rgb <= vec3(1.0 + eps)) && all(rgb >= vec3(-eps));
+}
+
+fn toe_inv(x: f32) -> f32 {
+    let k_1 = 0.206;
+    let k_2 = 0.03;
+    let k_3 = (1.0 + k_1) / (1.0 + k_2);
+    return (x * x + k_1 * x) / (k_3 * (x + k_2));
+}
+
+struct St {
+    s: f32,
+    t: f32,
+}
+
+fn get_st_max(cusp: Cusp) -> St {
+    return St(cusp.c / cusp.l, cusp.c / (1.0 - cusp.l));
+}
+
+// Returns a smooth approximation of the location of the cusp.
+fn get_st_mid(a_: f32, b_: f32) -> St {
+    let s = 0.11516993 + 1.0 / (
+        7.44778970 + 4.15901240 * b_
+        + a_ * (-2.19557347 + 1.75198401 * b_
+        + a_ * (-2.13704948 - 10.02301043 * b_
+        + a_ * (-4.24894561 + 5.38770819 * b_ + 4.69891013 * a_)))
+    );
+
+    let t = 0.11239642 + 1.0 / (
+        1.61320320 - 0.68124379 * b_
+        + a_ * (0.40370612 + 0.90148123 * b_
+        + a_ * (-0.27087943 + 0.61223990 * b_
+        + a_ * (0.00299215 - 0.45399568 * b_ - 0.14661872 * a_)))
+    );
+
+    return St(s, t);
+}
+
+struct Cs {
+    c_0: f32,
+    c_mid: f32,
+    c_max: f32,
+}
+
+fn get_cs(l: f32, a_: f32, b_: f32) -> Cs {
+    let cusp = find_cusp(a_, b_);
+
+    let c_max = find_gamut_intersection(a_, b_, l, 1.0, l, cusp);
+    let st_max = get_st_max(cusp);
+
+    let k = c_max / min(l * st_max.s, (1.0 - l) * st_max.t);
+
+    let st_mid = get_st_mid(a_, b_);
+    let c_a = l * st_mid.s;
+    let c_b = (1.0 - l) * st_mid.t;
+    let c_mid = 0.9 * k * sqrt(sqrt(1.0 / (1.0 / (c_a * c_a * c_a * c_a) + 1.0 / (c_b * c_b * c_b * c_b))));
+
+    let c_a0 = l * 0.4;
+    let c_b0 = (1.0 - l) * 0.8;
+    let c_0 = sqrt(1.0 / (1.0 / (c_a0 * c_a0) + 1.0 / (c_b0 * c_b0)));
+
+    return Cs(c_0, c_mid, c_max);
+}
+
+fn okhsl_to_oklab(hsl: vec3<f32>) -> vec3<f32> {
+    let h = hsl.x;
+    let s = hsl.y;
+    var l = hsl.z;
+
+    if l >= 1.0 {
+        return vec3(1.0, 0.0, 0.0);
+    }
+
+    if l <= 0.0 {
+        return vec3(0.0);
+    }
+
+    let a_ = cos(h);
+    let b_ = sin(h);
+    l = toe_inv(l);
+
+    let cs = get_cs(l, a_, b_);
+
+    let mid = 0.8;
+    let mid_inv = 1.25;
+
+    var c: f32;
+
+    if s < mid {
+        let t = mid_inv * s;
+
+        let k_1 = mid * cs.c_0;
+        let k_2 = 1.0 - k_1 / cs.c_mid;
+
+        c = t * k_1 / (1.0 - k_2 * t);
+    } else {
+        let t = (s - mid) / (1.0 - mid);
+
+        let k_0 = cs.c_mid;
+        let k_1 = (1.0 - mid) * cs.c_mid * cs.c_mid * mid_inv * mid_inv / cs.c_0;
+        let k_2 = 1.0 - k_1 / (cs.c_max - cs.c_mid);
+
+        c = k_0 + t * k_1 / (1.0 - k_2 * t);
+    }
+
+    return vec3(l, c * a_, c * b_);
+}
+
+fn okhsv_to_oklab(hsv: vec3<f32>) -> vec3<f32> {
+    let h = hsv.x;
+    let s = hsv.y;
+    let v = hsv.z;
+
+    let a_ = cos(h);
+    let b_ = sin(h);
+
+    let cusp = find_cusp(a_, b_);
+    let st_max = get_st_max(cusp);
+    let s_max = st_max.s;
+    let t_max = st_max.t;
+    let s_0 = 0.5;
+    let k = 1.0 - s_0 / s_max;
+
+    let l_v = 1.0 - s * s_0 / (s_0 + t_max - t_max * k * s);
+    let c_v = s * t_max * s_0 / (s_0 + t_max - t_max * k * s);
+
+    var l = v * l_v;
+    if l <= 0.0 || l >= 1.0 {
+        return vec3(clamp(l, 0.0, 1.0), 0.0, 0.0);
+    }
+
+    var c = v * c_v;
+
+    let l_vt = toe_inv(l_v);
+    let c_vt = c_v * l_vt / l_v;
+
+    let l_new = toe_inv(l);
+    c = c * l_new / l;
+    l = l_new;
+
+    let scale = scale_l(l_vt, c_vt, a_, b_);
+    l *= scale;
+    c *= scale;
+
+    return vec3(l, c * a_, c * b_);
+}
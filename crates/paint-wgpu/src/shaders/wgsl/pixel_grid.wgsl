@@ -0,0 +1,49 @@
+struct Immediates {
+    transform: mat2x2<f32>,
+    translation: vec2<f32>,
+    resolution: vec2<f32>,
+    opacity: f32,
+}
+
+var<immediate> imm: Immediates;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    const vertices = array<vec2<f32>, 6>(
+        vec2(0.0, 1.0),
+        vec2(1.0, 1.0),
+        vec2(0.0, 0.0),
+
+        vec2(1.0, 1.0),
+        vec2(1.0, 0.0),
+        vec2(0.0, 0.0)
+    );
+
+    var output: VertexOutput;
+
+    let vertex = vertices[in_vertex_index];
+
+    output.pos = vec4(imm.transform * vertex + imm.translation, 0.0, 1.0);
+    output.uv = vertex;
+
+    return output;
+}
+
+@fragment
+fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
+    // position in canvas pixels and its screen-space derivative
+    let pos = v.uv * imm.resolution;
+    let width = fwidth(pos);
+
+    // distance to the nearest grid line, in screen pixels
+    let cell = fract(pos);
+    let dist = min(cell, 1.0 - cell) / width;
+
+    let alpha = 1.0 - clamp(min(dist.x, dist.y), 0.0, 1.0);
+    return vec4(0.5, 0.5, 0.5, alpha * imm.opacity);
+}
@@ -0,0 +1,47 @@
+struct Immediates {
+    position: vec2<f32>,
+    radius: f32,
+    width: f32,
+}
+
+var<immediate> imm: Immediates;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    const uvs = array<vec2<f32>, 3>(
+        vec2(0.0,  1.0),
+        vec2(2.0,  1.0),
+        vec2(0.0, -1.0),
+    );
+
+    var output: VertexOutput;
+
+    output.pos = vec4(positions[in_vertex_index], 0.0, 1.0);
+    output.uv = uvs[in_vertex_index];
+
+    return output;
+}
+
+@fragment
+fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
+    // distance to the ring in pixels
+    let dist = abs(distance(v.pos.xy, imm.position) - imm.radius);
+
+    // white ring with a dark outline, visible on any color
+    let half_width = 0.5 * imm.width;
+    let ring = 1.0 - smoothstep(half_width - 0.5, half_width + 0.5, dist);
+    let outline = 1.0 - smoothstep(half_width + 0.5, half_width + 1.5, dist);
+
+    return vec4(vec3(ring), max(ring, outline * 0.75));
+}
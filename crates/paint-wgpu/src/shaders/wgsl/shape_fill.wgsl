@@ -0,0 +1,111 @@
+// Fills a shape with analytic antialiasing. Must match
+// `paint_core::shape::Shape::rasterize()` for filled shapes.
+//
+// Draws a fullscreen triangle, which is scissored to the bounds of the shape.
+
+const KIND_LINE: u32 = 0u;
+const KIND_RECTANGLE: u32 = 1u;
+const KIND_ELLIPSE: u32 = 2u;
+const KIND_POLYGON: u32 = 3u;
+
+const PI: f32 = 3.14159265358979;
+
+struct Immediates {
+    // linear
+    color: vec4<f32>,
+    start: vec2<f32>,
+    end: vec2<f32>,
+    kind: u32,
+    sides: u32,
+}
+
+var<immediate> imm: Immediates;
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    return vec4(positions[in_vertex_index], 0.0, 1.0);
+}
+
+fn segment_distance(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    var h = 0.0;
+    if any(ba != vec2(0.0)) {
+        h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    }
+    return length(pa - ba * h);
+}
+
+fn rectangle_distance(p: vec2<f32>) -> f32 {
+    let center = (imm.start + imm.end) / 2.0;
+    let half_size = abs(imm.end - imm.start) / 2.0;
+    let q = abs(p - center) - half_size;
+    return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0);
+}
+
+fn ellipse_distance(p: vec2<f32>) -> f32 {
+    let center = (imm.start + imm.end) / 2.0;
+    let radii = max(abs(imm.end - imm.start) / 2.0, vec2(1e-3));
+    let q = p - center;
+    let k1 = length(q / radii);
+    let k2 = length(q / (radii * radii));
+    if k2 == 0.0 {
+        return -min(radii.x, radii.y);
+    }
+
+    return k1 * (k1 - 1.0) / k2;
+}
+
+// from "2D distance functions" by Inigo Quilez
+fn polygon_distance(p: vec2<f32>) -> f32 {
+    let corner = imm.end - imm.start;
+    let radius = length(corner);
+    let q = p - imm.start;
+    if radius == 0.0 {
+        return length(q);
+    }
+
+    // rotated so the corner is on the positive Y axis
+    let d = corner / radius;
+    let r = vec2(d.x * q.y - d.y * q.x, dot(d, q));
+
+    let half_angle = PI / f32(imm.sides);
+    let a = atan2(r.x, r.y);
+    let angle = a - floor(a / (2.0 * half_angle)) * 2.0 * half_angle - half_angle;
+    var s = length(r) * vec2(cos(angle), abs(sin(angle)));
+    s -= radius * vec2(cos(half_angle), sin(half_angle));
+    s.y += clamp(-s.y, 0.0, radius * sin(half_angle));
+    return length(s) * sign(s.x);
+}
+
+@fragment
+fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // fragment positions are at pixel centers, as canvas coordinates
+    let p = position.xy;
+
+    var distance = 0.0;
+    switch imm.kind {
+        case KIND_LINE: {
+            // the hairline is a pixel wide
+            distance = segment_distance(p, imm.start, imm.end) - 0.5;
+        }
+        case KIND_RECTANGLE: {
+            distance = rectangle_distance(p);
+        }
+        case KIND_ELLIPSE: {
+            distance = ellipse_distance(p);
+        }
+        case KIND_POLYGON, default: {
+            distance = polygon_distance(p);
+        }
+    }
+
+    let alpha = clamp(0.5 - distance, 0.0, 1.0);
+    return vec4(imm.color.rgb, alpha);
+}
@@ -40,5 +40,11 @@ fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
 
 @fragment
 fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(u_texture, u_sampler, v.uv);
+    let color = textureSample(u_texture, u_sampler, v.uv);
+
+    // approximate coverage near the quad edges, so rotated quads stay smooth
+    let edge_dist = min(v.uv, 1.0 - v.uv) / fwidth(v.uv);
+    let coverage = clamp(min(edge_dist.x, edge_dist.y) + 0.5, 0.0, 1.0);
+
+    return vec4(color.rgb, color.a * coverage);
 }
@@ -1,4 +1,9 @@
+// Dabs are blended premultiplied in the blending space, which `resolve` in
+// `blend.wgsl` turns back into linear colors.
+
 struct Immediates {
+    // in the blending space
+    color: vec4<f32>,
     transform: mat2x2<f32>,
     translation: vec2<f32>,
 }
@@ -46,5 +51,5 @@ fn vertex(in: VertexInput) -> VertexOutput {
 fn fragment(v: VertexOutput) -> @location(0) vec4<f32> {
     let dist = length(v.rel_pos) - 0.5 * v.radius;
     let alpha = 1.0 - smoothstep(-0.5, 0.5, dist);
-    return vec4(0.0, 0.0, 0.0, alpha);
+    return vec4(imm.color.rgb * alpha, alpha);
 }
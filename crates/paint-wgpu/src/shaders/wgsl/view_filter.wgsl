@@ -0,0 +1,96 @@
+// Post-processing of the viewport. Must match
+// `paint_core::presentation::ViewFilter::apply()`.
+
+const FILTER_LUMINANCE: u32 = 1u;
+const FILTER_PROTANOPIA: u32 = 2u;
+const FILTER_DEUTERANOPIA: u32 = 3u;
+const FILTER_TRITANOPIA: u32 = 4u;
+const FILTER_VALUE_BANDS: u32 = 5u;
+
+struct Immediates {
+    view_filter: u32,
+    bands: u32,
+}
+
+var<immediate> imm: Immediates;
+
+@group(0) @binding(0)
+var u_sampler: sampler;
+
+@group(0) @binding(1)
+var u_texture: texture_2d<f32>;
+
+@vertex
+fn vertex(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    const positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0, 3.0),
+    );
+
+    return vec4(positions[in_vertex_index], 0.0, 1.0);
+}
+
+// WGSL matrices are column-major, so these are the transposed matrices of
+// `ViewFilter`.
+const PROTANOPIA = mat3x3(
+    0.11238, 0.11238, 0.00401,
+    0.88762, 0.88762, -0.00401,
+    0.0, 0.0, 1.0,
+);
+const DEUTERANOPIA = mat3x3(
+    0.29275, 0.29275, -0.02234,
+    0.70725, 0.70725, 0.02234,
+    0.0, 0.0, 1.0,
+);
+const TRITANOPIA_1 = mat3x3(
+    1.01277, -0.01243, 0.07589,
+    0.13548, 0.86812, 0.805,
+    -0.14826, 0.14431, 0.11911,
+);
+const TRITANOPIA_2 = mat3x3(
+    0.93678, 0.06154, -0.37562,
+    0.18979, 0.81526, 1.12767,
+    -0.12657, 0.1232, 0.24796,
+);
+const TRITANOPIA_SEPARATION = vec3(0.03901, -0.02788, -0.01113);
+
+fn gray(l: f32) -> vec3<f32> {
+    let l_ = clamp(l, 0.0, 1.0);
+    return vec3(l_ * l_ * l_);
+}
+
+fn apply(c: vec3<f32>) -> vec3<f32> {
+    switch imm.view_filter {
+        case FILTER_LUMINANCE: {
+            return gray(linear_srgb_to_oklab(c).x);
+        }
+        case FILTER_VALUE_BANDS: {
+            let bands = f32(max(imm.bands, 2u));
+            let l = clamp(linear_srgb_to_oklab(c).x, 0.0, 1.0);
+            let band = min(floor(l * bands), bands - 1.0);
+            return gray(band / (bands - 1.0));
+        }
+        case FILTER_PROTANOPIA: {
+            return clamp(PROTANOPIA * c, vec3(0.0), vec3(1.0));
+        }
+        case FILTER_DEUTERANOPIA: {
+            return clamp(DEUTERANOPIA * c, vec3(0.0), vec3(1.0));
+        }
+        case FILTER_TRITANOPIA: {
+            if dot(TRITANOPIA_SEPARATION, c) >= 0.0 {
+                return clamp(TRITANOPIA_1 * c, vec3(0.0), vec3(1.0));
+            }
+            return clamp(TRITANOPIA_2 * c, vec3(0.0), vec3(1.0));
+        }
+        default: {
+            return c;
+        }
+    }
+}
+
+@fragment
+fn fragment(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(u_texture, vec2<i32>(pos.xy), 0);
+    return vec4(apply(color.rgb), color.a);
+}
@@ -0,0 +1,174 @@
+use std::sync::{Arc, Mutex};
+
+use glam::{UVec2, Vec4};
+use paint_core::behaviour::StrokeSettings;
+use paint_core::geometry::DamageRegion;
+use paint_core::shape::Shape;
+use zerocopy::IntoBytes as _;
+
+use crate::brush_engine::{BRUSH_COLOR, DabAccumulator};
+use crate::context::{FrameContext, GlobalContext};
+use crate::render_pipelines::{self, shape_fill, stamped_brush};
+use crate::resources::ring_buffer::RingBuffer;
+use crate::resources::texture_pool::{self, PooledTexture};
+use crate::texture::{self, Texture};
+
+pub struct ShapeEngine {
+    context: Arc<GlobalContext>,
+    instance_buffer: Arc<Mutex<RingBuffer>>,
+}
+
+impl ShapeEngine {
+    pub fn new(context: Arc<GlobalContext>) -> Self {
+        let instance_buffer = Arc::new(Mutex::new(RingBuffer::new(
+            context.device.clone(),
+            context.queue.clone(),
+            "Shape Instance Buffer",
+            wgpu::BufferUsages::VERTEX,
+            64 * 1024,
+        )));
+
+        Self {
+            context,
+            instance_buffer,
+        }
+    }
+}
+
+impl paint_core::behaviour::ShapeEngine for ShapeEngine {
+    type Drawing = ShapeDrawing;
+
+    fn begin_drawing(&self, settings: &StrokeSettings) -> Self::Drawing {
+        ShapeDrawing::new(&self.context, self.instance_buffer.clone(), settings)
+    }
+}
+
+/// An editable shape, which is drawn anew whenever it changes.
+///
+/// Strokes are stamped like brush strokes, fills are rendered from the signed
+/// distance of the shape.
+pub struct ShapeDrawing {
+    context: Arc<GlobalContext>,
+    dabs: DabAccumulator,
+    instance_buffer: Arc<Mutex<RingBuffer>>,
+    format: wgpu::TextureFormat,
+    texture: PooledTexture,
+    texture_view: wgpu::TextureView,
+    shape: Option<Shape>,
+    /// Area touched by the current shape.
+    damage: DamageRegion,
+    /// Whether the texture doesn't show the current shape yet.
+    dirty: bool,
+}
+
+impl ShapeDrawing {
+    pub fn new(
+        context: &Arc<GlobalContext>,
+        instance_buffer: Arc<Mutex<RingBuffer>>,
+        settings: &StrokeSettings,
+    ) -> Self {
+        let format = texture::wgpu_format(settings.canvas_format);
+
+        // the compositor only reads the base level, so unlike brush strokes
+        // there are no mipmaps to keep up to date
+        let texture = context.texture_pool.acquire(
+            "Shape Texture",
+            texture_pool::Key {
+                width: settings.canvas_resolution.x,
+                height: settings.canvas_resolution.y,
+                mip_level_count: 1,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        );
+        let texture_view = texture.create_view(&Default::default());
+
+        Self {
+            context: context.clone(),
+            dabs: DabAccumulator::new(context, settings),
+            instance_buffer,
+            format,
+            texture,
+            texture_view,
+            shape: None,
+            damage: DamageRegion::new(),
+            dirty: true,
+        }
+    }
+}
+
+impl paint_core::behaviour::ShapeDrawing for ShapeDrawing {
+    type Texture = Texture;
+    type Context = FrameContext;
+
+    fn set_shape(&mut self, shape: &Shape) {
+        self.shape = Some(*shape);
+        self.damage = DamageRegion::from_rect(shape.bounds());
+        self.dirty = true;
+    }
+
+    fn damage(&self) -> &DamageRegion {
+        &self.damage
+    }
+
+    fn render(&mut self, ctx: &mut FrameContext) -> Texture {
+        if !self.dirty {
+            return Texture(self.texture_view.clone());
+        }
+
+        // the previous shape may be anywhere, and the pooled texture may
+        // contain an older drawing, so the whole texture is cleared
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+
+        if let Some(shape) = &self.shape
+            && let Some(width) = shape.stroke_width()
+        {
+            let instances: Vec<_> = shape
+                .dabs()
+                .into_iter()
+                .map(|pos| stamped_brush::Instance { pos, radius: width })
+                .collect();
+
+            self.dabs
+                .accumulate(ctx, &self.instance_buffer, &instances, clear);
+            self.dabs
+                .resolve(ctx, &self.texture_view, &self.damage, clear);
+        } else {
+            let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: clear,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+
+            let size = self.texture.size();
+            let resolution = UVec2::new(size.width, size.height);
+
+            if let Some(shape) = &self.shape
+                && let Some((offset, size)) = shape.bounds().to_pixels(resolution)
+            {
+                let pipeline = self
+                    .context
+                    .render_pipelines
+                    .get(render_pipelines::Key::ShapeFill(self.format));
+                let color = Vec4::new(BRUSH_COLOR.r, BRUSH_COLOR.g, BRUSH_COLOR.b, 1.0);
+                let immediates = shape_fill::Immediates::new(shape, color);
+
+                pass.set_pipeline(&pipeline);
+                pass.set_immediates(0, immediates.as_bytes());
+                pass.set_scissor_rect(offset.x, offset.y, size.x, size.y);
+                pass.draw(0..3, 0..1);
+            }
+        }
+
+        self.dirty = false;
+        Texture(self.texture_view.clone())
+    }
+}
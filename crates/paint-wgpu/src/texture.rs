@@ -3,11 +3,32 @@ use std::borrow::Cow;
 use glam::UVec2;
 use paint_core::persistence;
 
-use crate::FrameContext;
+use crate::{FrameContext, mipmaps};
 
 #[derive(Debug, Clone)]
 pub struct Texture(pub wgpu::TextureView);
 
+/// Returns the GPU format for textures of the given persistence format.
+pub(crate) fn wgpu_format(format: persistence::TextureFormat) -> wgpu::TextureFormat {
+    match format {
+        persistence::TextureFormat::Rgba8NonlinearSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+        persistence::TextureFormat::Rgba16LinearSrgb => wgpu::TextureFormat::Rgba16Unorm,
+        persistence::TextureFormat::Rgba16FloatLinearSrgb => wgpu::TextureFormat::Rgba16Float,
+    }
+}
+
+/// Inverse of [`wgpu_format()`].
+pub(crate) fn persistence_format(
+    format: wgpu::TextureFormat,
+) -> Option<persistence::TextureFormat> {
+    match format {
+        wgpu::TextureFormat::Rgba8UnormSrgb => Some(persistence::TextureFormat::Rgba8NonlinearSrgb),
+        wgpu::TextureFormat::Rgba16Unorm => Some(persistence::TextureFormat::Rgba16LinearSrgb),
+        wgpu::TextureFormat::Rgba16Float => Some(persistence::TextureFormat::Rgba16FloatLinearSrgb),
+        _ => None,
+    }
+}
+
 impl paint_core::behaviour::Texture for Texture {
     type Context = FrameContext;
     type Downloaded = DownloadedTexture;
@@ -19,18 +40,17 @@ impl paint_core::behaviour::Texture for Texture {
             depth_or_array_layers: 1,
         };
 
-        let format = match texture.format {
-            persistence::TextureFormat::Rgba8NonlinearSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
-        };
+        let format = wgpu_format(texture.format);
 
         let wgpu_texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
             label: None,
             size,
-            mip_level_count: 1,
+            mip_level_count: mipmaps::mip_level_count(size.width, size.height),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::COPY_SRC
                 | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
@@ -46,14 +66,15 @@ impl paint_core::behaviour::Texture for Texture {
             &texture.data,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(
-                    format.block_copy_size(None).unwrap() * texture.row_stride as u32,
-                ),
+                bytes_per_row: Some(texture.row_stride as u32),
                 rows_per_image: Some(texture.resolution.y),
             },
             size,
         );
 
+        ctx.mipmap_generator
+            .generate(&mut ctx.encoder, &wgpu_texture);
+
         let wgpu_texture_view = wgpu_texture.create_view(&Default::default());
         Texture(wgpu_texture_view)
     }
@@ -64,14 +85,12 @@ impl paint_core::behaviour::Texture for Texture {
     ) -> impl Future<Output = Self::Downloaded> + Send + 'static {
         let texture_size = self.0.texture().size();
 
-        let (bytes_per_block, persistence_format) = match self.0.texture().format() {
-            wgpu::TextureFormat::Rgba8UnormSrgb => {
-                (4, persistence::TextureFormat::Rgba8NonlinearSrgb)
-            }
-            _ => unimplemented!(),
-        };
+        let persistence_format = persistence_format(self.0.texture().format())
+            .expect("texture format should have a persistence counterpart");
+        let bytes_per_pixel = persistence_format.bytes_per_pixel() as u32;
 
-        let bytes_per_row = (bytes_per_block * texture_size.width).next_multiple_of(256);
+        let bytes_per_row = (bytes_per_pixel * texture_size.width)
+            .next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
         let rows_per_image = texture_size.height;
         let buffer_size = u64::from(bytes_per_row) * u64::from(rows_per_image);
 
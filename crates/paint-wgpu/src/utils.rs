@@ -1,10 +1,22 @@
+use glam::UVec2;
+use paint_core::geometry::DamageRegion;
 use wgpu::util::DeviceExt;
 
 pub fn create_default_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    create_solid_texture(device, queue, "Default Texture", [255, 255, 255, 255])
+}
+
+/// Creates a single pixel sRGB texture of the color.
+pub fn create_solid_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    color: [u8; 4],
+) -> wgpu::Texture {
     device.create_texture_with_data(
         queue,
         &wgpu::TextureDescriptor {
-            label: Some("Default Texture"),
+            label: Some(label),
             size: wgpu::Extent3d {
                 width: 1,
                 height: 1,
@@ -18,7 +30,7 @@ pub fn create_default_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> wgp
             view_formats: &[],
         },
         wgpu::util::TextureDataOrder::LayerMajor,
-        &[255, 255, 255, 255],
+        &color,
     )
 }
 
@@ -30,3 +42,174 @@ pub fn create_default_sampler(device: &wgpu::Device) -> wgpu::Sampler {
         ..Default::default()
     })
 }
+
+/// Sampler for minified and moderately magnified textures.
+///
+/// Filters between mip levels and uses anisotropic filtering where supported.
+pub fn create_trilinear_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Trilinear Sampler"),
+        min_filter: wgpu::FilterMode::Linear,
+        mag_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::MipmapFilterMode::Linear,
+        anisotropy_clamp: 16,
+        ..Default::default()
+    })
+}
+
+/// Sampler for strongly magnified textures, where individual pixels should
+/// stay crisp.
+pub fn create_nearest_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Nearest Sampler"),
+        min_filter: wgpu::FilterMode::Linear,
+        mag_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::MipmapFilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+/// Copies the base mip level within the region.
+pub fn copy_region(
+    encoder: &mut wgpu::CommandEncoder,
+    src: &wgpu::Texture,
+    dst: &wgpu::Texture,
+    region: &DamageRegion,
+    resolution: UVec2,
+) {
+    for rect in region.rects() {
+        let Some((offset, size)) = rect.to_pixels(resolution) else {
+            continue;
+        };
+
+        let origin = wgpu::Origin3d {
+            x: offset.x,
+            y: offset.y,
+            z: 0,
+        };
+
+        encoder.copy_texture_to_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: src,
+                mip_level: 0,
+                origin,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyTextureInfo {
+                texture: dst,
+                mip_level: 0,
+                origin,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/// Setup and readback shared by the GPU tests.
+#[cfg(test)]
+pub mod testing {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    use futures_lite::future::block_on;
+    use glam::UVec2;
+    use paint_core::behaviour::{DownloadedTexture as _, Texture as _};
+    use paint_core::color::NonlinearSrgb;
+    use paint_core::persistence;
+
+    use crate::{FrameContext, GlobalContext, Texture};
+
+    /// Creates a context for GPU tests, on a software adapter such as
+    /// llvmpipe if there is no GPU.
+    ///
+    /// Panics without any adapter, so GPU tests never pass without running.
+    pub fn create_context() -> Arc<GlobalContext> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = [false, true]
+            .into_iter()
+            .find_map(|force_fallback_adapter| {
+                let options = wgpu::RequestAdapterOptions {
+                    force_fallback_adapter,
+                    ..Default::default()
+                };
+                block_on(instance.request_adapter(&options)).ok()
+            })
+            .expect("GPU tests need an adapter, such as a software one like llvmpipe");
+
+        let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            required_features: crate::get_required_wgpu_features(),
+            required_limits: crate::get_required_wgpu_limits().using_resolution(adapter.limits()),
+            ..Default::default()
+        }))
+        .expect("adapter should support the required features and limits");
+
+        Arc::new(GlobalContext::new(&adapter, device, queue, None))
+    }
+
+    /// Creates an sRGB texture with a single mip level, like surface textures,
+    /// which can be rendered into and downloaded.
+    pub fn create_target(context: &GlobalContext, resolution: UVec2) -> Texture {
+        let texture = context.device().create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: resolution.x,
+                height: resolution.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        Texture(texture.create_view(&Default::default()))
+    }
+
+    /// Uploads opaque sRGB colors, row-major.
+    pub fn upload(
+        context: &Arc<GlobalContext>,
+        resolution: UVec2,
+        colors: &[NonlinearSrgb<u8>],
+    ) -> Texture {
+        let mut ctx = FrameContext::new(context);
+        let texture = Texture::upload(
+            &mut ctx,
+            persistence::Texture {
+                resolution,
+                format: persistence::TextureFormat::Rgba8NonlinearSrgb,
+                data: Cow::Owned(colors.iter().flat_map(|c| [c.r, c.g, c.b, 255]).collect()),
+                row_stride: 4 * resolution.x as usize,
+            },
+        );
+        context.submit(ctx);
+        texture
+    }
+
+    /// Downloads an 8-bit texture, returning tightly packed RGBA pixels.
+    pub fn download(context: &Arc<GlobalContext>, texture: &Texture) -> Vec<u8> {
+        let mut ctx = FrameContext::new(context);
+        let downloaded = texture.download(&mut ctx);
+        context.submit(ctx);
+        context
+            .device()
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("device should not be lost");
+
+        let downloaded = block_on(downloaded);
+        let downloaded = downloaded.as_persistence();
+        let row_size = 4 * downloaded.resolution.x as usize;
+        downloaded
+            .data
+            .chunks(downloaded.row_stride)
+            .flat_map(|row| &row[..row_size])
+            .copied()
+            .collect()
+    }
+}